@@ -0,0 +1,184 @@
+//! Benchmarks del parser y de los codificadores de imágenes (`cargo bench`).
+//!
+//! Para comparar contra una línea base:
+//! `cargo bench --bench parse -- --save-baseline main` en la rama principal y
+//! `cargo bench --bench parse -- --baseline main` en la rama con cambios.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use escpos_viewer::barcode::{encode_code128_runs, encode_runs};
+use escpos_viewer::bitmap::{qr_bitmap, raster_bitmap, runs_bitmap};
+use escpos_viewer::escpos::{
+    parse_escpos_incremental, parse_escpos_simple, parse_escpos_with_spans,
+};
+use escpos_viewer::model::{CodePage, CommandType, Control, QrModel};
+
+/// Ticket de venta típico: logo raster, encabezado grande, `items` líneas con precio,
+/// QR, barcode CODE128 y corte.
+fn receipt(items: usize) -> Vec<u8> {
+    let mut out = b"\x1b@\x1ba\x01".to_vec();
+
+    // Logo de 384 puntos x 96 filas.
+    let (width_bytes, height) = (48u16, 96u16);
+    out.extend_from_slice(&[0x1D, 0x76, 0x30, 0x00]);
+    out.extend_from_slice(&width_bytes.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend((0..width_bytes as usize * height as usize).map(|i| (i * 37 % 251) as u8));
+
+    out.extend_from_slice(b"\x1d!\x11MI TIENDA\n\x1d!\x00Av. Siempre Viva 742\n\x1ba\x00");
+    out.extend_from_slice(b"--------------------------------\n");
+    for i in 0..items {
+        out.extend_from_slice(
+            format!(
+                "{:>2} x Producto {:<10}\t{:>7}.{:02}\n",
+                i % 5 + 1,
+                i,
+                i * 13 % 997,
+                i % 100
+            )
+            .as_bytes(),
+        );
+    }
+    out.extend_from_slice(b"--------------------------------\n\x1bE\x01TOTAL\t1234.50\n\x1bE\x00");
+
+    // QR (GS ( k): tamaño de módulo, ECC, guardar datos e imprimir.
+    let qr = b"https://example.com/factura?id=000123456789&total=1234.50";
+    out.extend_from_slice(b"\x1ba\x01\x1d(k\x03\x001C\x06\x1d(k\x03\x001E\x31");
+    out.extend_from_slice(&[0x1D, 0x28, 0x6B]);
+    out.extend_from_slice(&(qr.len() as u16 + 3).to_le_bytes());
+    out.extend_from_slice(b"1P0");
+    out.extend_from_slice(qr);
+    out.extend_from_slice(b"\x1d(k\x03\x001Q0");
+
+    // CODE128 (GS k 73).
+    let code = b"{B000123456789";
+    out.extend_from_slice(&[0x1D, 0x6B, 73, code.len() as u8]);
+    out.extend_from_slice(code);
+    out.extend_from_slice(b"\n\n\n\x1dV\x01");
+    out
+}
+
+/// Varios tickets seguidos hasta pasar `min_len` bytes (captura larga o multi-MB).
+fn batch(min_len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(min_len + 16 * 1024);
+    let mut n = 0;
+    while out.len() < min_len {
+        out.extend_from_slice(&receipt(20 + n % 40));
+        n += 1;
+    }
+    out
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, data) in [
+        ("ticket_20_items", receipt(20)),
+        ("ticket_500_items", receipt(500)),
+        ("batch_4mb", batch(4 * 1024 * 1024)),
+    ] {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::new("parse_escpos", name), &data, |b, data| {
+            b.iter(|| parse_escpos_simple(black_box(data), CodePage::Cp437))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("parse_escpos_with_spans", name),
+            &data,
+            |b, data| b.iter(|| parse_escpos_with_spans(black_box(data), &CodePage::Cp437.into())),
+        );
+    }
+    group.finish();
+}
+
+/// La simulación vuelve a parsear el buffer revelado en cada tick: el costo total crece con
+/// el cuadrado del tamaño del job si cada re-parse es lineal.
+fn bench_simulation_reparse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulation");
+    group.sample_size(10);
+    for size in [64 * 1024, 256 * 1024] {
+        let data = batch(size);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("reparse_every_4kb", data.len()),
+            &data,
+            |b, data| {
+                b.iter(|| {
+                    let mut commands = 0;
+                    for end in (4096..=data.len()).step_by(4096) {
+                        commands += parse_escpos_incremental(
+                            black_box(&data[..end]),
+                            &CodePage::Cp437.into(),
+                        )
+                        .0
+                        .len();
+                    }
+                    commands
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_encoders(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    group.bench_function("code128_runs", |b| {
+        b.iter(|| encode_code128_runs(black_box(b"{B000123456789")))
+    });
+    group.bench_function("code128_bitmap", |b| {
+        b.iter(|| {
+            let (runs, _) = encode_code128_runs(black_box(b"{B000123456789")).unwrap();
+            runs_bitmap(&runs, true, 2, 160, 10)
+        })
+    });
+    for len in [60, 600] {
+        let data = vec![b'A'; len];
+        group.bench_with_input(BenchmarkId::new("qr_bitmap", len), &data, |b, data| {
+            b.iter(|| qr_bitmap(black_box(data), 49, 6, QrModel::Model2))
+        });
+    }
+    group.finish();
+}
+
+/// Job de varias páginas completo: parse y rasterizado de todas sus imágenes, QR y barcodes
+/// (lo que el preview hace antes de maquetar el texto con egui).
+fn bench_full_job(c: &mut Criterion) {
+    let data = batch(1024 * 1024);
+    let mut group = c.benchmark_group("full_job");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("parse_and_rasterize_1mb", |b| {
+        b.iter(|| {
+            let mut dots = 0usize;
+            for (_, command) in parse_escpos_simple(black_box(&data), CodePage::Cp437) {
+                let bitmap = match command {
+                    CommandType::Control(Control::RasterImage {
+                        width_bytes,
+                        height,
+                        data,
+                        ..
+                    }) => raster_bitmap(width_bytes, height, &data),
+                    CommandType::Control(Control::Qr {
+                        model,
+                        module_size,
+                        ecc,
+                        data,
+                    }) => qr_bitmap(&data, ecc, module_size, model).map(|(bitmap, _)| bitmap),
+                    CommandType::Control(Control::Barcode { m, data }) => encode_runs(m, &data)
+                        .and_then(|(runs, _)| runs_bitmap(&runs, true, 2, 160, 10)),
+                    _ => None,
+                };
+                dots += bitmap.map_or(0, |bitmap| bitmap.dots.len());
+            }
+            dots
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_parse,
+    bench_simulation_reparse,
+    bench_encoders,
+    bench_full_job
+);
+criterion_main!(benches);
@@ -1,21 +1,44 @@
-use crate::escpos::parse_escpos;
-use crate::hex_dump::pretty_hex;
+use crate::barcode::{self, ScanCheck};
+use crate::bitmap::{
+    bit_image_bitmap, qr_bitmap, raster_bitmap, runs_bitmap, scan_middle_row, Bitmap,
+};
+use crate::coverage::{coverage_report, Support};
+use crate::escpos::{
+    parse_escpos, parse_escpos_incremental, parse_escpos_simple, parse_escpos_with_spans,
+    ParseOptions, ParsedCommand,
+};
+use crate::export::{export_jobs, job_plain_text, sanitize_filename, ExportFormats, ExportJob};
+use crate::hex_dump::{
+    hex_bytes, hex_lines, hex_row, hex_row_count, parse_hex_dump, HEX_ROW_BYTES, HEX_ROW_WIDTHS,
+};
+use crate::hotkey::{parse_hotkey, GlobalHotkey, HotkeyEvent, DEFAULT_HOTKEY};
 use crate::model::{
-    Align, BarcodeHriPosition, CodePage, CommandType, Control, PaperWidth, PrinterState,
+    Align, BarcodeHriPosition, CodePage, CommandType, Control, PaperWidth, PrinterState, QrModel,
+};
+use crate::print_time::{elapsed_marks, format_secs, total_secs, PrintRate};
+use crate::prune::{PruneCandidate, PrunePolicy};
+use crate::receipt_meta::{extract_metadata_with, MetaPatterns};
+use crate::replay::{load_session, Replay, ReplayJob};
+use crate::settings::SettingsStore;
+use crate::snippets::{self, Snippet};
+use crate::tcp_capture::{
+    bind_retry_delay, first_free_port, send_test_job, CaptureLimits, TcpCapture, DEFAULT_PORT,
+    FALLBACK_PORTS,
 };
-use crate::tcp_capture::TcpCapture;
+use crate::ticket_image::{encode_png, Canvas};
 use crate::tray::SystemTray;
-use crate::window_control::WindowControl;
+use crate::webhook::{WebhookConfig, WebhookEvent, WebhookFormat, WebhookJob, WebhookSender};
+use crate::window_control::{ScreenCorner, WindowControl};
 use eframe::egui;
-use qrcode::types::Color;
-use qrcode::{EcLevel, QrCode};
 use rfd::FileDialog;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::mem;
-use std::path::Path;
-use std::time::{Duration, Instant};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum UiMode {
@@ -23,61 +46,594 @@ enum UiMode {
     Full,
 }
 
+/// Cómo se marca cada orden en tickets de cocina apilados (avance + corte parcial por orden).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OrderSeparatorStyle {
+    /// Solo el rótulo "— Orden N —".
+    Label,
+    /// Rótulo y fondo tenue en las órdenes pares.
+    Tint,
+}
+
+impl OrderSeparatorStyle {
+    const ALL: [OrderSeparatorStyle; 2] = [OrderSeparatorStyle::Label, OrderSeparatorStyle::Tint];
+
+    fn key(self) -> &'static str {
+        match self {
+            OrderSeparatorStyle::Label => "label",
+            OrderSeparatorStyle::Tint => "tint",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|style| style.key() == key)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            OrderSeparatorStyle::Label => "Solo número de orden",
+            OrderSeparatorStyle::Tint => "Número y fondo alternado",
+        }
+    }
+
+    /// Fondo de la orden `number`: tenue en las pares con `Tint`, ninguno en el resto.
+    fn tint(self, number: usize) -> Option<egui::Color32> {
+        (self == OrderSeparatorStyle::Tint && number.is_multiple_of(2))
+            .then(|| egui::Color32::from_rgba_unmultiplied(90, 120, 200, 18))
+    }
+}
+
 #[derive(Debug, Clone)]
 struct JobEntry {
     id: u64,
     label: String,
     created_at: Instant,
+    /// Hora de recepción (reloj del sistema) para exportar.
+    received_at: SystemTime,
+    /// Marcado con ★: no lo borran los límites del historial ni "Limpiar historial".
+    pinned: bool,
 
     full_bytes: Vec<u8>,
     display_bytes: Vec<u8>,
     parsed_commands: Vec<(PrinterState, CommandType)>,
+    /// Bytes de `display_bytes` que generó cada comando (paralelo a `parsed_commands`).
+    command_spans: Vec<Range<usize>>,
+    /// Cortes que cierran una orden de cocina (`kitchen_order_cuts`), ordenados. Se recalculan
+    /// con cada parseo para no recorrer los comandos en cada frame.
+    order_cuts: Vec<usize>,
+    /// Paradas de auto-tabla por renglón (`tab_table_stops`), calculadas con cada parseo.
+    table_stops: HashMap<usize, Vec<usize>>,
 
     sim_active: bool,
     sim_started_at: Option<Instant>,
     sim_sent: usize,
+    /// Modo ráfaga: fin (en bytes) de cada línea/comando visible de `full_bytes`.
+    sim_boundaries: Vec<usize>,
+    /// Modo ráfaga: momento en que se revela la próxima línea.
+    sim_next_burst_at: Option<Instant>,
+    /// Bytes anteriores a cada cambio del editor hex (para Deshacer). No vacío = modificado.
+    edit_undo: Vec<Vec<u8>>,
+    /// Primer comando de cada renglón cuyo texto cambió en el último re-parse por un cambio
+    /// de configuración. Ordenados, para buscarlos con `binary_search` al dibujar.
+    changed_lines: Vec<usize>,
+    /// Nota libre del usuario (p.ej. "el total sale mal"); se exporta y sobrevive a re-parseos.
+    note: String,
+    /// Ancho de papel propio del job (p.ej. la comandera de 80mm junto a la caja de 58mm).
+    /// `None` = el ancho global de la configuración.
+    paper_width: Option<PaperWidth>,
+}
+
+impl JobEntry {
+    /// Guarda el resultado de un parseo junto con lo que se deriva de él.
+    fn set_parsed(&mut self, (commands, spans): (Vec<ParsedCommand>, Vec<Range<usize>>)) {
+        self.order_cuts = EscPosViewer::kitchen_order_cuts(&commands);
+        self.table_stops = EscPosViewer::tab_table_stops(&commands);
+        self.parsed_commands = commands;
+        self.command_spans = spans;
+    }
+}
+
+/// Inspector de un elemento de un renglón al revés: rect sin girar, id, detalle y datos a copiar.
+type DeferredInspector = (egui::Rect, egui::Id, String, Option<String>);
+
+/// Texto del editor hex del job `job_id` (se conserva tal cual lo escribe el usuario).
+struct HexEdit {
+    job_id: u64,
+    text: String,
+    /// El texto actual no es hex válido: se muestra y los bytes quedan como estaban.
+    error: Option<String>,
+}
+
+/// Imagen asignada a un logo NV (`FS p n`): un archivo ESC/POS con el raster (`GS v 0`)
+/// que se guardó en la impresora.
+struct NvLogo {
+    path: PathBuf,
+    /// (bytes por fila, alto, datos) del raster, o por qué no se pudo leer.
+    image: Result<(u16, u16, Vec<u8>), String>,
+}
+
+impl NvLogo {
+    fn load(path: PathBuf) -> Self {
+        let image = fs::read(&path)
+            .map_err(|e| format!("No se pudo leer: {e}"))
+            .and_then(|data| {
+                Self::raster_from_job(&data)
+                    .ok_or_else(|| "El archivo no tiene una imagen GS v 0".to_string())
+            });
+        Self { path, image }
+    }
+
+    /// Primer raster `GS v 0` de un job (el logo tal como se mandaría a la impresora).
+    fn raster_from_job(data: &[u8]) -> Option<(u16, u16, Vec<u8>)> {
+        parse_escpos_simple(data, CodePage::Cp437)
+            .into_iter()
+            .find_map(|(_, cmd)| match cmd {
+                CommandType::Control(Control::RasterImage {
+                    width_bytes,
+                    height,
+                    data,
+                    ..
+                }) => Some((width_bytes, height, data)),
+                _ => None,
+            })
+    }
+
+    /// Factor (ancho, alto) de `FS p n m`: 1 doble ancho, 2 doble alto, 3 ambos (también en
+    /// ASCII, de '0' a '3').
+    fn scale(m: u8) -> (usize, usize) {
+        (1 + (m & 1) as usize, 1 + (m >> 1 & 1) as usize)
+    }
+}
+
+/// Raya con la que se reemplaza una línea separadora (`----`, `====`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SeparatorRule {
+    /// Raya simple de `dots` puntos de grosor.
+    Single { dots: f32 },
+    /// Doble raya (`=`).
+    Double,
+}
+
+/// Línea del ticket en curso (desde el último LF): el glifo más alto define cuánto avanza.
+#[derive(Debug, Default)]
+struct LineMetrics {
+    glyph_px: f32,
+}
+
+impl LineMetrics {
+    fn add_text(&mut self, state: &PrinterState) {
+        self.glyph_px = self.glyph_px.max(EscPosViewer::glyph_height_px(state));
+    }
+
+    /// Cierra la línea en el LF: avance = max(glifo más alto, interlineado). Una línea vacía
+    /// avanza con el tamaño vigente en el LF.
+    fn finish(&mut self, lf_state: &PrinterState, dots_to_pixels: f32) -> f32 {
+        let glyph_px = if self.glyph_px > 0.0 {
+            self.glyph_px
+        } else {
+            EscPosViewer::glyph_height_px(lf_state)
+        };
+        self.glyph_px = 0.0;
+        let line_spacing_px = lf_state.line_spacing.unwrap_or(30) as f32 * dots_to_pixels;
+        glyph_px.max(line_spacing_px)
+    }
+}
+
+/// Paso del recorrido de un ticket (`TicketWalk`).
+enum TicketStep<'a> {
+    /// Empieza un renglón en el comando `usize`.
+    LineStart(usize),
+    /// Un comando del job. `text` es el texto acumulado que el comando cierra: se dibuja antes
+    /// que él.
+    Command {
+        idx: usize,
+        state: &'a PrinterState,
+        command: &'a CommandType,
+        text: Option<(PrinterState, String)>,
+    },
+    /// Empieza la orden de cocina número `usize`: la primera antes de todo y cada una de las
+    /// demás después del corte que cierra la anterior.
+    Order(usize),
+    /// Termina el renglón, con el texto que quedó sin dibujar.
+    LineEnd(Option<(PrinterState, String)>),
+}
+
+/// Recorrido de los comandos de un job que comparten la vista previa y el PNG: orden de los
+/// renglones, texto acumulado por estilo (con `ESC $`, `ESC \` y los tabs ya resueltos) y
+/// cortes entre órdenes de cocina. Quien lo recorre solo dibuja.
+struct TicketWalk<'a> {
+    commands: &'a [ParsedCommand],
+    /// Comandos en el orden en que se dibujan y si cada uno cierra su renglón.
+    draw_order: std::vec::IntoIter<(usize, bool)>,
+    steps: VecDeque<TicketStep<'a>>,
+    /// Paradas de auto-tabla (`None` = desactivada).
+    table_stops: Option<&'a HashMap<usize, Vec<usize>>>,
+    tab_right_align: bool,
+    /// Relleno de los tabs: espacio, o NBSP con los espacios visibles.
+    tab_fill: &'static str,
+    order_cuts: &'a [usize],
+    order: usize,
+    /// Ticket al revés (opción o `ESC { 1` al comienzo).
+    flipped: bool,
+    pending: Option<(PrinterState, String)>,
+    at_line_start: bool,
+    /// Paradas de auto-tabla del renglón en curso y tabs ya usados.
+    line_stops: Option<&'a Vec<usize>>,
+    line_tabs: usize,
+    /// El renglón ya tiene texto (la posición de `ESC $` ya se aplicó).
+    line_has_text: bool,
+}
+
+impl<'a> TicketWalk<'a> {
+    fn take_text(&mut self) -> Option<(PrinterState, String)> {
+        self.pending.take().filter(|(_, text)| !text.is_empty())
+    }
+
+    /// Suma `command` al texto del renglón. Devuelve el texto que queda cerrado antes de él.
+    fn accumulate(
+        &mut self,
+        state: &PrinterState,
+        command: &CommandType,
+    ) -> Option<(PrinterState, String)> {
+        let control = match command {
+            CommandType::Text(text) => {
+                let first_text_of_line = !mem::replace(&mut self.line_has_text, true);
+                match &mut self.pending {
+                    Some((ps, buf)) => {
+                        if state.cursor_x != ps.cursor_x {
+                            if let Some(cursor_x) = state.cursor_x {
+                                EscPosViewer::pad_to_cursor(buf, cursor_x);
+                            }
+                        }
+                        if EscPosViewer::same_line_style(ps, state) {
+                            buf.push_str(text);
+                            return None;
+                        }
+                        return self
+                            .pending
+                            .replace((state.clone(), text.clone()))
+                            .filter(|(_, text)| !text.is_empty());
+                    }
+                    None => {
+                        // Primer texto del renglón tras ESC $ / ESC \.
+                        let mut buf = String::new();
+                        if let (true, Some(cursor_x)) = (first_text_of_line, state.cursor_x) {
+                            EscPosViewer::pad_to_cursor(&mut buf, cursor_x);
+                        }
+                        buf.push_str(text);
+                        self.pending = Some((state.clone(), buf));
+                        return None;
+                    }
+                }
+            }
+            CommandType::Control(control) => control,
+            CommandType::Unknown(_) => return None,
+        };
+        match control {
+            Control::Tab => {
+                let table_stop = self.line_stops.and_then(|stops| stops.get(self.line_tabs));
+                self.line_tabs += 1;
+                if let Some(&stop) = table_stop {
+                    // Auto-tabla: el campo termina en la columna del bloque (un campo vacío al
+                    // inicio también cuenta).
+                    let (_, text) = self
+                        .pending
+                        .get_or_insert_with(|| (state.clone(), String::new()));
+                    let spaces = stop.saturating_sub(text.chars().count()).max(1);
+                    text.push_str(&self.tab_fill.repeat(spaces));
+                } else if let Some((_, text)) = &mut self.pending {
+                    if self.tab_right_align {
+                        // El relleno depende del ancho del campo que sigue: se resuelve al
+                        // emitir la línea completa.
+                        text.push('\t');
+                    } else {
+                        // Siguiente parada fija, cada 8 columnas.
+                        let current_len = text.chars().count();
+                        let next_tab = (current_len / 8 + 1) * 8;
+                        text.push_str(&self.tab_fill.repeat(next_tab - current_len));
+                    }
+                }
+                None
+            }
+            Control::RelativePosition { offset } => {
+                if let Some((_, buf)) = &mut self.pending {
+                    EscPosViewer::shift_pending_text(buf, *offset);
+                }
+                None
+            }
+            // Todo lo que ocupa lugar propio en el papel (o lo marca) cierra el texto.
+            Control::Newline
+            | Control::FeedLines(_)
+            | Control::FeedDots(_)
+            | Control::FormFeed
+            | Control::Cut { .. }
+            | Control::RasterImage { .. }
+            | Control::BitImage { .. }
+            | Control::Qr { .. }
+            | Control::TwoDimBarcode { .. }
+            | Control::Barcode { .. }
+            | Control::NvLogo { .. }
+            | Control::OpenDrawer
+            | Control::Bell
+            | Control::Buzzer { .. } => self.take_text(),
+            // Cambios de estado: ya viajan en el PrinterState de cada comando.
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Iterator for TicketWalk<'a> {
+    type Item = TicketStep<'a>;
+
+    fn next(&mut self) -> Option<TicketStep<'a>> {
+        if let Some(step) = self.steps.pop_front() {
+            return Some(step);
+        }
+        let (idx, line_end) = self.draw_order.next()?;
+        let (state, command) = &self.commands[idx];
+        if mem::replace(&mut self.at_line_start, line_end) {
+            self.line_stops = self.table_stops.and_then(|stops| stops.get(&idx));
+            self.line_tabs = 0;
+            self.line_has_text = false;
+            self.steps.push_back(TicketStep::LineStart(idx));
+        }
+        let text = self.accumulate(state, command);
+        self.steps.push_back(TicketStep::Command {
+            idx,
+            state,
+            command,
+            text,
+        });
+        if self.order_cuts.binary_search(&idx).is_ok() {
+            self.order += 1;
+            self.steps.push_back(TicketStep::Order(self.order));
+        }
+        if line_end {
+            let text = self.take_text();
+            self.steps.push_back(TicketStep::LineEnd(text));
+        }
+        self.steps.pop_front()
+    }
+}
+
+/// Preferencias de render de texto (copiadas del visor en cada frame).
+#[derive(Debug, Clone, Copy)]
+struct TextRenderOptions {
+    use_thermal_font: bool,
+    /// Ajustar la fuente térmica a píxeles físicos enteros (evita texto borroso al escalar).
+    snap_thermal_font: bool,
+    /// Sombrear líneas de encabezado (negrita + subrayado) aunque no pidan inversión.
+    shade_header_lines: bool,
+    /// Dibujar como una raya las líneas hechas solo de `-`, `=`, `_`… a todo el ancho.
+    draw_separator_rules: bool,
+    /// Halo de sobrecalentamiento en negrita (parte de los efectos realistas).
+    heat_bleed: bool,
+    /// Marcar espacios (·) y tabs (→) en gris, como la vista de espacios de un editor.
+    show_whitespace: bool,
+    /// Color de la tinta según la densidad de impresión.
+    ink: egui::Color32,
 }
 
 pub struct EscPosViewer {
     jobs: Vec<JobEntry>,
     active_job_idx: Option<usize>,
     next_job_id: u64,
+    /// Último job mostrado en el preview (para detectar cambio de pestaña).
+    last_rendered_job_id: Option<u64>,
+    /// Navegación por cortes del job activo: corte mostrado (0 = primero) y corte al que
+    /// desplazar el preview en el próximo frame.
+    cut_nav_current: usize,
+    cut_nav_target: Option<usize>,
+    /// Vista congelada: los jobs nuevos se guardan pero no cambian el job activo.
+    freeze_view: bool,
+    /// Jobs recibidos mientras la vista estaba congelada.
+    frozen_new_jobs: usize,
+    /// Se cambió de job con el teclado: la pestaña activa toma el foco en el próximo frame.
+    focus_active_tab: bool,
 
     max_jobs: usize,
     auto_prune_by_age: bool,
     prune_after: Duration,
+    /// Jobs más recientes que se conservan siempre, aunque superen `prune_after`.
+    prune_keep_min: usize,
+    /// Pestañas con el job más reciente a la izquierda (se insertan al principio).
+    newest_first: bool,
+    /// Cargar al iniciar el último archivo abierto (si no se pasó uno por línea de comandos).
+    reopen_last_file: bool,
+    last_opened_file: Option<PathBuf>,
     auto_scroll_on_print: bool,
     paper_width: PaperWidth,
     last_paper_width: PaperWidth,
     did_apply_initial_window_size: bool,
     did_apply_initial_window_position: bool,
     show_debug_controls: bool,
+    /// Debug: espacios, tabs y saltos de línea visibles en el ticket (sin cambiar columnas).
+    show_whitespace: bool,
     show_debug_panels: bool,
+    /// Bytes por fila del volcado hex (8, 16 o 32).
+    hex_row_bytes: usize,
+    /// Vista dividida (modo Completo): bytes crudos por comando junto al render.
+    split_view: bool,
+    /// Log de comandos en una ventana flotante en vez del panel lateral.
+    log_detached: bool,
+    /// Inspector: `PrinterState` acumulado en el comando bajo el mouse, el seleccionado o el
+    /// último revelado por la simulación.
+    show_state_inspector: bool,
+    /// Grilla de celdas (columnas × interlineado) sobre el ticket; solo con "Debug comandos".
+    show_char_grid: bool,
+    /// Línea del Log bajo el mouse en el frame anterior (la consume el inspector).
+    log_hovered_cmd: Option<usize>,
+    /// Panel "Datos detectados": fecha, total y artículos reconocidos en el texto del ticket.
+    show_receipt_meta: bool,
+    /// Palabras de la línea del total (separadas por coma) y símbolo de moneda.
+    meta_total_keywords: String,
+    meta_currency: String,
+    /// Comando seleccionado en el Log o en la vista dividida (índice en `parsed_commands`).
+    selected_cmd: Option<usize>,
+    /// Pedidos de desplazamiento hacia `selected_cmd` (render y panel crudo), de un solo uso.
+    cmd_scroll_request: bool,
+    raw_scroll_request: bool,
     show_settings: bool,
+    show_export_all: bool,
+    export_formats: ExportFormats,
+    /// Resultado de la última exportación en lote.
+    export_status: Option<String>,
+    show_replay: bool,
+    /// Sesión que se está reproduciendo (jobs re-enviados con su cadencia original).
+    replay: Option<Replay>,
+    /// Multiplicador de velocidad de la reproducción (2.0 = el doble de rápido).
+    replay_speed: f32,
+    show_snippets: bool,
+    /// Biblioteca de snippets (se lee de `snippets.json` al abrir el editor la primera vez).
+    snippet_library: Option<Vec<Snippet>>,
+    /// Snippet de la biblioteca que se está editando.
+    snippet_editing: Option<usize>,
+    /// Snippets elegidos para armar el job, en orden, con los valores de sus parámetros.
+    snippet_composition: Vec<(Snippet, HashMap<String, String>)>,
+    /// Editor hex abierto en el panel de debug.
+    hex_edit: Option<HexEdit>,
     ui_mode: UiMode,
     last_ui_mode: UiMode,
+    /// Variante mínima del Preview: sin bordes de ventana, sin botones flotantes y con
+    /// márgenes reducidos (se sale con F1 o desde el menú contextual del ticket).
+    compact_mode: bool,
+    /// Último estado de decoraciones enviado a la ventana (para no repetir el comando).
+    decorations_applied: Option<bool>,
     codepage: CodePage,
+    /// Tabla que la impresora asume antes del primer `ESC t`. None = la del visor (`codepage`).
+    initial_codepage: Option<CodePage>,
+    /// Alineación del texto que llega antes del primer `ESC a` (POS que nunca lo mandan).
+    default_alignment: Align,
+    /// Aviso breve (codepage con Ctrl+E, resultado de pegar): texto y momento en que se mostró.
+    toast: Option<(String, Instant)>,
+    /// Ctrl+V seguía apretado en el frame anterior (se pega una vez por pulsación).
+    paste_keys_held: bool,
+    /// Animación del cajón portamonedas: momento en que la simulación envió un `ESC p`.
+    drawer_anim_started: Option<Instant>,
+    /// Resaltado de los renglones que cambiaron al re-parsear por un cambio de configuración.
+    reparse_flash_started: Option<Instant>,
     texture_cache: HashMap<u64, egui::TextureHandle>,
 
     tcp_capture: Option<TcpCapture>,
     tcp_last_error: Option<String>,
+    /// Puerto del listener (9100 salvo que esté ocupado y se haya elegido otro).
+    tcp_port: u16,
+    /// Intentos seguidos en que no se pudo escuchar (espacian los reintentos).
+    tcp_bind_failures: u32,
+    /// Próximo reintento mientras el puerto siga ocupado.
+    tcp_retry_at: Option<Instant>,
+    /// Puerto libre de `FALLBACK_PORTS` que se ofrece si el configurado sigue ocupado.
+    tcp_port_suggestion: Option<u16>,
+    /// Envío de prueba por TCP en curso (el resultado llega desde el hilo cliente).
+    tcp_test_rx: Option<Receiver<Result<(), String>>>,
+    /// Estado de la impresora virtual en la bienvenida (`None` = todavía sin consultar).
+    printer_installed: Option<bool>,
+    /// Consulta o instalación de la impresora en curso (PowerShell corre en otro hilo).
+    printer_probe_rx: Option<Receiver<Result<bool, String>>>,
+    /// Última consulta: se repite cada tanto por si se instaló o se quitó desde afuera.
+    printer_probed_at: Option<Instant>,
+    /// Resultado del último intento de instalación desde la bienvenida.
+    printer_setup_message: Option<String>,
     tcp_enabled: bool,
     ignore_noise_jobs: bool,
     ignore_noise_jobs_max_bytes: usize,
+    capture_limits: CaptureLimits,
+    /// Log de diagnóstico en archivo (rotativo, en la carpeta de configuración).
+    file_log_enabled: bool,
+    /// Reenvío de los jobs capturados por TCP a un endpoint HTTP (apagado por defecto).
+    webhook_enabled: bool,
+    webhook_url: String,
+    /// `Nombre: valor` o solo el valor de `Authorization`.
+    webhook_auth: String,
+    webhook_format: WebhookFormat,
+    webhook: Option<WebhookSender>,
+    webhook_sent: u64,
+    webhook_last_error: Option<String>,
 
     tray: Option<SystemTray>,
     tray_error: Option<String>,
     pending_hide_to_tray: bool,
     hidden_to_tray: bool,
+    /// Atajo de teclado global (en todo el sistema) que muestra u oculta la ventana.
+    global_hotkey_enabled: bool,
+    global_hotkey: String,
+    /// Lo que se está escribiendo en el campo del atajo; pasa a `global_hotkey` al salir del
+    /// campo o con Enter, si es válido.
+    hotkey_draft: String,
+    /// Por qué no se aplicó `hotkey_draft` (el atajo anterior sigue registrado).
+    hotkey_draft_error: Option<String>,
+    hotkey: Option<GlobalHotkey>,
+    /// Combinación con la que se intentó registrar por última vez (`None` = desactivado).
+    hotkey_applied: Option<String>,
+    hotkey_error: Option<String>,
+    /// Minutos sin jobs ni interacción antes de ocultar a bandeja (0 = desactivado).
+    idle_to_tray_mins: u32,
+    /// Al recibir un job: volver a mostrar la ventana (también si está en la bandeja).
+    show_on_job: bool,
+    /// Al mostrarla, traerla al frente quitándole el foco a la aplicación activa.
+    steal_focus_on_job: bool,
+    last_activity: Instant,
 
     window: WindowControl,
+    /// Monitor donde se acomoda el Preview (nombre de dispositivo). None = el actual.
+    preview_monitor: Option<String>,
+    /// Esquina donde se acomoda el Preview.
+    dock_corner: ScreenCorner,
+    /// Modo acoplado: la ventana vuelve sola a su esquina si se mueve.
+    docked_mode: bool,
+    dock_last_outer_pos: Option<egui::Pos2>,
+    dock_pos_changed_at: Instant,
+    dock_last_snap: Instant,
 
     simulate_printing: bool,
     sim_bytes_per_sec: usize,
 
+    /// Margen blanco a cada lado de los barcodes, en módulos.
+    barcode_quiet_zone: usize,
+    /// Leer cada barcode dibujado y marcarlo con ✅/⚠ según se pueda escanear.
+    barcode_scan_check: bool,
+    /// Imagen de cada logo NV (`FS p n`); sin asignar se dibuja un recuadro.
+    nv_logos: BTreeMap<u8, NvLogo>,
+    /// Número de logo elegido en Configuración para asignarle un archivo.
+    nv_logo_new_n: u8,
+    /// Revelar línea a línea con pausas (como un POS real) en vez de byte a byte.
+    sim_burst_mode: bool,
+    /// Tiempo estimado junto a cada corte y al final del ticket.
+    show_print_times: bool,
+    /// Estimar el tiempo por avance de papel (`printer_speed_mm_s`) en vez de por bytes/s.
+    print_time_by_paper: bool,
+    /// Velocidad de la impresora real (independiente de la velocidad de la simulación).
+    printer_speed_mm_s: u32,
+
     // Realistic thermal paper effects
     realistic_effects: bool,
     use_thermal_font: bool,
+    snap_thermal_font: bool,
+    shade_header_lines: bool,
+    /// Separadores de caracteres repetidos (`----`, `====`) dibujados como rayas.
+    draw_separator_rules: bool,
+    /// El texto después de un tab termina en la parada (o en el borde) en vez de empezar en ella.
+    tab_right_align: bool,
+    /// Auto-tabla: bloques de líneas con la misma cantidad de tabs se alinean en columnas
+    /// según el campo más ancho de cada una (como `column -t`).
+    tab_auto_table: bool,
+    /// El contenido más ancho que el papel (imágenes, barcodes) no se achica: scroll horizontal.
+    overflow_scroll: bool,
+    /// Ticket entero girado 180° (instalaciones que cortan el papel desde arriba).
+    flip_ticket: bool,
+    /// Tickets de cocina: rótulo "— Orden N —" después de cada avance + corte parcial.
+    kitchen_orders: bool,
+    order_separator: OrderSeparatorStyle,
+    /// Densidad de impresión (0 = claro, 100 = oscuro): tono del texto y umbral de las imágenes.
+    print_density: u8,
+    /// Píxeles por punto de la impresora al exportar el ticket como PNG.
+    png_scale: u8,
+
+    /// Última versión guardada de las preferencias (para escribir solo si cambian).
+    saved_settings: SettingsStore,
+    /// Preferencias cambiadas que todavía no se escribieron y desde cuándo están así: se
+    /// guardan cuando dejan de cambiar (no en cada paso de un slider).
+    pending_settings: Option<(SettingsStore, Instant)>,
 }
 
 impl Default for EscPosViewer {
@@ -86,45 +642,518 @@ impl Default for EscPosViewer {
             jobs: Vec::new(),
             active_job_idx: None,
             next_job_id: 1,
+            last_rendered_job_id: None,
+            cut_nav_current: 0,
+            cut_nav_target: None,
+            freeze_view: false,
+            frozen_new_jobs: 0,
+            focus_active_tab: false,
 
             max_jobs: 25,
             auto_prune_by_age: false,
             prune_after: Duration::from_secs(60 * 60 * 2),
+            prune_keep_min: 10,
+            newest_first: false,
+            reopen_last_file: false,
+            last_opened_file: None,
             auto_scroll_on_print: true,
             paper_width: PaperWidth::W58mm,
             last_paper_width: PaperWidth::W58mm,
             did_apply_initial_window_size: false,
             did_apply_initial_window_position: false,
             show_debug_controls: false,
+            show_whitespace: false,
             show_debug_panels: false,
+            hex_row_bytes: HEX_ROW_BYTES,
+            split_view: false,
+            log_detached: false,
+            show_state_inspector: false,
+            show_char_grid: false,
+            log_hovered_cmd: None,
+            show_receipt_meta: false,
+            meta_total_keywords: MetaPatterns::default().total_keywords.join(", "),
+            meta_currency: MetaPatterns::default().currency,
+            selected_cmd: None,
+            cmd_scroll_request: false,
+            raw_scroll_request: false,
             show_settings: false,
+            show_export_all: false,
+            export_formats: ExportFormats::default(),
+            export_status: None,
+            show_replay: false,
+            replay: None,
+            replay_speed: 1.0,
+            show_snippets: false,
+            snippet_library: None,
+            snippet_editing: None,
+            snippet_composition: Vec::new(),
+            hex_edit: None,
             ui_mode: UiMode::Preview,
             last_ui_mode: UiMode::Preview,
+            compact_mode: false,
+            decorations_applied: None,
             codepage: CodePage::Utf8Lossy,
+            initial_codepage: None,
+            default_alignment: Align::Left,
+            toast: None,
+            paste_keys_held: false,
+            drawer_anim_started: None,
+            reparse_flash_started: None,
             texture_cache: HashMap::new(),
             tcp_capture: None,
             tcp_last_error: None,
+            tcp_port: DEFAULT_PORT,
+            tcp_bind_failures: 0,
+            tcp_retry_at: None,
+            tcp_port_suggestion: None,
+            tcp_test_rx: None,
+            printer_installed: None,
+            printer_probe_rx: None,
+            printer_probed_at: None,
+            printer_setup_message: None,
             tcp_enabled: true,
             ignore_noise_jobs: true,
             ignore_noise_jobs_max_bytes: 32,
+            capture_limits: CaptureLimits::default(),
+            file_log_enabled: false,
+            webhook_enabled: false,
+            webhook_url: String::new(),
+            webhook_auth: String::new(),
+            webhook_format: WebhookFormat::Json,
+            webhook: None,
+            webhook_sent: 0,
+            webhook_last_error: None,
 
             tray: None,
             tray_error: None,
             pending_hide_to_tray: false,
             hidden_to_tray: false,
+            global_hotkey_enabled: false,
+            global_hotkey: DEFAULT_HOTKEY.to_string(),
+            hotkey_draft: DEFAULT_HOTKEY.to_string(),
+            hotkey_draft_error: None,
+            hotkey: None,
+            hotkey_applied: None,
+            hotkey_error: None,
+            idle_to_tray_mins: 0,
+            show_on_job: true,
+            steal_focus_on_job: true,
+            last_activity: Instant::now(),
 
             window: WindowControl::default(),
+            preview_monitor: None,
+            dock_corner: ScreenCorner::BottomRight,
+            docked_mode: false,
+            dock_last_outer_pos: None,
+            dock_pos_changed_at: Instant::now(),
+            dock_last_snap: Instant::now(),
 
             simulate_printing: true,
             sim_bytes_per_sec: 1_000,
+            sim_burst_mode: false,
+            show_print_times: true,
+            print_time_by_paper: false,
+            printer_speed_mm_s: 200,
+            barcode_quiet_zone: 10,
+            barcode_scan_check: true,
+            nv_logos: BTreeMap::new(),
+            nv_logo_new_n: 1,
 
             realistic_effects: true,
             use_thermal_font: true,
+            snap_thermal_font: true,
+            shade_header_lines: false,
+            draw_separator_rules: false,
+            tab_right_align: false,
+            tab_auto_table: false,
+            overflow_scroll: false,
+            flip_ticket: false,
+            kitchen_orders: false,
+            order_separator: OrderSeparatorStyle::Tint,
+            print_density: 100,
+            png_scale: 2,
+
+            saved_settings: SettingsStore::default(),
+            pending_settings: None,
         }
     }
 }
 
 impl EscPosViewer {
+    /// Crea el visor aplicando las preferencias guardadas.
+    pub fn new() -> Self {
+        let mut viewer = Self::default();
+        let store = SettingsStore::load();
+        viewer.apply_settings(&store);
+        viewer.saved_settings = viewer.settings_snapshot();
+        viewer.sync_webhook();
+        viewer
+    }
+
+    /// Visor con un job inicial: el archivo pasado por línea de comandos o, si no hay y está
+    /// activado "Reabrir último archivo", el último abierto (si todavía existe).
+    pub fn with_initial_file(arg: Option<PathBuf>) -> Self {
+        let mut viewer = Self::new();
+        viewer.load_initial_file(arg);
+        viewer
+    }
+
+    fn load_initial_file(&mut self, arg: Option<PathBuf>) {
+        let path = arg.or_else(|| {
+            self.last_opened_file
+                .clone()
+                .filter(|p| self.reopen_last_file && p.is_file())
+        });
+        if let Some(path) = path {
+            self.try_load_path(&path);
+        }
+    }
+
+    fn align_key(align: Align) -> &'static str {
+        match align {
+            Align::Left => "left",
+            Align::Center => "center",
+            Align::Right => "right",
+        }
+    }
+
+    fn corner_key(corner: ScreenCorner) -> &'static str {
+        match corner {
+            ScreenCorner::TopLeft => "top_left",
+            ScreenCorner::TopRight => "top_right",
+            ScreenCorner::BottomLeft => "bottom_left",
+            ScreenCorner::BottomRight => "bottom_right",
+        }
+    }
+
+    fn apply_settings(&mut self, store: &SettingsStore) {
+        self.tcp_enabled = store.get_bool("tcp_enabled", self.tcp_enabled);
+        let tcp_port: u16 = store.get_parsed("tcp_port", self.tcp_port);
+        if tcp_port != 0 {
+            self.tcp_port = tcp_port;
+        }
+        self.ignore_noise_jobs = store.get_bool("ignore_noise_jobs", self.ignore_noise_jobs);
+        self.ignore_noise_jobs_max_bytes = store.get_parsed(
+            "ignore_noise_jobs_max_bytes",
+            self.ignore_noise_jobs_max_bytes,
+        );
+        self.capture_limits.max_connections_per_sec = store.get_parsed(
+            "tcp_max_connections_per_sec",
+            self.capture_limits.max_connections_per_sec,
+        );
+        self.capture_limits.max_job_bytes = store
+            .get_parsed("tcp_max_job_kb", self.capture_limits.max_job_bytes / 1024)
+            .max(1)
+            * 1024;
+        let timeout_ms = store.get_parsed(
+            "tcp_read_timeout_ms",
+            self.capture_limits.read_timeout.as_millis() as u64,
+        );
+        self.capture_limits.read_timeout = Duration::from_millis(timeout_ms.clamp(500, 10_000));
+        self.capture_limits.allow_remote =
+            store.get_bool("tcp_allow_remote", self.capture_limits.allow_remote);
+        self.webhook_enabled = store.get_bool("webhook_enabled", self.webhook_enabled);
+        if let Some(url) = store.get_str("webhook_url") {
+            self.webhook_url = url.to_string();
+        }
+        if let Some(auth) = store.get_str("webhook_auth") {
+            self.webhook_auth = auth.to_string();
+        }
+        if let Some(format) = store
+            .get_str("webhook_format")
+            .and_then(WebhookFormat::from_key)
+        {
+            self.webhook_format = format;
+        }
+        self.file_log_enabled = store.get_bool("file_log_enabled", self.file_log_enabled);
+        crate::diag_log::set_enabled(self.file_log_enabled);
+
+        self.simulate_printing = store.get_bool("simulate_printing", self.simulate_printing);
+        self.sim_bytes_per_sec = store.get_parsed("sim_bytes_per_sec", self.sim_bytes_per_sec);
+        self.sim_burst_mode = store.get_bool("sim_burst_mode", self.sim_burst_mode);
+        self.show_print_times = store.get_bool("show_print_times", self.show_print_times);
+        self.print_time_by_paper = store.get_bool("print_time_by_paper", self.print_time_by_paper);
+        self.printer_speed_mm_s = store
+            .get_parsed("printer_speed_mm_s", self.printer_speed_mm_s)
+            .clamp(20, 500);
+        self.barcode_quiet_zone = store
+            .get_parsed("barcode_quiet_zone", self.barcode_quiet_zone)
+            .min(40);
+        self.barcode_scan_check = store.get_bool("barcode_scan_check", self.barcode_scan_check);
+        for n in 1..=u8::MAX {
+            if let Some(path) = store
+                .get_str(&format!("nv_logo_{n}"))
+                .filter(|p| !p.is_empty())
+            {
+                self.nv_logos.insert(n, NvLogo::load(PathBuf::from(path)));
+            }
+        }
+
+        self.paper_width = match store.get_str("paper_width") {
+            Some("80") => PaperWidth::W80mm,
+            Some("58") => PaperWidth::W58mm,
+            _ => self.paper_width,
+        };
+        self.last_paper_width = self.paper_width;
+        if let Some(cp) = store.get_str("codepage").and_then(CodePage::from_key) {
+            self.codepage = cp;
+        }
+        self.initial_codepage = store
+            .get_str("initial_codepage")
+            .and_then(CodePage::from_key);
+        if let Some(key) = store.get_str("default_alignment") {
+            for align in [Align::Left, Align::Center, Align::Right] {
+                if Self::align_key(align) == key {
+                    self.default_alignment = align;
+                }
+            }
+        }
+
+        self.auto_scroll_on_print =
+            store.get_bool("auto_scroll_on_print", self.auto_scroll_on_print);
+        self.max_jobs = store.get_parsed("max_jobs", self.max_jobs).max(1);
+        self.auto_prune_by_age = store.get_bool("auto_prune_by_age", self.auto_prune_by_age);
+        let prune_mins = store.get_parsed("prune_after_mins", self.prune_after.as_secs() / 60);
+        self.prune_after = Duration::from_secs(prune_mins.max(1) * 60);
+        self.prune_keep_min = store.get_parsed("prune_keep_min", self.prune_keep_min);
+        self.newest_first = store.get_bool("newest_first", self.newest_first);
+        self.replay_speed = store
+            .get_parsed("replay_speed", self.replay_speed)
+            .clamp(0.25, 10.0);
+        self.reopen_last_file = store.get_bool("reopen_last_file", self.reopen_last_file);
+        self.last_opened_file = store
+            .get_str("last_opened_file")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from);
+
+        self.realistic_effects = store.get_bool("realistic_effects", self.realistic_effects);
+        self.use_thermal_font = store.get_bool("use_thermal_font", self.use_thermal_font);
+        self.snap_thermal_font = store.get_bool("snap_thermal_font", self.snap_thermal_font);
+        self.shade_header_lines = store.get_bool("shade_header_lines", self.shade_header_lines);
+        self.draw_separator_rules =
+            store.get_bool("draw_separator_rules", self.draw_separator_rules);
+        self.tab_right_align = store.get_bool("tab_right_align", self.tab_right_align);
+        self.tab_auto_table = store.get_bool("tab_auto_table", self.tab_auto_table);
+        self.overflow_scroll = store.get_bool("overflow_scroll", self.overflow_scroll);
+        self.flip_ticket = store.get_bool("flip_ticket", self.flip_ticket);
+        self.kitchen_orders = store.get_bool("kitchen_orders", self.kitchen_orders);
+        if let Some(style) = store
+            .get_str("order_separator")
+            .and_then(OrderSeparatorStyle::from_key)
+        {
+            self.order_separator = style;
+        }
+        self.print_density = store
+            .get_parsed("print_density", self.print_density)
+            .min(100);
+        self.png_scale = store.get_parsed("png_scale", self.png_scale).clamp(1, 4);
+        self.show_debug_panels = store.get_bool("show_debug_panels", self.show_debug_panels);
+        let hex_row_bytes = store.get_parsed("hex_row_bytes", self.hex_row_bytes);
+        if HEX_ROW_WIDTHS.contains(&hex_row_bytes) {
+            self.hex_row_bytes = hex_row_bytes;
+        }
+        self.split_view = store.get_bool("split_view", self.split_view);
+        self.log_detached = store.get_bool("log_detached", self.log_detached);
+        self.show_state_inspector =
+            store.get_bool("show_state_inspector", self.show_state_inspector);
+        self.show_char_grid = store.get_bool("show_char_grid", self.show_char_grid);
+        self.show_receipt_meta = store.get_bool("show_receipt_meta", self.show_receipt_meta);
+        if let Some(keywords) = store.get_str("meta_total_keywords") {
+            self.meta_total_keywords = keywords.to_string();
+        }
+        if let Some(currency) = store.get_str("meta_currency") {
+            self.meta_currency = currency.to_string();
+        }
+        self.show_debug_controls = store.get_bool("show_debug_controls", self.show_debug_controls);
+        self.show_whitespace = store.get_bool("show_whitespace", self.show_whitespace);
+
+        self.preview_monitor = store
+            .get_str("preview_monitor")
+            .filter(|m| !m.is_empty())
+            .map(|m| m.to_string());
+        if let Some(key) = store.get_str("dock_corner") {
+            for corner in [
+                ScreenCorner::TopLeft,
+                ScreenCorner::TopRight,
+                ScreenCorner::BottomLeft,
+                ScreenCorner::BottomRight,
+            ] {
+                if Self::corner_key(corner) == key {
+                    self.dock_corner = corner;
+                }
+            }
+        }
+        self.docked_mode = store.get_bool("docked_mode", self.docked_mode);
+        self.compact_mode = store.get_bool("compact_mode", self.compact_mode);
+        self.idle_to_tray_mins = store.get_parsed("idle_to_tray_mins", self.idle_to_tray_mins);
+        self.show_on_job = store.get_bool("show_on_job", self.show_on_job);
+        self.steal_focus_on_job = store.get_bool("steal_focus_on_job", self.steal_focus_on_job);
+        self.global_hotkey_enabled =
+            store.get_bool("global_hotkey_enabled", self.global_hotkey_enabled);
+        if let Some(hotkey) = store.get_str("global_hotkey").filter(|h| !h.is_empty()) {
+            self.global_hotkey = hotkey.to_string();
+            self.hotkey_draft = hotkey.to_string();
+        }
+    }
+
+    fn settings_snapshot(&self) -> SettingsStore {
+        let mut store = SettingsStore::default();
+        store.set("tcp_enabled", self.tcp_enabled);
+        store.set("tcp_port", self.tcp_port);
+        store.set("ignore_noise_jobs", self.ignore_noise_jobs);
+        store.set(
+            "ignore_noise_jobs_max_bytes",
+            self.ignore_noise_jobs_max_bytes,
+        );
+        store.set(
+            "tcp_max_connections_per_sec",
+            self.capture_limits.max_connections_per_sec,
+        );
+        store.set("tcp_max_job_kb", self.capture_limits.max_job_bytes / 1024);
+        store.set(
+            "tcp_read_timeout_ms",
+            self.capture_limits.read_timeout.as_millis(),
+        );
+        store.set("tcp_allow_remote", self.capture_limits.allow_remote);
+        store.set("file_log_enabled", self.file_log_enabled);
+        store.set("webhook_enabled", self.webhook_enabled);
+        store.set("webhook_url", &self.webhook_url);
+        store.set("webhook_auth", &self.webhook_auth);
+        store.set("webhook_format", self.webhook_format.key());
+
+        store.set("simulate_printing", self.simulate_printing);
+        store.set("sim_bytes_per_sec", self.sim_bytes_per_sec);
+        store.set("sim_burst_mode", self.sim_burst_mode);
+        store.set("show_print_times", self.show_print_times);
+        store.set("print_time_by_paper", self.print_time_by_paper);
+        store.set("printer_speed_mm_s", self.printer_speed_mm_s);
+        store.set("barcode_quiet_zone", self.barcode_quiet_zone);
+        store.set("barcode_scan_check", self.barcode_scan_check);
+        for (n, logo) in &self.nv_logos {
+            store.set(&format!("nv_logo_{n}"), logo.path.display());
+        }
+
+        store.set(
+            "paper_width",
+            match self.paper_width {
+                PaperWidth::W58mm => "58",
+                PaperWidth::W80mm => "80",
+            },
+        );
+        store.set("codepage", self.codepage.key());
+        store.set(
+            "initial_codepage",
+            self.initial_codepage.map_or("", CodePage::key),
+        );
+        store.set("default_alignment", Self::align_key(self.default_alignment));
+
+        store.set("auto_scroll_on_print", self.auto_scroll_on_print);
+        store.set("max_jobs", self.max_jobs);
+        store.set("auto_prune_by_age", self.auto_prune_by_age);
+        store.set("prune_after_mins", self.prune_after.as_secs() / 60);
+        store.set("prune_keep_min", self.prune_keep_min);
+        store.set("newest_first", self.newest_first);
+        store.set("replay_speed", self.replay_speed);
+        store.set("reopen_last_file", self.reopen_last_file);
+        store.set(
+            "last_opened_file",
+            self.last_opened_file
+                .as_deref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+        );
+
+        store.set("realistic_effects", self.realistic_effects);
+        store.set("use_thermal_font", self.use_thermal_font);
+        store.set("snap_thermal_font", self.snap_thermal_font);
+        store.set("shade_header_lines", self.shade_header_lines);
+        store.set("draw_separator_rules", self.draw_separator_rules);
+        store.set("tab_right_align", self.tab_right_align);
+        store.set("tab_auto_table", self.tab_auto_table);
+        store.set("overflow_scroll", self.overflow_scroll);
+        store.set("flip_ticket", self.flip_ticket);
+        store.set("kitchen_orders", self.kitchen_orders);
+        store.set("order_separator", self.order_separator.key());
+        store.set("print_density", self.print_density);
+        store.set("png_scale", self.png_scale);
+        store.set("show_debug_panels", self.show_debug_panels);
+        store.set("hex_row_bytes", self.hex_row_bytes);
+        store.set("split_view", self.split_view);
+        store.set("log_detached", self.log_detached);
+        store.set("show_state_inspector", self.show_state_inspector);
+        store.set("show_char_grid", self.show_char_grid);
+        store.set("show_receipt_meta", self.show_receipt_meta);
+        store.set("meta_total_keywords", &self.meta_total_keywords);
+        store.set("meta_currency", &self.meta_currency);
+        store.set("show_debug_controls", self.show_debug_controls);
+        store.set("show_whitespace", self.show_whitespace);
+
+        store.set(
+            "preview_monitor",
+            self.preview_monitor.as_deref().unwrap_or(""),
+        );
+        store.set("dock_corner", Self::corner_key(self.dock_corner));
+        store.set("docked_mode", self.docked_mode);
+        store.set("compact_mode", self.compact_mode);
+        store.set("idle_to_tray_mins", self.idle_to_tray_mins);
+        store.set("show_on_job", self.show_on_job);
+        store.set("steal_focus_on_job", self.steal_focus_on_job);
+        store.set("global_hotkey_enabled", self.global_hotkey_enabled);
+        store.set("global_hotkey", &self.global_hotkey);
+        store
+    }
+
+    /// Guarda las preferencias si cambiaron desde la última escritura, una vez que llevan
+    /// `SETTINGS_SAVE_DELAY` sin cambiar y no se está arrastrando nada (sliders).
+    fn persist_settings_if_changed(&mut self, ctx: &egui::Context) {
+        const SETTINGS_SAVE_DELAY: Duration = Duration::from_millis(750);
+
+        let snapshot = self.settings_snapshot();
+        if snapshot == self.saved_settings {
+            self.pending_settings = None;
+            return;
+        }
+        let settled = match &self.pending_settings {
+            Some((pending, since)) if *pending == snapshot => {
+                since.elapsed() >= SETTINGS_SAVE_DELAY && !ctx.input(|i| i.pointer.any_down())
+            }
+            _ => {
+                self.pending_settings = Some((snapshot.clone(), Instant::now()));
+                false
+            }
+        };
+        if settled {
+            let _ = snapshot.save();
+            self.saved_settings = snapshot;
+            self.pending_settings = None;
+        } else {
+            ctx.request_repaint_after(SETTINGS_SAVE_DELAY);
+        }
+    }
+
+    /// Oculta a la bandeja tras N minutos sin jobs nuevos ni interacción del usuario.
+    fn tick_idle_to_tray(&mut self, ctx: &egui::Context) {
+        let user_active = ctx.input(|i| !i.events.is_empty() || i.pointer.is_moving());
+        if user_active {
+            self.last_activity = Instant::now();
+            // Si el usuario interactúa, la ventana está visible (p.ej. reabierta desde el tray).
+            self.hidden_to_tray = false;
+        }
+
+        if self.idle_to_tray_mins == 0 || self.tray.is_none() || self.hidden_to_tray {
+            return;
+        }
+
+        let timeout = Duration::from_secs(self.idle_to_tray_mins as u64 * 60);
+        let idle = self.last_activity.elapsed();
+        if idle >= timeout {
+            self.pending_hide_to_tray = true;
+        } else {
+            // Despertar al vencer el plazo aunque no haya eventos.
+            ctx.request_repaint_after(timeout - idle);
+        }
+    }
+
     fn should_ignore_tcp_job(&self, bytes: &[u8]) -> bool {
         if !self.ignore_noise_jobs {
             return false;
@@ -140,7 +1169,7 @@ impl EscPosViewer {
 
         // Heurística: si el job no produce salida visible (texto/imagen/qr/barcode/corte), lo ignoramos.
         // Esto evita tabs "fantasma" de 10-20 bytes que algunos POS envían como consulta de estado.
-        let parsed = parse_escpos(bytes, self.codepage);
+        let parsed = parse_escpos(bytes, &self.parse_options());
         for (_state, cmd) in parsed {
             match cmd {
                 CommandType::Text(t) => {
@@ -150,9 +1179,11 @@ impl EscPosViewer {
                 }
                 CommandType::Control(control) => match control {
                     Control::RasterImage { .. }
+                    | Control::NvLogo { .. }
                     | Control::Qr { .. }
+                    | Control::TwoDimBarcode { .. }
                     | Control::Barcode { .. }
-                    | Control::Cut => {
+                    | Control::Cut { .. } => {
                         return false;
                     }
                     _ => {}
@@ -176,12 +1207,67 @@ impl EscPosViewer {
         format!("{}h", hours)
     }
 
+    /// Nombre de la pestaña para lectores de pantalla: lo mismo que el texto visible, con
+    /// palabras en lugar de los íconos.
+    fn job_tab_accessible_name(job: &JobEntry, age: Duration) -> String {
+        let mut name = format!("Job #{} {}", job.id, job.label);
+        if job.pinned {
+            name.push_str(", fijado");
+        }
+        if !job.edit_undo.is_empty() {
+            name.push_str(", editado");
+        }
+        if !job.note.is_empty() {
+            name.push_str(&format!(", nota: {}", job.note));
+        }
+        if let Some(width) = job.paper_width {
+            name.push_str(&format!(", papel de {}", width.label()));
+        }
+        name.push_str(&format!(
+            ", hace {}, {} bytes",
+            Self::format_age_short(age),
+            job.full_bytes.len()
+        ));
+        name
+    }
+
+    /// Nombre para lectores de pantalla (AccessKit) de un botón cuyo texto es solo un ícono.
+    fn accessible_name(response: egui::Response, name: &str) -> egui::Response {
+        let enabled = response.enabled();
+        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, enabled, name));
+        response
+    }
+
+    /// Ctrl+RePág / Ctrl+AvPág: pestaña anterior o siguiente (sin dar la vuelta).
+    fn select_adjacent_job(&mut self, forward: bool) {
+        let Some(current) = self.active_job_idx else {
+            self.active_job_idx = self.newest_job_idx();
+            self.focus_active_tab = self.active_job_idx.is_some();
+            return;
+        };
+        let next = if forward {
+            (current + 1).min(self.jobs.len().saturating_sub(1))
+        } else {
+            current.saturating_sub(1)
+        };
+        if next != current {
+            self.active_job_idx = Some(next);
+            self.focus_active_tab = true;
+        }
+    }
+
     fn ui_job_tabs(&mut self, ui: &mut egui::Ui) {
         if self.jobs.is_empty() {
             return;
         }
 
         let mut to_close: Option<usize> = None;
+        let mut to_toggle_pin: Option<usize> = None;
+        // (id del job arrastrado, índice de la pestaña donde se soltó)
+        let mut to_move: Option<(u64, usize)> = None;
+        let mut to_merge: Option<usize> = None;
+        let mut to_set_width: Option<(usize, Option<PaperWidth>)> = None;
+        let job_count = self.jobs.len();
         ui.separator();
         egui::ScrollArea::horizontal()
             .id_salt("job_tabs_scroll")
@@ -191,6 +1277,21 @@ impl EscPosViewer {
                     // Más espacio entre pestañas (antes quedaban muy pegadas).
                     ui.spacing_mut().item_spacing.x = 10.0;
 
+                    let mut frozen = self.freeze_view;
+                    let freeze_label = if self.frozen_new_jobs > 0 {
+                        format!("❄ Congelar vista ({} nuevos)", self.frozen_new_jobs)
+                    } else {
+                        "❄ Congelar vista".to_string()
+                    };
+                    if ui
+                        .toggle_value(&mut frozen, freeze_label)
+                        .on_hover_text("Sigue capturando, pero no cambia el job activo ni trae la ventana al frente")
+                        .changed()
+                    {
+                        self.set_freeze_view(frozen);
+                    }
+                    ui.separator();
+
                     let now = Instant::now();
                     for (idx, job) in self.jobs.iter().enumerate() {
                         let selected = self.active_job_idx == Some(idx);
@@ -203,19 +1304,96 @@ impl EscPosViewer {
                         }
 
                         let tab_text = format!(
-                            "#{} {} ({} · {}b)",
+                            "{}{}{}#{} {} ({} · {}b{})",
+                            if job.pinned { "★ " } else { "" },
+                            if job.edit_undo.is_empty() { "" } else { "✏ " },
+                            if job.note.is_empty() { "" } else { "📝 " },
                             job.id,
                             title,
                             Self::format_age_short(age),
-                            job.full_bytes.len()
+                            job.full_bytes.len(),
+                            job.paper_width.map_or(String::new(), |w| format!(" · {}", w.label()))
                         );
 
                         let tab_btn = egui::Button::new(tab_text)
                             .selected(selected)
                             .min_size(egui::vec2(0.0, 24.0));
-                        if ui.add(tab_btn).clicked() {
+
+                        // Arrastrar una pestaña sobre otra la reubica en esa posición.
+                        let drag_id = egui::Id::new(("job_tab_drag", job.id));
+                        let drag = ui.dnd_drag_source(drag_id, job.id, |ui| ui.add(tab_btn));
+                        let tab_name = Self::job_tab_accessible_name(job, age);
+                        drag.inner.widget_info(|| {
+                            egui::WidgetInfo::selected(
+                                egui::WidgetType::Button,
+                                true,
+                                selected,
+                                &tab_name,
+                            )
+                        });
+                        if selected && std::mem::take(&mut self.focus_active_tab) {
+                            drag.inner.request_focus();
+                        }
+                        if drag.inner.gained_focus() {
+                            drag.inner.scroll_to_me(None);
+                        }
+                        if drag.inner.clicked() {
                             self.active_job_idx = Some(idx);
                         }
+                        if !job.note.is_empty() {
+                            drag.inner.clone().on_hover_text(format!("📝 {}", job.note));
+                        }
+                        drag.inner.context_menu(|ui| {
+                            if ui
+                                .add_enabled(idx + 1 < job_count, egui::Button::new("🔗 Unir con siguiente"))
+                                .on_hover_text("Concatena este job con el de la pestaña siguiente (p.ej. un ticket cortado por el timeout)")
+                                .clicked()
+                            {
+                                to_merge = Some(idx);
+                                ui.close_menu();
+                            }
+                            ui.menu_button("📏 Ancho de papel", |ui| {
+                                let global = format!("Global ({})", self.paper_width.label());
+                                for (width, text) in [
+                                    (None, global.as_str()),
+                                    (Some(PaperWidth::W58mm), "58mm"),
+                                    (Some(PaperWidth::W80mm), "80mm"),
+                                ] {
+                                    if ui.radio(job.paper_width == width, text).clicked() {
+                                        to_set_width = Some((idx, width));
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                        });
+                        // Soltar acá deja el job en esta posición: delante de la pestaña si viene
+                        // de la derecha, detrás si viene de la izquierda (ver `move_job`).
+                        let drop_after = drag.response.dnd_hover_payload::<u64>().map(|dragged| {
+                            self.jobs
+                                .iter()
+                                .position(|j| j.id == *dragged)
+                                .is_some_and(|from| from < idx)
+                        });
+                        if let Some(dragged_id) = drag.response.dnd_release_payload::<u64>() {
+                            if *dragged_id != job.id {
+                                to_move = Some((*dragged_id, idx));
+                            }
+                        }
+
+                        let pin_btn = egui::Button::new(if job.pinned { "★" } else { "☆" })
+                            .frame(false)
+                            .min_size(egui::vec2(16.0, 24.0));
+                        let pin_hint = if job.pinned {
+                            "Quitar de fijados"
+                        } else {
+                            "Fijar (no se borra al limpiar el historial)"
+                        };
+                        let pin = ui.add(pin_btn).on_hover_text(pin_hint);
+                        let pin_name = format!("{pin_hint}: job #{}", job.id);
+                        let pin = Self::accessible_name(pin, &pin_name);
+                        if pin.clicked() {
+                            to_toggle_pin = Some(idx);
+                        }
 
                         // Usar 'X' ASCII (evita el cuadrito por falta de glyph).
                         let close_btn = egui::Button::new(
@@ -226,13 +1404,49 @@ impl EscPosViewer {
                         .fill(egui::Color32::from_gray(70))
                         .min_size(egui::vec2(24.0, 24.0));
 
-                        if ui.add(close_btn).on_hover_text("Cerrar").clicked() {
+                        let close = ui.add(close_btn).on_hover_text("Cerrar");
+                        let close_name = format!("Cerrar job #{}", job.id);
+                        let close = Self::accessible_name(close, &close_name);
+                        if close.clicked() {
                             to_close = Some(idx);
                         }
+
+                        if let Some(after) = drop_after {
+                            let x = if after {
+                                close.rect.right() + 5.0
+                            } else {
+                                drag.response.rect.left() - 5.0
+                            };
+                            ui.painter().vline(
+                                x,
+                                drag.response.rect.y_range(),
+                                ui.visuals().selection.stroke,
+                            );
+                        }
                     }
                 });
             });
 
+        if let Some(idx) = to_toggle_pin {
+            if let Some(job) = self.jobs.get_mut(idx) {
+                job.pinned = !job.pinned;
+            }
+        }
+
+        if let Some((job_id, target_idx)) = to_move {
+            self.move_job(job_id, target_idx);
+        }
+
+        if let Some((idx, width)) = to_set_width {
+            if let Some(job) = self.jobs.get_mut(idx) {
+                job.paper_width = width;
+            }
+        }
+
+        if let Some(idx) = to_merge {
+            self.merge_with_next(idx);
+        }
+
         if let Some(idx) = to_close {
             self.jobs.remove(idx);
             if self.jobs.is_empty() {
@@ -247,28 +1461,549 @@ impl EscPosViewer {
         }
     }
 
-    fn ui_settings_modal(&mut self, ctx: &egui::Context) {
-        if !self.show_settings {
+    /// Une el job `idx` con el de la pestaña siguiente: bytes concatenados en orden de llegada,
+    /// con el id, la etiqueta y la hora del más viejo (así el orden por id de la autolimpieza
+    /// no cambia). Con "más reciente primero" la pestaña siguiente es la más vieja.
+    fn merge_with_next(&mut self, idx: usize) {
+        if idx + 1 >= self.jobs.len() {
+            return;
+        }
+        let active_id = self.active_job().map(|j| j.id);
+        // Se quita el más nuevo de los dos; el que queda termina siempre en la posición `idx`.
+        let newer = if self.jobs[idx + 1].id > self.jobs[idx].id {
+            idx + 1
+        } else {
+            idx
+        };
+        let second = self.jobs.remove(newer);
+        let options = self.parse_options();
+        let first = &mut self.jobs[idx];
+
+        // Si alguno se estaba simulando, el resultado se muestra completo.
+        first.full_bytes.extend_from_slice(&second.full_bytes);
+        first.display_bytes = first.full_bytes.clone();
+        first.set_parsed(parse_escpos_with_spans(&first.display_bytes, &options));
+        first.pinned |= second.pinned;
+        first.paper_width = first.paper_width.or(second.paper_width);
+        if !second.note.is_empty() {
+            if !first.note.is_empty() {
+                first.note.push_str(" / ");
+            }
+            first.note.push_str(&second.note);
+        }
+        first.sim_active = false;
+        first.sim_started_at = None;
+        first.sim_sent = first.full_bytes.len();
+        first.sim_boundaries = Vec::new();
+        first.sim_next_burst_at = None;
+        crate::diag_log::log_job(
+            first.id,
+            &format!(
+                "unido con job #{} ({} bytes en total)",
+                second.id,
+                first.full_bytes.len()
+            ),
+        );
+
+        let first_id = first.id;
+        self.active_job_idx = active_id
+            .map(|id| if id == second.id { first_id } else { id })
+            .and_then(|id| self.jobs.iter().position(|j| j.id == id));
+    }
+
+    fn clear_unpinned_jobs(&mut self) {
+        let active_id = self.active_job().map(|j| j.id);
+        self.jobs.retain(|j| j.pinned);
+        self.active_job_idx = active_id
+            .and_then(|id| self.jobs.iter().position(|j| j.id == id))
+            .or_else(|| self.newest_job_idx());
+    }
+
+    /// Ancho de papel con el que se dibuja `job`: el suyo o, si no tiene, el global.
+    fn job_paper_width(&self, job: &JobEntry) -> PaperWidth {
+        job.paper_width.unwrap_or(self.paper_width)
+    }
+
+    fn active_paper_width(&self) -> PaperWidth {
+        self.active_job()
+            .map_or(self.paper_width, |job| self.job_paper_width(job))
+    }
+
+    /// Posición del job recibido más recientemente (el de id mayor), sea cual sea el orden.
+    fn newest_job_idx(&self) -> Option<usize> {
+        self.jobs
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, j)| j.id)
+            .map(|(idx, _)| idx)
+    }
+
+    /// Al cambiar "más reciente primero/último" se invierten las pestañas existentes.
+    fn reverse_job_order(&mut self) {
+        self.jobs.reverse();
+        self.active_job_idx = self.active_job_idx.map(|idx| self.jobs.len() - 1 - idx);
+    }
+
+    fn ui_export_all_modal(&mut self, ctx: &egui::Context) {
+        if !self.show_export_all {
             return;
         }
 
-        let mut open = self.show_settings;
-        egui::Window::new("Configuración")
+        let mut open = self.show_export_all;
+        egui::Window::new("Exportar todos")
             .open(&mut open)
             .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
             .collapsible(false)
             .resizable(false)
-            .default_width(560.0)
             .show(ctx, |ui| {
-                ui.spacing_mut().item_spacing = egui::vec2(10.0, 8.0);
-
-                ui.horizontal(|ui| {
-                    ui.label(egui::RichText::new("Ajustes del visor").strong());
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.button("Cerrar").clicked() {
-                            self.show_settings = false;
+                ui.label(format!("{} jobs en el historial", self.jobs.len()));
+                ui.checkbox(&mut self.export_formats.prn, ".prn (bytes originales)");
+                ui.checkbox(&mut self.export_formats.txt, ".txt (texto del ticket)");
+                ui.checkbox(
+                    &mut self.export_formats.json,
+                    ".json (datos del job y detectados: fecha, total, artículos)",
+                );
+                ui.checkbox(
+                    &mut self.export_formats.png,
+                    ".png (imagen del ticket, como \"Exportar PNG\")",
+                );
+                ui.label(
+                    egui::RichText::new(
+                        "Se agrega index.csv con id, hora, bytes, archivos y cadencia (para reproducir la sesión).",
+                    )
+                        .weak()
+                        .small(),
+                );
+                ui.add_space(6.0);
+
+                let formats = self.export_formats;
+                let any_format = formats.prn || formats.txt || formats.json || formats.png;
+                if ui
+                    .add_enabled(
+                        any_format,
+                        egui::Button::new("📁 Elegir carpeta y exportar"),
+                    )
+                    .clicked()
+                {
+                    if let Some(dir) = FileDialog::new().pick_folder() {
+                        let first_arrival = self.first_arrival().unwrap_or_else(Instant::now);
+                        let jobs: Vec<ExportJob> = self
+                            .jobs
+                            .iter()
+                            .map(|j| ExportJob {
+                                id: j.id,
+                                label: &j.label,
+                                received_at: j.received_at,
+                                offset: j.created_at.saturating_duration_since(first_arrival),
+                                bytes: &j.full_bytes,
+                                commands: &j.parsed_commands,
+                                note: &j.note,
+                                paper_width: j.paper_width,
+                                png: formats.png.then(|| {
+                                    let image =
+                                        self.render_ticket_image(ctx, j, self.png_scale as usize);
+                                    encode_png(&image)
+                                }),
+                            })
+                            .collect();
+                        let summary =
+                            export_jobs(&dir, &jobs, self.export_formats, &self.meta_patterns());
+                        let mut status = format!(
+                            "{} archivos escritos en {}",
+                            summary.files_written,
+                            dir.display()
+                        );
+                        for failure in &summary.failures {
+                            status.push_str(&format!("\nError: {failure}"));
                         }
-                    });
+                        self.export_status = Some(status);
+                    }
+                }
+
+                if let Some(status) = &self.export_status {
+                    ui.separator();
+                    ui.label(egui::RichText::new(status).small());
+                }
+            });
+        if !open {
+            self.show_export_all = false;
+        }
+    }
+
+    /// Llegada del job más viejo del historial (origen de los `offset_ms` de la sesión).
+    fn first_arrival(&self) -> Option<Instant> {
+        self.jobs.iter().map(|j| j.created_at).min()
+    }
+
+    /// El historial actual como sesión: cada job con su llegada relativa al primero.
+    fn history_as_session(&self) -> Vec<ReplayJob> {
+        let Some(first) = self.first_arrival() else {
+            return Vec::new();
+        };
+        self.jobs
+            .iter()
+            .map(|j| ReplayJob {
+                label: j.label.clone(),
+                bytes: j.full_bytes.clone(),
+                offset: j.created_at.saturating_duration_since(first),
+                note: j.note.clone(),
+                paper_width: j.paper_width,
+            })
+            .collect()
+    }
+
+    fn start_replay(&mut self, jobs: Vec<ReplayJob>) {
+        crate::diag_log::log(&format!(
+            "Reproducción: {} jobs a x{}",
+            jobs.len(),
+            self.replay_speed
+        ));
+        self.replay = Some(Replay::new(jobs, self.replay_speed, Instant::now()));
+    }
+
+    /// Re-envía por `push_new_job` los jobs de la sesión cuyo momento ya llegó
+    /// en `now`.
+    fn tick_replay(&mut self, ctx: &egui::Context, now: Instant) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+        let due = replay.due(now);
+        let next = replay.next_in(now);
+        let finished = replay.is_finished();
+
+        for job in due {
+            let id = self.push_new_job(format!("↻ {}", job.label), job.bytes);
+            if let Some(entry) = self.jobs.iter_mut().find(|j| j.id == id) {
+                entry.note = job.note;
+                entry.paper_width = job.paper_width;
+            }
+        }
+        if finished {
+            self.replay = None;
+            self.show_toast("Reproducción terminada".to_string());
+        } else if let Some(next) = next {
+            ctx.request_repaint_after(next);
+        }
+    }
+
+    fn ui_replay_modal(&mut self, ctx: &egui::Context) {
+        if !self.show_replay {
+            return;
+        }
+
+        let mut open = self.show_replay;
+        egui::Window::new("Reproducir sesión")
+            .open(&mut open)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(
+                        "Vuelve a recibir los jobs con la cadencia con la que llegaron.",
+                    )
+                    .weak()
+                    .small(),
+                );
+                ui.add_space(6.0);
+
+                if let Some(replay) = &self.replay {
+                    let (sent, total) = replay.progress();
+                    ui.label(format!(
+                        "Reproduciendo: {sent}/{total} jobs (x{})",
+                        self.replay_speed
+                    ));
+                    if ui.button("⏹ Cancelar").clicked() {
+                        self.replay = None;
+                        crate::diag_log::log("Reproducción cancelada");
+                    }
+                    return;
+                }
+
+                ui.add(
+                    egui::Slider::new(&mut self.replay_speed, 0.25..=10.0)
+                        .logarithmic(true)
+                        .text("velocidad")
+                        .suffix("x"),
+                );
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !self.jobs.is_empty(),
+                            egui::Button::new("▶ Historial actual"),
+                        )
+                        .clicked()
+                    {
+                        let session = self.history_as_session();
+                        self.start_replay(session);
+                    }
+                    if ui
+                        .button("📂 Sesión exportada…")
+                        .on_hover_text("index.csv de \"Exportar todos\" (con los .prn)")
+                        .clicked()
+                    {
+                        if let Some(path) = FileDialog::new()
+                            .add_filter("Índice de sesión", &["csv"])
+                            .pick_file()
+                        {
+                            match load_session(&path) {
+                                Ok(session) => self.start_replay(session),
+                                Err(e) => {
+                                    self.show_toast(format!("No se pudo leer la sesión: {e}"))
+                                }
+                            }
+                        }
+                    }
+                });
+            });
+        if !open {
+            self.show_replay = false;
+        }
+    }
+
+    /// Bytes de los snippets elegidos, uno detrás de otro.
+    fn assemble_composition(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        for (snippet, values) in &self.snippet_composition {
+            let part = snippets::assemble(&snippet.template, values, self.start_codepage())
+                .map_err(|e| format!("{}: {e}", snippet.name))?;
+            bytes.extend(part);
+        }
+        Ok(bytes)
+    }
+
+    fn load_snippet_job(&mut self) {
+        match self.assemble_composition() {
+            Ok(bytes) if !bytes.is_empty() => {
+                let names: Vec<&str> = self
+                    .snippet_composition
+                    .iter()
+                    .map(|(s, _)| s.name.as_str())
+                    .collect();
+                let label = format!("🧩 {}", names.join(" + "));
+                self.push_new_job(label, bytes);
+            }
+            Ok(_) => self.show_toast("Los snippets elegidos no generan bytes".to_string()),
+            Err(e) => self.show_toast(e),
+        }
+    }
+
+    /// Biblioteca de snippets (editable y guardada en `snippets.json`) y armado de un job a
+    /// partir de varios de ellos con sus parámetros.
+    fn ui_snippets_modal(&mut self, ctx: &egui::Context) {
+        if !self.show_snippets {
+            return;
+        }
+        if self.snippet_library.is_none() {
+            // Si `snippets.json` no se puede leer se muestran los ejemplos, pero `save` no lo pisa.
+            let library = snippets::load().unwrap_or_else(|e| {
+                crate::diag_log::log(&format!("Snippets: {e}"));
+                self.show_toast(format!("No se pudieron leer los snippets: {e}"));
+                snippets::default_snippets()
+            });
+            self.snippet_library = Some(library);
+        }
+        let library = self
+            .snippet_library
+            .get_or_insert_with(snippets::default_snippets);
+
+        let mut open = self.show_snippets;
+        let mut add = None;
+        let mut remove = None;
+        let mut load = false;
+        let mut save_error = None;
+        egui::Window::new("Snippets ESC/POS")
+            .open(&mut open)
+            .default_size([680.0, 460.0])
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.columns(2, |cols| {
+                    // Biblioteca
+                    let ui = &mut cols[0];
+                    ui.label(egui::RichText::new("Biblioteca").strong());
+                    egui::ScrollArea::vertical()
+                        .id_salt("snippet_library")
+                        .max_height(160.0)
+                        .show(ui, |ui| {
+                            for (idx, snippet) in library.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    let add_btn =
+                                        ui.small_button("➕").on_hover_text("Agregar al job");
+                                    let add_name = format!("Agregar {} al job", snippet.name);
+                                    if Self::accessible_name(add_btn, &add_name).clicked() {
+                                        add = Some(snippet.clone());
+                                    }
+                                    if ui
+                                        .selectable_label(
+                                            self.snippet_editing == Some(idx),
+                                            &snippet.name,
+                                        )
+                                        .clicked()
+                                    {
+                                        self.snippet_editing = Some(idx);
+                                    }
+                                });
+                            }
+                        });
+                    ui.horizontal(|ui| {
+                        if ui.button("Nuevo").clicked() {
+                            library.push(Snippet {
+                                name: "Nuevo snippet".to_string(),
+                                template: r"{texto}\n".to_string(),
+                            });
+                            self.snippet_editing = Some(library.len() - 1);
+                        }
+                        if ui.button("💾 Guardar").clicked() {
+                            save_error = snippets::save(library).err();
+                        }
+                        if ui
+                            .button("↺ Ejemplos")
+                            .on_hover_text("Vuelve a la biblioteca inicial (sin guardar)")
+                            .clicked()
+                        {
+                            *library = snippets::default_snippets();
+                            self.snippet_editing = None;
+                        }
+                    });
+
+                    ui.separator();
+                    match self.snippet_editing.filter(|&idx| idx < library.len()) {
+                        Some(idx) => {
+                            let snippet = &mut library[idx];
+                            ui.text_edit_singleline(&mut snippet.name);
+                            ui.add(
+                                egui::TextEdit::multiline(&mut snippet.template)
+                                    .code_editor()
+                                    .desired_rows(4)
+                                    .desired_width(f32::INFINITY),
+                            );
+                            ui.label(
+                                egui::RichText::new(
+                                    r"\xNN = byte, \n = salto, {nombre} = parámetro, {len:nombre} / {len2+3:nombre} = largo",
+                                )
+                                .weak()
+                                .small(),
+                            );
+                            if ui.button("🗑 Borrar").clicked() {
+                                library.remove(idx);
+                                self.snippet_editing = None;
+                            }
+                        }
+                        None => {
+                            ui.label(
+                                egui::RichText::new("Elige un snippet para editarlo.")
+                                    .weak()
+                                    .small(),
+                            );
+                        }
+                    }
+
+                    // Job a armar
+                    let ui = &mut cols[1];
+                    ui.label(egui::RichText::new("Job").strong());
+                    egui::ScrollArea::vertical()
+                        .id_salt("snippet_composition")
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            for (idx, (snippet, values)) in
+                                self.snippet_composition.iter_mut().enumerate()
+                            {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("{}. {}", idx + 1, snippet.name));
+                                    let remove_btn = ui.small_button("✖").on_hover_text("Quitar");
+                                    let remove_name = format!("Quitar {}", snippet.name);
+                                    if Self::accessible_name(remove_btn, &remove_name).clicked() {
+                                        remove = Some(idx);
+                                    }
+                                });
+                                for param in snippets::params(&snippet.template) {
+                                    ui.horizontal(|ui| {
+                                        ui.label(egui::RichText::new(&param).weak());
+                                        ui.text_edit_singleline(values.entry(param).or_default());
+                                    });
+                                }
+                            }
+                        });
+                    if self.snippet_composition.is_empty() {
+                        ui.label(
+                            egui::RichText::new("Agrega snippets con ➕.")
+                                .weak()
+                                .small(),
+                        );
+                    }
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                !self.snippet_composition.is_empty(),
+                                egui::Button::new("▶ Cargar como job"),
+                            )
+                            .clicked()
+                        {
+                            load = true;
+                        }
+                        if ui.button("Vaciar").clicked() {
+                            self.snippet_composition.clear();
+                        }
+                    });
+                });
+            });
+
+        if let Some(e) = save_error {
+            self.show_toast(format!("No se pudieron guardar los snippets: {e}"));
+        }
+        if let Some(snippet) = add {
+            self.snippet_composition.push((snippet, HashMap::new()));
+        }
+        if let Some(idx) = remove {
+            self.snippet_composition.remove(idx);
+        }
+        if load {
+            self.load_snippet_job();
+        }
+        if !open {
+            self.show_snippets = false;
+        }
+    }
+
+    /// Mueve el job `job_id` a la posición `target_idx`, manteniendo seleccionado el mismo job.
+    fn move_job(&mut self, job_id: u64, target_idx: usize) {
+        let active_id = self.active_job().map(|j| j.id);
+        let Some(from) = self.jobs.iter().position(|j| j.id == job_id) else {
+            return;
+        };
+        let job = self.jobs.remove(from);
+        let to = target_idx.min(self.jobs.len());
+        self.jobs.insert(to, job);
+
+        if let Some(id) = active_id {
+            self.active_job_idx = self.jobs.iter().position(|j| j.id == id);
+        }
+    }
+
+    fn ui_settings_modal(&mut self, ctx: &egui::Context) {
+        if !self.show_settings {
+            return;
+        }
+
+        let mut open = self.show_settings;
+        egui::Window::new("Configuración")
+            .open(&mut open)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .collapsible(false)
+            .resizable(false)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.spacing_mut().item_spacing = egui::vec2(10.0, 8.0);
+
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("Ajustes del visor").strong());
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Cerrar").clicked() {
+                            self.show_settings = false;
+                        }
+                    });
                 });
                 ui.separator();
 
@@ -280,7 +2015,10 @@ impl EscPosViewer {
                         ui.label(egui::RichText::new("Captura").strong());
                         ui.vertical(|ui| {
                             let enabled_before = self.tcp_enabled;
-                            ui.checkbox(&mut self.tcp_enabled, "Escuchar impresora (TCP 9100)");
+                            ui.checkbox(
+                                &mut self.tcp_enabled,
+                                format!("Escuchar impresora (TCP {})", self.tcp_port),
+                            );
                             if self.tcp_enabled != enabled_before {
                                 if self.tcp_enabled {
                                     self.set_tcp_capture(true, Some(ctx.clone()));
@@ -293,7 +2031,18 @@ impl EscPosViewer {
                                     egui::RichText::new(err).color(egui::Color32::RED).small(),
                                 );
                             } else {
-                                ui.label(egui::RichText::new("127.0.0.1:9100").weak().small());
+                                ui.label(egui::RichText::new(self.tcp_bind_addr()).weak().small());
+                            }
+                            self.ui_tcp_port_recovery(ui);
+                            if ui
+                                .add_enabled(
+                                    self.tcp_test_rx.is_none(),
+                                    egui::Button::new("📡 Enviar prueba por TCP"),
+                                )
+                                .on_hover_text("Manda el ticket de ejemplo al listener, como un POS")
+                                .clicked()
+                            {
+                                self.send_tcp_test();
                             }
 
                             ui.add_space(4.0);
@@ -310,6 +2059,116 @@ impl EscPosViewer {
                                     .text("bytes"),
                                 );
                             }
+
+                            ui.add_space(4.0);
+                            let limits_before = self.capture_limits;
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut self.capture_limits.max_connections_per_sec,
+                                    0..=200,
+                                )
+                                .text("conexiones/s (0 = sin límite)"),
+                            );
+                            let mut max_kb = self.capture_limits.max_job_bytes / 1024;
+                            ui.add(
+                                egui::Slider::new(&mut max_kb, 64..=64 * 1024)
+                                    .logarithmic(true)
+                                    .text("KB máx por job"),
+                            );
+                            self.capture_limits.max_job_bytes = max_kb * 1024;
+                            let mut timeout_secs = self.capture_limits.read_timeout.as_secs_f32();
+                            ui.add(
+                                egui::Slider::new(&mut timeout_secs, 0.5..=10.0)
+                                    .step_by(0.1)
+                                    .suffix(" s")
+                                    .text("fin de job por inactividad"),
+                            )
+                            .on_hover_text(
+                                "Muy corto puede partir un ticket en 2 jobs; muy largo demora su aparición",
+                            );
+                            self.capture_limits.read_timeout =
+                                Duration::from_millis((timeout_secs * 1000.0).round() as u64);
+                            ui.checkbox(
+                                &mut self.capture_limits.allow_remote,
+                                "Aceptar jobs de otras PCs de la red",
+                            )
+                            .on_hover_text(
+                                "Escucha en 0.0.0.0 en vez de 127.0.0.1: cualquiera que llegue al puerto puede mandar jobs",
+                            );
+                            if self.capture_limits.allow_remote != limits_before.allow_remote {
+                                // Cambia la dirección de escucha: hay que volver a abrir el puerto.
+                                if self.tcp_capture.is_some() {
+                                    self.set_tcp_capture(false, None);
+                                    self.set_tcp_capture(true, Some(ctx.clone()));
+                                }
+                            } else if self.capture_limits != limits_before {
+                                if let Some(cap) = &self.tcp_capture {
+                                    cap.set_limits(self.capture_limits);
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        // Reenvío HTTP
+                        ui.label(egui::RichText::new("Webhook").strong());
+                        ui.vertical(|ui| {
+                            // El envío se reconfigura al confirmar (no en cada tecla): rearmar
+                            // el hilo reinicia los reintentos de la cola.
+                            let format_before = self.webhook_format;
+                            let mut commit = ui
+                                .checkbox(
+                                    &mut self.webhook_enabled,
+                                    "Reenviar jobs TCP por HTTP POST",
+                                )
+                                .changed();
+                            commit |= ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut self.webhook_url)
+                                        .hint_text("https://example.com/tickets")
+                                        .desired_width(260.0),
+                                )
+                                .lost_focus();
+                            commit |= ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut self.webhook_auth)
+                                        .hint_text("Authorization (opcional)")
+                                        .password(true)
+                                        .desired_width(260.0),
+                                )
+                                .on_hover_text(
+                                    "Solo el valor (\"Bearer …\") o una cabecera completa (\"X-Api-Key: …\").\nCon http:// viaja sin cifrar: mejor https://.",
+                                )
+                                .lost_focus();
+                            ui.horizontal(|ui| {
+                                ui.radio_value(
+                                    &mut self.webhook_format,
+                                    WebhookFormat::Json,
+                                    "JSON (base64)",
+                                );
+                                ui.radio_value(
+                                    &mut self.webhook_format,
+                                    WebhookFormat::Raw,
+                                    "Bytes crudos",
+                                );
+                            });
+                            if commit || self.webhook_format != format_before {
+                                self.sync_webhook();
+                            }
+                            if let Some(err) = &self.webhook_last_error {
+                                ui.label(
+                                    egui::RichText::new(err).color(egui::Color32::RED).small(),
+                                );
+                            } else if let Some(sender) = &self.webhook {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "Enviados: {} · en cola: {}",
+                                        self.webhook_sent,
+                                        sender.pending()
+                                    ))
+                                    .weak()
+                                    .small(),
+                                );
+                            }
                         });
                         ui.end_row();
 
@@ -322,9 +2181,27 @@ impl EscPosViewer {
                                 egui::Slider::new(&mut self.sim_bytes_per_sec, 1_000..=200_000)
                                     .text("bytes/s"),
                             );
+                            ui.checkbox(&mut self.sim_burst_mode, "Modo ráfaga")
+                                .on_hover_text(
+                                    "Revela líneas completas con pausas breves, como llega la transmisión de un POS real",
+                                );
                             if before_sim && !self.simulate_printing {
                                 self.stop_active_simulation_show_full();
                             }
+                            ui.checkbox(&mut self.show_print_times, "⏱ Tiempos estimados")
+                                .on_hover_text("Tiempo acumulado junto a cada corte y al final del ticket");
+                            ui.horizontal(|ui| {
+                                ui.label("Estimar con:");
+                                ui.selectable_value(&mut self.print_time_by_paper, false, "bytes/s");
+                                ui.selectable_value(&mut self.print_time_by_paper, true, "papel (mm/s)");
+                            });
+                            if self.print_time_by_paper {
+                                ui.add(
+                                    egui::Slider::new(&mut self.printer_speed_mm_s, 20..=500)
+                                        .text("mm/s"),
+                                )
+                                .on_hover_text("Velocidad de la impresora real (no cambia la simulación)");
+                            }
                             if let Some(job) = self.active_job() {
                                 if job.sim_active {
                                     let total = job.full_bytes.len().max(1);
@@ -339,11 +2216,87 @@ impl EscPosViewer {
                         });
                         ui.end_row();
 
+                        // Barcodes
+                        ui.label(egui::RichText::new("Barcodes").strong());
+                        ui.vertical(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut self.barcode_quiet_zone, 0..=40)
+                                    .text("zona de silencio (módulos)"),
+                            );
+                            ui.checkbox(&mut self.barcode_scan_check, "Verificar lectura (✅/⚠)")
+                                .on_hover_text("Lee cada CODE128/EAN/UPC-A dibujado y avisa si no se podría escanear");
+                        });
+                        ui.end_row();
+
+                        // Logos NV (FS p)
+                        ui.label(egui::RichText::new("Logos NV").strong());
+                        ui.vertical(|ui| {
+                            ui.label(
+                                egui::RichText::new(
+                                    "Imagen de cada FS p n: un archivo ESC/POS con el raster (GS v 0) del logo",
+                                )
+                                .weak()
+                                .small(),
+                            );
+                            let mut remove = None;
+                            for (n, logo) in &self.nv_logos {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("#{n}"));
+                                    ui.label(
+                                        egui::RichText::new(logo.path.display().to_string())
+                                            .small(),
+                                    );
+                                    let remove_btn = ui.small_button("✖").on_hover_text("Quitar");
+                                    let remove_name = format!("Quitar logo #{n}");
+                                    if Self::accessible_name(remove_btn, &remove_name).clicked() {
+                                        remove = Some(*n);
+                                    }
+                                });
+                                if let Err(err) = &logo.image {
+                                    ui.label(
+                                        egui::RichText::new(err).color(egui::Color32::RED).small(),
+                                    );
+                                }
+                            }
+                            if let Some(n) = remove {
+                                self.nv_logos.remove(&n);
+                            }
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.nv_logo_new_n)
+                                        .range(1..=255)
+                                        .prefix("#"),
+                                );
+                                if ui.button("📂 Asignar archivo…").clicked() {
+                                    if let Some(path) = FileDialog::new()
+                                        .add_filter("Printer Files", &["prn", "bin"])
+                                        .pick_file()
+                                    {
+                                        self.nv_logos
+                                            .insert(self.nv_logo_new_n, NvLogo::load(path));
+                                    }
+                                }
+                            });
+                        });
+                        ui.end_row();
+
                         // Papel
                         ui.label(egui::RichText::new("Papel").strong());
-                        ui.horizontal(|ui| {
-                            ui.selectable_value(&mut self.paper_width, PaperWidth::W58mm, "58mm");
-                            ui.selectable_value(&mut self.paper_width, PaperWidth::W80mm, "80mm");
+                        ui.vertical(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(&mut self.paper_width, PaperWidth::W58mm, "58mm");
+                                ui.selectable_value(&mut self.paper_width, PaperWidth::W80mm, "80mm");
+                            });
+                            if ui
+                                .button("📏 Regla de calibración")
+                                .on_hover_text(
+                                    "Genera un ticket con el ancho exacto en columnas para compararlo con la impresora real",
+                                )
+                                .clicked()
+                            {
+                                let bytes = Self::calibration_ruler_bytes(self.paper_width);
+                                self.push_new_job("Regla de calibración".to_string(), bytes);
+                            }
                         });
                         ui.end_row();
 
@@ -351,66 +2304,60 @@ impl EscPosViewer {
                         ui.label(egui::RichText::new("Codificación").strong());
                         ui.vertical(|ui| {
                             let before = self.codepage;
-                            egui::ComboBox::from_label("Codepage")
-                                .selected_text(match self.codepage {
-                                    CodePage::Utf8Lossy => "UTF-8 (auto)",
-                                    CodePage::Cp437 => "CP437",
-                                    CodePage::Cp850 => "CP850",
-                                    CodePage::Windows1252 => "Windows-1252",
-                                    CodePage::Pc858 => "PC858 (€)",
-                                    CodePage::Iso88591 => "ISO-8859-1",
-                                    CodePage::Cp866 => "CP866 (Cyrillic)",
-                                    CodePage::Cp860 => "CP860 (Portuguese)",
-                                    CodePage::Cp865 => "CP865 (Nordic)",
-                                })
+                            // Con codepage inicial el del visor no se usa: se deshabilita en vez
+                            // de dejar un combo que no cambia nada.
+                            ui.add_enabled_ui(self.initial_codepage.is_none(), |ui| {
+                                egui::ComboBox::from_label("Codepage")
+                                    .selected_text(self.codepage.label())
+                                    .show_ui(ui, |ui| {
+                                        for cp in CodePage::ALL {
+                                            ui.selectable_value(&mut self.codepage, cp, cp.label());
+                                        }
+                                    })
+                                    .response
+                                    .on_disabled_hover_text("Lo reemplaza el codepage inicial de abajo; con \"Igual que el visor\" vuelve a aplicarse");
+                            });
+                            let initial_before = self.initial_codepage;
+                            egui::ComboBox::from_label("Codepage inicial (antes de ESC t)")
+                                .selected_text(
+                                    self.initial_codepage
+                                        .map_or("Igual que el visor", CodePage::label),
+                                )
                                 .show_ui(ui, |ui| {
                                     ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Utf8Lossy,
-                                        "UTF-8 (auto: fallback Win-1252)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Cp437,
-                                        "CP437 (USA/Europe)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Cp850,
-                                        "CP850 (Multilingual)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Windows1252,
-                                        "Windows-1252",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Pc858,
-                                        "PC858 (CP850 + €)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Iso88591,
-                                        "ISO-8859-1 (Latin-1)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Cp866,
-                                        "CP866 (Cyrillic/Russian)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Cp860,
-                                        "CP860 (Portuguese)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.codepage,
-                                        CodePage::Cp865,
-                                        "CP865 (Nordic)",
+                                        &mut self.initial_codepage,
+                                        None,
+                                        "Igual que el visor",
                                     );
-                                });
-                            if self.codepage != before {
+                                    ui.separator();
+                                    for cp in CodePage::ALL {
+                                        ui.selectable_value(
+                                            &mut self.initial_codepage,
+                                            Some(cp),
+                                            cp.label(),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Tabla con la que arranca la impresora: se usa hasta el primer ESC t del stream. Para equipos que siempre empiezan en CP850 (u otra) aunque el visor muestre otra cosa");
+                            let alignment_before = self.default_alignment;
+                            egui::ComboBox::from_label("Alineación por defecto (antes de ESC a)")
+                                .selected_text(Self::align_label(self.default_alignment))
+                                .show_ui(ui, |ui| {
+                                    for align in [Align::Left, Align::Center, Align::Right] {
+                                        ui.selectable_value(
+                                            &mut self.default_alignment,
+                                            align,
+                                            Self::align_label(align),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Para POS que nunca mandan ESC a: el texto arranca con esta alineación (también tras ESC @). Un ESC a del stream la reemplaza");
+                            if self.codepage != before
+                                || self.initial_codepage != initial_before
+                                || self.default_alignment != alignment_before
+                            {
                                 self.reparse_all_jobs();
                             }
                         });
@@ -421,16 +2368,37 @@ impl EscPosViewer {
                         ui.vertical(|ui| {
                             ui.checkbox(&mut self.auto_scroll_on_print, "Auto-scroll al imprimir");
                             ui.add(egui::Slider::new(&mut self.max_jobs, 1..=100).text("Máx jobs"));
+                            ui.horizontal(|ui| {
+                                ui.label("Orden:");
+                                let before = self.newest_first;
+                                ui.selectable_value(&mut self.newest_first, false, "más reciente último");
+                                ui.selectable_value(&mut self.newest_first, true, "más reciente primero");
+                                if self.newest_first != before {
+                                    self.reverse_job_order();
+                                }
+                            });
                             ui.checkbox(&mut self.auto_prune_by_age, "Autolimpieza por edad");
                             if self.auto_prune_by_age {
                                 let mut mins = (self.prune_after.as_secs() / 60).max(1);
                                 ui.add(egui::Slider::new(&mut mins, 1..=24 * 60).text("min"));
                                 self.prune_after = Duration::from_secs(mins * 60);
+                                ui.add(
+                                    egui::Slider::new(&mut self.prune_keep_min, 0..=self.max_jobs)
+                                        .text("conservar siempre los últimos"),
+                                );
                             }
+                            ui.checkbox(&mut self.reopen_last_file, "Reabrir último archivo al iniciar")
+                                .on_hover_text(match &self.last_opened_file {
+                                    Some(path) => format!("Último: {}", path.display()),
+                                    None => "Todavía no se abrió ningún archivo".to_string(),
+                                });
                             ui.horizontal(|ui| {
-                                if ui.button("🧹 Limpiar historial").clicked() {
-                                    self.jobs.clear();
-                                    self.active_job_idx = None;
+                                if ui
+                                    .button("🧹 Limpiar historial")
+                                    .on_hover_text("Conserva los jobs marcados con ★")
+                                    .clicked()
+                                {
+                                    self.clear_unpinned_jobs();
                                 }
                                 ui.label(
                                     egui::RichText::new(format!("Jobs: {}", self.jobs.len()))
@@ -446,18 +2414,177 @@ impl EscPosViewer {
                             ui.checkbox(&mut self.realistic_effects, "🎫 Ticket realista");
                             if self.realistic_effects {
                                 ui.label(
-                                    egui::RichText::new("Bordes ondulados, textura, sombra curvada")
+                                    egui::RichText::new("Bordes ondulados, textura, sombra curvada, negrita sangrada")
                                         .weak()
                                         .small(),
                                 );
                             }
-                            ui.checkbox(&mut self.use_thermal_font, "🔤 Fuente térmica");
-                            if self.use_thermal_font {
-                                ui.label(
+                            ui.checkbox(&mut self.shade_header_lines, "Resaltar encabezados")
+                                .on_hover_text("Sombrea las líneas en negrita + subrayado");
+                            ui.checkbox(&mut self.draw_separator_rules, "Separadores como rayas")
+                                .on_hover_text("Las líneas de solo ----, ==== o ____ a todo el ancho se dibujan como una raya continua (simple, doble o gruesa)");
+                            ui.checkbox(&mut self.tab_right_align, "Alinear precios a la derecha en tabs")
+                                .on_hover_text("El texto después de un tab termina en la parada siguiente; el último campo de la línea, en el borde derecho (columna de precios)");
+                            ui.checkbox(&mut self.tab_auto_table, "Auto-tabla en tabs")
+                                .on_hover_text("Líneas seguidas con la misma cantidad de tabs se alinean como tabla: cada columna toma el ancho de su campo más largo en el bloque (como column -t). Tiene prioridad sobre las paradas cada 8 columnas y la alineación de precios");
+                            ui.checkbox(&mut self.overflow_scroll, "Desbordar a lo ancho (scroll horizontal)")
+                                .on_hover_text("Imágenes y barcodes más anchos que el papel se muestran a su ancho real en vez de achicarse; el sobrante se ve con scroll horizontal. Útil para etiquetas o streams mal posicionados");
+                            ui.checkbox(&mut self.flip_ticket, "Ticket al revés (girado 180°)")
+                                .on_hover_text("Para impresoras instaladas de modo que el papel se corta desde arriba: el ticket entero se muestra girado, barcodes y QR incluidos. Un ESC { 1 antes del contenido lo gira aunque la opción esté apagada");
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.kitchen_orders, "Separar órdenes de cocina")
+                                    .on_hover_text("Cuando un corte parcial viene después de un avance de papel (fin de comanda) y le sigue otro ticket, se numera cada orden: — Orden 2 —");
+                                ui.add_enabled_ui(self.kitchen_orders, |ui| {
+                                    egui::ComboBox::from_id_salt("order_separator")
+                                        .selected_text(self.order_separator.label())
+                                        .show_ui(ui, |ui| {
+                                            for style in OrderSeparatorStyle::ALL {
+                                                ui.selectable_value(
+                                                    &mut self.order_separator,
+                                                    style,
+                                                    style.label(),
+                                                );
+                                            }
+                                        });
+                                });
+                            });
+                            ui.add(
+                                egui::Slider::new(&mut self.print_density, 0..=100)
+                                    .text("Densidad")
+                                    .suffix("%"),
+                            )
+                            .on_hover_text(
+                                "Oscuridad de impresión (claro → oscuro): tono del texto y umbral de las imágenes",
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut self.png_scale, 1..=4)
+                                    .text("Escala PNG")
+                                    .suffix("×"),
+                            )
+                            .on_hover_text(
+                                "Píxeles por punto de la impresora en \"Exportar PNG\" (1× = 203 dpi)",
+                            );
+                            ui.checkbox(&mut self.use_thermal_font, "🔤 Fuente térmica");
+                            if self.use_thermal_font {
+                                ui.label(
                                     egui::RichText::new("DotMatrix (estilo impresora)")
                                         .weak()
                                         .small(),
                                 );
+                                ui.checkbox(&mut self.snap_thermal_font, "Nitidez de fuente")
+                                    .on_hover_text(
+                                        "Ajusta tamaños y espacios a píxeles enteros para que el texto escalado no se vea borroso",
+                                    );
+                            }
+                        });
+                        ui.end_row();
+
+                        // Ventana
+                        ui.label(egui::RichText::new("Ventana").strong());
+                        ui.vertical(|ui| {
+                            let monitors = self.window.monitor_names();
+                            let before = self.preview_monitor.clone();
+                            let corner_before = self.dock_corner;
+                            egui::ComboBox::from_label("Monitor del preview")
+                                .selected_text(
+                                    self.preview_monitor
+                                        .clone()
+                                        .unwrap_or_else(|| "Automático (actual)".to_string()),
+                                )
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.preview_monitor,
+                                        None,
+                                        "Automático (actual)",
+                                    );
+                                    for name in &monitors {
+                                        ui.selectable_value(
+                                            &mut self.preview_monitor,
+                                            Some(name.clone()),
+                                            name.as_str(),
+                                        );
+                                    }
+                                });
+                            ui.horizontal(|ui| {
+                                ui.label("Esquina:");
+                                for (corner, icon, name) in [
+                                    (ScreenCorner::TopLeft, "↖", "Arriba a la izquierda"),
+                                    (ScreenCorner::TopRight, "↗", "Arriba a la derecha"),
+                                    (ScreenCorner::BottomLeft, "↙", "Abajo a la izquierda"),
+                                    (ScreenCorner::BottomRight, "↘", "Abajo a la derecha"),
+                                ] {
+                                    let response = ui
+                                        .selectable_value(&mut self.dock_corner, corner, icon)
+                                        .on_hover_text(name);
+                                    let selected = self.dock_corner == corner;
+                                    response.widget_info(|| {
+                                        egui::WidgetInfo::selected(
+                                            egui::WidgetType::SelectableLabel,
+                                            true,
+                                            selected,
+                                            name,
+                                        )
+                                    });
+                                }
+                            });
+                            ui.checkbox(&mut self.docked_mode, "Modo acoplado")
+                                .on_hover_text("La ventana vuelve a su esquina si se mueve");
+                            ui.checkbox(&mut self.compact_mode, "Preview compacto (sin bordes)")
+                                .on_hover_text("Oculta bordes y botones; salir con F1 o clic derecho en el ticket");
+                            ui.add(
+                                egui::Slider::new(&mut self.idle_to_tray_mins, 0..=120)
+                                    .text("min inactivo → bandeja"),
+                            )
+                            .on_hover_text("Ocultar a la bandeja sin jobs ni uso (0 = nunca)");
+                            let window_prefs = (self.show_on_job, self.steal_focus_on_job);
+                            ui.checkbox(&mut self.show_on_job, "Mostrar al recibir un job")
+                                .on_hover_text("También la saca de la bandeja");
+                            ui.add_enabled(
+                                self.show_on_job,
+                                egui::Checkbox::new(
+                                    &mut self.steal_focus_on_job,
+                                    "Robar foco al imprimir",
+                                ),
+                            )
+                            .on_hover_text("Si está apagado, la ventana aparece sin quitar el foco a la aplicación activa");
+                            if window_prefs != (self.show_on_job, self.steal_focus_on_job) {
+                                self.sync_capture_window_prefs();
+                            }
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.global_hotkey_enabled, "Atajo global")
+                                    .on_hover_text("Muestra u oculta el visor desde cualquier aplicación");
+                                let response = ui.add_enabled(
+                                    self.global_hotkey_enabled,
+                                    egui::TextEdit::singleline(&mut self.hotkey_draft)
+                                        .hint_text(DEFAULT_HOTKEY)
+                                        .desired_width(110.0),
+                                );
+                                // `lost_focus` también cubre el Enter de un campo de una línea.
+                                if response.lost_focus() {
+                                    self.commit_hotkey_draft();
+                                }
+                            });
+                            let hotkey_error = self.hotkey_draft_error.as_ref().or(self.hotkey_error.as_ref());
+                            if let Some(err) = hotkey_error.filter(|_| self.global_hotkey_enabled) {
+                                ui.label(egui::RichText::new(err).color(egui::Color32::RED).small());
+                            }
+                            if (self.preview_monitor != before || self.dock_corner != corner_before)
+                                && self.ui_mode == UiMode::Preview
+                            {
+                                self.window.snap_to_monitor(
+                                    self.preview_monitor_index(),
+                                    self.dock_corner,
+                                    14,
+                                );
+                            }
+                            if let Some(name) = &self.preview_monitor {
+                                if !monitors.contains(name) {
+                                    ui.label(
+                                        egui::RichText::new("Desconectado: se usa el monitor actual")
+                                            .weak()
+                                            .small(),
+                                    );
+                                }
                             }
                         });
                         ui.end_row();
@@ -467,6 +2594,42 @@ impl EscPosViewer {
                         ui.vertical(|ui| {
                             ui.checkbox(&mut self.show_debug_panels, "Mostrar Hex/Log");
                             ui.checkbox(&mut self.show_debug_controls, "Debug comandos");
+                            ui.add_enabled(
+                                self.show_debug_controls,
+                                egui::Checkbox::new(&mut self.show_char_grid, "Grilla de caracteres"),
+                            )
+                            .on_hover_text("Celdas de Font A (columnas del papel × interlineado por defecto) sobre el ticket, para ver si el texto cae en su celda");
+                            ui.checkbox(&mut self.show_state_inspector, "Inspector de estado")
+                                .on_hover_text("Estado acumulado (negrita, alineación, tamaño, codepage…) en el comando del Log bajo el mouse o en la posición de la simulación");
+                            ui.checkbox(&mut self.show_receipt_meta, "Datos detectados (experimental)")
+                                .on_hover_text("Fecha, hora, total y artículos reconocidos en el texto del ticket");
+                            if self.show_receipt_meta {
+                                ui.horizontal(|ui| {
+                                    ui.label("Total:");
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.meta_total_keywords)
+                                            .desired_width(160.0),
+                                    )
+                                    .on_hover_text("Palabras de la línea del total, separadas por coma");
+                                    ui.label("Moneda:");
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.meta_currency)
+                                            .desired_width(32.0),
+                                    );
+                                });
+                            }
+                            ui.checkbox(&mut self.show_whitespace, "Mostrar espacios/tabs")
+                                .on_hover_text("Espacios como ·, tabs como → y saltos de línea como ¶ (no cambia las columnas)");
+                            if ui
+                                .checkbox(&mut self.file_log_enabled, "Log de diagnóstico en archivo")
+                                .on_hover_text("Capturas, conexiones y comandos no reconocidos (máx. ~4 MB)")
+                                .changed()
+                            {
+                                crate::diag_log::set_enabled(self.file_log_enabled);
+                            }
+                            if ui.button("📁 Abrir carpeta de logs").clicked() {
+                                let _ = crate::diag_log::open_log_dir();
+                            }
                         });
                         ui.end_row();
                     });
@@ -474,6 +2637,41 @@ impl EscPosViewer {
 
         self.show_settings = open;
     }
+    /// Índice del monitor elegido para el Preview; None si no hay preferencia o está desconectado
+    /// (en ese caso WindowControl usa el monitor más cercano).
+    fn preview_monitor_index(&self) -> Option<usize> {
+        let name = self.preview_monitor.as_ref()?;
+        self.window.monitor_names().iter().position(|n| n == name)
+    }
+
+    /// Modo acoplado: devuelve el Preview a su esquina cuando se lo movió y se soltó.
+    fn tick_docked_mode(&mut self, ctx: &egui::Context) {
+        if !self.docked_mode || self.hidden_to_tray || self.ui_mode != UiMode::Preview {
+            return;
+        }
+
+        const SETTLE: Duration = Duration::from_millis(1500);
+        const INTERVAL: Duration = Duration::from_secs(1);
+
+        let pos = ctx.input(|i| i.viewport().outer_rect.map(|r| r.min));
+        if pos != self.dock_last_outer_pos {
+            self.dock_last_outer_pos = pos;
+            self.dock_pos_changed_at = Instant::now();
+        }
+        let dragging = ctx.input(|i| i.pointer.any_down());
+
+        if !dragging
+            && self.dock_pos_changed_at.elapsed() >= SETTLE
+            && self.dock_last_snap.elapsed() >= INTERVAL
+        {
+            self.dock_last_snap = Instant::now();
+            self.window
+                .snap_to_monitor(self.preview_monitor_index(), self.dock_corner, 14);
+        }
+
+        ctx.request_repaint_after(INTERVAL);
+    }
+
     fn active_job(&self) -> Option<&JobEntry> {
         self.active_job_idx.and_then(|idx| self.jobs.get(idx))
     }
@@ -483,8 +2681,131 @@ impl EscPosViewer {
         self.jobs.get_mut(idx)
     }
 
+    /// Cambios del editor hex que se pueden deshacer por job.
+    const HEX_UNDO_LIMIT: usize = 100;
+    /// Jobs más grandes no se editan (el editor trabaja con el texto completo).
+    const HEX_EDIT_MAX_BYTES: usize = 256 * 1024;
+
+    /// Reemplaza los bytes del job activo (editor hex), guardando los anteriores para Deshacer,
+    /// y lo vuelve a parsear completo.
+    fn apply_hex_edit(&mut self, bytes: Vec<u8>) {
+        let options = self.parse_options();
+        let Some(job) = self.active_job_mut() else {
+            return;
+        };
+        if job.full_bytes == bytes {
+            return;
+        }
+        let previous = mem::replace(&mut job.full_bytes, bytes);
+        job.edit_undo.push(previous);
+        if job.edit_undo.len() > Self::HEX_UNDO_LIMIT {
+            job.edit_undo.remove(0);
+        }
+        Self::show_edited_bytes(job, options);
+    }
+
+    fn undo_hex_edit(&mut self) {
+        let options = self.parse_options();
+        let Some(job) = self.active_job_mut() else {
+            return;
+        };
+        let Some(previous) = job.edit_undo.pop() else {
+            return;
+        };
+        job.full_bytes = previous;
+        Self::show_edited_bytes(job, options);
+        let text = hex_lines(&job.full_bytes);
+        if let Some(edit) = &mut self.hex_edit {
+            edit.text = text;
+            edit.error = None;
+        }
+    }
+
+    fn show_edited_bytes(job: &mut JobEntry, options: ParseOptions) {
+        job.display_bytes = job.full_bytes.clone();
+        job.set_parsed(parse_escpos_with_spans(&job.display_bytes, &options));
+        job.sim_active = false;
+        job.sim_started_at = None;
+        job.sim_sent = job.full_bytes.len();
+        job.sim_boundaries = Vec::new();
+        job.sim_next_burst_at = None;
+    }
+
+    /// Cabecera del Hex Dump: editar, marca de modificado y Deshacer.
+    fn ui_hex_edit_bar(&mut self, ui: &mut egui::Ui) {
+        let Some(job) = self.active_job() else {
+            return;
+        };
+        let (job_id, len, modified) = (job.id, job.full_bytes.len(), !job.edit_undo.is_empty());
+        ui.horizontal(|ui| {
+            let mut editing = self.hex_edit.is_some();
+            let too_big = len > Self::HEX_EDIT_MAX_BYTES;
+            if ui
+                .add_enabled(!too_big, egui::SelectableLabel::new(editing, "✏ Editar"))
+                .on_hover_text("Editar los bytes del job: el ticket se vuelve a renderizar en vivo")
+                .on_disabled_hover_text("Job demasiado grande para editar (máx. 256 KB)")
+                .clicked()
+            {
+                editing = !editing;
+            }
+            if editing && self.hex_edit.as_ref().is_none_or(|e| e.job_id != job_id) {
+                self.hex_edit = self.active_job().map(|job| HexEdit {
+                    job_id,
+                    text: hex_lines(&job.full_bytes),
+                    error: None,
+                });
+            } else if !editing {
+                self.hex_edit = None;
+            }
+            if modified {
+                ui.label(
+                    egui::RichText::new("● modificado")
+                        .color(egui::Color32::from_rgb(217, 119, 6))
+                        .strong(),
+                );
+                if ui
+                    .button("↶ Deshacer")
+                    .on_hover_text("Vuelve los bytes al estado anterior a la última edición")
+                    .clicked()
+                {
+                    self.undo_hex_edit();
+                }
+            }
+        });
+
+        let Some(mut edit) = self.hex_edit.take() else {
+            return;
+        };
+        egui::ScrollArea::vertical()
+            .id_salt("hex_edit_scroll")
+            .max_height(260.0)
+            .show(ui, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut edit.text)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(8),
+                );
+                if response.changed() {
+                    match parse_hex_dump(&edit.text) {
+                        Some(bytes) => {
+                            edit.error = None;
+                            self.apply_hex_edit(bytes);
+                        }
+                        None => {
+                            edit.error = Some("Hex inválido: el cambio no se aplicó".to_string());
+                        }
+                    }
+                }
+            });
+        if let Some(error) = &edit.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        self.hex_edit = Some(edit);
+    }
+
     fn stop_active_simulation_show_full(&mut self) {
-        let codepage = self.codepage;
+        let options = self.parse_options();
         let Some(job) = self.active_job_mut() else {
             return;
         };
@@ -494,10 +2815,27 @@ impl EscPosViewer {
         job.sim_active = false;
         job.sim_started_at = None;
         job.display_bytes = job.full_bytes.clone();
-        job.parsed_commands = parse_escpos(&job.display_bytes, codepage);
+        job.set_parsed(parse_escpos_with_spans(&job.display_bytes, &options));
         job.sim_sent = job.display_bytes.len();
     }
 
+    /// Modelo de tiempo de impresión elegido en la configuración.
+    fn print_rate(&self) -> PrintRate {
+        if self.print_time_by_paper {
+            PrintRate::MmPerSec(self.printer_speed_mm_s as f32)
+        } else {
+            PrintRate::BytesPerSec(self.sim_bytes_per_sec)
+        }
+    }
+
+    fn prune_policy(&self) -> PrunePolicy {
+        PrunePolicy {
+            max_jobs: self.max_jobs,
+            keep_min: self.prune_keep_min,
+            max_age: self.auto_prune_by_age.then_some(self.prune_after),
+        }
+    }
+
     fn prune_jobs(&mut self) {
         let active_id = self.active_job().map(|j| j.id);
 
@@ -506,17 +2844,19 @@ impl EscPosViewer {
             return;
         }
 
-        // Primero por edad (opcional)
-        if self.auto_prune_by_age {
-            let now = Instant::now();
-            self.jobs
-                .retain(|j| now.duration_since(j.created_at) <= self.prune_after);
-        }
-
-        // Luego por límite de cantidad (siempre)
-        if self.jobs.len() > self.max_jobs {
-            let remove_count = self.jobs.len() - self.max_jobs;
-            self.jobs.drain(0..remove_count);
+        let now = Instant::now();
+        let candidates: Vec<PruneCandidate> = self
+            .jobs
+            .iter()
+            .map(|j| PruneCandidate {
+                id: j.id,
+                age: now.duration_since(j.created_at),
+                pinned: j.pinned,
+            })
+            .collect();
+        let remove = self.prune_policy().ids_to_remove(&candidates);
+        if !remove.is_empty() {
+            self.jobs.retain(|j| !remove.contains(&j.id));
         }
 
         // Reajustar active_job_idx intentando mantener el mismo id.
@@ -532,12 +2872,18 @@ impl EscPosViewer {
             }
         }
 
-        self.active_job_idx = Some(self.jobs.len() - 1);
+        self.active_job_idx = self.newest_job_idx();
     }
 
-    fn push_new_job(&mut self, label: String, full_data: Vec<u8>) {
+    /// Agrega un job nuevo (simulando la impresión si corresponde) y devuelve su id.
+    fn push_new_job(&mut self, label: String, full_data: Vec<u8>) -> u64 {
+        // Con la vista congelada no se toca el job activo ni se simula el nuevo.
+        let simulate = self.simulate_printing && !self.freeze_view;
+
         // Si hay una simulación activa, la cerramos mostrando el job completo.
-        self.stop_active_simulation_show_full();
+        if simulate {
+            self.stop_active_simulation_show_full();
+        }
 
         let id = self.next_job_id;
         self.next_job_id = self.next_job_id.saturating_add(1);
@@ -546,29 +2892,157 @@ impl EscPosViewer {
             id,
             label,
             created_at: Instant::now(),
+            received_at: SystemTime::now(),
+            pinned: false,
             full_bytes: full_data,
             display_bytes: Vec::new(),
             parsed_commands: Vec::new(),
+            command_spans: Vec::new(),
+            order_cuts: Vec::new(),
+            table_stops: HashMap::new(),
             sim_active: false,
             sim_started_at: None,
             sim_sent: 0,
+            sim_boundaries: Vec::new(),
+            sim_next_burst_at: None,
+            edit_undo: Vec::new(),
+            changed_lines: Vec::new(),
+            note: String::new(),
+            paper_width: None,
         };
 
-        if self.simulate_printing {
+        if simulate {
             job.sim_active = true;
             job.sim_started_at = Some(Instant::now());
             job.display_bytes = Vec::with_capacity(job.full_bytes.len());
             job.parsed_commands.clear();
+            job.command_spans.clear();
+            job.order_cuts.clear();
+            job.table_stops.clear();
             job.sim_sent = 0;
         } else {
             job.display_bytes = job.full_bytes.clone();
-            job.parsed_commands = parse_escpos(&job.display_bytes, self.codepage);
+            job.set_parsed(parse_escpos_with_spans(
+                &job.display_bytes,
+                &self.parse_options(),
+            ));
             job.sim_sent = job.display_bytes.len();
         }
 
-        self.jobs.push(job);
-        self.active_job_idx = Some(self.jobs.len() - 1);
+        if crate::diag_log::is_enabled() {
+            Self::log_new_job(&job, self.parse_options());
+        }
+
+        let new_idx = if self.newest_first {
+            self.jobs.insert(0, job);
+            // El job que se estaba viendo se corrió una pestaña a la derecha.
+            self.active_job_idx = self.active_job_idx.map(|idx| idx + 1);
+            0
+        } else {
+            self.jobs.push(job);
+            self.jobs.len() - 1
+        };
+        if self.freeze_view {
+            self.frozen_new_jobs += 1;
+        } else {
+            self.active_job_idx = Some(new_idx);
+        }
+        self.last_activity = Instant::now();
         self.prune_jobs();
+        id
+    }
+
+    /// Registra el job recibido y cuántos comandos no reconoció el parser.
+    fn log_new_job(job: &JobEntry, options: ParseOptions) {
+        crate::diag_log::log_job(
+            job.id,
+            &format!("recibido: {} ({} bytes)", job.label, job.full_bytes.len()),
+        );
+        let commands = if job.display_bytes.len() == job.full_bytes.len() {
+            job.parsed_commands.clone()
+        } else {
+            parse_escpos(&job.full_bytes, &options)
+        };
+        let unknown: Vec<String> = coverage_report(&commands)
+            .into_iter()
+            .filter(|e| e.support == Support::Unsupported)
+            .map(|e| format!("{} x{}", e.command, e.count))
+            .collect();
+        if !unknown.is_empty() {
+            crate::diag_log::log_job(
+                job.id,
+                &format!("comandos no soportados: {}", unknown.join(", ")),
+            );
+        }
+    }
+
+    /// Pasa al hilo de captura qué hacer con la ventana al llegar un job.
+    /// Con la vista congelada nunca se muestra.
+    fn sync_capture_window_prefs(&self) {
+        if let Some(cap) = &self.tcp_capture {
+            cap.set_show_on_job(self.show_on_job && !self.freeze_view);
+            cap.set_focus_on_job(self.steal_focus_on_job);
+        }
+    }
+
+    fn compact_active(&self) -> bool {
+        self.compact_mode && self.ui_mode == UiMode::Preview
+    }
+
+    fn set_freeze_view(&mut self, frozen: bool) {
+        self.freeze_view = frozen;
+        self.sync_capture_window_prefs();
+        if !frozen {
+            // Al liberar la vista se muestra el último job recibido.
+            if self.frozen_new_jobs > 0 {
+                self.active_job_idx = self.newest_job_idx();
+            }
+            self.frozen_new_jobs = 0;
+        }
+    }
+
+    /// Job de calibración: regla de columnas y líneas de `#` al ancho exacto de cada fuente,
+    /// para comparar con un ticket impreso en la impresora real.
+    fn calibration_ruler_bytes(paper_width: PaperWidth) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        out.extend_from_slice(&[0x1B, 0x40]); // ESC @
+
+        for is_font_b in [false, true] {
+            let cols = Self::base_columns(paper_width, is_font_b);
+            // ESC M n
+            out.extend_from_slice(&[0x1B, 0x4D, is_font_b as u8]);
+
+            let font = if is_font_b { "B" } else { "A" };
+            out.extend_from_slice(format!("Fuente {font}: {cols} columnas\n").as_bytes());
+
+            // Decenas (solo en múltiplos de 10) y unidades.
+            let tens: String = (0..cols)
+                .map(|c| {
+                    if c % 10 == 0 {
+                        char::from(b'0' + ((c / 10) % 10) as u8)
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            let units: String = (0..cols)
+                .map(|c| char::from(b'0' + (c % 10) as u8))
+                .collect();
+            out.extend_from_slice(tens.as_bytes());
+            out.push(b'\n');
+            out.extend_from_slice(units.as_bytes());
+            out.push(b'\n');
+
+            for _ in 0..3 {
+                out.extend_from_slice("#".repeat(cols).as_bytes());
+                out.push(b'\n');
+            }
+            out.push(b'\n');
+        }
+
+        out.extend_from_slice(&[0x1B, 0x4D, 0x00]);
+        out.extend_from_slice(&[0x1D, 0x56, 0x00]); // GS V 0
+        out
     }
 
     fn target_window_width_px(paper_width: PaperWidth) -> f32 {
@@ -711,6 +3185,63 @@ impl EscPosViewer {
         painter.rect_filled(indicator_rect, 1.0, indicator_color);
     }
 
+    /// Oscurece en degradé las franjas superior e inferior del ticket, como el papel que se
+    /// curva al salir del rollo.
+    fn draw_paper_curl(painter: &egui::Painter, rect: egui::Rect) {
+        const STEPS: usize = 6;
+        const STEP_HEIGHT: f32 = 2.0;
+        for i in 0..STEPS {
+            let alpha = (14 - i * 2) as u8;
+            let color = egui::Color32::from_black_alpha(alpha);
+            let offset = i as f32 * STEP_HEIGHT;
+            let top = egui::Rect::from_min_size(
+                egui::pos2(rect.left(), rect.top() + offset),
+                egui::vec2(rect.width(), STEP_HEIGHT),
+            );
+            let bottom = egui::Rect::from_min_size(
+                egui::pos2(rect.left(), rect.bottom() - offset - STEP_HEIGHT),
+                egui::vec2(rect.width(), STEP_HEIGHT),
+            );
+            painter.rect_filled(top, 0.0, color);
+            painter.rect_filled(bottom, 0.0, color);
+        }
+    }
+
+    /// Celda de la grilla de caracteres (Font A, tamaño normal): ancho del papel dividido por las
+    /// columnas y alto del avance de una línea en el estado de encendido, el mismo que calcula
+    /// `LineMetrics` al dibujar el ticket.
+    fn char_grid_cell(paper_width: PaperWidth, content_width: f32) -> egui::Vec2 {
+        let state = PrinterState::default();
+        let cols = Self::effective_columns(paper_width, &state) as f32;
+        let dots_to_pixels = content_width / paper_width.dots();
+        let line_px = LineMetrics::default().finish(&state, dots_to_pixels);
+        egui::vec2(content_width / cols, line_px)
+    }
+
+    /// Grilla tenue sobre el contenido del ticket (cada 10 columnas, una línea más marcada).
+    fn draw_char_grid(painter: &egui::Painter, rect: egui::Rect, cell: egui::Vec2) {
+        if cell.x <= 0.0 || cell.y <= 0.0 {
+            return;
+        }
+        let faint = egui::Stroke::new(0.5, egui::Color32::from_rgba_unmultiplied(0, 120, 255, 40));
+        let strong =
+            egui::Stroke::new(0.5, egui::Color32::from_rgba_unmultiplied(0, 120, 255, 110));
+        let cols = (rect.width() / cell.x).round() as usize;
+        for col in 0..=cols {
+            let x = rect.left() + col as f32 * cell.x;
+            painter.vline(
+                x,
+                rect.y_range(),
+                if col % 10 == 0 { strong } else { faint },
+            );
+        }
+        let mut y = rect.top();
+        while y <= rect.bottom() {
+            painter.hline(rect.x_range(), y, faint);
+            y += cell.y;
+        }
+    }
+
     /// Crea una sombra curvada más realista para el ticket
     fn get_curved_shadow() -> egui::Shadow {
         egui::Shadow {
@@ -798,9 +3329,58 @@ impl EscPosViewer {
         }
     }
 
+    /// Fin de cada línea o elemento gráfico (en bytes de `data`): los puntos donde un POS real
+    /// suele hacer una pausa entre ráfagas. Siempre termina en `data.len()`.
+    fn burst_boundaries(data: &[u8], options: ParseOptions) -> Vec<usize> {
+        let (commands, spans) = parse_escpos_with_spans(data, &options);
+        let mut boundaries: Vec<usize> = commands
+            .iter()
+            .zip(&spans)
+            .filter(|((_, cmd), _)| {
+                matches!(
+                    cmd,
+                    CommandType::Control(
+                        Control::Newline
+                            | Control::FormFeed
+                            | Control::FeedLines(_)
+                            | Control::FeedDots(_)
+                            | Control::Cut { .. }
+                            | Control::RasterImage { .. }
+                            | Control::BitImage { .. }
+                            | Control::NvLogo { .. }
+                            | Control::Qr { .. }
+                            | Control::TwoDimBarcode { .. }
+                            | Control::Barcode { .. }
+                    )
+                )
+            })
+            .map(|(_, span)| span.end)
+            .collect();
+        boundaries.dedup();
+        if boundaries.last() != Some(&data.len()) {
+            boundaries.push(data.len());
+        }
+        boundaries
+    }
+
+    /// Pausa tras una ráfaga de `len` bytes: mantiene en promedio la velocidad configurada,
+    /// con un mínimo perceptible y una variación pseudoaleatoria (±50%) según `seed`.
+    fn burst_pause(len: usize, bytes_per_sec: usize, seed: u64) -> Duration {
+        // splitmix64: suficiente para variar las pausas sin depender de un crate de azar.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        let jitter = 0.5 + (z % 1000) as f32 / 1000.0;
+
+        let base = (len as f32 / bytes_per_sec.max(1) as f32).max(0.03);
+        Duration::from_secs_f32(base * jitter)
+    }
+
     fn tick_simulation(&mut self) {
         let bytes_per_sec = self.sim_bytes_per_sec;
-        let codepage = self.codepage;
+        let burst_mode = self.sim_burst_mode;
+        let options = self.parse_options();
         let Some(job) = self.active_job_mut() else {
             return;
         };
@@ -811,104 +3391,1375 @@ impl EscPosViewer {
             return;
         };
 
-        let elapsed = start.elapsed().as_secs_f32();
-        let target = (elapsed * bytes_per_sec as f32) as usize;
+        let target = if burst_mode {
+            if job.sim_boundaries.is_empty() {
+                job.sim_boundaries = Self::burst_boundaries(&job.full_bytes, options);
+            }
+            let now = Instant::now();
+            if job.sim_next_burst_at.is_some_and(|at| now < at) {
+                job.sim_sent
+            } else {
+                let next = job
+                    .sim_boundaries
+                    .iter()
+                    .copied()
+                    .find(|&b| b > job.sim_sent)
+                    .unwrap_or(job.full_bytes.len());
+                let pause = Self::burst_pause(
+                    next - job.sim_sent,
+                    bytes_per_sec,
+                    job.id ^ ((next as u64) << 20),
+                );
+                job.sim_next_burst_at = Some(now + pause);
+                next
+            }
+        } else {
+            let elapsed = start.elapsed().as_secs_f32();
+            (elapsed * bytes_per_sec as f32) as usize
+        };
         let target = target.min(job.full_bytes.len());
 
+        let mut drawer_kicked = false;
         if target > job.sim_sent {
+            let kicks_before = Self::drawer_kicks(&job.parsed_commands);
             job.display_bytes
                 .extend_from_slice(&job.full_bytes[job.sim_sent..target]);
             job.sim_sent = target;
-            job.parsed_commands = parse_escpos(&job.display_bytes, codepage);
+            // Mientras faltan bytes, un comando partido por el corte de la simulación espera
+            // al resto en vez de dibujarse como basura.
+            job.set_parsed(if target < job.full_bytes.len() {
+                parse_escpos_incremental(&job.display_bytes, &options)
+            } else {
+                parse_escpos_with_spans(&job.display_bytes, &options)
+            });
+            // El pulso sale cuando llega el último byte del ESC p.
+            drawer_kicked = Self::drawer_kicks(&job.parsed_commands) > kicks_before;
         }
 
         if job.sim_sent >= job.full_bytes.len() {
             job.sim_active = false;
             job.sim_started_at = None;
+            job.sim_boundaries = Vec::new();
+            job.sim_next_burst_at = None;
+        }
+
+        if drawer_kicked {
+            self.drawer_anim_started = Some(Instant::now());
         }
     }
 
+    fn drawer_kicks(commands: &[ParsedCommand]) -> usize {
+        commands
+            .iter()
+            .filter(|(_, cmd)| matches!(cmd, CommandType::Control(Control::OpenDrawer)))
+            .count()
+    }
+
     fn set_tcp_capture(&mut self, enabled: bool, repaint_ctx: Option<egui::Context>) {
         if enabled {
             if self.tcp_capture.is_some() {
                 return;
             }
-            match TcpCapture::start("127.0.0.1:9100", repaint_ctx, Some(self.window.clone())) {
+            let addr = self.tcp_bind_addr();
+            match TcpCapture::start(
+                &addr,
+                self.capture_limits,
+                repaint_ctx,
+                Some(self.window.clone()),
+            ) {
                 Ok(capture) => {
+                    crate::diag_log::log(&format!("TCP: escuchando en {addr}"));
                     self.tcp_capture = Some(capture);
-                    self.tcp_last_error = None;
+                    self.sync_capture_window_prefs();
+                    self.reset_tcp_retry();
                     // Al empezar a escuchar, ponemos la impresora ONLINE
                     let _ = crate::printer_setup::set_printer_offline(false);
                 }
                 Err(e) => {
-                    self.tcp_last_error =
-                        Some(format!("No se pudo escuchar 127.0.0.1:9100 ({})", e));
+                    // Puerto ocupado (p.ej. por un spooler real): se reintenta cada vez más
+                    // espaciado y, si sigue fallando, se busca un puerto libre para ofrecerlo.
+                    self.tcp_bind_failures += 1;
+                    let delay = bind_retry_delay(self.tcp_bind_failures);
+                    self.tcp_retry_at = Some(Instant::now() + delay);
+                    if self.tcp_bind_failures >= 2 && self.tcp_port_suggestion.is_none() {
+                        let current = self.tcp_port;
+                        self.tcp_port_suggestion =
+                            first_free_port(FALLBACK_PORTS.filter(|&p| p != current));
+                    }
+                    crate::diag_log::log(&format!(
+                        "TCP: no se pudo escuchar {addr}: {e} (reintento en {} s)",
+                        delay.as_secs()
+                    ));
+                    self.tcp_last_error = Some(format!(
+                        "No se pudo escuchar {addr} ({e}); reintento en {} s",
+                        delay.as_secs()
+                    ));
                     self.tcp_capture = None;
                 }
             }
-        } else if let Some(mut cap) = self.tcp_capture.take() {
-            cap.stop();
-            self.tcp_capture = None;
-            // Al dejar de escuchar, ponemos la impresora OFFLINE para retener trabajos
-            let _ = crate::printer_setup::set_printer_offline(true);
+        } else {
+            self.reset_tcp_retry();
+            if let Some(mut cap) = self.tcp_capture.take() {
+                cap.stop();
+                crate::diag_log::log("TCP: listener detenido");
+                // Al dejar de escuchar, ponemos la impresora OFFLINE para retener trabajos
+                let _ = crate::printer_setup::set_printer_offline(true);
+            }
         }
     }
 
-    fn try_load_path(&mut self, path: &Path) {
-        if let Ok(data) = fs::read(path) {
-            self.push_new_job(path.display().to_string(), data);
-        }
+    /// Dirección del listener: solo loopback salvo que se acepten jobs de la red.
+    fn tcp_bind_addr(&self) -> String {
+        let host = if self.capture_limits.allow_remote {
+            "0.0.0.0"
+        } else {
+            "127.0.0.1"
+        };
+        format!("{host}:{}", self.tcp_port)
     }
 
-    fn reparse_all_jobs(&mut self) {
-        for job in &mut self.jobs {
-            if job.display_bytes.is_empty() {
-                job.parsed_commands.clear();
-                continue;
+    fn reset_tcp_retry(&mut self) {
+        self.tcp_last_error = None;
+        self.tcp_bind_failures = 0;
+        self.tcp_retry_at = None;
+        self.tcp_port_suggestion = None;
+    }
+
+    /// Deja de escuchar donde estaba y vuelve a intentarlo en `port` (se guarda en la config).
+    fn use_tcp_port(&mut self, port: u16, ctx: &egui::Context) {
+        self.set_tcp_capture(false, None);
+        self.tcp_port = port;
+        self.tcp_enabled = true;
+        self.set_tcp_capture(true, Some(ctx.clone()));
+    }
+
+    /// "Usar 91xx" cuando el puerto sigue ocupado y hay uno libre cerca; "Volver a 9100" si se
+    /// está usando otro.
+    fn ui_tcp_port_recovery(&mut self, ui: &mut egui::Ui) {
+        if self.tcp_capture.is_none() {
+            if let Some(port) = self.tcp_port_suggestion {
+                if ui
+                    .button(format!("Usar {port}"))
+                    .on_hover_text(format!(
+                        "El puerto {} sigue ocupado. Escuchar en {port} (el POS tiene que \
+                         imprimir a 127.0.0.1:{port})",
+                        self.tcp_port
+                    ))
+                    .clicked()
+                {
+                    self.use_tcp_port(port, ui.ctx());
+                }
             }
-            job.parsed_commands = parse_escpos(&job.display_bytes, self.codepage);
+        }
+        if self.tcp_port != DEFAULT_PORT
+            && ui
+                .small_button(format!("Volver a {DEFAULT_PORT}"))
+                .on_hover_text("La impresora virtual de Windows imprime a 127.0.0.1:9100")
+                .clicked()
+        {
+            self.use_tcp_port(DEFAULT_PORT, ui.ctx());
         }
     }
 
-    fn debug_label_for_control(control: &Control) -> String {
-        match control {
-            Control::Newline => "LF".to_string(),
-            Control::Tab => "HT (TAB)".to_string(),
-            Control::Init => "ESC @ (INIT)".to_string(),
-            Control::Bold(on) => format!("ESC E (BOLD={})", on),
-            Control::Align(align) => format!("ESC a (ALIGN={:?})", align),
-            Control::CodePage(cp) => format!("ESC t (CODEPAGE={:?})", cp),
-            Control::Size { raw, width, height } => {
-                format!("GS ! (SIZE raw={:02X} w={} h={})", raw, width, height)
+    /// Pantalla de bienvenida cuando no hay jobs: estado de la impresora virtual y del
+    /// listener, con los pasos para dejar todo listo.
+    fn ui_onboarding(&mut self, ui: &mut egui::Ui) {
+        let status_row = |ui: &mut egui::Ui, ok: bool, text: &str| {
+            ui.horizontal(|ui| {
+                let color = if ok {
+                    egui::Color32::from_rgb(40, 160, 70)
+                } else {
+                    egui::Color32::from_rgb(200, 60, 50)
+                };
+                ui.label(egui::RichText::new("●").color(color));
+                ui.label(text);
+            });
+        };
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(8.0);
+            ui.label(egui::RichText::new("Visor ESC/POS").strong().size(18.0));
+            ui.label(
+                egui::RichText::new("Todavía no hay tickets. Sigue estos pasos para empezar:")
+                    .color(egui::Color32::GRAY),
+            );
+        });
+        ui.add_space(12.0);
+
+        // 1. Impresora virtual (Windows)
+        ui.label(egui::RichText::new("1. Impresora virtual").strong());
+        if cfg!(windows) {
+            self.poll_printer_probe(ui.ctx());
+            let stale = self
+                .printer_probed_at
+                .is_none_or(|at| at.elapsed() >= Self::PRINTER_PROBE_INTERVAL);
+            if self.printer_probe_rx.is_none() && stale {
+                self.printer_probe_rx = Some(crate::printer_setup::probe_printer());
             }
-            Control::Cut => "GS V (CUT)".to_string(),
-            Control::RasterImage {
-                m,
-                width_bytes,
-                height,
-                data,
-            } => {
-                format!(
-                    "GS v 0 (IMG m={:02X} {}x{} bytes={})",
-                    m,
-                    (*width_bytes as usize) * 8,
-                    *height as usize,
-                    data.len()
-                )
+            match self.printer_installed {
+                None => status_row(ui, false, "Consultando…"),
+                Some(true) => status_row(ui, true, "Instalada: ESCPos Viewer (TCP 9100)"),
+                Some(false) => {
+                    status_row(ui, false, "No instalada");
+                    if ui
+                        .add_enabled(
+                            self.printer_probe_rx.is_none(),
+                            egui::Button::new("🖨 Instalar impresora"),
+                        )
+                        .on_hover_text("Requiere ejecutar el visor como Administrador")
+                        .clicked()
+                    {
+                        self.printer_setup_message = None;
+                        self.printer_probe_rx =
+                            Some(crate::printer_setup::install_printer_in_background());
+                    }
+                }
             }
-            Control::Qr {
-                model,
-                module_size,
-                ecc,
-                data,
-            } => format!(
-                "QR (model={} size={} ecc={} bytes={})",
-                model,
-                module_size,
-                ecc,
-                data.len()
-            ),
-            Control::Barcode { m, data } => {
+            if let Some(msg) = &self.printer_setup_message {
+                ui.label(egui::RichText::new(msg).color(egui::Color32::RED).small());
+            }
+        } else {
+            ui.label(
+                egui::RichText::new("Solo en Windows; envía los tickets por TCP al puerto 9100.")
+                    .color(egui::Color32::GRAY)
+                    .small(),
+            );
+        }
+        ui.add_space(8.0);
+
+        // 2. Captura TCP
+        ui.label(egui::RichText::new("2. Captura TCP").strong());
+        if self.tcp_capture.is_some() {
+            status_row(ui, true, &format!("Escuchando en {}", self.tcp_bind_addr()));
+            self.ui_tcp_port_recovery(ui);
+        } else {
+            let text = self
+                .tcp_last_error
+                .clone()
+                .unwrap_or_else(|| "Listener desactivado".to_string());
+            status_row(ui, false, &text);
+            if ui.button("▶ Activar captura").clicked() {
+                self.tcp_enabled = true;
+                self.reset_tcp_retry();
+                self.set_tcp_capture(true, Some(ui.ctx().clone()));
+            }
+            self.ui_tcp_port_recovery(ui);
+        }
+        ui.add_space(8.0);
+
+        // 3. Probar
+        ui.label(egui::RichText::new("3. Probar").strong());
+        if ui.button("🧾 Cargar archivo de ejemplo").clicked() {
+            self.push_new_job(
+                "Ticket de ejemplo".to_string(),
+                crate::sample_ticket::sample_ticket(),
+            );
+        }
+        if ui
+            .add_enabled(
+                self.tcp_test_rx.is_none(),
+                egui::Button::new("📡 Enviar prueba por TCP"),
+            )
+            .clicked()
+        {
+            self.send_tcp_test();
+        }
+        ui.add_space(12.0);
+        ui.label(
+            egui::RichText::new("También puedes arrastrar un .prn/.bin a esta ventana.")
+                .color(egui::Color32::GRAY)
+                .size(12.0),
+        );
+    }
+
+    /// Manda el ticket de ejemplo al listener por un socket real (captura → parseo → render).
+    /// Sin listener se intenta igual contra el puerto configurado para que el fallo quede a la
+    /// vista.
+    fn send_tcp_test(&mut self) {
+        let addr = self
+            .tcp_capture
+            .as_ref()
+            .map(TcpCapture::local_addr)
+            .unwrap_or_else(|| ([127, 0, 0, 1], self.tcp_port).into());
+        self.tcp_test_rx = Some(send_test_job(addr, crate::sample_ticket::sample_ticket()));
+    }
+
+    /// Cada cuánto se vuelve a consultar la impresora mientras se ve la bienvenida.
+    const PRINTER_PROBE_INTERVAL: Duration = Duration::from_secs(15);
+
+    /// Recibe el resultado de `probe_printer` / `install_printer_in_background`.
+    fn poll_printer_probe(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.printer_probe_rx else {
+            ctx.request_repaint_after(Self::PRINTER_PROBE_INTERVAL);
+            return;
+        };
+        match rx.try_recv() {
+            Ok(result) => {
+                self.printer_probe_rx = None;
+                self.printer_probed_at = Some(Instant::now());
+                match result {
+                    Ok(installed) => self.printer_installed = Some(installed),
+                    Err(e) => self.printer_setup_message = Some(e),
+                }
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(Duration::from_millis(200));
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.printer_probe_rx = None;
+                self.printer_probed_at = Some(Instant::now());
+            }
+        }
+    }
+
+    fn poll_tcp_test(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.tcp_test_rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(result) => {
+                self.tcp_test_rx = None;
+                self.show_toast(match result {
+                    Ok(()) => "📡 Prueba enviada por TCP".to_string(),
+                    Err(err) => err,
+                });
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(Duration::from_millis(50));
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => self.tcp_test_rx = None,
+        }
+    }
+
+    /// Pasa el texto del campo a `global_hotkey` si es una combinación válida y distinta de
+    /// la actual; si no es válida queda el error y el atajo registrado no se toca.
+    fn commit_hotkey_draft(&mut self) {
+        let draft = self.hotkey_draft.trim();
+        match parse_hotkey(draft) {
+            Ok(hotkey) => {
+                if parse_hotkey(&self.global_hotkey).ok() != Some(hotkey) {
+                    self.global_hotkey = draft.to_string();
+                }
+                self.hotkey_draft = self.global_hotkey.clone();
+                self.hotkey_draft_error = None;
+            }
+            Err(e) => self.hotkey_draft_error = Some(e),
+        }
+    }
+
+    /// Registra (o suelta) el atajo global cuando cambia la configuración. Un fallo queda en
+    /// `hotkey_error` y no se reintenta hasta que se cambie la combinación.
+    fn sync_global_hotkey(&mut self, ctx: &egui::Context) {
+        let wanted = self
+            .global_hotkey_enabled
+            .then(|| self.global_hotkey.trim().to_string());
+        if wanted == self.hotkey_applied {
+            return;
+        }
+        // Soltar el anterior antes de registrar el nuevo (puede ser la misma tecla).
+        self.hotkey = None;
+        self.hotkey_error = None;
+        self.hotkey_applied = wanted.clone();
+        let Some(text) = wanted else {
+            return;
+        };
+        let started = parse_hotkey(&text).and_then(|hotkey| {
+            GlobalHotkey::start(hotkey, self.window.clone(), ctx.clone())
+                .map_err(|e| format!("No se pudo registrar {text}: {e}"))
+        });
+        match started {
+            Ok(hotkey) => {
+                crate::diag_log::log(&format!("Atajo global: {text}"));
+                self.hotkey = Some(hotkey);
+            }
+            Err(e) => self.hotkey_error = Some(e),
+        }
+    }
+
+    fn poll_global_hotkey(&mut self) {
+        let Some(hotkey) = &self.hotkey else {
+            return;
+        };
+        for event in hotkey.try_recv_all() {
+            // El hilo ya mostró/ocultó la ventana; acá solo se sigue el estado.
+            self.hidden_to_tray = event == HotkeyEvent::Hidden;
+            self.last_activity = Instant::now();
+        }
+    }
+
+    /// Arranca / detiene el hilo del webhook según la configuración y le pasa los cambios.
+    /// Se llama al arrancar y al confirmar un cambio en Configuración.
+    fn sync_webhook(&mut self) {
+        let url = self.webhook_url.trim();
+        if !self.webhook_enabled || url.is_empty() {
+            if let Some(sender) = self.webhook.take() {
+                let pending = sender.pending();
+                if pending > 0 {
+                    crate::diag_log::log(&format!(
+                        "Webhook: desactivado con {pending} job(s) sin enviar"
+                    ));
+                }
+            }
+            self.webhook_last_error = None;
+            return;
+        }
+        let config = WebhookConfig {
+            url: url.to_string(),
+            auth_header: self.webhook_auth.clone(),
+            format: self.webhook_format,
+        };
+        match &self.webhook {
+            Some(sender) => sender.set_config(config),
+            None => self.webhook = Some(WebhookSender::start(config)),
+        }
+    }
+
+    fn poll_webhook(&mut self, ctx: &egui::Context) {
+        let Some(sender) = &self.webhook else {
+            return;
+        };
+        let events = sender.try_recv_all();
+        if sender.pending() > 0 {
+            // El hilo reintenta solo; se repinta para refrescar el estado.
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+        for event in events {
+            match event {
+                WebhookEvent::Sent { .. } => {
+                    self.webhook_sent += 1;
+                    self.webhook_last_error = None;
+                }
+                WebhookEvent::Failed {
+                    id,
+                    error,
+                    retry_in,
+                } => {
+                    self.webhook_last_error = Some(format!(
+                        "Job #{id}: {error} (reintento en {} s)",
+                        retry_in.as_secs()
+                    ));
+                }
+                WebhookEvent::Dropped { id } => {
+                    self.show_toast(format!(
+                        "⚠ Webhook: cola llena, job #{id} descartado sin enviar"
+                    ));
+                }
+            }
+        }
+    }
+
+    fn try_load_path(&mut self, path: &Path) {
+        if let Ok(data) = fs::read(path) {
+            self.push_new_job(path.display().to_string(), data);
+            self.last_opened_file = Some(path.to_path_buf());
+        }
+    }
+
+    /// Tabla con la que arranca el parser: la inicial configurada o, si no hay, la del visor.
+    fn start_codepage(&self) -> CodePage {
+        self.initial_codepage.unwrap_or(self.codepage)
+    }
+
+    /// Estado inicial del parser (codepage y alineación antes de ESC t / ESC a).
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            codepage: self.start_codepage(),
+            alignment: self.default_alignment,
+            ..ParseOptions::default()
+        }
+    }
+
+    fn align_label(align: Align) -> &'static str {
+        match align {
+            Align::Left => "Izquierda",
+            Align::Center => "Centro",
+            Align::Right => "Derecha",
+        }
+    }
+
+    fn cycle_codepage(&mut self) {
+        if let Some(initial) = self.initial_codepage {
+            self.show_toast(format!(
+                "Codepage fijo en {} (codepage inicial de Ajustes)",
+                initial.label()
+            ));
+            return;
+        }
+        self.codepage = self.codepage.next();
+        let changed = self.reparse_all_jobs();
+        self.show_toast(if changed == 0 {
+            format!("Codepage: {} (el texto no cambia)", self.codepage.label())
+        } else {
+            format!("Codepage: {}", self.codepage.label())
+        });
+    }
+
+    fn show_toast(&mut self, text: String) {
+        self.toast = Some((text, Instant::now()));
+    }
+
+    /// Pega un job desde el portapapeles: archivos copiados en el explorador, un volcado hex
+    /// o, si no, el texto tal cual.
+    fn paste_from_clipboard(&mut self) {
+        let mut clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => clipboard,
+            Err(e) => {
+                self.show_toast(format!("No se pudo abrir el portapapeles: {e}"));
+                return;
+            }
+        };
+
+        if let Ok(paths) = clipboard.get().file_list() {
+            if !paths.is_empty() {
+                for path in &paths {
+                    self.try_load_path(path);
+                }
+                self.show_toast(format!("Pegado: {} archivo(s)", paths.len()));
+                return;
+            }
+        }
+
+        match clipboard.get_text() {
+            Ok(text) if !text.trim().is_empty() => {
+                if let Some(bytes) = parse_hex_dump(&text) {
+                    let len = bytes.len();
+                    self.push_new_job("Pegado (hex)".to_string(), bytes);
+                    self.show_toast(format!("Pegado como volcado hex ({len} bytes)"));
+                } else {
+                    let bytes = text.into_bytes();
+                    let len = bytes.len();
+                    self.push_new_job("Pegado (texto)".to_string(), bytes);
+                    self.show_toast(format!("Pegado como texto ({len} bytes)"));
+                }
+            }
+            _ => self
+                .show_toast("El portapapeles no tiene texto, volcado hex ni archivos".to_string()),
+        }
+    }
+
+    /// Aviso flotante (codepage elegido, resultado de pegar); se desvanece al final.
+    fn ui_toast(&mut self, ctx: &egui::Context) {
+        const VISIBLE: Duration = Duration::from_millis(1200);
+        const FADE: Duration = Duration::from_millis(500);
+
+        let Some((text, shown_at)) = &self.toast else {
+            return;
+        };
+        let elapsed = shown_at.elapsed();
+        if elapsed >= VISIBLE + FADE {
+            self.toast = None;
+            return;
+        }
+        let opacity = if elapsed <= VISIBLE {
+            1.0
+        } else {
+            1.0 - (elapsed - VISIBLE).as_secs_f32() / FADE.as_secs_f32()
+        };
+
+        egui::Area::new(egui::Id::new("toast"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 48.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.set_opacity(opacity);
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(egui::RichText::new(text).strong());
+                });
+            });
+        ctx.request_repaint();
+    }
+
+    /// Cajón que se abre deslizándose (solo durante la simulación, al enviarse un `ESC p`).
+    fn ui_drawer_animation(&mut self, ctx: &egui::Context) {
+        const DURATION: Duration = Duration::from_millis(1600);
+
+        let Some(started) = self.drawer_anim_started else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        if elapsed >= DURATION {
+            self.drawer_anim_started = None;
+            return;
+        }
+        let t = elapsed.as_secs_f32() / DURATION.as_secs_f32();
+        // Sale rápido, queda abierto y se desvanece en el último cuarto.
+        let open = 1.0 - (1.0 - (t / 0.3).min(1.0)).powi(3);
+        let opacity = if t < 0.75 { 1.0 } else { (1.0 - t) / 0.25 };
+
+        egui::Area::new(egui::Id::new("drawer_animation"))
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -24.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.set_opacity(opacity);
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::vec2(120.0, 72.0), egui::Sense::hover());
+                    let painter = ui.painter();
+                    let body_color = egui::Color32::from_gray(70);
+                    let cabinet = egui::Rect::from_min_size(
+                        rect.left_top() + egui::vec2(10.0, 4.0),
+                        egui::vec2(100.0, 30.0),
+                    );
+                    painter.rect_filled(cabinet, 3.0, body_color);
+                    // Cajón: baja desde el frente del mueble.
+                    let drawer = egui::Rect::from_min_size(
+                        egui::pos2(cabinet.left() + 6.0, cabinet.bottom() - 12.0 + 16.0 * open),
+                        egui::vec2(88.0, 12.0),
+                    );
+                    painter.rect_filled(drawer, 2.0, egui::Color32::from_rgb(217, 119, 6));
+                    painter.hline(
+                        (drawer.center().x - 10.0)..=(drawer.center().x + 10.0),
+                        drawer.center().y,
+                        egui::Stroke::new(2.0, body_color),
+                    );
+                    painter.text(
+                        egui::pos2(rect.center().x, rect.bottom() - 4.0),
+                        egui::Align2::CENTER_BOTTOM,
+                        "🔓 Cajón abierto",
+                        egui::FontId::proportional(11.0),
+                        ui.visuals().text_color(),
+                    );
+                });
+            });
+        ctx.request_repaint();
+    }
+
+    /// Cortes parciales que cierran una orden de cocina: vienen después de un avance (GS V 66 n,
+    /// ESC d / ESC J o al menos dos saltos de línea) y les sigue otro ticket con contenido.
+    fn kitchen_order_cuts(commands: &[ParsedCommand]) -> Vec<usize> {
+        let fed_before = |idx: usize| {
+            let mut newlines = 0;
+            for (_, cmd) in commands[..idx].iter().rev() {
+                match cmd {
+                    CommandType::Control(Control::Newline | Control::FormFeed) => newlines += 1,
+                    CommandType::Control(Control::FeedLines(_) | Control::FeedDots(_)) => {
+                        return true
+                    }
+                    // Parámetros sueltos y cambios de estado no cortan la secuencia.
+                    CommandType::Unknown(_)
+                    | CommandType::Control(
+                        Control::Init
+                        | Control::Bold(_)
+                        | Control::Align(_)
+                        | Control::Size { .. }
+                        | Control::Underline(_)
+                        | Control::Reverse(_)
+                        | Control::UpsideDown(_)
+                        | Control::MasterSelect(_)
+                        | Control::FontSelect(_)
+                        | Control::LineSpacing(_)
+                        | Control::LineSpacingDefault,
+                    ) => {}
+                    CommandType::Text(t) if t.trim().is_empty() => {}
+                    _ => break,
+                }
+            }
+            newlines >= 2
+        };
+        let content_after = |idx: usize| {
+            commands[idx + 1..].iter().any(|(_, cmd)| match cmd {
+                CommandType::Text(t) => !t.trim().is_empty(),
+                CommandType::Control(
+                    Control::RasterImage { .. }
+                    | Control::BitImage { .. }
+                    | Control::NvLogo { .. }
+                    | Control::Qr { .. }
+                    | Control::TwoDimBarcode { .. }
+                    | Control::Barcode { .. },
+                ) => true,
+                _ => false,
+            })
+        };
+
+        commands
+            .iter()
+            .enumerate()
+            .filter(|(idx, (_, cmd))| match cmd {
+                CommandType::Control(Control::Cut {
+                    partial: true,
+                    feed,
+                }) => (*feed > 0 || fed_before(*idx)) && content_after(*idx),
+                _ => false,
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    const ORDER_LABEL_COLOR: egui::Color32 = egui::Color32::from_rgb(70, 90, 130);
+
+    fn order_label(number: usize) -> String {
+        format!("— Orden {number} —")
+    }
+
+    /// Rótulo de una orden de cocina. Devuelve dónde empieza y, si lleva fondo, el hueco
+    /// (detrás del contenido) para pintarlo al terminar.
+    fn begin_order(
+        ui: &mut egui::Ui,
+        number: usize,
+        style: OrderSeparatorStyle,
+    ) -> (f32, Option<(egui::layers::ShapeIdx, egui::Color32)>) {
+        let top = ui.cursor().top();
+        let tint = style
+            .tint(number)
+            .map(|color| (ui.painter().add(egui::Shape::Noop), color));
+        ui.add_space(2.0);
+        ui.vertical_centered(|ui| {
+            ui.label(
+                egui::RichText::new(Self::order_label(number))
+                    .strong()
+                    .size(11.0)
+                    .color(Self::ORDER_LABEL_COLOR),
+            );
+        });
+        ui.add_space(4.0);
+        (top, tint)
+    }
+
+    fn end_order(
+        ui: &egui::Ui,
+        (top, tint): (f32, Option<(egui::layers::ShapeIdx, egui::Color32)>),
+    ) {
+        let Some((slot, color)) = tint else {
+            return;
+        };
+        let width = ui.max_rect().x_range();
+        let rect = egui::Rect::from_x_y_ranges(width, top..=ui.cursor().top());
+        ui.painter()
+            .set(slot, egui::Shape::rect_filled(rect, 0.0, color));
+    }
+
+    /// Auto-tabla: para cada renglón (por su primer comando) dentro de un bloque de al menos
+    /// dos renglones seguidos con la misma cantidad de tabs, la columna en la que termina cada
+    /// campo: el más ancho de esa columna en el bloque más dos espacios de separación.
+    fn tab_table_stops(commands: &[ParsedCommand]) -> HashMap<usize, Vec<usize>> {
+        const GAP: usize = 2;
+
+        // Ancho de cada campo entre tabs, por renglón.
+        let lines: Vec<(usize, Vec<usize>)> = Self::print_lines(commands)
+            .into_iter()
+            .map(|line| {
+                let mut fields = vec![0usize];
+                for (_, cmd) in &commands[line.clone()] {
+                    match cmd {
+                        CommandType::Text(t) => {
+                            if let Some(field) = fields.last_mut() {
+                                *field += t.chars().count();
+                            }
+                        }
+                        CommandType::Control(Control::Tab) => fields.push(0),
+                        _ => {}
+                    }
+                }
+                (line.start, fields)
+            })
+            .collect();
+
+        let mut stops = HashMap::new();
+        let mut block_start = 0;
+        while block_start < lines.len() {
+            let tabs = lines[block_start].1.len() - 1;
+            let block_end = lines[block_start..]
+                .iter()
+                .position(|(_, fields)| fields.len() - 1 != tabs)
+                .map_or(lines.len(), |len| block_start + len);
+            let block = &lines[block_start..block_end];
+            if tabs > 0 && block.len() >= 2 {
+                let mut column_stops = Vec::with_capacity(tabs);
+                let mut end = 0;
+                for field in 0..tabs {
+                    let width = block
+                        .iter()
+                        .map(|(_, fields)| fields[field])
+                        .max()
+                        .unwrap_or(0);
+                    end += width + GAP;
+                    column_stops.push(end);
+                }
+                for (first, _) in block {
+                    stops.insert(*first, column_stops.clone());
+                }
+            }
+            block_start = block_end;
+        }
+        stops
+    }
+
+    /// Job que pide imprimirse al revés: `ESC { 1` vigente en su primer contenido visible (un
+    /// `ESC @` antes lo anula).
+    fn starts_upside_down(commands: &[ParsedCommand]) -> bool {
+        commands
+            .iter()
+            .find(|(_, cmd)| match cmd {
+                CommandType::Text(t) => !t.trim().is_empty(),
+                CommandType::Control(control) => matches!(
+                    control,
+                    Control::RasterImage { .. }
+                        | Control::BitImage { .. }
+                        | Control::Qr { .. }
+                        | Control::TwoDimBarcode { .. }
+                        | Control::Barcode { .. }
+                        | Control::NvLogo { .. }
+                ),
+                CommandType::Unknown(_) => false,
+            })
+            .is_some_and(|(state, _)| state.is_upside_down)
+    }
+
+    /// Renglones impresos del job, en orden: cada uno termina en un salto de línea, un corte o
+    /// un elemento gráfico (imagen, QR, barcode). Al revés se dibujan del último al primero.
+    fn print_lines(commands: &[ParsedCommand]) -> Vec<Range<usize>> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for (idx, (_, cmd)) in commands.iter().enumerate() {
+            let ends_line = matches!(
+                cmd,
+                CommandType::Control(
+                    Control::Newline
+                        | Control::FormFeed
+                        | Control::FeedLines(_)
+                        | Control::FeedDots(_)
+                        | Control::Cut { .. }
+                        | Control::RasterImage { .. }
+                        | Control::BitImage { .. }
+                        | Control::Qr { .. }
+                        | Control::TwoDimBarcode { .. }
+                        | Control::Barcode { .. }
+                        | Control::NvLogo { .. }
+                )
+            );
+            if ends_line {
+                lines.push(start..idx + 1);
+                start = idx + 1;
+            }
+        }
+        if start < commands.len() {
+            lines.push(start..commands.len());
+        }
+        lines
+    }
+
+    /// Recorrido de `job` con las opciones del visor. Al revés, con `lines_bottom_up` los
+    /// renglones salen del último al primero (la vista previa gira cada uno; el PNG gira la
+    /// imagen entera). Las órdenes de cocina se numeran de arriba abajo: al revés no se marcan.
+    fn ticket_walk<'a>(
+        &self,
+        job: &'a JobEntry,
+        tab_fill: &'static str,
+        lines_bottom_up: bool,
+    ) -> TicketWalk<'a> {
+        let commands = &job.parsed_commands;
+        let flipped = self.flip_ticket || Self::starts_upside_down(commands);
+        let mut lines = Self::print_lines(commands);
+        if flipped && lines_bottom_up {
+            lines.reverse();
+        }
+        let draw_order: Vec<(usize, bool)> = lines
+            .into_iter()
+            .flat_map(|line| {
+                let last = line.end - 1;
+                line.map(move |idx| (idx, idx == last))
+            })
+            .collect();
+        let order_cuts: &[usize] = if self.kitchen_orders && !flipped {
+            &job.order_cuts
+        } else {
+            &[]
+        };
+        let mut steps = VecDeque::new();
+        if !order_cuts.is_empty() {
+            steps.push_back(TicketStep::Order(1));
+        }
+        TicketWalk {
+            commands,
+            draw_order: draw_order.into_iter(),
+            steps,
+            table_stops: self.tab_auto_table.then_some(&job.table_stops),
+            tab_right_align: self.tab_right_align,
+            tab_fill,
+            order_cuts,
+            order: 1,
+            flipped,
+            pending: None,
+            at_line_start: true,
+            line_stops: None,
+            line_tabs: 0,
+            line_has_text: false,
+        }
+    }
+
+    /// Gira 180° alrededor del centro de `rect` todo lo pintado en la capa de `ui` desde
+    /// `first` (texto, imágenes, barcodes y fondos de un renglón).
+    fn rotate_painted_180(ui: &egui::Ui, first: egui::layers::ShapeIdx, rect: egui::Rect) {
+        let center = rect.center();
+        ui.ctx().graphics_mut(|graphics| {
+            let list = graphics.entry(ui.layer_id());
+            let painted: Vec<egui::epaint::ClippedShape> =
+                list.all_entries().skip(first.0).cloned().collect();
+            for (offset, clipped) in painted.into_iter().enumerate() {
+                list.set(
+                    egui::layers::ShapeIdx(first.0 + offset),
+                    clipped.clip_rect,
+                    Self::rotated_180(clipped.shape, center),
+                );
+            }
+        });
+    }
+
+    fn rotated_180(shape: egui::Shape, center: egui::Pos2) -> egui::Shape {
+        let flip = |p: egui::Pos2| center + (center - p);
+        match shape {
+            egui::Shape::Vec(shapes) => egui::Shape::Vec(
+                shapes
+                    .into_iter()
+                    .map(|shape| Self::rotated_180(shape, center))
+                    .collect(),
+            ),
+            egui::Shape::Circle(mut circle) => {
+                circle.center = flip(circle.center);
+                egui::Shape::Circle(circle)
+            }
+            egui::Shape::LineSegment { points, stroke } => egui::Shape::LineSegment {
+                points: points.map(flip),
+                stroke,
+            },
+            egui::Shape::Path(mut path) => {
+                path.points.iter_mut().for_each(|p| *p = flip(*p));
+                egui::Shape::Path(path)
+            }
+            egui::Shape::Rect(mut rect) => {
+                rect.rect = egui::Rect::from_two_pos(flip(rect.rect.min), flip(rect.rect.max));
+                egui::Shape::Rect(rect)
+            }
+            // El galley gira alrededor de `pos` (su esquina superior izquierda): girado 180°,
+            // ocupa justo el rectángulo reflejado.
+            egui::Shape::Text(mut text) => {
+                text.pos = flip(text.pos);
+                text.angle += std::f32::consts::PI;
+                egui::Shape::Text(text)
+            }
+            egui::Shape::Mesh(mut mesh) => {
+                mesh.vertices.iter_mut().for_each(|v| v.pos = flip(v.pos));
+                egui::Shape::Mesh(mesh)
+            }
+            other => other,
+        }
+    }
+
+    fn cut_count(job: &JobEntry) -> usize {
+        job.parsed_commands
+            .iter()
+            .filter(|(_, cmd)| matches!(cmd, CommandType::Control(Control::Cut { .. })))
+            .count()
+    }
+
+    /// Vuelve a parsear todos los jobs con la configuración actual y resalta un momento los
+    /// renglones cuyo texto cambió. Devuelve cuántos renglones cambiaron en total.
+    fn reparse_all_jobs(&mut self) -> usize {
+        let options = self.parse_options();
+        let mut changed = 0;
+        for job in &mut self.jobs {
+            if job.display_bytes.is_empty() {
+                job.parsed_commands.clear();
+                job.command_spans.clear();
+                job.order_cuts.clear();
+                job.table_stops.clear();
+                job.changed_lines.clear();
+                continue;
+            }
+            let previous = mem::take(&mut job.parsed_commands);
+            job.set_parsed(if job.sim_active {
+                parse_escpos_incremental(&job.display_bytes, &options)
+            } else {
+                parse_escpos_with_spans(&job.display_bytes, &options)
+            });
+            job.changed_lines = Self::changed_lines(&previous, &job.parsed_commands);
+            changed += job.changed_lines.len();
+        }
+        self.reparse_flash_started = (changed > 0).then(Instant::now);
+        changed
+    }
+
+    /// Renglones de `new` (por su primer comando) cuyo texto difiere del renglón en la misma
+    /// posición de `old`, p.ej. acentos que otro codepage decodifica distinto. Salen en orden.
+    fn changed_lines(old: &[ParsedCommand], new: &[ParsedCommand]) -> Vec<usize> {
+        let line_text = |commands: &[ParsedCommand], line: Range<usize>| -> String {
+            commands[line]
+                .iter()
+                .filter_map(|(_, cmd)| match cmd {
+                    CommandType::Text(t) => Some(t.as_str()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let old_lines = Self::print_lines(old);
+        Self::print_lines(new)
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, line)| {
+                old_lines
+                    .get(*idx)
+                    .map(|old_line| line_text(old, old_line.clone()))
+                    != Some(line_text(new, line.clone()))
+            })
+            .map(|(_, line)| line.start)
+            .collect()
+    }
+
+    /// Opacidad del resaltado de renglones cambiados: fijo un momento y luego se desvanece.
+    fn reparse_flash_alpha(&mut self) -> Option<u8> {
+        const VISIBLE: Duration = Duration::from_millis(700);
+        const FADE: Duration = Duration::from_millis(800);
+        const ALPHA: f32 = 70.0;
+
+        let elapsed = self.reparse_flash_started?.elapsed();
+        if elapsed >= VISIBLE + FADE {
+            self.reparse_flash_started = None;
+            return None;
+        }
+        let opacity = if elapsed <= VISIBLE {
+            1.0
+        } else {
+            1.0 - (elapsed - VISIBLE).as_secs_f32() / FADE.as_secs_f32()
+        };
+        Some((ALPHA * opacity) as u8)
+    }
+
+    /// Línea del Log de comandos: tipo (TXT/CTL/UNK) y descripción corta.
+    fn command_log_line(cmd: &CommandType) -> String {
+        match cmd {
+            CommandType::Text(text) => {
+                let mut snippet = text.replace(['\r', '\n'], " ");
+                const MAX: usize = 60;
+                if snippet.len() > MAX {
+                    let cut = (0..=MAX)
+                        .rev()
+                        .find(|&i| snippet.is_char_boundary(i))
+                        .unwrap_or(0);
+                    snippet.truncate(cut);
+                    snippet.push('…');
+                }
+                format!("TXT  {}", snippet)
+            }
+            CommandType::Control(control) => {
+                format!("CTL  {}", Self::debug_label_for_control(control))
+            }
+            CommandType::Unknown(byte) => format!("UNK  {:02X}", byte),
+        }
+    }
+
+    /// Lista de comandos del job activo (panel lateral o ventana separada). Un clic selecciona
+    /// el comando y desplaza el render y la vista cruda hasta él.
+    fn ui_command_log(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .id_salt("cmd_scroll")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                let Some(job) = self.active_job() else {
+                    ui.label(egui::RichText::new("(sin comandos)").weak());
+                    return;
+                };
+                let mut clicked = None;
+                let mut hovered = None;
+                for (idx, (_state, cmd)) in job.parsed_commands.iter().enumerate() {
+                    let line = Self::command_log_line(cmd);
+                    let row = ui.add(egui::SelectableLabel::new(
+                        self.selected_cmd == Some(idx),
+                        egui::RichText::new(format!("{:04}: {}", idx, line))
+                            .monospace()
+                            .size(10.0),
+                    ));
+                    if row.clicked() {
+                        clicked = Some(idx);
+                    }
+                    if row.hovered() {
+                        hovered = Some(idx);
+                    }
+                }
+                if let Some(idx) = clicked {
+                    self.selected_cmd = Some(idx);
+                    self.cmd_scroll_request = true;
+                    self.raw_scroll_request = true;
+                }
+                if hovered != self.log_hovered_cmd && self.show_state_inspector {
+                    ui.ctx().request_repaint();
+                }
+                self.log_hovered_cmd = hovered;
+            });
+    }
+
+    /// Codepage vigente en el comando `idx`: el último `ESC t` anterior o, si no hay o un
+    /// `ESC @` lo anuló, el de arranque (`default`).
+    fn codepage_at(commands: &[ParsedCommand], idx: usize, default: CodePage) -> CodePage {
+        commands
+            .iter()
+            .take(idx + 1)
+            .rev()
+            .find_map(|(_, cmd)| match cmd {
+                CommandType::Control(
+                    Control::CodePage(cp) | Control::CodePageFallback { codepage: cp, .. },
+                ) => Some(*cp),
+                CommandType::Control(Control::Init) => Some(default),
+                _ => None,
+            })
+            .unwrap_or(default)
+    }
+
+    /// Filas del inspector de estado (campo, valor).
+    fn state_rows(state: &PrinterState, codepage: CodePage) -> Vec<(&'static str, String)> {
+        let on_off = |v: bool| if v { "Sí" } else { "No" }.to_string();
+        vec![
+            ("Negrita", on_off(state.is_bold)),
+            (
+                "Subrayado",
+                if state.is_underline {
+                    format!("{} punto(s)", state.underline_weight.max(1))
+                } else {
+                    "No".to_string()
+                },
+            ),
+            ("Invertido", on_off(state.is_reverse)),
+            ("Al revés", on_off(state.is_upside_down)),
+            (
+                "Fuente",
+                if state.is_font_b { "B" } else { "A" }.to_string(),
+            ),
+            ("Alineación", Self::align_label(state.alignment).to_string()),
+            (
+                "Tamaño",
+                format!(
+                    "{}x ancho, {}x alto",
+                    state.char_width_mul, state.char_height_mul
+                ),
+            ),
+            (
+                "Interlineado",
+                state
+                    .line_spacing
+                    .map_or("Por defecto".to_string(), |n| format!("{n} puntos")),
+            ),
+            (
+                "Cursor X",
+                state
+                    .cursor_x
+                    .map_or("Inicio de línea".to_string(), |x| format!("{x} puntos")),
+            ),
+            ("Codepage", codepage.label().to_string()),
+            ("Doble byte", on_off(state.double_byte)),
+            (
+                "Barcode",
+                format!(
+                    "alto {}, módulo {}, HRI {:?} (fuente {})",
+                    state.barcode_height,
+                    state.barcode_module_width,
+                    state.barcode_hri,
+                    if state.barcode_hri_font == 1 {
+                        "B"
+                    } else {
+                        "A"
+                    }
+                ),
+            ),
+        ]
+    }
+
+    fn meta_patterns(&self) -> MetaPatterns {
+        MetaPatterns {
+            total_keywords: MetaPatterns::parse_keywords(&self.meta_total_keywords),
+            currency: self.meta_currency.trim().to_string(),
+        }
+    }
+
+    /// Resumen heurístico del texto del job activo (fecha, hora, total, artículos).
+    fn ui_receipt_meta(&self, ui: &mut egui::Ui) {
+        let Some(job) = self.active_job() else {
+            ui.label(egui::RichText::new("(sin comandos)").weak());
+            return;
+        };
+        let meta =
+            extract_metadata_with(&job_plain_text(&job.parsed_commands), &self.meta_patterns());
+        let missing = || "—".to_string();
+        egui::Grid::new("receipt_meta_grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for (field, value) in [
+                    ("Fecha", meta.date.clone().unwrap_or_else(missing)),
+                    ("Hora", meta.time.clone().unwrap_or_else(missing)),
+                    ("Total", meta.total.clone().unwrap_or_else(missing)),
+                ] {
+                    ui.label(egui::RichText::new(field).weak());
+                    ui.monospace(value);
+                    ui.end_row();
+                }
+            });
+        if meta.items.is_empty() {
+            ui.label(
+                egui::RichText::new("Sin artículos reconocidos")
+                    .weak()
+                    .small(),
+            );
+            return;
+        }
+        ui.add_space(4.0);
+        egui::Grid::new("receipt_items_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for item in &meta.items {
+                    ui.monospace(item.quantity.as_deref().unwrap_or(""));
+                    ui.label(&item.description);
+                    ui.monospace(&item.amount);
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Grilla del inspector: el comando bajo el mouse en el Log, si no el seleccionado y,
+    /// si no, el último comando revelado (sigue a la simulación).
+    fn ui_state_inspector(&mut self, ui: &mut egui::Ui) {
+        let hovered = self.log_hovered_cmd;
+        let selected = self.selected_cmd;
+        let codepage = self.start_codepage();
+        let Some(job) = self.active_job() else {
+            ui.label(egui::RichText::new("(sin comandos)").weak());
+            return;
+        };
+        let Some(last) = job.parsed_commands.len().checked_sub(1) else {
+            ui.label(egui::RichText::new("(sin comandos)").weak());
+            return;
+        };
+        let (idx, origin) = match (hovered, selected) {
+            (Some(idx), _) if idx <= last => (idx, "bajo el mouse"),
+            (_, Some(idx)) if idx <= last => (idx, "seleccionado"),
+            _ if job.sim_active => (last, "simulación"),
+            _ => (last, "fin del job"),
+        };
+        let (state, cmd) = &job.parsed_commands[idx];
+        ui.label(
+            egui::RichText::new(format!(
+                "#{idx:04} ({origin}): {}",
+                Self::command_log_line(cmd)
+            ))
+            .monospace()
+            .size(10.0),
+        );
+        let codepage = Self::codepage_at(&job.parsed_commands, idx, codepage);
+        egui::Grid::new("state_inspector_grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for (field, value) in Self::state_rows(state, codepage) {
+                    ui.label(egui::RichText::new(field).weak());
+                    ui.monospace(value);
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Panel derecho de la vista dividida: cada comando con su offset y sus bytes crudos,
+    /// coloreado por tipo. Un clic selecciona el comando y desplaza el render hasta él.
+    fn ui_split_raw_panel(&mut self, ui: &mut egui::Ui) {
+        let scroll_request = mem::take(&mut self.raw_scroll_request);
+        let Some(job) = self.active_job() else {
+            ui.label(egui::RichText::new("(sin datos)").weak());
+            return;
+        };
+
+        let text_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let row_height = ui
+            .spacing()
+            .interact_size
+            .y
+            .max(text_height + 2.0 * ui.spacing().button_padding.y);
+        let mut scroll = egui::ScrollArea::vertical()
+            .id_salt("split_raw_scroll")
+            .auto_shrink([false, false]);
+        if let (true, Some(idx)) = (scroll_request, self.selected_cmd) {
+            let stride = row_height + ui.spacing().item_spacing.y;
+            scroll = scroll.vertical_scroll_offset((idx as f32 * stride - 3.0 * stride).max(0.0));
+        }
+
+        let mut clicked = None;
+        scroll.show_rows(ui, row_height, job.parsed_commands.len(), |ui, rows| {
+            for idx in rows {
+                let (_, cmd) = &job.parsed_commands[idx];
+                let span = job.command_spans.get(idx).cloned().unwrap_or(0..0);
+                let bytes = job.display_bytes.get(span.clone()).unwrap_or(&[]);
+                let color = match cmd {
+                    CommandType::Text(_) => ui.visuals().text_color(),
+                    CommandType::Control(Control::EscUnknown(_) | Control::GsUnknown(_))
+                    | CommandType::Unknown(_) => egui::Color32::RED,
+                    CommandType::Control(_) => egui::Color32::from_rgb(37, 99, 235),
+                };
+                let text = format!(
+                    "{:06x}  {:<26} {}",
+                    span.start,
+                    hex_bytes(bytes, 8),
+                    Self::command_log_line(cmd)
+                );
+                let row = ui.add(egui::SelectableLabel::new(
+                    self.selected_cmd == Some(idx),
+                    egui::RichText::new(text).monospace().color(color),
+                ));
+                if row.clicked() {
+                    clicked = Some(idx);
+                }
+            }
+        });
+
+        if let Some(idx) = clicked {
+            self.selected_cmd = Some(idx);
+            self.cmd_scroll_request = true;
+        }
+    }
+
+    fn debug_label_for_control(control: &Control) -> String {
+        match control {
+            Control::Newline => "LF".to_string(),
+            Control::Tab => "HT (TAB)".to_string(),
+            Control::Init => "ESC @ (INIT)".to_string(),
+            Control::Bold(on) => format!("ESC E (BOLD={})", on),
+            Control::Align(align) => format!("ESC a (ALIGN={:?})", align),
+            Control::CodePage(cp) => format!("ESC t (CODEPAGE={:?})", cp),
+            Control::CodePageFallback { n, codepage } => {
+                format!("ESC t {} (sin tabla, aprox. {:?})", n, codepage)
+            }
+            Control::Size { raw, width, height } => {
+                format!("GS ! (SIZE raw={:02X} w={} h={})", raw, width, height)
+            }
+            Control::Cut { partial, feed } => format!(
+                "GS V ({} CUT feed={})",
+                if *partial { "PARTIAL" } else { "FULL" },
+                feed
+            ),
+            Control::RasterImage {
+                m,
+                width_bytes,
+                height,
+                data,
+            } => {
+                format!(
+                    "GS v 0 (IMG m={:02X} {}x{} bytes={})",
+                    m,
+                    (*width_bytes as usize) * 8,
+                    *height as usize,
+                    data.len()
+                )
+            }
+            Control::Qr {
+                model,
+                module_size,
+                ecc,
+                data,
+            } => format!(
+                "QR (model={} size={} ecc={} bytes={})",
+                model.label(),
+                module_size,
+                ecc,
+                data.len()
+            ),
+            Control::Barcode { m, data } => {
                 format!("GS k (BARCODE m={:02X} bytes={})", m, data.len())
             }
             Control::BarcodeHriPosition(pos) => format!("GS H (HRI={:?})", pos),
@@ -917,16 +4768,41 @@ impl EscPosViewer {
             Control::BarcodeHriFont(n) => format!("GS f (HRI FONT={})", n),
             Control::AbsolutePosition { x } => format!("ESC $ (POS={})", x),
             Control::RelativePosition { offset } => format!("ESC \\ (OFFSET={})", offset),
+            Control::LeftMargin { dots } => format!("GS L (LEFT MARGIN={} dots)", dots),
+            Control::PrintAreaWidth { dots } => format!("GS W (PRINT WIDTH={} dots)", dots),
             Control::Underline(on) => format!("ESC - (UNDERLINE={})", on),
             Control::Reverse(on) => format!("GS B (REVERSE={})", on),
+            Control::UpsideDown(on) => format!("ESC {{ (UPSIDE DOWN={})", on),
             Control::MasterSelect(n) => format!("ESC ! (MASTER={:02X})", n),
             Control::LineSpacingDefault => "ESC 2 (LINE SPACING DEFAULT)".to_string(),
             Control::LineSpacing(n) => format!("ESC 3 (LINE SPACING={})", n),
+            Control::FeedLines(n) => format!("ESC d (FEED {} LINES)", n),
+            Control::FeedDots(n) => format!("ESC J (FEED {} DOTS)", n),
             Control::BitImage { mode, width, data } => {
                 format!("ESC * (BIT IMAGE mode={} w={} bytes={})", mode, width, data.len())
             }
             Control::FontSelect(on) => format!("ESC M (FONT SELECT font_b={})", on),
             Control::OpenDrawer => "ESC p (OPEN DRAWER)".to_string(),
+            Control::Bell => "BEL".to_string(),
+            Control::Buzzer { count } => format!("ESC ( A (BEEP x{})", count),
+            Control::FormFeed => "FF (FORM FEED)".to_string(),
+            Control::DoubleByteMode(on) => {
+                format!("FS {} (DOUBLE BYTE={})", if *on { "&" } else { "." }, on)
+            }
+            Control::NvLogo { n, m } => format!("FS p (NV LOGO n={} m={})", n, m),
+            Control::TwoDimBarcode { kind, data } => {
+                format!(
+                    "GS ( k ({} bytes={})",
+                    kind.label().to_uppercase(),
+                    data.len()
+                )
+            }
+            Control::EscBlock { function, len } => {
+                format!("ESC ( {:02X} (BLOCK len={})", function, len)
+            }
+            Control::GsBlock { function, len } => {
+                format!("GS ( {:02X} (BLOCK len={})", function, len)
+            }
             Control::EscUnknown(b) => format!("ESC {:02X} (?)", b),
             Control::GsUnknown(b) => format!("GS {:02X} (?)", b),
         }
@@ -949,9 +4825,82 @@ impl EscPosViewer {
         (base / div).max(1)
     }
 
+    /// (columnas de margen izquierdo, columnas del área de impresión) según GS L / GS W, con
+    /// el ancho de carácter vigente. El área no pasa del borde del papel.
+    fn print_area_columns(paper_width: PaperWidth, state: &PrinterState) -> (usize, usize) {
+        let cols = Self::effective_columns(paper_width, state);
+        let total_dots = paper_width.dots();
+        let dots_per_col = total_dots / cols as f32;
+        let margin = ((state.left_margin_dots as f32 / dots_per_col).round() as usize)
+            .min(cols.saturating_sub(1));
+        let area_dots = state
+            .print_width_dots
+            .map_or(total_dots, |w| w as f32)
+            .min(total_dots - margin as f32 * dots_per_col);
+        let area = ((area_dots / dots_per_col) as usize).clamp(1, cols - margin);
+        (margin, area)
+    }
+
+    /// Resuelve los `\t` de "Alinear precios a la derecha en tabs": cada campo termina en la
+    /// primera parada (cada 8 columnas) donde entra con un espacio antes, y el último campo de
+    /// la línea termina en el borde derecho (`cols`).
+    fn align_tab_fields(text: &str, cols: usize, fill: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for (line_idx, line) in text.split('\n').enumerate() {
+            if line_idx > 0 {
+                out.push('\n');
+            }
+            let mut fields = line.split('\t');
+            let first = fields.next().unwrap_or("");
+            out.push_str(first);
+            let mut len = first.chars().count();
+            let rest: Vec<&str> = fields.collect();
+            for (idx, field) in rest.iter().enumerate() {
+                let width = field.chars().count();
+                let end = if idx + 1 == rest.len() && len + 1 + width <= cols {
+                    cols
+                } else {
+                    (len + 1 + width).div_ceil(8) * 8
+                };
+                out.push_str(&fill.repeat(end - len - width));
+                out.push_str(field);
+                len = end;
+            }
+        }
+        out
+    }
+
+    /// `ESC $`: rellena con espacios hasta la columna de `cursor_x` (12 puntos por columna).
+    fn pad_to_cursor(buf: &mut String, cursor_x: u16) {
+        let target_col = (cursor_x / 12) as usize;
+        let current_col = buf.chars().count();
+        if target_col > current_col {
+            buf.push_str(&" ".repeat(target_col - current_col));
+        }
+    }
+
+    /// `ESC \`: corre el final del texto pendiente `offset` puntos (12 por columna, como `ESC $`).
+    /// Hacia atrás solo se quitan espacios: lo ya impreso no se pisa.
+    fn shift_pending_text(buf: &mut String, offset: i16) {
+        let cols = (offset / 12).unsigned_abs() as usize;
+        if offset >= 0 {
+            buf.push_str(&" ".repeat(cols));
+        } else {
+            for _ in 0..cols {
+                if !buf.ends_with(' ') {
+                    break;
+                }
+                buf.pop();
+            }
+        }
+    }
+
     fn same_line_style(a: &PrinterState, b: &PrinterState) -> bool {
         a.is_bold == b.is_bold
+            && a.left_margin_dots == b.left_margin_dots
+            && a.print_width_dots == b.print_width_dots
             && a.is_underline == b.is_underline
+            && a.underline_weight == b.underline_weight
             && a.is_reverse == b.is_reverse
             && a.alignment == b.alignment
             && a.char_width_mul == b.char_width_mul
@@ -964,6 +4913,23 @@ impl EscPosViewer {
         "\u{00A0}".repeat(count)
     }
 
+    /// Banda de fondo a todo el ancho para una línea de `len` caracteres: una línea invertida
+    /// que ocupa todas las columnas (típico de encabezados de tabla / filas cebra) o, si el
+    /// usuario lo pide, los encabezados en negrita + subrayado.
+    fn line_band(
+        state: &PrinterState,
+        len: usize,
+        cols: usize,
+        options: TextRenderOptions,
+    ) -> Option<egui::Color32> {
+        if state.is_reverse {
+            (len >= cols).then_some(options.ink)
+        } else {
+            (options.shade_header_lines && state.is_bold && state.is_underline)
+                .then_some(egui::Color32::from_gray(228))
+        }
+    }
+
     fn split_and_wrap(text: &str, width: usize) -> Vec<String> {
         if width == 0 {
             return vec![text.to_string()];
@@ -1002,38 +4968,64 @@ impl EscPosViewer {
         out
     }
 
+    /// Dibuja un bloque de texto partido en las columnas del papel. `paper_px` es el ancho
+    /// del papel en pantalla (de ahí sale la escala de puntos a píxeles).
     fn emit_text_with_columns(
         ui: &mut egui::Ui,
         paper_width: PaperWidth,
+        paper_px: f32,
         state: &PrinterState,
         text: &str,
-        use_thermal_font: bool,
-    ) {
-        let cols = Self::effective_columns(paper_width, state);
+        options: TextRenderOptions,
+    ) -> egui::Rect {
+        let (margin, cols) = Self::print_area_columns(paper_width, state);
+        let aligned;
+        let text = if text.contains('\t') {
+            let fill = if options.show_whitespace {
+                "\u{00A0}"
+            } else {
+                " "
+            };
+            aligned = Self::align_tab_fields(text, cols, fill);
+            aligned.as_str()
+        } else {
+            text
+        };
         let lines = Self::split_and_wrap(text, cols);
         let lines_len = lines.len();
 
+        let dots_to_pixels = paper_px / paper_width.dots();
+        let mut last_rect = egui::Rect::NOTHING;
+
         for (idx, line) in lines.into_iter().enumerate() {
             let len = line.chars().count();
             
             // Calculate padding based on alignment only
             // (cursor_x is handled by inserting spaces in the text buffer directly)
-            let pad = if len >= cols {
-                0
-            } else {
-                match state.alignment {
-                    Align::Left => 0,
-                    Align::Center => (cols - len) / 2,
-                    Align::Right => cols - len,
-                }
-            };
+            // La alineación es dentro del área de impresión, después del margen izquierdo (GS L).
+            let pad = margin
+                + if len >= cols {
+                    0
+                } else {
+                    match state.alignment {
+                        Align::Left => 0,
+                        Align::Center => (cols - len) / 2,
+                        Align::Right => cols - len,
+                    }
+                };
 
             let mut display = String::new();
             display.push_str(&Self::nbsp_pad(pad));
             display.push_str(&line);
 
+            let rule = if options.draw_separator_rules && !state.is_reverse {
+                Self::separator_rule(&line, cols)
+            } else {
+                None
+            };
+
             // Usar fuente DotMatrix si está habilitada, sino Monospace del sistema
-            let font_family = if use_thermal_font {
+            let font_family = if options.use_thermal_font {
                 egui::FontFamily::Name("DotMatrix".into())
             } else {
                 egui::FontFamily::Monospace
@@ -1046,189 +5038,424 @@ impl EscPosViewer {
             if state.is_font_b {
                 base_size *= 0.75; // Simular Fuente B compacta (25% más pequeña)
             }
-            let height_mul = state.char_height_mul.max(1) as f32;
-            let width_mul = state.char_width_mul.max(1) as f32;
-            // Escalar por el multiplicador de altura para texto grande
-            let font_size = base_size * height_mul.max(width_mul);
+            // Se arma la fuente con el multiplicador mayor y se comprime el otro eje:
+            // GS ! 0x01 (solo doble ancho) queda ancho pero de alto normal.
+            let (scale, stretch_x, stretch_y) =
+                Self::glyph_stretch(state.char_width_mul, state.char_height_mul);
+            let mut font_size = base_size * scale;
+            let snap_to_pixels = options.use_thermal_font && options.snap_thermal_font;
+            let pixels_per_point = ui.ctx().pixels_per_point();
+            if snap_to_pixels {
+                // Glifo base con alto entero en píxeles físicos y escalado por múltiplos enteros:
+                // la DotMatrix a tamaños fraccionarios se ve borrosa.
+                let base_px = (base_size * pixels_per_point).round().max(1.0);
+                font_size = base_px * scale / pixels_per_point;
+            }
             
-            let mut rich_text = egui::RichText::new(display)
-                .color(egui::Color32::BLACK)
-                .family(font_family)
-                .size(font_size);
+            // El subrayado se dibuja a mano para respetar el grosor (1 o 2 puntos); el de egui
+            // queda como respaldo si la línea no tiene texto con el que medir.
+            let manual_underline = state.is_underline && len > 0 && rule.is_none();
+
+            // Un separador se maqueta igual que el texto (mismo alto) pero invisible, y la raya
+            // se pinta encima.
+            let styled = |text: String| {
+                let mut rich_text = egui::RichText::new(text)
+                    .color(if rule.is_some() {
+                        egui::Color32::TRANSPARENT
+                    } else {
+                        options.ink
+                    })
+                    .family(font_family.clone())
+                    .size(font_size);
 
-            if state.is_bold {
-                rich_text = rich_text.strong();
-            }
+                if state.is_bold {
+                    rich_text = rich_text.strong();
+                }
+                if state.is_underline && !manual_underline {
+                    rich_text = rich_text.underline();
+                }
+                if state.is_reverse {
+                    // Invertir colores: texto blanco sobre fondo negro
+                    rich_text = rich_text
+                        .background_color(options.ink)
+                        .color(egui::Color32::WHITE);
+                }
+                rich_text
+            };
+            let rich_text = styled(display);
 
-            if state.is_underline {
-                rich_text = rich_text.underline();
-            }
+            let label_response = if let Some(fill) = Self::line_band(state, len, cols, options) {
+                egui::Frame::none()
+                    .fill(fill)
+                    .show(ui, |ui| {
+                        ui.set_min_width(ui.available_width());
+                        Self::add_scaled_text(ui, rich_text, stretch_x, stretch_y)
+                    })
+                    .inner
+            } else if options.heat_bleed
+                && state.is_bold
+                && !state.is_reverse
+                && rule.is_none()
+                && len > 0
+            {
+                // El halo se reserva antes del texto para quedar detrás de los glifos.
+                let halo_slot = ui.painter().add(egui::Shape::Noop);
+                let halo_text = rich_text.clone();
+                let response = Self::add_scaled_text(ui, rich_text, stretch_x, stretch_y);
+                let halo =
+                    Self::heat_bleed_shape(ui, halo_text, response.rect.min, stretch_x, stretch_y);
+                ui.painter().set(halo_slot, halo);
+                response
+            } else if state.is_reverse && pad > 0 {
+                // Solo los caracteres van invertidos: el margen y el relleno de alineación
+                // quedan en blanco (un título centrado es una banda negra al medio, no desde
+                // el borde).
+                let mut job = egui::text::LayoutJob::default();
+                let pad_text = egui::RichText::new(Self::nbsp_pad(pad))
+                    .family(font_family.clone())
+                    .size(font_size);
+                for part in [pad_text, styled(line.clone())] {
+                    part.append_to(
+                        &mut job,
+                        ui.style(),
+                        egui::FontSelection::Default,
+                        egui::Align::Center,
+                    );
+                }
+                Self::add_scaled_text(ui, job, stretch_x, stretch_y)
+            } else {
+                Self::add_scaled_text(ui, rich_text, stretch_x, stretch_y)
+            };
 
-            if state.is_reverse {
-                // Invertir colores: texto blanco sobre fondo negro
-                rich_text = rich_text
-                    .background_color(egui::Color32::BLACK)
-                    .color(egui::Color32::WHITE);
+            if manual_underline {
+                // Monoespaciada: se salta el relleno de alineación y se subraya solo el texto.
+                let rect = label_response.rect;
+                let char_w = rect.width() / (pad + len) as f32;
+                let weight = state.underline_weight.max(1) as f32;
+                let thickness = (weight * dots_to_pixels).max(weight / ui.ctx().pixels_per_point());
+                let color = if state.is_reverse {
+                    egui::Color32::WHITE
+                } else {
+                    options.ink
+                };
+                ui.painter().hline(
+                    (rect.left() + pad as f32 * char_w)..=rect.right(),
+                    rect.bottom() - thickness * 0.5 - 1.0,
+                    egui::Stroke::new(thickness, color),
+                );
             }
 
-            ui.add(egui::Label::new(rich_text));
+            if let Some(rule) = rule {
+                let rect = label_response.rect;
+                let bold = if state.is_bold { 2.0 } else { 1.0 };
+                let dots = |n: f32| (n * bold * dots_to_pixels).max(1.0 / pixels_per_point);
+                let char_w = rect.width() / (pad + len).max(1) as f32;
+                let x_range = (rect.left() + pad as f32 * char_w)..=rect.right();
+                match rule {
+                    SeparatorRule::Single { dots: n } => {
+                        ui.painter().hline(
+                            x_range,
+                            rect.center().y,
+                            egui::Stroke::new(dots(n), options.ink),
+                        );
+                    }
+                    SeparatorRule::Double => {
+                        let gap = dots(3.0);
+                        for y in [rect.center().y - gap, rect.center().y + gap] {
+                            ui.painter().hline(
+                                x_range.clone(),
+                                y,
+                                egui::Stroke::new(dots(2.0), options.ink),
+                            );
+                        }
+                    }
+                }
+            } else if options.show_whitespace && len > 0 {
+                Self::paint_whitespace_marks(ui, label_response.rect, pad, &line);
+            }
+            last_rect = label_response.rect;
 
             // Añadir el interlineado configurado entre líneas envueltas de un mismo bloque de texto
             if idx < lines_len - 1 {
-                let px_width = match paper_width {
-                    PaperWidth::W58mm => 240.0,
-                    PaperWidth::W80mm => 340.0,
-                };
-                let total_dots = match paper_width {
-                    PaperWidth::W58mm => 384.0,
-                    PaperWidth::W80mm => 576.0,
-                };
-                let dots_to_pixels = px_width / total_dots;
                 let n = state.line_spacing.unwrap_or(30) as f32;
                 let line_spacing_px = n * dots_to_pixels;
                 
-                let text_height_px = font_size * 1.15;
+                let text_height_px = font_size * stretch_y * 1.15;
                 let item_spacing_y = ui.spacing().item_spacing.y;
-                let extra_space = (line_spacing_px - text_height_px - item_spacing_y).max(0.0);
+                let mut extra_space = (line_spacing_px - text_height_px - item_spacing_y).max(0.0);
+                if snap_to_pixels {
+                    // Mantener las líneas siguientes alineadas a la grilla de píxeles.
+                    extra_space = (extra_space * pixels_per_point).round() / pixels_per_point;
+                }
                 if extra_space > 0.0 {
                     ui.add_space(extra_space);
                 }
             }
         }
+        last_rect
     }
 
-    fn hash_key<T: Hash>(value: &T) -> u64 {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        value.hash(&mut hasher);
-        hasher.finish()
+    /// Línea de separación hecha con un solo carácter repetido a todo el ancho (`cols`).
+    fn separator_rule(line: &str, cols: usize) -> Option<SeparatorRule> {
+        let mut chars = line.chars();
+        let first = chars.next()?;
+        let rule = match first {
+            '-' | '_' | '─' => SeparatorRule::Single { dots: 2.0 },
+            '━' | '▬' => SeparatorRule::Single { dots: 4.0 },
+            '=' | '═' => SeparatorRule::Double,
+            _ => return None,
+        };
+        (line.chars().count() >= cols && chars.all(|c| c == first)).then_some(rule)
     }
 
-    fn raster_to_image(width_bytes: u16, height: u16, data: &[u8]) -> Option<egui::ColorImage> {
-        let width_bits = (width_bytes as usize).checked_mul(8)?;
-        let height = height as usize;
-        if width_bits == 0 || height == 0 {
-            return None;
-        }
-        let expected = (width_bytes as usize).saturating_mul(height);
-        if data.len() < expected {
-            return None;
+    /// Tiempo acumulado, chico y a la derecha del papel, a la altura `y`.
+    fn paint_print_time(ui: &egui::Ui, y: f32, secs: f32) {
+        ui.painter().text(
+            egui::pos2(ui.max_rect().right(), y),
+            egui::Align2::RIGHT_CENTER,
+            format_secs(secs),
+            egui::FontId::monospace(9.0),
+            egui::Color32::GRAY,
+        );
+    }
+
+    const WHITESPACE_MARK_COLOR: egui::Color32 = egui::Color32::from_gray(170);
+
+    /// Dibuja · sobre cada espacio y → al inicio de cada tab (relleno NBSP) de una línea ya
+    /// maquetada. Monoespaciada: cada columna mide lo mismo, el texto no se toca.
+    fn paint_whitespace_marks(ui: &egui::Ui, rect: egui::Rect, pad: usize, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let char_w = rect.width() / (pad + chars.len()) as f32;
+        let font = egui::FontId::monospace((rect.height() * 0.6).max(6.0));
+        for (i, &ch) in chars.iter().enumerate() {
+            let mark = match ch {
+                ' ' => "·",
+                '\u{00A0}' if i == 0 || chars[i - 1] != '\u{00A0}' => "→",
+                _ => continue,
+            };
+            let center = egui::pos2(
+                rect.left() + (pad + i) as f32 * char_w + char_w * 0.5,
+                rect.center().y,
+            );
+            ui.painter().text(
+                center,
+                egui::Align2::CENTER_CENTER,
+                mark,
+                font.clone(),
+                Self::WHITESPACE_MARK_COLOR,
+            );
         }
+    }
+
+    /// Alto de una fila de texto con el tamaño de `state` (Font B 25% más chica).
+    fn glyph_height_px(state: &PrinterState) -> f32 {
+        let base_size = if state.is_font_b { 14.0 * 0.75 } else { 14.0 };
+        base_size * state.char_height_mul.max(1) as f32 * 1.15
+    }
 
-        let mut pixels = vec![egui::Color32::WHITE; width_bits * height];
+    /// Escala de fuente y estiramiento por eje para `GS !` / `ESC !`: ancho y alto son
+    /// independientes. Devuelve (escala uniforme, factor X, factor Y).
+    fn glyph_stretch(width_mul: u8, height_mul: u8) -> (f32, f32, f32) {
+        let w = width_mul.max(1) as f32;
+        let h = height_mul.max(1) as f32;
+        let scale = w.max(h);
+        (scale, w / scale, h / scale)
+    }
 
-        for y in 0..height {
-            let row = &data[y * width_bytes as usize..(y + 1) * width_bytes as usize];
-            for (xb, byte) in row.iter().enumerate() {
-                for bit in 0..8 {
-                    let is_black = (byte & (1 << (7 - bit))) != 0;
-                    let x = xb * 8 + bit;
-                    let idx = y * width_bits + x;
-                    if is_black {
-                        pixels[idx] = egui::Color32::BLACK;
-                    }
-                }
+    /// Label normal, o si los factores difieren, el texto maquetado y deformado por eje
+    /// (egui no escala glifos de forma no uniforme: se transforma la malla de cada fila).
+    fn add_scaled_text(
+        ui: &mut egui::Ui,
+        text: impl Into<egui::WidgetText>,
+        stretch_x: f32,
+        stretch_y: f32,
+    ) -> egui::Response {
+        if stretch_x == stretch_y {
+            return ui.add(egui::Label::new(text));
+        }
+
+        let galley = text.into().into_galley(
+            ui,
+            Some(egui::TextWrapMode::Extend),
+            f32::INFINITY,
+            egui::TextStyle::Monospace,
+        );
+        let size = egui::vec2(galley.size().x * stretch_x, galley.size().y * stretch_y);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        if ui.is_rect_visible(rect) {
+            let stretch = egui::vec2(stretch_x, stretch_y);
+            for mesh in Self::stretched_row_meshes(ui, &galley, rect.min, stretch, None) {
+                ui.painter().add(egui::Shape::mesh(mesh));
             }
         }
+        response
+    }
 
-        Some(egui::ColorImage {
-            size: [width_bits, height],
-            pixels,
-        })
+    /// Mallas de las filas de `galley` desde `origin`, estiradas por eje. Las UV de una galley
+    /// están en texeles del atlas de fuentes (las normaliza el teselador de `Shape::Text`), así
+    /// que al pintarlas como `Shape::mesh` hay que dividirlas por el tamaño del atlas. Con
+    /// `glyph_color` solo quedan los glifos, de ese color (fondos y subrayados se descartan).
+    fn stretched_row_meshes(
+        ui: &egui::Ui,
+        galley: &egui::Galley,
+        origin: egui::Pos2,
+        stretch: egui::Vec2,
+        glyph_color: Option<egui::Color32>,
+    ) -> Vec<egui::Mesh> {
+        let [tex_w, tex_h] = ui.ctx().fonts(|fonts| fonts.font_image_size());
+        let uv_scale = egui::vec2(1.0 / tex_w as f32, 1.0 / tex_h as f32);
+        let fallback = ui.visuals().text_color();
+        galley
+            .rows
+            .iter()
+            .map(|row| {
+                let mut mesh = row.visuals.mesh.clone();
+                for (i, v) in mesh.vertices.iter_mut().enumerate() {
+                    v.pos = origin + v.pos.to_vec2() * stretch;
+                    v.uv = (v.uv.to_vec2() * uv_scale).to_pos2();
+                    v.color = match glyph_color {
+                        Some(color) if row.visuals.glyph_vertex_range.contains(&i) => color,
+                        Some(_) => egui::Color32::TRANSPARENT,
+                        None if v.color == egui::Color32::PLACEHOLDER => fallback,
+                        None => v.color,
+                    };
+                }
+                mesh
+            })
+            .collect()
     }
 
-    /// Convert ESC * bit image (8/24-pin legacy format) to egui ColorImage.
-    /// mode 0,1 = 8-dot vertical (1 byte per column)
-    /// mode 32,33 = 24-dot vertical (3 bytes per column)
-    fn bitimage_to_image(mode: u8, width: u16, data: &[u8]) -> Option<egui::ColorImage> {
-        let width = width as usize;
-        if width == 0 {
-            return None;
-        }
+    /// "Sangrado" térmico de la negrita: copias del texto desplazadas una fracción de punto
+    /// y casi transparentes, que simulan el cabezal oscureciendo de más los puntos enfatizados.
+    fn heat_bleed_shape(
+        ui: &egui::Ui,
+        rich_text: egui::RichText,
+        origin: egui::Pos2,
+        stretch_x: f32,
+        stretch_y: f32,
+    ) -> egui::Shape {
+        const OFFSETS: [(f32, f32); 4] = [(-0.6, 0.0), (0.6, 0.0), (0.0, -0.5), (0.0, 0.5)];
+        let color = egui::Color32::from_black_alpha(30);
+
+        let galley = egui::WidgetText::from(rich_text).into_galley(
+            ui,
+            Some(egui::TextWrapMode::Extend),
+            f32::INFINITY,
+            egui::TextStyle::Monospace,
+        );
+        let stretch = egui::vec2(stretch_x, stretch_y);
+        let shapes = OFFSETS
+            .iter()
+            .flat_map(|&(dx, dy)| {
+                let origin = origin + egui::vec2(dx, dy);
+                Self::stretched_row_meshes(ui, &galley, origin, stretch, Some(color))
+            })
+            .map(egui::Shape::mesh)
+            .collect();
+        egui::Shape::Vec(shapes)
+    }
 
-        // 8-pin modes: 1 byte per column = 8 vertical dots
-        // 24-pin modes: 3 bytes per column = 24 vertical dots
-        let (bytes_per_col, height) = match mode {
-            0 | 1 => (1usize, 8usize),
-            32 | 33 => (3usize, 24usize),
-            _ => (1usize, 8usize),
-        };
+    fn hash_key<T: Hash>(value: &T) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
 
-        let expected = width.saturating_mul(bytes_per_col);
-        if data.len() < expected {
-            return None;
-        }
+    /// Tinta para una densidad de impresión: gris oscuro en 0, negro puro en 100.
+    fn density_ink(density: u8) -> egui::Color32 {
+        let light = 100 - density.min(100) as u16;
+        egui::Color32::from_gray((light * 110 / 100) as u8)
+    }
 
-        let mut pixels = vec![egui::Color32::WHITE; width * height];
+    /// Raster `GS v 0` con la densidad de impresión: los puntos con menos vecinos negros que el
+    /// umbral (zonas claras o tramadas) salen apagados; en 100 todos los puntos salen llenos.
+    fn raster_to_image(
+        width_bytes: u16,
+        height: u16,
+        data: &[u8],
+        density: u8,
+    ) -> Option<egui::ColorImage> {
+        let bitmap = raster_bitmap(width_bytes, height, data)?;
+        // Vecinos (de 8) que necesita un punto para imprimirse lleno: 0 a densidad máxima, 4 a mínima.
+        let threshold = (100 - density.min(100) as usize) * 4 / 100;
+        let ink = Self::density_ink(density);
+        let faint = egui::Color32::from_gray(ink.r() + (255 - ink.r()) / 5 * 3);
 
-        for col in 0..width {
-            let col_data = &data[col * bytes_per_col..(col + 1) * bytes_per_col];
-            for (byte_idx, &byte) in col_data.iter().enumerate() {
-                for bit in 0..8 {
-                    let y = byte_idx * 8 + bit;
-                    if y >= height {
-                        break;
-                    }
-                    let is_black = (byte & (1 << (7 - bit))) != 0;
-                    let idx = y * width + col;
-                    if is_black {
-                        pixels[idx] = egui::Color32::BLACK;
-                    }
+        let mut pixels = vec![egui::Color32::WHITE; bitmap.dots.len()];
+
+        for y in 0..bitmap.height {
+            for x in 0..bitmap.width {
+                if !bitmap.get(x, y) {
+                    continue;
                 }
+                let neighbors = if threshold == 0 {
+                    0
+                } else {
+                    bitmap.black_neighbors(x, y)
+                };
+                pixels[y * bitmap.width + x] = if neighbors >= threshold { ink } else { faint };
             }
         }
 
         Some(egui::ColorImage {
-            size: [width, height],
+            size: [bitmap.width, bitmap.height],
             pixels,
         })
     }
 
-    fn ecc_to_level(ecc: u8) -> EcLevel {
-        match ecc {
-            48 => EcLevel::L,
-            49 => EcLevel::M,
-            50 => EcLevel::Q,
-            51 => EcLevel::H,
-            _ => EcLevel::M,
+    /// Puntos negros sobre blanco.
+    fn bitmap_to_image(bitmap: &Bitmap) -> egui::ColorImage {
+        egui::ColorImage {
+            size: [bitmap.width, bitmap.height],
+            pixels: bitmap
+                .dots
+                .iter()
+                .map(|&black| {
+                    if black {
+                        egui::Color32::BLACK
+                    } else {
+                        egui::Color32::WHITE
+                    }
+                })
+                .collect(),
         }
     }
 
-    fn qr_to_image(data: &[u8], ecc: u8, module_size: u8) -> Option<egui::ColorImage> {
-        let ec_level = Self::ecc_to_level(ecc);
-        let code = QrCode::with_error_correction_level(data, ec_level).ok()?;
-        let width = code.width();
-        if width == 0 {
-            return None;
+    /// Repite cada punto `sx` veces a lo ancho y `sy` a lo alto (doble ancho / doble alto).
+    fn scale_image(image: egui::ColorImage, sx: usize, sy: usize) -> egui::ColorImage {
+        if (sx, sy) == (1, 1) {
+            return image;
         }
-
-        let module = (module_size as usize).clamp(1, 16);
-        let quiet = 4usize;
-        let out_w = (width + 2 * quiet) * module;
-        let out_h = out_w;
-
-        let mut pixels = vec![egui::Color32::WHITE; out_w * out_h];
-
-        let colors = code.to_colors();
-        for y in 0..width {
-            for x in 0..width {
-                let c = colors[y * width + x];
-                if c == Color::Dark {
-                    let base_x = (x + quiet) * module;
-                    let base_y = (y + quiet) * module;
-                    for dy in 0..module {
-                        for dx in 0..module {
-                            let idx = (base_y + dy) * out_w + (base_x + dx);
-                            pixels[idx] = egui::Color32::BLACK;
-                        }
-                    }
-                }
+        let [width, height] = image.size;
+        let mut pixels = Vec::with_capacity(width * sx * height * sy);
+        for row in image.pixels.chunks(width.max(1)) {
+            let scaled: Vec<egui::Color32> = row
+                .iter()
+                .flat_map(|&px| std::iter::repeat_n(px, sx))
+                .collect();
+            for _ in 0..sy {
+                pixels.extend_from_slice(&scaled);
             }
         }
-
-        Some(egui::ColorImage {
-            size: [out_w, out_h],
+        egui::ColorImage {
+            size: [width * sx, height * sy],
             pixels,
-        })
+        }
+    }
+
+    /// Convert ESC * bit image (8/24-pin legacy format) to egui ColorImage.
+    fn bitimage_to_image(mode: u8, width: u16, data: &[u8]) -> Option<egui::ColorImage> {
+        bit_image_bitmap(mode, width, data).map(|bitmap| Self::bitmap_to_image(&bitmap))
+    }
+
+    fn qr_to_image(
+        data: &[u8],
+        ecc: u8,
+        module_size: u8,
+        model: QrModel,
+    ) -> Option<(egui::ColorImage, Option<&'static str>)> {
+        let (bitmap, note) = qr_bitmap(data, ecc, module_size, model)?;
+        Some((Self::bitmap_to_image(&bitmap), note))
     }
 
     fn show_image_scaled(
@@ -1237,7 +5464,7 @@ impl EscPosViewer {
         key: u64,
         image: egui::ColorImage,
         target_width: f32,
-    ) {
+    ) -> egui::Response {
         let tex = cache
             .entry(key)
             .or_insert_with(|| {
@@ -1250,580 +5477,589 @@ impl EscPosViewer {
         let (w, h) = (size.x.max(1.0), size.y.max(1.0));
         let scale = target_width / w;
         let display = egui::vec2(target_width, h * scale);
-        ui.image((tex.id(), display));
-    }
-
-    fn runs_to_image(
-        runs: &[u8],
-        start_with_black: bool,
-        module_px: usize,
-        height_px: usize,
-        quiet_zone_modules: usize,
-    ) -> Option<egui::ColorImage> {
-        if runs.is_empty() || module_px == 0 || height_px == 0 {
-            return None;
-        }
-
-        let total_modules: usize =
-            runs.iter().map(|&r| r as usize).sum::<usize>() + quiet_zone_modules.saturating_mul(2);
-        if total_modules == 0 {
-            return None;
-        }
-
-        let width_px = total_modules.saturating_mul(module_px).max(1);
-        let height_px = height_px.max(1);
-
-        let mut pixels = vec![egui::Color32::WHITE; width_px * height_px];
-
-        // Quiet zone a la izquierda.
-        let mut x_px = quiet_zone_modules.saturating_mul(module_px);
-        let mut black = start_with_black;
-
-        for &run in runs {
-            let run_px = (run as usize).saturating_mul(module_px);
-            if black && run_px > 0 {
-                let x0 = x_px.min(width_px);
-                let x1 = (x_px + run_px).min(width_px);
-                for y in 0..height_px {
-                    let row = y * width_px;
-                    for x in x0..x1 {
-                        pixels[row + x] = egui::Color32::BLACK;
-                    }
-                }
-            }
-
-            x_px = x_px.saturating_add(run_px);
-            black = !black;
-            if x_px >= width_px {
-                break;
-            }
-        }
-
-        Some(egui::ColorImage {
-            size: [width_px, height_px],
-            pixels,
-        })
-    }
-
-    fn bits01_to_runs(bits: &[u8]) -> Option<(Vec<u8>, bool)> {
-        if bits.is_empty() {
-            return None;
-        }
-        let mut runs: Vec<u8> = Vec::new();
-        let mut current = bits[0];
-        let mut len: usize = 0;
-        for &b in bits {
-            if b == current {
-                len += 1;
-            } else {
-                runs.push(len.min(255) as u8);
-                current = b;
-                len = 1;
-            }
-        }
-        runs.push(len.min(255) as u8);
-        let start_with_black = bits[0] == 1;
-        Some((runs, start_with_black))
-    }
-
-    fn clean_code128_hri(data: &[u8]) -> String {
-        // ESC/POS suele enviar prefijos como "{B" y escapes "{{".
-        let s = String::from_utf8_lossy(data);
-        let mut out = String::new();
-        let mut chars = s.chars().peekable();
-
-        // Consumir prefijo inicial {A/{B/{C}
-        if let Some('{') = chars.peek().copied() {
-            let mut clone = chars.clone();
-            let _ = clone.next();
-            if let Some(next) = clone.next() {
-                if matches!(next, 'A' | 'B' | 'C') {
-                    let _ = chars.next();
-                    let _ = chars.next();
-                }
-            }
-        }
-
-        while let Some(ch) = chars.next() {
-            if ch == '{' {
-                match chars.peek().copied() {
-                    Some('{') => {
-                        let _ = chars.next();
-                        out.push('{');
-                    }
-                    Some('A' | 'B' | 'C') => {
-                        let _ = chars.next();
-                        // cambio de code set: no se imprime
-                    }
-                    Some('1' | '2' | '3' | '4') => {
-                        let _ = chars.next();
-                        // FNC*: omitimos en HRI
-                    }
-                    _ => {
-                        // Si no reconocemos, imprimimos el '{'
-                        out.push('{');
-                    }
-                }
-            } else {
-                out.push(ch);
-            }
-        }
-        out
+        ui.add(egui::Image::new((tex.id(), display)).sense(egui::Sense::click()))
     }
 
-    fn encode_code128_runs(data: &[u8]) -> Option<(Vec<u8>, String)> {
-        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-        enum CodeSet {
-            A,
-            B,
-            C,
-        }
-
-        // Tabla Code128 (widths alternando bar/space). Stop (106) tiene 7 dígitos.
-        const PATTERNS: [&str; 107] = [
-            "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312",
-            "132212", "221213", "221312", "231212", "112232", "122132", "122231", "113222",
-            "123122", "123221", "223211", "221132", "221231", "213212", "223112", "312131",
-            "311222", "321122", "321221", "312212", "322112", "322211", "212123", "212321",
-            "232121", "111323", "131123", "131321", "112313", "132113", "132311", "211313",
-            "231113", "231311", "112133", "112331", "132131", "113123", "113321", "133121",
-            "313121", "211331", "231131", "213113", "213311", "213131", "311123", "311321",
-            "331121", "312113", "312311", "332111", "314111", "221411", "431111", "111224",
-            "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
-            "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111",
-            "111242", "121142", "121241", "114212", "124112", "124211", "411212", "421112",
-            "421211", "212141", "214121", "412121", "111143", "111341", "131141", "114113",
-            "114311", "411113", "411311", "113141", "114131", "311141", "411131", "211412",
-            "211214", "211232", "2331112",
-        ];
-
-        let s = String::from_utf8_lossy(data);
-        let mut bytes = s.as_bytes();
-
-        let mut set = CodeSet::B;
-        if bytes.len() >= 2 && bytes[0] == b'{' {
-            match bytes[1] {
-                b'A' => {
-                    set = CodeSet::A;
-                    bytes = &bytes[2..];
-                }
-                b'B' => {
-                    set = CodeSet::B;
-                    bytes = &bytes[2..];
-                }
-                b'C' => {
-                    set = CodeSet::C;
-                    bytes = &bytes[2..];
-                }
-                _ => {}
-            }
-        }
-
-        let start_code: u8 = match set {
-            CodeSet::A => 103,
-            CodeSet::B => 104,
-            CodeSet::C => 105,
-        };
-
-        let hri = Self::clean_code128_hri(bytes);
-
-        let mut codes: Vec<u8> = Vec::new();
-        let mut i = 0usize;
-        while i < bytes.len() {
-            let b = bytes[i];
-            if b == b'{' && i + 1 < bytes.len() {
-                let n = bytes[i + 1];
-                match n {
-                    b'{' => {
-                        // literal '{'
-                        match set {
-                            CodeSet::B => {
-                                codes.push((b'{' - 32) as u8);
-                            }
-                            CodeSet::A => {
-                                codes.push((b'{' - 32) as u8);
-                            }
-                            CodeSet::C => {
-                                // en C no cabe, cambiamos a B
-                                codes.push(100);
-                                set = CodeSet::B;
-                                codes.push((b'{' - 32) as u8);
-                            }
-                        }
-                        i += 2;
-                        continue;
-                    }
-                    b'A' => {
-                        codes.push(101);
-                        set = CodeSet::A;
-                        i += 2;
-                        continue;
-                    }
-                    b'B' => {
-                        codes.push(100);
-                        set = CodeSet::B;
-                        i += 2;
-                        continue;
-                    }
-                    b'C' => {
-                        codes.push(99);
-                        set = CodeSet::C;
-                        i += 2;
-                        continue;
-                    }
-                    b'1' => {
-                        // FNC1
-                        codes.push(102);
-                        i += 2;
-                        continue;
-                    }
-                    _ => {}
-                }
-            }
-
-            match set {
-                CodeSet::C => {
-                    if i + 1 < bytes.len()
-                        && bytes[i].is_ascii_digit()
-                        && bytes[i + 1].is_ascii_digit()
-                    {
-                        let v = (bytes[i] - b'0') * 10 + (bytes[i + 1] - b'0');
-                        codes.push(v);
-                        i += 2;
-                    } else {
-                        // Cambiar a B para seguir.
-                        codes.push(100);
-                        set = CodeSet::B;
-                    }
-                }
-                CodeSet::B => {
-                    // Code B: ASCII 32..127
-                    if b >= 32 && b <= 127 {
-                        codes.push((b - 32) as u8);
-                    } else {
-                        codes.push((b'?' - 32) as u8);
-                    }
-                    i += 1;
-                }
-                CodeSet::A => {
-                    // Code A: 0..95
-                    let v: u8 = if b < 32 {
-                        b + 64
-                    } else if b <= 95 {
-                        b - 32
-                    } else {
-                        (b'?' - 32) as u8
-                    };
-                    codes.push(v);
-                    i += 1;
-                }
-            }
-        }
-
-        // Checksum
-        let mut sum: u32 = start_code as u32;
-        for (pos, &c) in codes.iter().enumerate() {
-            sum = sum.wrapping_add((c as u32) * ((pos + 1) as u32));
-        }
-        let checksum: u8 = (sum % 103) as u8;
-
-        let mut all_codes: Vec<u8> = Vec::with_capacity(2 + codes.len());
-        all_codes.push(start_code);
-        all_codes.extend_from_slice(&codes);
-        all_codes.push(checksum);
-        all_codes.push(106);
-
-        let mut runs: Vec<u8> = Vec::new();
-        for &code in &all_codes {
-            let pat = PATTERNS.get(code as usize)?;
-            for ch in pat.chars() {
-                let d = ch.to_digit(10)? as u8;
-                runs.push(d);
-            }
+    /// Distancia desde el borde izquierdo para una imagen de `image_width` según ESC a. Una
+    /// imagen que no entra (ya achicada al papel o desbordada) arranca en el borde.
+    fn image_x_offset(align: Align, image_width: f32, area_width: f32) -> f32 {
+        let free = (area_width - image_width).max(0.0);
+        match align {
+            Align::Left => 0.0,
+            Align::Center => free / 2.0,
+            Align::Right => free,
         }
-
-        Some((runs, hri))
     }
 
-    fn encode_ean_runs(digits: &str) -> Option<(Vec<u8>, String)> {
-        // Devuelve runs (módulos) para EAN-13 o EAN-8, según longitud.
-        let mut s: String = digits.chars().filter(|c| c.is_ascii_digit()).collect();
-        if s.len() == 7 || s.len() == 12 {
-            // calcular checksum y anexar
-            let sum: u32 = s
-                .chars()
-                .rev()
-                .enumerate()
-                .map(|(i, c)| {
-                    let d = c.to_digit(10).unwrap_or(0);
-                    let w = if i % 2 == 0 { 3 } else { 1 };
-                    d * w
-                })
-                .sum();
-            let chk = (10 - (sum % 10)) % 10;
-            s.push(char::from(b'0' + (chk as u8)));
-        }
-
-        if s.len() == 13 {
-            const L: [&str; 10] = [
-                "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111",
-                "0111011", "0110111", "0001011",
-            ];
-            const G: [&str; 10] = [
-                "0100111", "0110011", "0011011", "0100001", "0011101", "0111001", "0000101",
-                "0010001", "0001001", "0010111",
-            ];
-            const R: [&str; 10] = [
-                "1110010", "1100110", "1101100", "1000010", "1011100", "1001110", "1010000",
-                "1000100", "1001000", "1110100",
-            ];
-            const PAR: [&str; 10] = [
-                "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG",
-                "LGLGGL", "LGGLGL",
-            ];
-
-            let first = s.chars().next()?.to_digit(10)? as usize;
-            let parity = PAR[first];
-            let left = &s[1..7];
-            let right = &s[7..13];
-
-            let mut bits: Vec<u8> = Vec::with_capacity(95);
-            // start guard
-            bits.extend_from_slice(&[1, 0, 1]);
-            // left
-            for (i, ch) in left.chars().enumerate() {
-                let d = ch.to_digit(10)? as usize;
-                let pat = match parity.chars().nth(i)? {
-                    'L' => L[d],
-                    'G' => G[d],
-                    _ => L[d],
-                };
-                for b in pat.bytes() {
-                    bits.push((b == b'1') as u8);
-                }
-            }
-            // middle guard
-            bits.extend_from_slice(&[0, 1, 0, 1, 0]);
-            // right
-            for ch in right.chars() {
-                let d = ch.to_digit(10)? as usize;
-                let pat = R[d];
-                for b in pat.bytes() {
-                    bits.push((b == b'1') as u8);
-                }
-            }
-            // end guard
-            bits.extend_from_slice(&[1, 0, 1]);
-
-            let (runs, start_black) = Self::bits01_to_runs(&bits)?;
-            if !start_black {
-                return None;
-            }
-            return Some((runs, s));
-        }
-
-        if s.len() == 8 {
-            const L: [&str; 10] = [
-                "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111",
-                "0111011", "0110111", "0001011",
-            ];
-            const R: [&str; 10] = [
-                "1110010", "1100110", "1101100", "1000010", "1011100", "1001110", "1010000",
-                "1000100", "1001000", "1110100",
-            ];
-
-            let left = &s[0..4];
-            let right = &s[4..8];
-
-            let mut bits: Vec<u8> = Vec::with_capacity(67);
-            bits.extend_from_slice(&[1, 0, 1]);
-            for ch in left.chars() {
-                let d = ch.to_digit(10)? as usize;
-                for b in L[d].bytes() {
-                    bits.push((b == b'1') as u8);
-                }
-            }
-            bits.extend_from_slice(&[0, 1, 0, 1, 0]);
-            for ch in right.chars() {
-                let d = ch.to_digit(10)? as usize;
-                for b in R[d].bytes() {
-                    bits.push((b == b'1') as u8);
-                }
-            }
-            bits.extend_from_slice(&[1, 0, 1]);
-
-            let (runs, start_black) = Self::bits01_to_runs(&bits)?;
-            if !start_black {
-                return None;
-            }
-            return Some((runs, s));
-        }
-
-        None
+    /// Muestra la imagen respetando la alineación ESC/POS (ESC a).
+    fn show_image_aligned(
+        ui: &mut egui::Ui,
+        cache: &mut HashMap<u64, egui::TextureHandle>,
+        key: u64,
+        image: egui::ColorImage,
+        target_width: f32,
+        align: Align,
+    ) -> egui::Response {
+        let offset = Self::image_x_offset(align, target_width, ui.available_width());
+        ui.horizontal(|ui| {
+            ui.add_space(offset);
+            Self::show_image_scaled(ui, cache, key, image, target_width)
+        })
+        .inner
     }
 
-    /// Encode Code39 barcode. Supports digits, uppercase letters, and special chars: - . $ / + % SPACE
-    fn encode_code39_runs(data: &str) -> Option<(Vec<u8>, String)> {
-        // Code39 patterns: 9 elements per character (5 bars, 4 spaces)
-        // 1 = wide, 0 = narrow. Pattern is: BSBSBSBSB (bar-space alternating)
-        const PATTERNS: &[(char, &str)] = &[
-            ('0', "101001101101"), ('1', "110100101011"), ('2', "101100101011"),
-            ('3', "110110010101"), ('4', "101001101011"), ('5', "110100110101"),
-            ('6', "101100110101"), ('7', "101001011011"), ('8', "110100101101"),
-            ('9', "101100101101"), ('A', "110101001011"), ('B', "101101001011"),
-            ('C', "110110100101"), ('D', "101011001011"), ('E', "110101100101"),
-            ('F', "101101100101"), ('G', "101010011011"), ('H', "110101001101"),
-            ('I', "101101001101"), ('J', "101011001101"), ('K', "110101010011"),
-            ('L', "101101010011"), ('M', "110110101001"), ('N', "101011010011"),
-            ('O', "110101101001"), ('P', "101101101001"), ('Q', "101010110011"),
-            ('R', "110101011001"), ('S', "101101011001"), ('T', "101011011001"),
-            ('U', "110010101011"), ('V', "100110101011"), ('W', "110011010101"),
-            ('X', "100101101011"), ('Y', "110010110101"), ('Z', "100110110101"),
-            ('-', "100101011011"), ('.', "110010101101"), (' ', "100110101101"),
-            ('$', "100100100101"), ('/', "100100101001"), ('+', "100101001001"),
-            ('%', "101001001001"), ('*', "100101101101"), // Start/stop character
-        ];
-
-        fn get_pattern(c: char) -> Option<&'static str> {
-            PATTERNS.iter()
-                .find(|(ch, _)| *ch == c.to_ascii_uppercase())
-                .map(|(_, p)| *p)
-        }
-
-        let hri: String = data.chars()
-            .filter(|c| get_pattern(*c).is_some() && *c != '*')
-            .collect();
-        
-        if hri.is_empty() {
-            return None;
-        }
-
-        let mut bits: Vec<u8> = Vec::new();
-        
-        // Start character (*)
-        let start = get_pattern('*')?;
-        for b in start.bytes() {
-            bits.push((b == b'1') as u8);
-        }
-        bits.push(0); // Inter-character gap
-        
-        // Data characters
-        for c in hri.chars() {
-            let pattern = get_pattern(c)?;
-            for b in pattern.bytes() {
-                bits.push((b == b'1') as u8);
+    /// Texto del inspector (hover / clic derecho) para barcodes, QR e imágenes del preview.
+    fn inspect_text(control: &Control, state: &PrinterState, hri: Option<&str>) -> String {
+        match control {
+            Control::Qr {
+                model,
+                module_size,
+                ecc,
+                data,
+            } => {
+                let level = match ecc {
+                    48 => "L",
+                    49 => "M",
+                    50 => "Q",
+                    51 => "H",
+                    _ => "?",
+                };
+                format!(
+                    "QR (GS ( k)\nModelo: {}\nMódulo: {} dots\nECC: {} ({})\nDatos ({} bytes):\n{}",
+                    model.label(),
+                    module_size,
+                    level,
+                    ecc,
+                    data.len(),
+                    String::from_utf8_lossy(data)
+                )
             }
-            bits.push(0); // Inter-character gap
-        }
-        
-        // Stop character (*)
-        let stop = get_pattern('*')?;
-        for b in stop.bytes() {
-            bits.push((b == b'1') as u8);
-        }
-        
-        let (runs, start_black) = Self::bits01_to_runs(&bits)?;
-        if !start_black {
-            return None;
+            Control::Barcode { m, data } => format!(
+                "Código de barras {} (GS k m={})\nDatos: {}\nHRI: {}\nAlto: {} dots · Módulo: {}",
+                barcode::symbology_name(*m),
+                m,
+                String::from_utf8_lossy(data),
+                hri.unwrap_or("-"),
+                state.barcode_height,
+                state.barcode_module_width
+            ),
+            Control::RasterImage {
+                m,
+                width_bytes,
+                height,
+                data,
+            } => format!(
+                "Imagen raster (GS v 0, m={})\n{}x{} dots\n{} bytes",
+                m,
+                (*width_bytes as usize) * 8,
+                height,
+                data.len()
+            ),
+            Control::TwoDimBarcode { kind, data } => format!(
+                "{} (GS ( k)\nSímbolo no dibujado (solo contenido)\nDatos ({} bytes):\n{}",
+                kind.label(),
+                data.len(),
+                String::from_utf8_lossy(data)
+            ),
+            Control::NvLogo { n, m } => format!(
+                "Logo NV #{} (FS p, m={})\nLa imagen está guardada en la impresora, no en el job",
+                n, m
+            ),
+            Control::BitImage { mode, width, data } => format!(
+                "Imagen bit image (ESC *, modo {})\n{}x{} dots\n{} bytes",
+                mode,
+                width,
+                if matches!(mode, 32 | 33) { 24 } else { 8 },
+                data.len()
+            ),
+            other => Self::debug_label_for_control(other),
         }
-        
-        Some((runs, hri))
     }
 
-    fn encode_itf_runs(digits: &str) -> Option<(Vec<u8>, String)> {
-        let mut s: String = digits.chars().filter(|c| c.is_ascii_digit()).collect();
-        if s.is_empty() {
-            return None;
-        }
-        if s.len() % 2 == 1 {
-            s.insert(0, '0');
-        }
-
-        fn pat(d: u8) -> [u8; 5] {
-            match d {
-                0 => [1, 1, 3, 3, 1],
-                1 => [3, 1, 1, 1, 3],
-                2 => [1, 3, 1, 1, 3],
-                3 => [3, 3, 1, 1, 1],
-                4 => [1, 1, 3, 1, 3],
-                5 => [3, 1, 3, 1, 1],
-                6 => [1, 3, 3, 1, 1],
-                7 => [1, 1, 1, 3, 3],
-                8 => [3, 1, 1, 3, 1],
-                _ => [1, 3, 1, 3, 1],
-            }
+    /// Como `attach_inspector`, salvo con el ticket al revés: el renglón se gira recién al
+    /// terminarlo, así que el inspector queda en `deferred` para registrarlo donde quedó dibujado.
+    fn attach_or_defer_inspector(
+        deferred: Option<&mut Vec<DeferredInspector>>,
+        response: &egui::Response,
+        info: &str,
+        copy_data: Option<&str>,
+    ) {
+        match deferred {
+            Some(deferred) => deferred.push((
+                response.rect,
+                response.id,
+                info.to_string(),
+                copy_data.map(str::to_string),
+            )),
+            None => Self::attach_inspector(response, info, copy_data),
         }
+    }
 
-        let bytes = s.as_bytes();
-        let mut runs: Vec<u8> = Vec::new();
-        // Start: 1010 => [1,1,1,1]
-        runs.extend_from_slice(&[1, 1, 1, 1]);
-
-        let mut i = 0usize;
-        while i + 1 < bytes.len() {
-            let a = (bytes[i] - b'0') as u8;
-            let b = (bytes[i + 1] - b'0') as u8;
-            let pa = pat(a);
-            let pb = pat(b);
-            for k in 0..5 {
-                runs.push(pa[k]); // bar
-                runs.push(pb[k]); // space
+    /// Tooltip + menú contextual con el detalle. `copy_data` agrega "Copiar datos"
+    /// (payload del QR o HRI del barcode).
+    fn attach_inspector(response: &egui::Response, info: &str, copy_data: Option<&str>) {
+        let response = response.clone().on_hover_text(info);
+        response.context_menu(|ui| {
+            ui.label(egui::RichText::new(info).monospace().size(11.0));
+            if let Some(data) = copy_data {
+                ui.separator();
+                if ui.button("📋 Copiar datos").clicked() {
+                    ui.ctx().copy_text(data.to_string());
+                    ui.close_menu();
+                }
             }
-            i += 2;
-        }
-
-        // Stop: wide bar, narrow space, narrow bar => [3,1,1]
-        runs.extend_from_slice(&[3, 1, 1]);
-        Some((runs, s))
+        });
     }
 
+    /// Imagen del barcode, su HRI y (si hay lector para la simbología) el resultado de
+    /// escanear la imagen ya dibujada.
     fn render_barcode(
         state: &PrinterState,
         m: u8,
         data: &[u8],
         target_width: f32,
-    ) -> Option<(egui::ColorImage, Option<String>)> {
+        quiet: usize,
+    ) -> Option<(egui::ColorImage, Option<String>, Option<ScanCheck>)> {
         // módulo/ancho en "módulos" (no confundir con píxeles)
         let module_px = (state.barcode_module_width as usize).clamp(1, 6);
         // altura: aproximamos dots a px
         let height_px = ((state.barcode_height as f32) * 0.9).round() as usize;
         let height_px = height_px.clamp(28, 220);
-        let quiet = 10usize;
-
-        // m según Epson ESC/POS (GS k):
-        // 67 EAN13, 68 EAN8, 70 ITF, 73 CODE128
-        let (runs, start_black, hri) = match m {
-            0x49 => {
-                let (runs, hri) = Self::encode_code128_runs(data)?;
-                (runs, true, Some(hri))
-            }
-            0x43 | 0x44 => {
-                let digits = String::from_utf8_lossy(data);
-                let (runs, hri) = Self::encode_ean_runs(&digits)?;
-                (runs, true, Some(hri))
-            }
-            0x46 => {
-                let digits = String::from_utf8_lossy(data);
-                let (runs, hri) = Self::encode_itf_runs(&digits)?;
-                (runs, true, Some(hri))
-            }
-            0x45 => {
-                // Code39
-                let text = String::from_utf8_lossy(data);
-                let (runs, hri) = Self::encode_code39_runs(&text)?;
-                (runs, true, Some(hri))
+
+        // m según Epson ESC/POS (GS k), formas A (0..6) y B (65..79).
+        let (runs, hri) = barcode::encode_runs(m, data)?;
+        let start_black = true;
+
+        let bitmap = runs_bitmap(&runs, start_black, module_px, height_px, quiet)?;
+        let scan = barcode::has_decoder(m).then(|| match scan_middle_row(&bitmap, module_px) {
+            Some((read_runs, quiet)) => barcode::scan_check(m, &read_runs, quiet, &hri),
+            None => ScanCheck::Unreadable,
+        });
+
+        // Si el barcode queda demasiado pequeño, egui lo escalará con show_image_scaled.
+        let _ = target_width;
+        Some((Self::bitmap_to_image(&bitmap), Some(hri), scan))
+    }
+
+    /// Margen alrededor del papel en el PNG exportado, en puntos.
+    const PNG_MARGIN_DOTS: usize = 16;
+
+    /// "Exportar PNG": el ticket activo dibujado fuera de pantalla y guardado donde elija el
+    /// usuario (por defecto `#{id}_{label}.png`, como en "Exportar todos").
+    fn export_png(&mut self, ctx: &egui::Context) {
+        let Some(job) = self.active_job() else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .set_file_name(format!("#{}_{}.png", job.id, sanitize_filename(&job.label)))
+            .save_file()
+        else {
+            return;
+        };
+        let image = self.render_ticket_image(ctx, job, self.png_scale as usize);
+        let result = encode_png(&image)
+            .and_then(|png| fs::write(&path, png).map_err(|e| format!("{}: {e}", path.display())));
+        self.show_toast(match result {
+            Ok(()) => format!("PNG guardado: {}", path.display()),
+            Err(e) => format!("No se pudo guardar el PNG: {e}"),
+        });
+    }
+
+    /// Alto de una celda de carácter en puntos: 24 (fuente A) o 17 (fuente B) por el
+    /// multiplicador de alto.
+    fn char_height_dots(state: &PrinterState) -> f32 {
+        let base = if state.is_font_b { 17.0 } else { 24.0 };
+        base * state.char_height_mul.max(1) as f32
+    }
+
+    /// `emit_text_with_columns` para el PNG: mismas columnas, cortes, alineación, bandas y rayas,
+    /// medidos en puntos. Los glifos salen de las fuentes del visor y se estiran a su celda.
+    /// Devuelve dónde termina el texto.
+    fn image_text(
+        ctx: &egui::Context,
+        canvas: &mut Canvas,
+        paper_width: PaperWidth,
+        state: &PrinterState,
+        text: &str,
+        options: TextRenderOptions,
+        top: f32,
+    ) -> f32 {
+        let (margin, cols) = Self::print_area_columns(paper_width, state);
+        let aligned;
+        let text = if text.contains('\t') {
+            aligned = Self::align_tab_fields(text, cols, " ");
+            aligned.as_str()
+        } else {
+            text
+        };
+        let total_dots = paper_width.dots();
+        let char_w = total_dots / Self::effective_columns(paper_width, state) as f32;
+        let char_h = Self::char_height_dots(state);
+        let font_family = if options.use_thermal_font {
+            egui::FontFamily::Name("DotMatrix".into())
+        } else {
+            egui::FontFamily::Monospace
+        };
+        // Se rasteriza cerca del tamaño final (con tope: el atlas de fuentes es finito).
+        let pixels_per_point = ctx.pixels_per_point();
+        let font_size = (char_h * canvas.scale() as f32).min(160.0) / pixels_per_point;
+
+        let mut y = top;
+        for line in Self::split_and_wrap(text, cols) {
+            let len = line.chars().count();
+            let pad = margin
+                + if len >= cols {
+                    0
+                } else {
+                    match state.alignment {
+                        Align::Left => 0,
+                        Align::Center => (cols - len) / 2,
+                        Align::Right => cols - len,
+                    }
+                };
+            let cell = egui::Rect::from_min_size(
+                egui::pos2(pad as f32 * char_w, y),
+                egui::vec2(len as f32 * char_w, char_h),
+            );
+            // Bandas a todo el ancho como en la vista previa (`line_band`); si no, una línea
+            // invertida solo lleva fondo bajo sus caracteres.
+            if let Some(fill) = Self::line_band(state, len, cols, options) {
+                canvas.fill_dots(0.0, cell.top(), total_dots, cell.height(), fill);
+            } else if state.is_reverse {
+                canvas.fill_dots(
+                    cell.left(),
+                    cell.top(),
+                    cell.width(),
+                    cell.height(),
+                    options.ink,
+                );
             }
-            _ => {
-                // No soportado aún
-                return None;
+            let color = if state.is_reverse {
+                egui::Color32::WHITE
+            } else {
+                options.ink
+            };
+            let rule = if options.draw_separator_rules && !state.is_reverse {
+                Self::separator_rule(&line, cols)
+            } else {
+                None
+            };
+            if let Some(rule) = rule {
+                let bold = if state.is_bold { 2.0 } else { 1.0 };
+                let center = cell.center().y;
+                let mut hline = |y: f32, dots: f32| {
+                    let thickness = dots * bold;
+                    canvas.fill_dots(
+                        cell.left(),
+                        y - thickness / 2.0,
+                        cell.width(),
+                        thickness,
+                        color,
+                    );
+                };
+                match rule {
+                    SeparatorRule::Single { dots } => hline(center, dots),
+                    SeparatorRule::Double => {
+                        hline(center - 3.0 * bold, 2.0);
+                        hline(center + 3.0 * bold, 2.0);
+                    }
+                }
+            } else if len > 0 {
+                let galley = ctx.fonts(|fonts| {
+                    fonts.layout_no_wrap(
+                        line,
+                        egui::FontId::new(font_size, font_family.clone()),
+                        color,
+                    )
+                });
+                let bleed = options.heat_bleed && state.is_bold && !state.is_reverse;
+                canvas.queue_text(galley, cell, color, state.is_bold, bleed, pixels_per_point);
+                if state.is_underline {
+                    let weight = state.underline_weight.max(1) as f32;
+                    canvas.fill_dots(
+                        cell.left(),
+                        cell.bottom() - weight,
+                        cell.width(),
+                        weight,
+                        color,
+                    );
+                }
             }
+            y += char_h;
+        }
+        y
+    }
+
+    /// El ticket de `job` en un lienzo en memoria (`scale` píxeles por punto), con el ancho de
+    /// papel del job y la fuente, densidad y efectos del visor. Recorre el job con
+    /// `ticket_walk`, como la vista previa, pero en puntos de la impresora en vez de widgets.
+    fn render_ticket_image(
+        &self,
+        ctx: &egui::Context,
+        job: &JobEntry,
+        scale: usize,
+    ) -> egui::ColorImage {
+        let paper = self.job_paper_width(job);
+        let total_dots = paper.dots();
+        let paper_fill = if self.realistic_effects {
+            Self::THERMAL_PAPER_COLOR
+        } else {
+            egui::Color32::WHITE
         };
+        let options = TextRenderOptions {
+            use_thermal_font: self.use_thermal_font,
+            snap_thermal_font: self.snap_thermal_font,
+            shade_header_lines: self.shade_header_lines,
+            draw_separator_rules: self.draw_separator_rules,
+            heat_bleed: self.realistic_effects,
+            show_whitespace: false,
+            ink: Self::density_ink(self.print_density),
+        };
+        let mut canvas = Canvas::new(
+            total_dots as usize,
+            Self::PNG_MARGIN_DOTS,
+            scale,
+            paper_fill,
+        );
 
-        let img = Self::runs_to_image(&runs, start_black, module_px, height_px, quiet)?;
+        let draw_text =
+            |canvas: &mut Canvas, text: Option<(PrinterState, String)>, y: f32| match text {
+                Some((s, t)) => Self::image_text(ctx, canvas, paper, &s, &t, options, y),
+                None => y,
+            };
+        // Imagen alineada como el texto; si es más ancha que el papel se achica entera.
+        let place =
+            |canvas: &mut Canvas, img: &egui::ColorImage, width: f32, align: Align, y: f32| {
+                let width = width.min(total_dots);
+                let height = img.size[1] as f32 * width / img.size[0].max(1) as f32;
+                let x = match align {
+                    Align::Left => 0.0,
+                    Align::Center => (total_dots - width) / 2.0,
+                    Align::Right => total_dots - width,
+                };
+                canvas.blit(img, x, y, width, height);
+                y + height
+            };
+        let plain = PrinterState::default();
+
+        // Al revés se recorre de arriba abajo y se gira la imagen entera al final.
+        let walk = self.ticket_walk(job, " ", false);
+        let flipped = walk.flipped;
+        let order_style = self.order_separator;
+        let label_state = PrinterState {
+            alignment: Align::Center,
+            is_bold: true,
+            is_font_b: true,
+            ..PrinterState::default()
+        };
+        let label_options = TextRenderOptions {
+            ink: Self::ORDER_LABEL_COLOR,
+            ..options
+        };
 
-        // Si el barcode queda demasiado pequeño, egui lo escalará con show_image_scaled.
-        let _ = target_width;
-        Some((img, hri))
+        let mut y = 0.0_f32;
+        // Renglón en curso: dónde empieza y si tiene texto.
+        let mut line_top = 0.0_f32;
+        let mut line_has_text = false;
+        // Orden de cocina en curso: número y dónde empieza.
+        let mut order: Option<(usize, f32)> = None;
+        for step in walk {
+            let (state, cmd, text) = match step {
+                TicketStep::LineStart(_) => continue,
+                TicketStep::LineEnd(text) => {
+                    y = draw_text(&mut canvas, text, y);
+                    line_top = y;
+                    line_has_text = false;
+                    continue;
+                }
+                TicketStep::Order(number) => {
+                    if let Some((current, top)) = order.take() {
+                        if let Some(tint) = order_style.tint(current) {
+                            canvas.tint_dots(0.0, top, total_dots, y - top, tint);
+                        }
+                    }
+                    order = Some((number, y));
+                    let label = Self::order_label(number);
+                    y = Self::image_text(
+                        ctx,
+                        &mut canvas,
+                        paper,
+                        &label_state,
+                        &label,
+                        label_options,
+                        y + 2.0,
+                    ) + 4.0;
+                    line_top = y;
+                    continue;
+                }
+                TicketStep::Command {
+                    state,
+                    command,
+                    text,
+                    ..
+                } => (state, command, text),
+            };
+            y = draw_text(&mut canvas, text, y);
+            match cmd {
+                CommandType::Text(_) => line_has_text = true,
+                CommandType::Control(
+                    control @ (Control::Newline | Control::FeedLines(_) | Control::FeedDots(_)),
+                ) => {
+                    // Avance = max(glifo más alto, interlineado); una línea vacía avanza con el
+                    // tamaño vigente en el LF.
+                    let glyph = if line_has_text {
+                        0.0
+                    } else {
+                        Self::char_height_dots(state)
+                    };
+                    let spacing = state.line_spacing.unwrap_or(30) as f32;
+                    let advance = match control {
+                        // ESC J n: n puntos desde el borde superior del renglón.
+                        Control::FeedDots(n) => *n as f32,
+                        // ESC d n: el renglón como un LF y n-1 líneas más.
+                        Control::FeedLines(n) => {
+                            spacing.max(glyph) + (*n).saturating_sub(1) as f32 * spacing
+                        }
+                        _ => spacing.max(glyph),
+                    };
+                    y = y.max(line_top + advance);
+                }
+                CommandType::Control(Control::FormFeed) => y += 8.0,
+                CommandType::Control(Control::Cut { feed, .. }) => {
+                    y += *feed as f32 + 12.0;
+                    let mut x = 0.0;
+                    while x < total_dots {
+                        canvas.fill_dots(x, y, 8.0, 1.0, egui::Color32::GRAY);
+                        x += 16.0;
+                    }
+                    y += 13.0;
+                }
+                CommandType::Control(Control::RasterImage {
+                    width_bytes,
+                    height,
+                    data,
+                    ..
+                }) => {
+                    if let Some(img) =
+                        Self::raster_to_image(*width_bytes, *height, data, self.print_density)
+                    {
+                        y = place(
+                            &mut canvas,
+                            &img,
+                            *width_bytes as f32 * 8.0,
+                            state.alignment,
+                            y,
+                        );
+                    }
+                }
+                CommandType::Control(Control::BitImage { mode, width, data }) => {
+                    if let Some(img) = Self::bitimage_to_image(*mode, *width, data) {
+                        // Densidad simple (m = 0 / 32): cada columna ocupa dos puntos.
+                        let dots = *width as f32 * if mode & 1 == 0 { 2.0 } else { 1.0 };
+                        y = place(&mut canvas, &img, dots, state.alignment, y);
+                    }
+                }
+                CommandType::Control(Control::Qr {
+                    model,
+                    module_size,
+                    ecc,
+                    data,
+                }) => {
+                    if let Some((img, _)) = Self::qr_to_image(data, *ecc, *module_size, *model) {
+                        let width = img.size[0] as f32;
+                        y = place(&mut canvas, &img, width, state.alignment, y);
+                    }
+                }
+                CommandType::Control(Control::Barcode { m, data }) => {
+                    let rendered =
+                        Self::render_barcode(state, *m, data, total_dots, self.barcode_quiet_zone);
+                    if let Some((img, hri, _)) = rendered {
+                        let hri = hri.unwrap_or_else(|| String::from_utf8_lossy(data).to_string());
+                        let hri_state = PrinterState {
+                            alignment: state.alignment,
+                            ..PrinterState::default()
+                        };
+                        let (above, below) = match state.barcode_hri {
+                            BarcodeHriPosition::None => (false, false),
+                            BarcodeHriPosition::Above => (true, false),
+                            BarcodeHriPosition::Below => (false, true),
+                            BarcodeHriPosition::Both => (true, true),
+                        };
+                        if above {
+                            y = Self::image_text(
+                                ctx,
+                                &mut canvas,
+                                paper,
+                                &hri_state,
+                                &hri,
+                                options,
+                                y,
+                            );
+                        }
+                        let width = img.size[0] as f32;
+                        y = place(&mut canvas, &img, width, state.alignment, y);
+                        if below {
+                            y = Self::image_text(
+                                ctx,
+                                &mut canvas,
+                                paper,
+                                &hri_state,
+                                &hri,
+                                options,
+                                y,
+                            );
+                        }
+                    }
+                }
+                CommandType::Control(Control::TwoDimBarcode { kind, data }) => {
+                    let label = format!(
+                        "[{} {}]",
+                        kind.label().to_uppercase(),
+                        String::from_utf8_lossy(data)
+                    );
+                    y = Self::image_text(ctx, &mut canvas, paper, &plain, &label, options, y);
+                }
+                CommandType::Control(Control::NvLogo { n, m }) => {
+                    let logo = self
+                        .nv_logos
+                        .get(n)
+                        .and_then(|logo| logo.image.as_ref().ok())
+                        .and_then(|(width_bytes, height, data)| {
+                            let img = Self::raster_to_image(
+                                *width_bytes,
+                                *height,
+                                data,
+                                self.print_density,
+                            )?;
+                            let (sx, sy) = NvLogo::scale(*m);
+                            let img = Self::scale_image(img, sx, sy);
+                            let width = img.size[0] as f32;
+                            Some((img, width))
+                        });
+                    y = match logo {
+                        Some((img, width)) => place(&mut canvas, &img, width, state.alignment, y),
+                        None => {
+                            let label = format!("[Logo NV #{n}]");
+                            Self::image_text(ctx, &mut canvas, paper, &plain, &label, options, y)
+                        }
+                    };
+                }
+                // Cambios de estado: ya viajan en el PrinterState de cada comando; los tabs y
+                // `ESC \` ya están resueltos en el texto.
+                CommandType::Control(_) | CommandType::Unknown(_) => {}
+            }
+        }
+        if let Some((current, top)) = order {
+            if let Some(tint) = order_style.tint(current) {
+                canvas.tint_dots(0.0, top, total_dots, y - top, tint);
+            }
+        }
+
+        // Los glifos ya están en el atlas de fuentes: se pintan todos de una vez.
+        let atlas = ctx.fonts(|fonts| fonts.image());
+        canvas.paint_text(&atlas);
+        canvas.finish(y, flipped)
     }
 }
 
@@ -1831,7 +6067,15 @@ impl Drop for EscPosViewer {
     fn drop(&mut self) {
         // Al cerrar la aplicación, forzamos que la impresora quede en OFFLINE.
         // Esto permite que los trabajos se acumulen en el Spooler de Windows.
-        let _ = crate::printer_setup::set_printer_offline(true);
+        // Los visores que crean los tests no tocan la impresora real.
+        #[cfg(not(test))]
+        {
+            let _ = crate::printer_setup::set_printer_offline(true);
+            // Un cambio de preferencias que no llegó a asentarse antes de cerrar.
+            if self.pending_settings.is_some() {
+                let _ = self.settings_snapshot().save();
+            }
+        }
     }
 }
 
@@ -1845,6 +6089,39 @@ impl eframe::App for EscPosViewer {
             };
         }
 
+        // Modo compacto: la ventana pierde los bordes solo mientras se está en Preview.
+        let decorations = !self.compact_active();
+        if self.decorations_applied != Some(decorations) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(decorations));
+            self.decorations_applied = Some(decorations);
+        }
+
+        // Ctrl+E: probar el siguiente codepage sin abrir Ajustes.
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::E)) {
+            self.cycle_codepage();
+        }
+
+        // Ctrl+RePág / Ctrl+AvPág: cambiar de pestaña sin el mouse (el foco la sigue, así el
+        // lector de pantalla anuncia el job).
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::PageUp)) {
+            self.select_adjacent_job(false);
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::PageDown)) {
+            self.select_adjacent_job(true);
+        }
+
+        // Ctrl+V fuera de un campo de texto: egui lo entrega como Event::Paste solo si hay
+        // texto; con solo archivos no llega ningún evento, así que se mira el teclado.
+        let paste_keys = self.window.paste_keys_down();
+        let paste_pressed = paste_keys && !self.paste_keys_held;
+        self.paste_keys_held = paste_keys;
+        if !ctx.wants_keyboard_input()
+            && (paste_pressed
+                || ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Paste(_)))))
+        {
+            self.paste_from_clipboard();
+        }
+
         // Cachear HWND (Windows) lo antes posible.
         self.window.try_update_from_frame(frame);
 
@@ -1852,14 +6129,15 @@ impl eframe::App for EscPosViewer {
         if self.ui_mode == UiMode::Preview && self.last_ui_mode != UiMode::Preview {
             #[cfg(target_os = "windows")]
             {
-                self.window.snap_near_right(14);
+                self.window
+                    .snap_to_monitor(self.preview_monitor_index(), self.dock_corner, 14);
             }
         }
 
         // Aplicar tamaño inicial una sola vez.
         if !self.did_apply_initial_window_size {
             self.did_apply_initial_window_size = true;
-            let w = Self::target_window_width_px(self.paper_width);
+            let w = Self::target_window_width_px(self.active_paper_width());
             Self::request_window_width(ctx, w);
         }
 
@@ -1869,18 +6147,21 @@ impl eframe::App for EscPosViewer {
             if self.ui_mode == UiMode::Preview {
                 #[cfg(target_os = "windows")]
                 {
-                    self.window.center_on_screen();
+                    self.window.center_on_screen(self.preview_monitor_index());
                 }
             }
         }
 
-        // Si cambió el papel, ajustar ancho de ventana.
-        if self.paper_width != self.last_paper_width {
-            self.last_paper_width = self.paper_width;
-            let w = Self::target_window_width_px(self.paper_width);
+        // Si cambió el papel (en Ajustes o por pasar a un job de otro ancho), ajustar la ventana.
+        let paper = self.active_paper_width();
+        if paper != self.last_paper_width {
+            self.last_paper_width = paper;
+            let w = Self::target_window_width_px(paper);
             Self::request_window_width(ctx, w);
         }
 
+        self.tick_docked_mode(ctx);
+
         // Inicializar System Tray una sola vez.
         if self.tray.is_none() && self.tray_error.is_none() {
             match SystemTray::new(self.window.clone()) {
@@ -1889,6 +6170,9 @@ impl eframe::App for EscPosViewer {
             }
         }
 
+        self.sync_global_hotkey(ctx);
+        self.poll_global_hotkey();
+
         // Si el usuario intenta cerrar la ventana (X), ocultamos a bandeja.
         // Nota: esto solo aplica si el tray existe; si falló, dejamos que cierre normal.
         if self.tray.is_some() {
@@ -1899,7 +6183,9 @@ impl eframe::App for EscPosViewer {
             }
         }
 
-        // Si se pidió ocultar a bandeja (por cerrar ventana), lo aplicamos aquí.
+        self.tick_idle_to_tray(ctx);
+
+        // Si se pidió ocultar a bandeja (por cerrar ventana o inactividad), lo aplicamos aquí.
         if self.pending_hide_to_tray {
             self.pending_hide_to_tray = false;
             self.hidden_to_tray = true;
@@ -1907,6 +6193,7 @@ impl eframe::App for EscPosViewer {
         }
 
         self.tick_simulation();
+        self.tick_replay(ctx, Instant::now());
         if self.active_job().is_some_and(|j| j.sim_active) {
             // Forzar repaints para animar la simulación.
             ctx.request_repaint();
@@ -1915,28 +6202,58 @@ impl eframe::App for EscPosViewer {
         // Autolimpieza / límites del historial.
         self.prune_jobs();
 
-        // Mantener el listener TCP 9100 sincronizado con el checkbox.
-        // Evita reintentos automáticos constantes si el puerto está ocupado.
+        // Mantener el listener TCP sincronizado con el checkbox. Si el puerto está ocupado se
+        // reintenta con espera creciente (`tcp_retry_at`), no en cada frame.
         if self.tcp_enabled {
-            if self.tcp_capture.is_none() && self.tcp_last_error.is_none() {
-                self.set_tcp_capture(true, Some(ctx.clone()));
+            if self.tcp_capture.is_none() {
+                let now = Instant::now();
+                match self.tcp_retry_at {
+                    Some(at) if at > now => ctx.request_repaint_after(at - now),
+                    _ => self.set_tcp_capture(true, Some(ctx.clone())),
+                }
             }
         } else if self.tcp_capture.is_some() {
             self.set_tcp_capture(false, None);
         }
 
-        // Captura TCP (impresora virtual 9100)
+        self.poll_tcp_test(ctx);
+        self.poll_webhook(ctx);
+
+        // Captura TCP (impresora virtual)
         if let Some(cap) = &self.tcp_capture {
             let jobs = cap.try_recv_all();
             for job in jobs {
                 if self.should_ignore_tcp_job(&job.bytes) {
+                    crate::diag_log::log(&format!(
+                        "TCP: job ignorado por ruido ({} bytes, {})",
+                        job.bytes.len(),
+                        job.source
+                    ));
                     continue;
                 }
-                let label = format!("TCP 9100 ({})", job.source);
-                self.push_new_job(label, job.bytes);
+                let label = format!("TCP {} ({})", self.tcp_port, job.source);
+                let mirror = self
+                    .webhook
+                    .is_some()
+                    .then(|| (label.clone(), job.bytes.clone()));
+                let id = self.push_new_job(label, job.bytes);
+                if let (Some(sender), Some((label, bytes))) = (&self.webhook, mirror) {
+                    sender.enqueue(WebhookJob {
+                        id,
+                        label,
+                        received_unix: SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0),
+                        bytes,
+                    });
+                }
 
                 // Si estaba oculto a la bandeja, el hilo TCP ya lo re-muestra (Windows).
-                self.hidden_to_tray = false;
+                // Con la vista congelada o "Mostrar al recibir un job" apagado no se re-muestra.
+                if self.show_on_job && !self.freeze_view {
+                    self.hidden_to_tray = false;
+                }
             }
         }
 
@@ -1961,6 +6278,53 @@ impl eframe::App for EscPosViewer {
                             self.try_load_path(&path);
                         }
                     }
+                    if ui
+                        .button("📋 Pegar")
+                        .on_hover_text("Ctrl+V: archivos copiados, volcado hex o texto")
+                        .clicked()
+                    {
+                        self.paste_from_clipboard();
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.jobs.is_empty(),
+                            egui::Button::new("📦 Exportar todos"),
+                        )
+                        .clicked()
+                    {
+                        self.export_status = None;
+                        self.show_export_all = true;
+                    }
+                    if ui
+                        .add_enabled(
+                            self.active_job().is_some(),
+                            egui::Button::new("🖼 Exportar PNG"),
+                        )
+                        .on_hover_text(format!(
+                            "Imagen del ticket activo a {}× (Configuración → Escala PNG)",
+                            self.png_scale
+                        ))
+                        .clicked()
+                    {
+                        self.export_png(ui.ctx());
+                    }
+                    let replay_label = match &self.replay {
+                        Some(replay) => {
+                            let (sent, total) = replay.progress();
+                            format!("⏯ Reproduciendo {sent}/{total}")
+                        }
+                        None => "⏯ Reproducir sesión".to_string(),
+                    };
+                    if ui.button(replay_label).clicked() {
+                        self.show_replay = true;
+                    }
+                    if ui
+                        .button("🧩 Snippets")
+                        .on_hover_text("Arma un job de prueba con fragmentos ESC/POS")
+                        .clicked()
+                    {
+                        self.show_snippets = true;
+                    }
 
                     ui.separator();
                     egui::ComboBox::from_label("Modo")
@@ -1974,6 +6338,8 @@ impl eframe::App for EscPosViewer {
                         });
 
                     ui.separator();
+                    ui.toggle_value(&mut self.split_view, "🔀 Raw | Render")
+                        .on_hover_text("Bytes crudos por comando junto al ticket");
                     if ui.button("⚙ Configuración").clicked() {
                         self.show_settings = true;
                     }
@@ -1981,6 +6347,18 @@ impl eframe::App for EscPosViewer {
                     if let Some(job) = self.active_job() {
                         ui.separator();
                         ui.label(egui::RichText::new(format!("📄 {}", job.label)).weak());
+                        let secs =
+                            total_secs(&job.parsed_commands, &job.command_spans, self.print_rate());
+                        ui.label(egui::RichText::new(format!("⏱ {}", format_secs(secs))).weak())
+                            .on_hover_text("Tiempo estimado de impresión");
+                    }
+                    if let Some(job) = self.active_job_mut() {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut job.note)
+                                .hint_text("📝 Nota…")
+                                .desired_width(180.0),
+                        )
+                        .on_hover_text("Nota del job: se muestra en la pestaña y se exporta");
                     }
                 });
 
@@ -2001,64 +6379,142 @@ impl eframe::App for EscPosViewer {
                     egui::CollapsingHeader::new("Hex Dump")
                         .default_open(true)
                         .show(ui, |ui| {
-                            egui::ScrollArea::vertical()
-                                .id_salt("hex_scroll")
-                                .show(ui, |ui| {
-                                    if let Some(job) = self.active_job() {
-                                        ui.monospace(pretty_hex(&job.display_bytes));
-                                    } else {
-                                        ui.monospace("(sin datos)");
-                                    }
-                                });
+                            ui.horizontal(|ui| {
+                                ui.label("Bytes por fila:");
+                                for width in HEX_ROW_WIDTHS {
+                                    ui.selectable_value(
+                                        &mut self.hex_row_bytes,
+                                        width,
+                                        width.to_string(),
+                                    );
+                                }
+                            });
+                            self.ui_hex_edit_bar(ui);
+                            // Solo se formatean las filas visibles: jobs de varios MB no
+                            // generan un String gigante en cada frame.
+                            let bytes_per_row = self.hex_row_bytes;
+                            let bytes: &[u8] = self
+                                .active_job()
+                                .map(|j| j.display_bytes.as_slice())
+                                .unwrap_or_default();
+                            if bytes.is_empty() {
+                                ui.monospace("(sin datos)");
+                            } else {
+                                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                                egui::ScrollArea::vertical()
+                                    .id_salt("hex_scroll")
+                                    .show_rows(
+                                        ui,
+                                        row_height,
+                                        hex_row_count(bytes.len(), bytes_per_row),
+                                        |ui, rows| {
+                                            for row in rows {
+                                                ui.monospace(hex_row(bytes, row, bytes_per_row));
+                                            }
+                                        },
+                                    );
+                            }
                         });
 
                     ui.add_space(8.0);
 
-                    egui::CollapsingHeader::new("Log (Comandos)")
-                        .default_open(true)
+                    egui::CollapsingHeader::new("Cobertura")
+                        .default_open(false)
                         .show(ui, |ui| {
-                            egui::ScrollArea::vertical()
-                                .id_salt("cmd_scroll")
+                            let Some(job) = self.active_job() else {
+                                ui.label(egui::RichText::new("(sin comandos)").weak());
+                                return;
+                            };
+                            egui::Grid::new("coverage_grid")
+                                .num_columns(3)
+                                .striped(true)
                                 .show(ui, |ui| {
-                                    let Some(job) = self.active_job() else {
-                                        ui.label(egui::RichText::new("(sin comandos)").weak());
-                                        return;
-                                    };
-                                    for (idx, (_state, cmd)) in
-                                        job.parsed_commands.iter().enumerate()
-                                    {
-                                        let line = match cmd {
-                                            CommandType::Text(text) => {
-                                                let mut snippet = text.replace(['\r', '\n'], " ");
-                                                const MAX: usize = 60;
-                                                if snippet.len() > MAX {
-                                                    snippet.truncate(MAX);
-                                                    snippet.push_str("…");
-                                                }
-                                                format!("TXT  {}", snippet)
-                                            }
-                                            CommandType::Control(control) => format!(
-                                                "CTL  {}",
-                                                Self::debug_label_for_control(control)
-                                            ),
-                                            CommandType::Unknown(byte) => {
-                                                format!("UNK  {:02X}", byte)
+                                    for entry in coverage_report(&job.parsed_commands) {
+                                        let color = match entry.support {
+                                            Support::Full => egui::Color32::from_rgb(22, 163, 74),
+                                            Support::Partial => {
+                                                egui::Color32::from_rgb(217, 119, 6)
                                             }
+                                            Support::Unsupported => egui::Color32::RED,
                                         };
-
+                                        ui.monospace(entry.command);
                                         ui.label(
-                                            egui::RichText::new(format!("{:04}: {}", idx, line))
-                                                .monospace()
-                                                .size(10.0),
+                                            egui::RichText::new(entry.support.label())
+                                                .color(color)
+                                                .small(),
                                         );
+                                        ui.monospace(format!("{}x", entry.count));
+                                        ui.end_row();
                                     }
                                 });
                         });
+
+                    ui.add_space(8.0);
+
+                    if self.show_state_inspector {
+                        egui::CollapsingHeader::new("Estado de impresora")
+                            .default_open(true)
+                            .show(ui, |ui| self.ui_state_inspector(ui));
+                        ui.add_space(8.0);
+                    }
+
+                    if self.show_receipt_meta {
+                        egui::CollapsingHeader::new("Datos detectados (experimental)")
+                            .default_open(true)
+                            .show(ui, |ui| self.ui_receipt_meta(ui));
+                        ui.add_space(8.0);
+                    }
+
+                    if self.log_detached {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new("Log en ventana aparte").weak());
+                            if ui.small_button("📌 Acoplar log").clicked() {
+                                self.log_detached = false;
+                            }
+                        });
+                    } else {
+                        egui::CollapsingHeader::new("Log (Comandos)")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                if ui
+                                    .small_button("⧉ Separar log")
+                                    .on_hover_text("Abre el log en una ventana que se puede mover y redimensionar")
+                                    .clicked()
+                                {
+                                    self.log_detached = true;
+                                }
+                                self.ui_command_log(ui);
+                            });
+                    }
+                });
+        }
+
+        if self.ui_mode == UiMode::Full && self.log_detached {
+            let mut open = true;
+            egui::Window::new("Log (Comandos)")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([420.0, 520.0])
+                .show(ctx, |ui| self.ui_command_log(ui));
+            if !open {
+                self.log_detached = false;
+            }
+        }
+
+        if self.ui_mode == UiMode::Full && self.split_view {
+            egui::SidePanel::right("split_raw_panel")
+                .resizable(true)
+                .min_width(260.0)
+                .default_width(420.0)
+                .show(ctx, |ui| {
+                    ui.heading("Raw");
+                    ui.separator();
+                    self.ui_split_raw_panel(ui);
                 });
         }
 
-        // En modo Preview: botón flotante para volver a mostrar menús.
-        if self.ui_mode == UiMode::Preview {
+        // En modo Preview: botón flotante para volver a mostrar menús (no en compacto).
+        if self.ui_mode == UiMode::Preview && !self.compact_mode {
             egui::Area::new("preview_menu_button".into())
                 .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-12.0, 10.0))
                 .interactable(true)
@@ -2075,28 +6531,83 @@ impl eframe::App for EscPosViewer {
                 });
         }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
+        let compact = self.compact_active();
+        let ticket_margin = if compact { 4.0 } else { 15.0 };
+        let mut central = egui::CentralPanel::default();
+        if compact {
+            central = central.frame(egui::Frame::central_panel(&ctx.style()).inner_margin(2.0));
+        }
+        central.show(ctx, |ui| {
             let (job_id, stick_bottom) = match self.active_job() {
                 Some(j) => (j.id, self.auto_scroll_on_print && j.sim_active),
                 None => (0, false),
             };
 
+            // Al cambiar de job, volver al inicio del ticket (salvo que se esté imprimiendo:
+            // en ese caso manda el stick-to-bottom).
+            let active_id = self.active_job().map(|j| j.id);
+            let job_changed = active_id != self.last_rendered_job_id;
+            self.last_rendered_job_id = active_id;
+            if job_changed {
+                self.cut_nav_current = 0;
+                self.cut_nav_target = None;
+                self.selected_cmd = None;
+            }
+
+            // Navegación entre cortes: solo si el job trae varios tickets.
+            let cuts = self.active_job().map(Self::cut_count).unwrap_or(0);
+            if cuts > 1 {
+                self.cut_nav_current = self.cut_nav_current.min(cuts - 1);
+                ui.horizontal(|ui| {
+                    let prev = ui
+                        .add_enabled(self.cut_nav_current > 0, egui::Button::new("⏶"))
+                        .on_hover_text("Corte anterior");
+                    if Self::accessible_name(prev, "Corte anterior").clicked() {
+                        self.cut_nav_current -= 1;
+                        self.cut_nav_target = Some(self.cut_nav_current);
+                    }
+                    ui.label(format!("Corte {} de {}", self.cut_nav_current + 1, cuts));
+                    let next = ui
+                        .add_enabled(self.cut_nav_current + 1 < cuts, egui::Button::new("⏷"))
+                        .on_hover_text("Corte siguiente");
+                    if Self::accessible_name(next, "Corte siguiente").clicked() {
+                        self.cut_nav_current += 1;
+                        self.cut_nav_target = Some(self.cut_nav_current);
+                    }
+                });
+            }
+
             ui.push_id(job_id, |ui| {
-                egui::ScrollArea::vertical()
-                    .id_salt("render_scroll")
-                    .stick_to_bottom(stick_bottom)
-                    .show(ui, |ui| {
-                    let desired: f32 = match self.paper_width {
+                let paper = self.active_paper_width();
+                let overflow = self.overflow_scroll;
+                let mut scroll = if overflow {
+                    egui::ScrollArea::both()
+                } else {
+                    egui::ScrollArea::vertical()
+                }
+                .id_salt("render_scroll")
+                .stick_to_bottom(stick_bottom);
+                if job_changed && !stick_bottom {
+                    scroll = scroll.vertical_scroll_offset(0.0);
+                }
+                scroll.show(ui, |ui| {
+                    let desired: f32 = match paper {
                         PaperWidth::W58mm => 300.0,
                         PaperWidth::W80mm => 450.0,
                     };
                     let available: f32 = ui.available_width().max(0.0);
-                    let paper_width: f32 = desired.min((available - 20.0).max(180.0));
+                    // Con desborde el papel no se achica con la ventana: para eso está el scroll.
+                    let paper_width: f32 = if overflow {
+                        desired
+                    } else {
+                        desired.min((available - 20.0).max(180.0))
+                    };
+                    let max_image_width = if overflow { f32::INFINITY } else { paper_width };
 
                     // Centrar el ticket en la ventana, pero el contenido interno respetará la alineación ESC/POS
                     ui.horizontal(|ui| {
                         // Calcular margen para centrar (incluir padding del Frame: 15px * 2 lados + stroke)
-                        let total_ticket_width = paper_width + 30.0 + 2.0; // inner_margin * 2 + stroke
+                        let total_ticket_width = paper_width + ticket_margin * 2.0 + 2.0; // inner_margin * 2 + stroke
                         let available = ui.available_width();
                         let margin = ((available - total_ticket_width) / 2.0).max(0.0);
                         ui.add_space(margin);
@@ -2120,73 +6631,172 @@ impl eframe::App for EscPosViewer {
                             .fill(paper_fill)
                             .shadow(shadow)
                             .stroke(egui::Stroke::new(1.0, stroke_color))
-                            .inner_margin(15.0)
+                            .inner_margin(ticket_margin)
                             .rounding(0.0) // Sin redondeo para parecer papel real
                             .show(ui, |ui| {
                                 // Contenido vertical SIN centrado automático para respetar alineación ESC/POS
                                 ui.vertical(|ui| {
                                 ui.set_min_width(paper_width);
-                                ui.set_max_width(paper_width);
+                                if overflow {
+                                    // El frame (y los efectos, que se anclan a su rect) crece con el contenido.
+                                    ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+                                } else {
+                                    ui.set_max_width(paper_width);
+                                }
                                 ui.set_min_height(400.0);
 
                                 let mut texture_cache = mem::take(&mut self.texture_cache);
+                                let scroll_to_cut = self.cut_nav_target.take();
+                                let selected_cmd = self.selected_cmd;
+                                let scroll_to_selected = mem::take(&mut self.cmd_scroll_request);
+                                let flash_alpha = self.reparse_flash_alpha();
+                                if flash_alpha.is_some() {
+                                    ui.ctx().request_repaint();
+                                }
+                                let mut cut_idx = 0usize;
 
                                 let Some(job) = self.active_job() else {
-                                    ui.label(
-                                        egui::RichText::new("Arrastra un .prn/.bin o imprime por TCP 9100")
-                                            .color(egui::Color32::GRAY)
-                                            .size(12.0),
-                                    );
+                                    if self.jobs.is_empty() {
+                                        self.ui_onboarding(ui);
+                                    } else {
+                                        ui.label(
+                                            egui::RichText::new("Arrastra un .prn/.bin o imprime por TCP 9100")
+                                                .color(egui::Color32::GRAY)
+                                                .size(12.0),
+                                        );
+                                    }
                                     self.texture_cache = texture_cache;
                                     return;
                                 };
 
-                                let mut pending: Option<(PrinterState, String)> = None;
-                                let use_thermal_font = self.use_thermal_font;
-                                let flush_pending = |ui: &mut egui::Ui,
-                                                     pending: &mut Option<(PrinterState, String)>| {
-                                    if let Some((s, t)) = pending.take() {
-                                        if !t.is_empty() {
-                                            Self::emit_text_with_columns(
-                                                ui,
-                                                self.paper_width,
-                                                &s,
-                                                &t,
-                                                use_thermal_font,
-                                            );
-                                        }
-                                    }
+                                let text_options = TextRenderOptions {
+                                    use_thermal_font: self.use_thermal_font,
+                                    snap_thermal_font: self.snap_thermal_font,
+                                    shade_header_lines: self.shade_header_lines,
+                                    draw_separator_rules: self.draw_separator_rules,
+                                    heat_bleed: self.realistic_effects,
+                                    show_whitespace: self.show_whitespace,
+                                    ink: Self::density_ink(self.print_density),
+                                };
+                                // Devuelve el rect de la última línea dibujada (para marcar el ¶).
+                                let draw_text = |ui: &mut egui::Ui,
+                                                 text: Option<(PrinterState, String)>|
+                                 -> Option<egui::Rect> {
+                                    let (s, t) = text?;
+                                    Some(Self::emit_text_with_columns(
+                                        ui,
+                                        paper,
+                                        paper_width,
+                                        &s,
+                                        &t,
+                                        text_options,
+                                    ))
                                 };
 
-                                for (state, cmd) in &job.parsed_commands {
-                                    match cmd {
-                                        CommandType::Text(text) => match &mut pending {
-                                            Some((ps, buf)) => {
-                                                // If cursor_x changed, add padding spaces
-                                                if state.cursor_x != ps.cursor_x {
-                                                    if let Some(cursor_x) = state.cursor_x {
-                                                        // Convert dots to columns
-                                                        let dots_per_col = 12u16;
-                                                        let target_col = (cursor_x / dots_per_col) as usize;
-                                                        let current_col = buf.chars().count();
-                                                        if target_col > current_col {
-                                                            let spaces = target_col - current_col;
-                                                            buf.push_str(&" ".repeat(spaces));
-                                                        }
-                                                    }
+                                let mut line_metrics = LineMetrics::default();
+                                let print_marks = if self.show_print_times {
+                                    elapsed_marks(&job.parsed_commands, &job.command_spans, self.print_rate())
+                                } else {
+                                    Vec::new()
+                                };
+
+                                // Al revés (opción o ESC { 1 al comienzo) los renglones se dibujan del
+                                // último al primero y cada uno se gira sobre sí mismo.
+                                let fill = if self.show_whitespace { "\u{00A0}" } else { " " };
+                                let walk = self.ticket_walk(job, fill, true);
+                                let flipped = walk.flipped;
+                                // Renglón en curso: dónde empieza y si lleva el resaltado de
+                                // cambios.
+                                let mut line_start: Option<(egui::layers::ShapeIdx, f32, bool)> =
+                                    None;
+                                // Al revés: inspectores del renglón en curso, hasta girarlo.
+                                let mut line_inspectors: Vec<DeferredInspector> = Vec::new();
+                                let order_style = self.order_separator;
+                                // Orden en curso: número, borde superior y hueco para su fondo.
+                                let mut order = None;
+
+                                for step in walk {
+                                    let (cmd_idx, state, cmd, text) = match step {
+                                        TicketStep::Order(number) => {
+                                            if let Some(current) = order.take() {
+                                                Self::end_order(ui, current);
+                                            }
+                                            order = Some(Self::begin_order(ui, number, order_style));
+                                            continue;
+                                        }
+                                        TicketStep::LineStart(cmd_idx) => {
+                                            let changed = flash_alpha.is_some()
+                                                && job.changed_lines.binary_search(&cmd_idx).is_ok();
+                                            if flipped || changed {
+                                                let slot = ui.painter().add(egui::Shape::Noop);
+                                                line_start = Some((slot, ui.cursor().top(), changed));
+                                            }
+                                            continue;
+                                        }
+                                        TicketStep::LineEnd(text) => {
+                                            draw_text(ui, text);
+                                            if let Some((first, top, changed)) = line_start.take() {
+                                                let line = egui::Rect::from_x_y_ranges(
+                                                    ui.max_rect().x_range(),
+                                                    top..=ui.cursor().top(),
+                                                );
+                                                if let (true, Some(alpha)) = (changed, flash_alpha) {
+                                                    ui.painter().set(
+                                                        first,
+                                                        egui::Shape::rect_filled(
+                                                            line,
+                                                            0.0,
+                                                            egui::Color32::from_rgba_unmultiplied(
+                                                                250, 204, 21, alpha,
+                                                            ),
+                                                        ),
+                                                    );
                                                 }
-                                                
-                                                if Self::same_line_style(ps, state) {
-                                                    buf.push_str(text);
-                                                } else {
-                                                    flush_pending(ui, &mut pending);
-                                                    pending = Some((state.clone(), text.clone()));
+                                                if flipped {
+                                                    Self::rotate_painted_180(ui, first, line);
+                                                    // El hover sigue a lo dibujado: cada elemento
+                                                    // se registra en su lugar girado.
+                                                    let center = line.center();
+                                                    for (rect, id, info, copy) in line_inspectors.drain(..) {
+                                                        let rotated = egui::Rect::from_center_size(
+                                                            center + (center - rect.center()),
+                                                            rect.size(),
+                                                        );
+                                                        let response =
+                                                            ui.interact(rotated, id.with("al_reves"), egui::Sense::click());
+                                                        Self::attach_inspector(&response, &info, copy.as_deref());
+                                                    }
                                                 }
                                             }
-                                            None => {
-                                                pending = Some((state.clone(), text.clone()));
-                                            }
-                                        },
+                                            continue;
+                                        }
+                                        TicketStep::Command {
+                                            idx,
+                                            state,
+                                            command,
+                                            text,
+                                        } => (idx, state, command, text),
+                                    };
+                                    if selected_cmd == Some(cmd_idx) {
+                                        // Marca en el margen a la altura del comando elegido en el Log/Raw.
+                                        let marker = egui::Rect::from_min_size(
+                                            egui::pos2(ui.max_rect().left() - 10.0, ui.cursor().top()),
+                                            egui::vec2(4.0, 14.0),
+                                        );
+                                        ui.painter().rect_filled(
+                                            marker,
+                                            1.0,
+                                            egui::Color32::from_rgb(37, 99, 235),
+                                        );
+                                        if scroll_to_selected {
+                                            ui.scroll_to_rect(marker, Some(egui::Align::Center));
+                                        }
+                                    }
+                                    match cmd {
+                                        CommandType::Text(_) => {
+                                            line_metrics.add_text(state);
+                                            draw_text(ui, text);
+                                        }
                                         CommandType::Control(control) => {
                                             if self.show_debug_controls {
                                                 let label =
@@ -2198,42 +6808,64 @@ impl eframe::App for EscPosViewer {
                                                         .monospace(),
                                                 );
                                             }
+                                            let last_line = draw_text(ui, text);
 
                                             match control {
-                                                Control::Newline => {
-                                                    flush_pending(ui, &mut pending);
+                                                Control::Newline
+                                                | Control::FeedLines(_)
+                                                | Control::FeedDots(_) => {
+                                                    if self.show_whitespace {
+                                                        let pos = last_line.map_or(
+                                                            egui::pos2(ui.max_rect().left(), ui.cursor().top() + 7.0),
+                                                            |rect| rect.right_center() + egui::vec2(2.0, 0.0),
+                                                        );
+                                                        ui.painter().text(
+                                                            pos,
+                                                            egui::Align2::LEFT_CENTER,
+                                                            "¶",
+                                                            egui::FontId::monospace(10.0),
+                                                            Self::WHITESPACE_MARK_COLOR,
+                                                        );
+                                                    }
                                                     
-                                                    let total_dots = match self.paper_width {
-                                                        PaperWidth::W58mm => 384.0,
-                                                        PaperWidth::W80mm => 576.0,
-                                                    };
+                                                    let total_dots = paper.dots();
                                                     let dots_to_pixels = paper_width / total_dots;
-                                                    let n = state.line_spacing.unwrap_or(30) as f32;
-                                                    let line_spacing_px = n * dots_to_pixels;
-                                                    
-                                                    let mut base_size = 14.0_f32;
-                                                    if state.is_font_b {
-                                                        base_size *= 0.75;
+                                                    let mut advance = line_metrics.finish(state, dots_to_pixels);
+                                                    let spacing_px =
+                                                        state.line_spacing.unwrap_or(30) as f32 * dots_to_pixels;
+                                                    match control {
+                                                        // ESC J n: n puntos desde el borde superior del renglón.
+                                                        Control::FeedDots(n) => advance = *n as f32 * dots_to_pixels,
+                                                        // ESC d n: el renglón como un LF y n-1 líneas más.
+                                                        Control::FeedLines(n) => {
+                                                            advance += (*n).saturating_sub(1) as f32 * spacing_px
+                                                        }
+                                                        _ => {}
                                                     }
-                                                    let height_mul = state.char_height_mul.max(1) as f32;
-                                                    let font_size = base_size * height_mul;
-                                                    let text_height_px = font_size * 1.15;
-                                                    
-                                                    let item_spacing_y = ui.spacing().item_spacing.y;
-                                                    let extra_space = (line_spacing_px - text_height_px - item_spacing_y).max(0.0);
-                                                    
-                                                    ui.add_space(extra_space.max(1.0));
+                                                    // La línea ya ocupó el alto de su texto (más el espaciado de
+                                                    // egui): se completa hasta el avance desde su borde superior.
+                                                    let line_top = last_line.map_or(ui.cursor().top(), |rect| rect.top());
+                                                    ui.add_space((line_top + advance - ui.cursor().top()).max(1.0));
                                                 }
-                                                Control::Cut => {
-                                                    flush_pending(ui, &mut pending);
+                                                Control::Cut { feed, .. } => {
+                                                    // Avance de GS V 65/66 n antes del corte.
+                                                    let total_dots = paper.dots();
+                                                    ui.add_space(*feed as f32 * paper_width / total_dots);
                                                     ui.add_space(15.0);
-                                                    ui.label(
+                                                    let cut_label = ui.label(
                                                         egui::RichText::new(
                                                             "- - - - - - CORTE - - - - - -",
                                                         )
                                                         .size(10.0)
                                                         .color(egui::Color32::GRAY),
                                                     );
+                                                    if let Some(secs) = print_marks.get(cmd_idx) {
+                                                        Self::paint_print_time(ui, cut_label.rect.center().y, *secs);
+                                                    }
+                                                    if scroll_to_cut == Some(cut_idx) {
+                                                        cut_label.scroll_to_me(Some(egui::Align::Center));
+                                                    }
+                                                    cut_idx += 1;
                                                     ui.add_space(15.0);
                                                 }
                                                 Control::RasterImage {
@@ -2242,80 +6874,59 @@ impl eframe::App for EscPosViewer {
                                                     height,
                                                     data,
                                                 } => {
-                                                    flush_pending(ui, &mut pending);
                                                     if let Some(img) = Self::raster_to_image(
                                                         *width_bytes,
                                                         *height,
                                                         data,
+                                                        self.print_density,
                                                     ) {
                                                         let key = Self::hash_key(&(
                                                             "raster",
                                                             width_bytes,
                                                             height,
                                                             data,
+                                                            self.print_density,
                                                         ));
                                                         
                                                         // Calcular ancho visual proporcional real basado en dots
-                                                        let total_dots = match self.paper_width {
-                                                            PaperWidth::W58mm => 384.0,
-                                                            PaperWidth::W80mm => 576.0,
-                                                        };
+                                                        let total_dots = paper.dots();
                                                         let dots_to_pixels = paper_width / total_dots;
-                                                        let img_display_width = ((*width_bytes as f32 * 8.0) * dots_to_pixels).min(paper_width);
-
-                                                        match state.alignment {
-                                                            Align::Center => {
-                                                                ui.vertical_centered(|ui| {
-                                                                    Self::show_image_scaled(
-                                                                        ui,
-                                                                        &mut texture_cache,
-                                                                        key,
-                                                                        img,
-                                                                        img_display_width,
-                                                                    );
-                                                                });
-                                                            }
-                                                            Align::Right => {
-                                                                ui.with_layout(
-                                                                    egui::Layout::right_to_left(egui::Align::Center),
-                                                                    |ui| {
-                                                                        Self::show_image_scaled(
-                                                                            ui,
-                                                                            &mut texture_cache,
-                                                                            key,
-                                                                            img,
-                                                                            img_display_width,
-                                                                        );
-                                                                    },
-                                                                );
-                                                            }
-                                                            Align::Left => {
-                                                                Self::show_image_scaled(
-                                                                    ui,
-                                                                    &mut texture_cache,
-                                                                    key,
-                                                                    img,
-                                                                    img_display_width,
-                                                                );
-                                                            }
-                                                        }
+                                                        let img_display_width = ((*width_bytes as f32 * 8.0) * dots_to_pixels).min(max_image_width);
+
+                                                        let response = Self::show_image_aligned(
+                                                            ui,
+                                                            &mut texture_cache,
+                                                            key,
+                                                            img,
+                                                            img_display_width,
+                                                            state.alignment,
+                                                        );
+                                                        let raster_info =
+                                                            Self::inspect_text(control, state, None);
+                                                        Self::attach_or_defer_inspector(
+                                                            flipped.then_some(&mut line_inspectors),
+                                                            &response,
+                                                            &raster_info,
+                                                            None,
+                                                        );
                                                         ui.add_space(8.0);
                                                     }
                                                 }
                                                 Control::Qr {
-                                                    model: _,
+                                                    model,
                                                     module_size,
                                                     ecc,
                                                     data,
                                                 } => {
-                                                    flush_pending(ui, &mut pending);
-                                                    if let Some(img) = Self::qr_to_image(
+                                                    if let Some((img, note)) = Self::qr_to_image(
                                                         data,
                                                         *ecc,
                                                         *module_size,
+                                                        *model,
                                                     ) {
                                                         let key = Self::hash_key(&(
                                                             "qr",
+                                                            model,
                                                             ecc,
                                                             module_size,
                                                             data,
@@ -2323,41 +6934,29 @@ impl eframe::App for EscPosViewer {
                                                         let target =
                                                             paper_width.min(260.0);
 
-                                                        match state.alignment {
-                                                            Align::Center => {
-                                                                ui.vertical_centered(|ui| {
-                                                                    Self::show_image_scaled(
-                                                                        ui,
-                                                                        &mut texture_cache,
-                                                                        key,
-                                                                        img,
-                                                                        target,
-                                                                    );
-                                                                });
-                                                            }
-                                                            Align::Right => {
-                                                                ui.with_layout(
-                                                                    egui::Layout::right_to_left(egui::Align::Center),
-                                                                    |ui| {
-                                                                        Self::show_image_scaled(
-                                                                            ui,
-                                                                            &mut texture_cache,
-                                                                            key,
-                                                                            img,
-                                                                            target,
-                                                                        );
-                                                                    },
-                                                                );
-                                                            }
-                                                            Align::Left => {
-                                                                Self::show_image_scaled(
-                                                                    ui,
-                                                                    &mut texture_cache,
-                                                                    key,
-                                                                    img,
-                                                                    target,
-                                                                );
-                                                            }
+                                                        let response = Self::show_image_aligned(
+                                                            ui,
+                                                            &mut texture_cache,
+                                                            key,
+                                                            img,
+                                                            target,
+                                                            state.alignment,
+                                                        );
+                                                        let qr_info =
+                                                            Self::inspect_text(control, state, None);
+                                                        let qr_payload = String::from_utf8_lossy(data);
+                                                        Self::attach_or_defer_inspector(
+                                                            flipped.then_some(&mut line_inspectors),
+                                                            &response,
+                                                            &qr_info,
+                                                            Some(&qr_payload),
+                                                        );
+                                                        if let Some(note) = note {
+                                                            ui.label(
+                                                                egui::RichText::new(note)
+                                                                    .size(9.0)
+                                                                    .color(egui::Color32::GRAY),
+                                                            );
                                                         }
                                                         ui.add_space(8.0);
                                                     } else {
@@ -2370,8 +6969,106 @@ impl eframe::App for EscPosViewer {
                                                         );
                                                     }
                                                 }
+                                                Control::TwoDimBarcode { kind, data } => {
+                                                    ui.add_space(6.0);
+                                                    let payload = String::from_utf8_lossy(data);
+                                                    let response = egui::Frame::none()
+                                                        .stroke(egui::Stroke::new(1.0, egui::Color32::BLACK))
+                                                        .inner_margin(6.0)
+                                                        .show(ui, |ui| {
+                                                            ui.label(
+                                                                egui::RichText::new(format!("▦ {}", kind.label()))
+                                                                    .color(egui::Color32::BLACK)
+                                                                    .strong()
+                                                                    .size(11.0),
+                                                            );
+                                                            ui.label(
+                                                                egui::RichText::new(payload.as_ref())
+                                                                    .color(egui::Color32::BLACK)
+                                                                    .monospace()
+                                                                    .size(10.0),
+                                                            );
+                                                        })
+                                                        .response
+                                                        .interact(egui::Sense::click());
+                                                    let info = Self::inspect_text(control, state, None);
+                                                    Self::attach_or_defer_inspector(
+                                                        flipped.then_some(&mut line_inspectors),
+                                                        &response,
+                                                        &info,
+                                                        Some(&payload),
+                                                    );
+                                                    ui.add_space(6.0);
+                                                }
+                                                Control::NvLogo { n, m } => {
+                                                    let logo = self.nv_logos.get(n);
+                                                    let shown = logo
+                                                        .and_then(|logo| logo.image.as_ref().ok())
+                                                        .and_then(|(width_bytes, height, data)| {
+                                                            let img = Self::raster_to_image(
+                                                                *width_bytes,
+                                                                *height,
+                                                                data,
+                                                                self.print_density,
+                                                            )?;
+                                                            let (sx, sy) = NvLogo::scale(*m);
+                                                            let img = Self::scale_image(img, sx, sy);
+                                                            let key = Self::hash_key(&(
+                                                                "raster",
+                                                                width_bytes,
+                                                                height,
+                                                                data,
+                                                                self.print_density,
+                                                                (sx, sy),
+                                                            ));
+                                                            let total_dots = paper.dots();
+                                                            let width = (img.size[0] as f32 * paper_width
+                                                                / total_dots)
+                                                                .min(max_image_width);
+                                                            Some(Self::show_image_aligned(
+                                                                ui,
+                                                                &mut texture_cache,
+                                                                key,
+                                                                img,
+                                                                width,
+                                                                state.alignment,
+                                                            ))
+                                                        });
+                                                    let response = shown.unwrap_or_else(|| {
+                                                        ui.add_space(6.0);
+                                                        let note = match logo.map(|l| &l.image) {
+                                                            Some(Err(err)) => err.clone(),
+                                                            _ => "Sin imagen asignada (Configuración → Logos NV)".to_string(),
+                                                        };
+                                                        egui::Frame::none()
+                                                            .stroke(egui::Stroke::new(1.0, egui::Color32::GRAY))
+                                                            .inner_margin(6.0)
+                                                            .show(ui, |ui| {
+                                                                ui.label(
+                                                                    egui::RichText::new(format!("[Logo NV #{n}]"))
+                                                                        .color(egui::Color32::BLACK)
+                                                                        .strong()
+                                                                        .size(11.0),
+                                                                );
+                                                                ui.label(
+                                                                    egui::RichText::new(note)
+                                                                        .color(egui::Color32::GRAY)
+                                                                        .size(9.0),
+                                                                );
+                                                            })
+                                                            .response
+                                                            .interact(egui::Sense::click())
+                                                    });
+                                                    let info = Self::inspect_text(control, state, None);
+                                                    Self::attach_or_defer_inspector(
+                                                        flipped.then_some(&mut line_inspectors),
+                                                        &response,
+                                                        &info,
+                                                        None,
+                                                    );
+                                                    ui.add_space(6.0);
+                                                }
                                                 Control::OpenDrawer => {
-                                                    flush_pending(ui, &mut pending);
                                                     ui.add_space(8.0);
                                                     ui.group(|ui| {
                                                         ui.horizontal(|ui| {
@@ -2385,14 +7082,42 @@ impl eframe::App for EscPosViewer {
                                                     });
                                                     ui.add_space(8.0);
                                                 }
+                                                Control::Bell => {
+                                                    ui.label(
+                                                        egui::RichText::new("🔔 BEL")
+                                                            .size(10.0)
+                                                            .color(egui::Color32::GRAY),
+                                                    );
+                                                }
+                                                Control::Buzzer { count } => {
+                                                    ui.label(
+                                                        egui::RichText::new(format!("🔔 Beep x{count}"))
+                                                            .size(10.0)
+                                                            .color(egui::Color32::GRAY),
+                                                    );
+                                                }
+                                                Control::FormFeed => {
+                                                    // Sin modo página: termina la línea y deja un avance visible.
+                                                    ui.add_space(6.0);
+                                                    ui.label(
+                                                        egui::RichText::new("- - - FF (avance de página) - - -")
+                                                            .size(10.0)
+                                                            .color(egui::Color32::GRAY),
+                                                    );
+                                                    ui.add_space(6.0);
+                                                }
                                                 Control::Barcode { m, data } => {
-                                                    flush_pending(ui, &mut pending);
                                                     ui.add_space(6.0);
                                                     let hri_pos = state.barcode_hri;
-                                                    let target = paper_width.min(360.0);
-                                                    if let Some((img, hri)) =
-                                                        Self::render_barcode(state, *m, data, target)
+                                                    let mut target = paper_width.min(360.0);
+                                                    if let Some((img, hri, scan)) =
+                                                        Self::render_barcode(state, *m, data, target, self.barcode_quiet_zone)
                                                     {
+                                                        if overflow {
+                                                            // Un barcode más ancho que el papel se ve a su ancho real en dots.
+                                                            let total_dots = paper.dots();
+                                                            target = target.max(img.size[0] as f32 * paper_width / total_dots);
+                                                        }
                                                         let key = Self::hash_key(&(
                                                             "barcode",
                                                             *m,
@@ -2400,6 +7125,7 @@ impl eframe::App for EscPosViewer {
                                                             state.barcode_hri as u8,
                                                             state.barcode_height,
                                                             state.barcode_module_width,
+                                                            self.barcode_quiet_zone,
                                                             Self::hash_key(data),
                                                         ));
 
@@ -2416,42 +7142,40 @@ impl eframe::App for EscPosViewer {
                                                             ui.add_space(2.0);
                                                         }
 
-                                                        match state.alignment {
-                                                            Align::Center => {
-                                                                ui.vertical_centered(|ui| {
-                                                                    Self::show_image_scaled(
-                                                                        ui,
-                                                                        &mut texture_cache,
-                                                                        key,
-                                                                        img,
-                                                                        target,
-                                                                    );
-                                                                });
-                                                            }
-                                                            Align::Right => {
-                                                                ui.with_layout(
-                                                                    egui::Layout::right_to_left(egui::Align::Center),
-                                                                    |ui| {
-                                                                        Self::show_image_scaled(
-                                                                            ui,
-                                                                            &mut texture_cache,
-                                                                            key,
-                                                                            img,
-                                                                            target,
-                                                                        );
-                                                                    },
-                                                                );
-                                                            }
-                                                            Align::Left => {
-                                                                Self::show_image_scaled(
-                                                                    ui,
-                                                                    &mut texture_cache,
-                                                                    key,
-                                                                    img,
-                                                                    target,
-                                                                );
-                                                            }
+                                                        let response = Self::show_image_aligned(
+                                                            ui,
+                                                            &mut texture_cache,
+                                                            key,
+                                                            img,
+                                                            target,
+                                                            state.alignment,
+                                                        );
+                                                        let mut barcode_info = Self::inspect_text(
+                                                            control,
+                                                            state,
+                                                            Some(hri_text.as_str()),
+                                                        );
+                                                        if let Some(scan) = scan.filter(|_| self.barcode_scan_check) {
+                                                            let (mark, color) = if scan.is_ok() {
+                                                                ("✅", egui::Color32::from_rgb(22, 163, 74))
+                                                            } else {
+                                                                ("⚠", egui::Color32::from_rgb(217, 119, 6))
+                                                            };
+                                                            ui.painter().text(
+                                                                response.rect.right_top() + egui::vec2(-2.0, 2.0),
+                                                                egui::Align2::RIGHT_TOP,
+                                                                mark,
+                                                                egui::FontId::proportional(11.0),
+                                                                color,
+                                                            );
+                                                            barcode_info.push_str(&format!("\nLectura: {}", scan.label()));
                                                         }
+                                                        Self::attach_or_defer_inspector(
+                                                            flipped.then_some(&mut line_inspectors),
+                                                            &response,
+                                                            &barcode_info,
+                                                            Some(hri_text.as_str()),
+                                                        );
 
                                                         // Mostrar HRI abajo
                                                         if matches!(hri_pos, BarcodeHriPosition::Below | BarcodeHriPosition::Both) {
@@ -2466,30 +7190,32 @@ impl eframe::App for EscPosViewer {
                                                     } else {
                                                         // Fallback: placeholder
                                                         let preview = String::from_utf8_lossy(data);
-                                                        ui.label(
-                                                            egui::RichText::new(format!(
-                                                                "[BARCODE m={:02X}] {}",
-                                                                m, preview
-                                                            ))
-                                                            .color(egui::Color32::BLACK)
-                                                            .monospace()
-                                                            .size(11.0),
+                                                        let response = ui.add(
+                                                            egui::Label::new(
+                                                                egui::RichText::new(format!(
+                                                                    "[BARCODE m={:02X}] {}",
+                                                                    m, preview
+                                                                ))
+                                                                .color(egui::Color32::BLACK)
+                                                                .monospace()
+                                                                .size(11.0),
+                                                            )
+                                                            .sense(egui::Sense::click()),
+                                                        );
+                                                        let barcode_info =
+                                                            Self::inspect_text(control, state, None);
+                                                        Self::attach_or_defer_inspector(
+                                                            flipped.then_some(&mut line_inspectors),
+                                                            &response,
+                                                            &barcode_info,
+                                                            Some(&preview),
                                                         );
                                                     }
                                                     ui.add_space(6.0);
                                                 }
-                                                Control::Tab => {
-                                                    // Agregar tabulador al texto pendiente para simular columnas
-                                                    if let Some((_, ref mut text)) = pending {
-                                                        // Tab = saltar a siguiente posición de tabulador (cada 8 caracteres típicamente)
-                                                        let current_len = text.chars().count();
-                                                        let next_tab = ((current_len / 8) + 1) * 8;
-                                                        let spaces = next_tab.saturating_sub(current_len);
-                                                        text.push_str(&" ".repeat(spaces.max(1)));
-                                                    }
-                                                }
+                                                // Ya resueltos en el texto del renglón (TicketWalk).
+                                                Control::Tab | Control::RelativePosition { .. } => {}
                                                 Control::BitImage { mode, width, data } => {
-                                                    flush_pending(ui, &mut pending);
                                                     if let Some(img) = Self::bitimage_to_image(
                                                         *mode,
                                                         *width,
@@ -2501,24 +7227,74 @@ impl eframe::App for EscPosViewer {
                                                             width,
                                                             data,
                                                         ));
-                                                        Self::show_image_scaled(
+                                                        let total_dots = paper.dots();
+                                                        // Densidad simple (m = 0 / 32): cada columna ocupa dos puntos.
+                                                        let dots = *width as f32 * if mode & 1 == 0 { 2.0 } else { 1.0 };
+                                                        let display_width =
+                                                            (dots * paper_width / total_dots).min(max_image_width);
+                                                        let response = Self::show_image_aligned(
                                                             ui,
                                                             &mut texture_cache,
                                                             key,
                                                             img,
-                                                            paper_width,
+                                                            display_width,
+                                                            state.alignment,
+                                                        );
+                                                        let bitimage_info =
+                                                            Self::inspect_text(control, state, None);
+                                                        Self::attach_or_defer_inspector(
+                                                            flipped.then_some(&mut line_inspectors),
+                                                            &response,
+                                                            &bitimage_info,
+                                                            None,
                                                         );
                                                         ui.add_space(4.0);
                                                     }
                                                 }
-                                                _ => {}
+                                                // Cambios de estado de texto: ya viajan en el PrinterState
+                                                // de cada comando y los aplica el texto siguiente.
+                                                Control::Init
+                                                | Control::Bold(_)
+                                                | Control::Align(_)
+                                                | Control::CodePage(_)
+                                                | Control::CodePageFallback { .. }
+                                                | Control::Size { .. }
+                                                | Control::Underline(_)
+                                                | Control::Reverse(_)
+                                                | Control::UpsideDown(_)
+                                                | Control::MasterSelect(_)
+                                                | Control::FontSelect(_)
+                                                | Control::DoubleByteMode(_)
+                                                | Control::LineSpacingDefault
+                                                | Control::LineSpacing(_)
+                                                | Control::AbsolutePosition { .. }
+                                                | Control::LeftMargin { .. }
+                                                | Control::PrintAreaWidth { .. } => {}
+                                                // Configuración de barcode: se aplica al próximo GS k.
+                                                Control::BarcodeHriPosition(_)
+                                                | Control::BarcodeHeight(_)
+                                                | Control::BarcodeModuleWidth(_)
+                                                | Control::BarcodeHriFont(_) => {}
+                                                // Sin efecto visual: solo aparecen en el log / debug comandos.
+                                                Control::EscBlock { .. }
+                                                | Control::GsBlock { .. }
+                                                | Control::EscUnknown(_)
+                                                | Control::GsUnknown(_) => {}
                                             }
                                         }
                                         CommandType::Unknown(_) => {}
                                     }
                                 }
 
-                                flush_pending(ui, &mut pending);
+                                if let Some(current) = order {
+                                    Self::end_order(ui, current);
+                                }
+                                if let Some(secs) = print_marks.last() {
+                                    ui.add_space(4.0);
+                                    let y = ui.cursor().top() + 6.0;
+                                    ui.add_space(12.0);
+                                    Self::paint_print_time(ui, y, *secs);
+                                }
 
                                 self.texture_cache = texture_cache;
                                 }); // fin ui.vertical
@@ -2551,15 +7327,33 @@ impl eframe::App for EscPosViewer {
                             // 5. Indicador de fin de rollo (línea rosa si ticket largo)
                             let ticket_height = rect.height();
                             Self::draw_end_of_roll_indicator(painter, rect, ticket_height);
+
+                            // 6. Curvatura del papel (sombra degradada arriba y abajo)
+                            Self::draw_paper_curl(painter, rect);
                         }
                         // ===== END REALISTIC EFFECTS =====
 
+                        if self.show_debug_controls && self.show_char_grid {
+                            let content = ticket.response.rect.shrink(ticket_margin);
+                            let cell = Self::char_grid_cell(paper, content.width());
+                            Self::draw_char_grid(ui.painter(), content, cell);
+                        }
+
                         if self.ui_mode == UiMode::Preview {
                             ticket.response.context_menu(|ui| {
                                 ui.label("Modo");
                                 ui.separator();
                                 ui.selectable_value(&mut self.ui_mode, UiMode::Preview, "Preview");
                                 ui.selectable_value(&mut self.ui_mode, UiMode::Full, "Completo");
+                                ui.checkbox(&mut self.compact_mode, "Compacto (sin bordes)");
+                                if self.compact_mode {
+                                    // Sin botones flotantes: la configuración queda en este menú.
+                                    ui.separator();
+                                    if ui.button("⚙ Config").clicked() {
+                                        self.show_settings = true;
+                                        ui.close_menu();
+                                    }
+                                }
                             });
                         }
                     });
@@ -2569,7 +7363,984 @@ impl eframe::App for EscPosViewer {
 
         // Modal de configuración (se muestra sobre Preview o Completo).
         self.ui_settings_modal(ctx);
+        self.ui_export_all_modal(ctx);
+        self.ui_replay_modal(ctx);
+        self.ui_snippets_modal(ctx);
+        self.ui_toast(ctx);
+        self.ui_drawer_animation(ctx);
+
+        self.persist_settings_if_changed(ctx);
 
         self.last_ui_mode = self.ui_mode;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Visor sin simulación de impresión: cada job se parsea entero al llegar.
+    fn test_viewer() -> EscPosViewer {
+        let mut viewer = EscPosViewer::default();
+        viewer.simulate_printing = false;
+        viewer
+    }
+
+    #[test]
+    fn full_width_reverse_rows_get_an_edge_to_edge_band() {
+        // Cebra de 58mm: filas invertidas a todo el ancho (32 columnas) y una corta.
+        let mut data = Vec::new();
+        for (reverse, row) in [
+            (1, format!("{:<26}{:>6}", "Cafe", "3.00")),
+            (0, "Medialunas 12.50".to_string()),
+            (1, format!("{:<26}{:>6}", "Total", "15.50")),
+            (1, "Corta".to_string()),
+        ] {
+            data.extend_from_slice(&[0x1D, 0x42, reverse]);
+            data.extend_from_slice(row.as_bytes());
+            data.push(b'\n');
+        }
+        data.extend_from_slice(b"\x1dB\x00\x1bE\x01\x1b-\x01Encabezado\n");
+        let commands = parse_escpos_simple(&data, CodePage::Cp437);
+
+        let ink = egui::Color32::from_gray(20);
+        let options = |shade_header_lines| TextRenderOptions {
+            use_thermal_font: false,
+            snap_thermal_font: false,
+            shade_header_lines,
+            draw_separator_rules: false,
+            heat_bleed: false,
+            show_whitespace: false,
+            ink,
+        };
+        let bands = |shade_header_lines| -> Vec<Option<egui::Color32>> {
+            commands
+                .iter()
+                .filter_map(|(state, cmd)| match cmd {
+                    CommandType::Text(t) => {
+                        let (_, cols) = EscPosViewer::print_area_columns(PaperWidth::W58mm, state);
+                        Some(EscPosViewer::line_band(
+                            state,
+                            t.chars().count(),
+                            cols,
+                            options(shade_header_lines),
+                        ))
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        let header = Some(egui::Color32::from_gray(228));
+        assert_eq!(bands(false), vec![Some(ink), None, Some(ink), None, None]);
+        assert_eq!(bands(true), vec![Some(ink), None, Some(ink), None, header]);
+    }
+
+    #[test]
+    fn settings_round_trip_through_the_store() {
+        let mut viewer = test_viewer();
+        viewer.tcp_port = 9105;
+        viewer.capture_limits.allow_remote = true;
+        viewer.capture_limits.max_connections_per_sec = 5;
+        viewer.idle_to_tray_mins = 15;
+        viewer.docked_mode = true;
+        viewer.dock_corner = ScreenCorner::TopLeft;
+        viewer.paper_width = PaperWidth::W80mm;
+        viewer.codepage = CodePage::Cp850;
+        viewer.initial_codepage = Some(CodePage::Cp866);
+        viewer.global_hotkey = "Ctrl+Shift+F9".to_string();
+        viewer.webhook_url = "https://example.com/hook?token=a=b".to_string();
+        viewer.webhook_format = WebhookFormat::Raw;
+
+        let store = viewer.settings_snapshot();
+        let mut loaded = test_viewer();
+        loaded.apply_settings(&store);
+        assert_eq!(loaded.settings_snapshot(), store);
+        assert_eq!(loaded.tcp_port, 9105);
+        assert!(loaded.capture_limits.allow_remote);
+        assert_eq!(loaded.idle_to_tray_mins, 15);
+        assert_eq!(loaded.dock_corner, ScreenCorner::TopLeft);
+        assert_eq!(loaded.initial_codepage, Some(CodePage::Cp866));
+        assert_eq!(loaded.hotkey_draft, "Ctrl+Shift+F9");
+        assert_eq!(loaded.webhook_url, "https://example.com/hook?token=a=b");
+    }
+
+    #[test]
+    fn hotkey_draft_applies_only_valid_changes() {
+        let mut viewer = test_viewer();
+
+        // A medio escribir: no se aplica y el atajo anterior sigue.
+        viewer.hotkey_draft = "Ctrl+Alt+".to_string();
+        viewer.commit_hotkey_draft();
+        assert_eq!(viewer.global_hotkey, DEFAULT_HOTKEY);
+        assert!(viewer.hotkey_draft_error.is_some());
+
+        // La misma combinación escrita distinto no cuenta como cambio.
+        viewer.hotkey_draft = " ctrl + alt + r ".to_string();
+        viewer.commit_hotkey_draft();
+        assert_eq!(viewer.global_hotkey, DEFAULT_HOTKEY);
+        assert_eq!(viewer.hotkey_draft, DEFAULT_HOTKEY);
+        assert_eq!(viewer.hotkey_draft_error, None);
+
+        viewer.hotkey_draft = "Ctrl+Shift+F9".to_string();
+        viewer.commit_hotkey_draft();
+        assert_eq!(viewer.global_hotkey, "Ctrl+Shift+F9");
+    }
+
+    #[test]
+    fn auto_table_aligns_blocks_with_the_same_tab_count() {
+        let data = b"Cant\tProducto\tPrecio\n\
+                     2\tCafe\t3.00\n\
+                     10\tMedialunas\t12.50\n\
+                     Total\t15.50\n\
+                     Solo\tuna\n";
+        let commands = parse_escpos_simple(data, CodePage::Cp437);
+        let lines = EscPosViewer::print_lines(&commands);
+        let stops = EscPosViewer::tab_table_stops(&commands);
+        // Bloque de 3 renglones con 2 tabs: "Cant" (4) y "Medialunas" (10) más 2 de separación.
+        for line in &lines[..3] {
+            assert_eq!(stops.get(&line.start), Some(&vec![6, 18]));
+        }
+        // "Total" y "Solo" tienen un tab cada uno: otro bloque, con su propio ancho.
+        assert_eq!(stops.get(&lines[3].start), Some(&vec![7]));
+        assert_eq!(stops.get(&lines[4].start), Some(&vec![7]));
+
+        // Un renglón suelto con tabs no forma tabla.
+        let single = parse_escpos_simple(b"Hola\nA\tB\nChau\n", CodePage::Cp437);
+        assert!(EscPosViewer::tab_table_stops(&single).is_empty());
+
+        // El job guarda las paradas calculadas al parsear.
+        let mut viewer = test_viewer();
+        viewer.push_new_job("tabla".to_string(), data.to_vec());
+        assert_eq!(viewer.jobs[0].table_stops, stops);
+    }
+
+    #[test]
+    fn upside_down_jobs_draw_their_lines_last_to_first() {
+        let data = b"\x1b{\x01\x1b@\x1b{\x01Uno\nDos\x1dk\x04123\x00Tres";
+        let commands = parse_escpos_simple(data, CodePage::Cp437);
+        assert!(EscPosViewer::starts_upside_down(&commands));
+        let lines = EscPosViewer::print_lines(&commands);
+        let texts: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|line| {
+                commands[line.clone()]
+                    .iter()
+                    .filter_map(|(_, cmd)| match cmd {
+                        CommandType::Text(t) => Some(t.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        // "Dos" y su barcode forman un renglón; el texto final sin LF, otro.
+        assert_eq!(texts, vec![vec!["Uno"], vec!["Dos"], vec!["Tres"]]);
+        assert!(matches!(
+            commands[lines[1].end - 1].1,
+            CommandType::Control(Control::Barcode { .. })
+        ));
+        assert_eq!(lines.last().map(|l| l.end), Some(commands.len()));
+
+        // ESC { después del contenido solo gira ese bloque: el ticket no se da vuelta.
+        let late = parse_escpos_simple(b"Hola\n\x1b{\x01Chau\n", CodePage::Cp437);
+        assert!(!EscPosViewer::starts_upside_down(&late));
+        let off = parse_escpos_simple(b"\x1b{\x00Hola\n", CodePage::Cp437);
+        assert!(!EscPosViewer::starts_upside_down(&off));
+        // ESC @ vuelve al modo de encendido: lo que sigue se imprime derecho.
+        let reset = parse_escpos_simple(b"\x1b{\x01\x1b@Hola\n", CodePage::Cp437);
+        assert!(!EscPosViewer::starts_upside_down(&reset));
+    }
+
+    #[test]
+    fn reverse_toggled_mid_line_starts_a_new_run() {
+        // GS B 1 / GS B 0 y ESC - 1 dentro de la misma línea centrada a doble alto.
+        let data = b"\x1ba\x01\x1d!\x10\x1dB\x01MENU\x1dB\x00 del dia\x1b-\x01hoy\n";
+        let texts: Vec<(PrinterState, String)> = parse_escpos_simple(data, CodePage::Cp437)
+            .into_iter()
+            .filter_map(|(state, cmd)| match cmd {
+                CommandType::Text(text) => Some((state, text)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts.len(), 3);
+        let (banner, rest, underlined) = (&texts[0].0, &texts[1].0, &texts[2].0);
+        assert!(banner.is_reverse && !rest.is_reverse);
+        assert_eq!(banner.alignment, Align::Center);
+        assert_eq!(banner.char_height_mul, 2);
+        assert!(!EscPosViewer::same_line_style(banner, rest));
+        assert!(!EscPosViewer::same_line_style(rest, underlined));
+        assert!(EscPosViewer::same_line_style(banner, &banner.clone()));
+    }
+
+    #[test]
+    fn relative_positions_move_the_end_of_the_pending_run() {
+        let mut buf = "Cafe".to_string();
+        EscPosViewer::shift_pending_text(&mut buf, 30);
+        assert_eq!(buf, "Cafe  ");
+        // Hacia atrás solo se comen espacios, nunca texto.
+        EscPosViewer::shift_pending_text(&mut buf, -60);
+        assert_eq!(buf, "Cafe");
+
+        let mut buf = String::new();
+        EscPosViewer::pad_to_cursor(&mut buf, 48);
+        assert_eq!(buf, "    ");
+        EscPosViewer::pad_to_cursor(&mut buf, 24);
+        assert_eq!(buf, "    ");
+    }
+
+    #[test]
+    fn glyph_stretch_scales_width_and_height_independently() {
+        // GS ! 0x10: doble alto, ancho normal.
+        assert_eq!(EscPosViewer::glyph_stretch(1, 2), (2.0, 0.5, 1.0));
+        // GS ! 0x01: doble ancho, alto normal.
+        assert_eq!(EscPosViewer::glyph_stretch(2, 1), (2.0, 1.0, 0.5));
+        // GS ! 0x11: ambos, sin deformar.
+        assert_eq!(EscPosViewer::glyph_stretch(2, 2), (2.0, 1.0, 1.0));
+        assert_eq!(EscPosViewer::glyph_stretch(0, 0), (1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn double_height_lines_advance_more_than_the_body() {
+        let data = b"\x1d!\x11TITULO\x1d!\x00\ncuerpo\n\n\x1b3\x0aapretado\n";
+        let dots_to_pixels = 300.0 / 384.0;
+        let mut line = LineMetrics::default();
+        let mut advances = Vec::new();
+        for (state, cmd) in parse_escpos_simple(data, CodePage::Cp437) {
+            match cmd {
+                CommandType::Text(_) => line.add_text(&state),
+                CommandType::Control(Control::Newline) => {
+                    advances.push(line.finish(&state, dots_to_pixels))
+                }
+                _ => {}
+            }
+        }
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        let normal = 14.0 * 1.15;
+        let spacing = 30.0 * dots_to_pixels;
+        // El encabezado manda su alto aunque el LF llegue con el tamaño ya normal.
+        assert!(close(advances[0], 2.0 * normal), "{advances:?}");
+        assert!(close(advances[1], spacing), "{advances:?}");
+        // Línea vacía: avanza igual que una de texto.
+        assert!(close(advances[2], spacing), "{advances:?}");
+        // ESC 3 10 no deja que las líneas se pisen.
+        assert!(close(advances[3], normal), "{advances:?}");
+    }
+
+    #[test]
+    fn left_margin_and_print_width_narrow_the_text_columns() {
+        // 58 mm: 384 dots / 32 columnas = 12 dots por columna.
+        let data = b"\x1dL\x30\x00A\n\x1dW\xf0\x00B\n";
+        let states: Vec<PrinterState> = parse_escpos_simple(data, CodePage::Cp437)
+            .into_iter()
+            .filter(|(_, cmd)| matches!(cmd, CommandType::Text(_)))
+            .map(|(state, _)| state)
+            .collect();
+        let area = |i: usize| EscPosViewer::print_area_columns(PaperWidth::W58mm, &states[i]);
+        // GS L 48: 4 columnas de margen y el resto del papel.
+        assert_eq!(area(0), (4, 28));
+        // GS W 240: 20 columnas a partir del margen.
+        assert_eq!(area(1), (4, 20));
+        // Doble ancho: 24 dots por columna.
+        let wide = PrinterState {
+            char_width_mul: 2,
+            ..states[1].clone()
+        };
+        assert_eq!(
+            EscPosViewer::print_area_columns(PaperWidth::W58mm, &wide),
+            (2, 10)
+        );
+        // Un margen mayor que el papel deja al menos una columna.
+        let huge = PrinterState {
+            left_margin_dots: 1_000,
+            print_width_dots: None,
+            ..PrinterState::default()
+        };
+        assert_eq!(
+            EscPosViewer::print_area_columns(PaperWidth::W58mm, &huge),
+            (31, 1)
+        );
+    }
+
+    #[test]
+    fn double_width_halves_columns_but_double_height_does_not() {
+        let mut state = PrinterState {
+            char_width_mul: 2,
+            ..PrinterState::default()
+        };
+        assert_eq!(
+            EscPosViewer::effective_columns(PaperWidth::W58mm, &state),
+            16
+        );
+
+        state.char_width_mul = 1;
+        state.char_height_mul = 2;
+        assert_eq!(
+            EscPosViewer::effective_columns(PaperWidth::W58mm, &state),
+            32
+        );
+    }
+
+    #[test]
+    fn char_grid_cell_matches_columns_and_line_pitch() {
+        let cell = EscPosViewer::char_grid_cell(PaperWidth::W58mm, 150.0);
+        assert_eq!(cell.x, 150.0 / 32.0);
+        // 30 dots a 150/384 px = 11.7 px, pero la línea no baja del alto del texto.
+        assert!((cell.y - 14.0 * 1.15).abs() < 1e-4);
+
+        let cell = EscPosViewer::char_grid_cell(PaperWidth::W80mm, 450.0);
+        assert_eq!(cell.x, 450.0 / 48.0);
+        assert!((cell.y - 30.0 * 450.0 / 576.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn burst_boundaries_end_at_lines_and_graphics() {
+        // "AB\n" + barcode GS k + "C" (sin LF final).
+        let mut data = b"AB\n".to_vec();
+        data.extend_from_slice(&[0x1D, 0x6B, 73, 3, b'{', b'B', b'1']);
+        data.push(b'C');
+        assert_eq!(
+            EscPosViewer::burst_boundaries(&data, CodePage::Cp437.into()),
+            vec![3, 10, 11]
+        );
+    }
+
+    #[test]
+    fn burst_pause_keeps_the_average_rate() {
+        for seed in 0..50 {
+            let pause = EscPosViewer::burst_pause(1_000, 1_000, seed).as_secs_f32();
+            assert!((0.5..=1.5).contains(&pause), "{pause}");
+            // Ráfagas diminutas: nunca por debajo del mínimo perceptible (con jitter).
+            assert!(EscPosViewer::burst_pause(1, 100_000, seed).as_secs_f32() >= 0.014);
+        }
+    }
+
+    #[test]
+    fn changed_lines_are_the_ones_a_new_codepage_decodes_differently() {
+        // 0x82: 'é' en CP437/CP850; 0xA4: 'ñ' en CP437, '¤' en Windows-1252.
+        let data = b"Total\n\x82xito\nSe\xa4or\x1dk\x04123\x00Fin\n";
+        let cp437 = parse_escpos_simple(data, CodePage::Cp437);
+        let cp850 = parse_escpos_simple(data, CodePage::Cp850);
+        let windows = parse_escpos_simple(data, CodePage::Windows1252);
+
+        assert_eq!(
+            EscPosViewer::changed_lines(&cp437, &cp850),
+            Vec::<usize>::new()
+        );
+        let changed = EscPosViewer::changed_lines(&cp437, &windows);
+        let first_texts: Vec<&str> = changed
+            .iter()
+            .map(|&idx| match &windows[idx].1 {
+                CommandType::Text(t) => t.as_str(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(first_texts, vec!["\u{201A}xito", "Se¤or"]);
+        // Renglones nuevos (el job creció) cuentan como cambiados, en orden.
+        let grown = EscPosViewer::changed_lines(&[], &cp437);
+        assert_eq!(grown.len(), 4);
+        assert!(grown.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn initial_codepage_applies_until_the_first_esc_t() {
+        let mut viewer = test_viewer();
+        viewer.codepage = CodePage::Windows1252;
+        viewer.initial_codepage = Some(CodePage::Cp850);
+        // 0x82: 'é' en CP850, '‚' en Windows-1252 (ESC t 16).
+        viewer.push_new_job("t".to_string(), b"\x82\n\x1bt\x10\x82\n".to_vec());
+        let texts = |viewer: &EscPosViewer| -> Vec<String> {
+            viewer.jobs[0]
+                .parsed_commands
+                .iter()
+                .filter_map(|(_, cmd)| match cmd {
+                    CommandType::Text(t) => Some(t.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(texts(&viewer), vec!["é", "\u{201A}"]);
+
+        // Ctrl+E no cambia nada mientras haya codepage inicial.
+        viewer.cycle_codepage();
+        assert_eq!(viewer.codepage, CodePage::Windows1252);
+        assert_eq!(texts(&viewer), vec!["é", "\u{201A}"]);
+
+        // Sin codepage inicial manda el del visor.
+        viewer.initial_codepage = None;
+        viewer.reparse_all_jobs();
+        assert_eq!(texts(&viewer), vec!["\u{201A}", "\u{201A}"]);
+    }
+
+    #[test]
+    fn kitchen_orders_split_at_fed_partial_cuts_followed_by_another_ticket() {
+        let mut data = Vec::new();
+        for order in ["Mesa 1", "Mesa 2", "Mesa 3"] {
+            data.extend_from_slice(order.as_bytes());
+            data.extend_from_slice(b"\n\n\n\x1dV\x01");
+        }
+        // GS V 66 n también cuenta como avance; un corte total no separa órdenes.
+        data.extend_from_slice(b"Mesa 4\n\x1dV\x42\x30Mesa 5\n\x1dV\x00Mesa 6\n");
+        let commands = parse_escpos_simple(&data, CodePage::Cp437);
+        let cuts = EscPosViewer::kitchen_order_cuts(&commands);
+        let cut_positions: Vec<usize> = commands
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, c))| matches!(c, CommandType::Control(Control::Cut { .. })))
+            .map(|(idx, _)| idx)
+            .collect();
+        assert_eq!(cut_positions.len(), 5);
+        assert_eq!(cuts, cut_positions[..4].to_vec());
+
+        // El job guarda los cortes calculados al parsear.
+        let mut viewer = test_viewer();
+        viewer.push_new_job("ordenes".to_string(), data);
+        assert_eq!(viewer.jobs[0].order_cuts, cuts);
+
+        // Sin avance antes del corte (o sin ticket después) no hay separador.
+        let commands = parse_escpos_simple(b"A\n\x1dV\x01B\n\n\x1dV\x01\n\n", CodePage::Cp437);
+        assert!(EscPosViewer::kitchen_order_cuts(&commands).is_empty());
+        // ESC d n / ESC J n antes del corte parcial también son avance.
+        for feed in [&b"\x1bd\x04"[..], b"\x1bJ\x40"] {
+            let mut data = b"Mesa 1\n".to_vec();
+            data.extend_from_slice(feed);
+            data.extend_from_slice(b"\x1dV\x01Mesa 2\n");
+            let commands = parse_escpos_simple(&data, CodePage::Cp437);
+            assert_eq!(EscPosViewer::kitchen_order_cuts(&commands).len(), 1);
+            assert!(!commands.iter().any(
+                |(_, c)| matches!(c, CommandType::Text(t) if t.contains('@') || t.contains('\x04'))
+            ));
+        }
+    }
+
+    #[test]
+    fn merge_with_next_concatenates_and_keeps_the_first_job() {
+        let mut viewer = test_viewer();
+        viewer.push_new_job("Parte 1".to_string(), b"Hola ".to_vec());
+        viewer.push_new_job("Parte 2".to_string(), b"mundo\n".to_vec());
+        viewer.push_new_job("Otro".to_string(), b"X".to_vec());
+        let first_id = viewer.jobs[0].id;
+        viewer.active_job_idx = Some(1);
+        viewer.jobs[1].note = "cortado".to_string();
+
+        viewer.merge_with_next(0);
+
+        assert_eq!(viewer.jobs.len(), 2);
+        assert_eq!(viewer.jobs[0].id, first_id);
+        assert_eq!(viewer.jobs[0].label, "Parte 1");
+        assert_eq!(viewer.jobs[0].full_bytes, b"Hola mundo\n");
+        assert_eq!(viewer.jobs[0].display_bytes, b"Hola mundo\n");
+        assert_eq!(viewer.jobs[0].note, "cortado");
+        assert_eq!(viewer.active_job_idx, Some(0));
+
+        // El último no tiene siguiente.
+        viewer.merge_with_next(1);
+        assert_eq!(viewer.jobs.len(), 2);
+    }
+
+    #[test]
+    fn keyboard_tab_switching_stops_at_the_ends_and_names_the_icons() {
+        let mut viewer = test_viewer();
+        viewer.push_new_job("Caja".to_string(), b"Hola\n".to_vec());
+        viewer.push_new_job("Cocina".to_string(), b"X".to_vec());
+        assert_eq!(viewer.active_job_idx, Some(1));
+
+        viewer.select_adjacent_job(true);
+        assert_eq!(viewer.active_job_idx, Some(1));
+        assert!(!viewer.focus_active_tab);
+        viewer.select_adjacent_job(false);
+        assert_eq!(viewer.active_job_idx, Some(0));
+        assert!(viewer.focus_active_tab);
+
+        let job = &mut viewer.jobs[0];
+        job.pinned = true;
+        job.note = "revisar total".to_string();
+        let name = EscPosViewer::job_tab_accessible_name(job, Duration::from_secs(90));
+        assert_eq!(
+            name,
+            format!(
+                "Job #{} Caja, fijado, nota: revisar total, hace 1m, 5 bytes",
+                job.id
+            )
+        );
+    }
+
+    #[test]
+    fn jobs_with_their_own_paper_width_use_its_columns() {
+        let mut viewer = test_viewer();
+        viewer.push_new_job("Caja".to_string(), b"Hola\n".to_vec());
+        viewer.push_new_job("Cocina".to_string(), b"Pedido\n".to_vec());
+        viewer.jobs[1].paper_width = Some(PaperWidth::W80mm);
+
+        let state = PrinterState::default();
+        let columns = |viewer: &EscPosViewer, idx: usize| {
+            EscPosViewer::effective_columns(viewer.job_paper_width(&viewer.jobs[idx]), &state)
+        };
+        assert_eq!(columns(&viewer, 0), 32);
+        assert_eq!(columns(&viewer, 1), 48);
+        assert_eq!(viewer.active_paper_width(), PaperWidth::W80mm);
+
+        // Los jobs sin ancho propio siguen al global.
+        viewer.paper_width = PaperWidth::W80mm;
+        assert_eq!(columns(&viewer, 0), 48);
+        viewer.paper_width = PaperWidth::W58mm;
+        viewer.active_job_idx = Some(0);
+        assert_eq!(viewer.active_paper_width(), PaperWidth::W58mm);
+    }
+
+    #[test]
+    fn newest_first_prepends_jobs_and_prunes_the_oldest() {
+        let mut viewer = test_viewer();
+        viewer.newest_first = true;
+        viewer.max_jobs = 2;
+        for label in ["a", "b", "c"] {
+            viewer.push_new_job(label.to_string(), label.as_bytes().to_vec());
+        }
+
+        let labels: Vec<&str> = viewer.jobs.iter().map(|j| j.label.as_str()).collect();
+        assert_eq!(labels, ["c", "b"]);
+        assert_eq!(viewer.active_job_idx, Some(0));
+
+        // Con la vista congelada el job activo no cambia aunque se corra de posición.
+        viewer.set_freeze_view(true);
+        viewer.push_new_job("d".to_string(), b"d".to_vec());
+        assert_eq!(viewer.active_job().map(|j| j.label.as_str()), Some("c"));
+
+        viewer.reverse_job_order();
+        let labels: Vec<&str> = viewer.jobs.iter().map(|j| j.label.as_str()).collect();
+        assert_eq!(labels, ["c", "d"]);
+        assert_eq!(viewer.active_job().map(|j| j.label.as_str()), Some("c"));
+    }
+
+    #[test]
+    fn merge_with_next_follows_arrival_order_when_newest_first() {
+        let mut viewer = test_viewer();
+        viewer.newest_first = true;
+        viewer.push_new_job("Parte 1".to_string(), b"Hola ".to_vec());
+        viewer.push_new_job("Parte 2".to_string(), b"mundo\n".to_vec());
+
+        viewer.merge_with_next(0);
+
+        assert_eq!(viewer.jobs.len(), 1);
+        assert_eq!(viewer.jobs[0].label, "Parte 1");
+        assert_eq!(viewer.jobs[0].full_bytes, b"Hola mundo\n");
+        assert_eq!(viewer.active_job_idx, Some(0));
+    }
+
+    #[test]
+    fn images_follow_esc_a_and_oversized_ones_start_at_the_edge() {
+        let offsets: Vec<f32> = [Align::Left, Align::Center, Align::Right]
+            .into_iter()
+            .map(|align| EscPosViewer::image_x_offset(align, 100.0, 300.0))
+            .collect();
+        assert_eq!(offsets, vec![0.0, 100.0, 200.0]);
+        assert_eq!(
+            EscPosViewer::image_x_offset(Align::Right, 300.0, 300.0),
+            0.0
+        );
+        assert_eq!(
+            EscPosViewer::image_x_offset(Align::Center, 500.0, 300.0),
+            0.0
+        );
+    }
+
+    #[test]
+    fn print_density_darkens_text_and_thresholds_raster_dots() {
+        assert_eq!(EscPosViewer::density_ink(100), egui::Color32::BLACK);
+        assert_eq!(EscPosViewer::density_ink(0), egui::Color32::from_gray(110));
+
+        // 16x4: un punto suelto (tramado claro) en x=1 y un bloque lleno en x=8..16.
+        let data = [0x40, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF];
+        let dark = EscPosViewer::raster_to_image(2, 4, &data, 100).unwrap();
+        assert_eq!(dark.pixels[1], egui::Color32::BLACK);
+        assert_eq!(dark.pixels[16 + 9], egui::Color32::BLACK);
+
+        let light = EscPosViewer::raster_to_image(2, 4, &data, 0).unwrap();
+        let ink = EscPosViewer::density_ink(0);
+        assert_eq!(light.pixels[16 + 9], ink);
+        assert!(light.pixels[1].r() > ink.r());
+        assert_eq!(light.pixels[0], egui::Color32::WHITE);
+    }
+
+    #[test]
+    fn initial_file_argument_wins_over_the_last_opened_file() {
+        let dir = std::env::temp_dir().join(format!("escpos_reopen_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let last = dir.join("ultimo.prn");
+        let arg = dir.join("argumento.prn");
+        fs::write(&last, b"ultimo\n").unwrap();
+        fs::write(&arg, b"argumento\n").unwrap();
+
+        let reopening = || {
+            let mut viewer = test_viewer();
+            viewer.reopen_last_file = true;
+            viewer.last_opened_file = Some(last.clone());
+            viewer
+        };
+
+        let mut viewer = reopening();
+        viewer.load_initial_file(Some(arg.clone()));
+        assert_eq!(viewer.jobs.len(), 1);
+        assert_eq!(viewer.jobs[0].full_bytes, b"argumento\n");
+        assert_eq!(viewer.last_opened_file.as_deref(), Some(arg.as_path()));
+
+        let mut viewer = reopening();
+        viewer.load_initial_file(None);
+        assert_eq!(viewer.jobs[0].full_bytes, b"ultimo\n");
+
+        // Si el archivo ya no existe se arranca vacío.
+        fs::remove_file(&last).unwrap();
+        let mut viewer = reopening();
+        viewer.load_initial_file(None);
+        assert!(viewer.jobs.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn hex_edits_reparse_the_job_and_can_be_undone() {
+        let mut viewer = test_viewer();
+        viewer.push_new_job("job".to_string(), b"\x1d!\x00Hola\n".to_vec());
+        viewer.hex_edit = Some(HexEdit {
+            job_id: viewer.active_job().unwrap().id,
+            text: String::new(),
+            error: None,
+        });
+
+        // GS ! 0x11: doble ancho y alto.
+        viewer.apply_hex_edit(b"\x1d!\x11Hola\n".to_vec());
+        let job = viewer.active_job().unwrap();
+        assert_eq!(job.edit_undo.len(), 1);
+        assert!(job.parsed_commands.iter().any(|(state, cmd)| {
+            matches!(cmd, CommandType::Text(t) if t == "Hola") && state.char_width_mul == 2
+        }));
+
+        // Un cambio que deja los mismos bytes no ensucia el historial.
+        viewer.apply_hex_edit(b"\x1d!\x11Hola\n".to_vec());
+        assert_eq!(viewer.active_job().unwrap().edit_undo.len(), 1);
+
+        viewer.undo_hex_edit();
+        let job = viewer.active_job().unwrap();
+        assert!(job.edit_undo.is_empty());
+        assert_eq!(job.full_bytes, b"\x1d!\x00Hola\n");
+        assert!(job
+            .parsed_commands
+            .iter()
+            .all(|(state, _)| state.char_width_mul == 1));
+        assert_eq!(
+            viewer.hex_edit.as_ref().map(|e| e.text.as_str()),
+            Some("1d 21 00 48 6f 6c 61 0a")
+        );
+    }
+
+    #[test]
+    fn drawer_animation_starts_only_when_the_simulation_sends_esc_p() {
+        let data = b"Total\n\x1bp\x00\x19\xfaGracias\n".to_vec();
+
+        let mut viewer = test_viewer();
+        viewer.push_new_job("estatico".to_string(), data.clone());
+        assert!(viewer.drawer_anim_started.is_none());
+
+        let mut viewer = EscPosViewer::default();
+        viewer.simulate_printing = true;
+        viewer.sim_bytes_per_sec = 1_000;
+        viewer.push_new_job("simulado".to_string(), data);
+        viewer.tick_simulation();
+        assert!(viewer.drawer_anim_started.is_none());
+
+        let job = viewer.active_job_mut().unwrap();
+        job.sim_started_at = Some(Instant::now() - Duration::from_secs(1));
+        viewer.tick_simulation();
+        assert!(viewer.drawer_anim_started.is_some());
+    }
+
+    #[test]
+    fn history_replays_through_push_new_job_at_the_recorded_cadence() {
+        let mut viewer = test_viewer();
+        viewer.push_new_job("a".to_string(), b"a\n".to_vec());
+        viewer.push_new_job("b".to_string(), b"b\n".to_vec());
+        viewer.jobs[1].created_at = viewer.jobs[0].created_at + Duration::from_secs(4);
+
+        let session = viewer.history_as_session();
+        assert_eq!(session[0].offset, Duration::ZERO);
+        assert_eq!(session[1].offset, Duration::from_secs(4));
+
+        let ctx = egui::Context::default();
+        let now = Instant::now();
+        viewer.replay = Some(Replay::new(session, 2.0, now));
+        viewer.tick_replay(&ctx, now + Duration::from_secs(1));
+        assert_eq!(viewer.jobs.len(), 3);
+        assert_eq!(viewer.jobs[2].label, "↻ a");
+        assert!(viewer.replay.is_some());
+
+        viewer.tick_replay(&ctx, now + Duration::from_secs(2));
+        assert_eq!(viewer.jobs.len(), 4);
+        assert_eq!(viewer.jobs[3].label, "↻ b");
+        assert!(viewer.replay.is_none());
+    }
+
+    #[test]
+    fn state_inspector_reports_the_state_at_a_command() {
+        // ESC t 16, ESC a 1, ESC E 1, "A", ESC t 0, "B"
+        let data = b"\x1bt\x10\x1ba\x01\x1bE\x01A\n\x1bt\x00B";
+        let commands = parse_escpos_simple(data, CodePage::Utf8Lossy);
+        let a = commands
+            .iter()
+            .position(|(_, c)| matches!(c, CommandType::Text(t) if t == "A"))
+            .unwrap();
+        let b = commands
+            .iter()
+            .position(|(_, c)| matches!(c, CommandType::Text(t) if t == "B"))
+            .unwrap();
+
+        assert_eq!(
+            EscPosViewer::codepage_at(&commands, a, CodePage::Utf8Lossy),
+            CodePage::Windows1252
+        );
+        assert_eq!(
+            EscPosViewer::codepage_at(&commands, b, CodePage::Utf8Lossy),
+            CodePage::Cp437
+        );
+        // Antes de cualquier ESC t rige el codepage del visor.
+        let before = parse_escpos_simple(b"X\x1bt\x10", CodePage::Utf8Lossy);
+        assert_eq!(
+            EscPosViewer::codepage_at(&before, 0, CodePage::Cp850),
+            CodePage::Cp850
+        );
+        // ESC @ vuelve al codepage de arranque.
+        let reset = parse_escpos_simple(b"\x1bt\x10\x1b@C", CodePage::Cp850);
+        assert_eq!(
+            EscPosViewer::codepage_at(&reset, reset.len() - 1, CodePage::Cp850),
+            CodePage::Cp850
+        );
+
+        let rows = EscPosViewer::state_rows(&commands[a].0, CodePage::Windows1252);
+        let value = |field: &str| rows.iter().find(|(f, _)| *f == field).unwrap().1.clone();
+        assert_eq!(value("Negrita"), "Sí");
+        assert_eq!(value("Alineación"), "Centro");
+        assert_eq!(value("Codepage"), CodePage::Windows1252.label());
+    }
+
+    #[test]
+    fn snippet_composition_loads_as_one_job() {
+        let mut viewer = test_viewer();
+        let library = snippets::default_snippets();
+        let pick = |name: &str, values: &[(&str, &str)]| {
+            let snippet = library.iter().find(|s| s.name == name).unwrap().clone();
+            let values = values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            (snippet, values)
+        };
+        viewer.snippet_composition = vec![
+            pick("Encabezado negrita centrado", &[("titulo", "MI TIENDA")]),
+            pick("Corte", &[]),
+        ];
+
+        viewer.load_snippet_job();
+        assert_eq!(viewer.jobs.len(), 1);
+        let job = &viewer.jobs[0];
+        assert!(job.label.contains("Encabezado negrita centrado + Corte"));
+        assert!(job.parsed_commands.iter().any(|(state, cmd)| {
+            matches!(cmd, CommandType::Text(t) if t == "MI TIENDA")
+                && state.is_bold
+                && state.alignment == Align::Center
+        }));
+        assert!(job
+            .parsed_commands
+            .iter()
+            .any(|(_, cmd)| matches!(cmd, CommandType::Control(Control::Cut { .. }))));
+    }
+
+    #[test]
+    fn only_full_width_runs_of_one_separator_become_rules() {
+        let rule = EscPosViewer::separator_rule;
+        assert_eq!(
+            rule(&"-".repeat(32), 32),
+            Some(SeparatorRule::Single { dots: 2.0 })
+        );
+        assert_eq!(rule(&"=".repeat(48), 48), Some(SeparatorRule::Double));
+        assert_eq!(
+            rule(&"━".repeat(32), 32),
+            Some(SeparatorRule::Single { dots: 4.0 })
+        );
+        // Corta, mezclada o con otro carácter: queda como texto.
+        assert_eq!(rule(&"-".repeat(20), 32), None);
+        assert_eq!(rule(&format!("{}=", "-".repeat(31)), 32), None);
+        assert_eq!(rule(&"*".repeat(32), 32), None);
+        assert_eq!(rule("", 32), None);
+    }
+
+    #[test]
+    fn nv_logo_mapping_takes_the_first_raster_of_the_file() {
+        // ESC @ + GS v 0 de 1 byte x 2 filas + LF
+        let file = b"\x1b\x40\x1d\x76\x30\x00\x01\x00\x02\x00\xf0\x0f\n";
+        assert_eq!(
+            NvLogo::raster_from_job(file),
+            Some((1, 2, vec![0xF0, 0x0F]))
+        );
+        assert_eq!(NvLogo::raster_from_job(b"solo texto\n"), None);
+    }
+
+    #[test]
+    fn nv_logo_scale_follows_double_width_and_height() {
+        assert_eq!(NvLogo::scale(0), (1, 1));
+        assert_eq!(NvLogo::scale(1), (2, 1));
+        assert_eq!(NvLogo::scale(b'2'), (1, 2));
+        assert_eq!(NvLogo::scale(3), (2, 2));
+
+        // 8x2 puntos a doble alto: mismo ancho, cada fila repetida.
+        let img = EscPosViewer::raster_to_image(1, 2, &[0xF0, 0x0F], 100).unwrap();
+        let tall = EscPosViewer::scale_image(img.clone(), 1, 2);
+        assert_eq!(tall.size, [8, 4]);
+        assert_eq!(tall.pixels[..8], img.pixels[..8]);
+        assert_eq!(tall.pixels[8..16], img.pixels[..8]);
+        assert_eq!(tall.pixels[16..24], img.pixels[8..]);
+    }
+
+    #[test]
+    fn right_aligned_tab_fields_end_at_the_stop_or_the_edge() {
+        let align = EscPosViewer::align_tab_fields;
+        // El precio (último campo) termina en el borde de 32 columnas.
+        let line = align("Coffee\t3.50", 32, " ");
+        assert_eq!(line.chars().count(), 32);
+        assert_eq!(line, format!("Coffee{}3.50", " ".repeat(22)));
+        // Campo intermedio: termina en la primera parada donde entra (16).
+        assert_eq!(
+            align("Coffee\t2x\t7.00", 32, " "),
+            format!("Coffee{}2x{}7.00", " ".repeat(8), " ".repeat(12))
+        );
+        // Un campo que no entra antes del borde queda en la parada siguiente.
+        assert_eq!(
+            align("12345678901234567890\t1234567890123", 32, " ")
+                .chars()
+                .count(),
+            40
+        );
+    }
+
+    #[test]
+    fn exported_png_uses_the_job_paper_width_and_scale() {
+        let mut viewer = test_viewer();
+        viewer.use_thermal_font = false;
+        viewer.realistic_effects = true;
+        // "Hola" y un raster de 8 × 2 puntos negros alineado a la izquierda.
+        viewer.push_new_job(
+            "caja".to_string(),
+            b"\x1b@Hola\n\x1dv0\x00\x01\x00\x02\x00\xff\xff".to_vec(),
+        );
+        viewer.jobs[0].paper_width = Some(PaperWidth::W80mm);
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+
+        let image = viewer.render_ticket_image(&ctx, &viewer.jobs[0], 2);
+        let margin = EscPosViewer::PNG_MARGIN_DOTS;
+        assert_eq!(image.size[0], (576 + margin * 2) * 2);
+        let at =
+            |x: usize, y: usize| image.pixels[(y + margin) * 2 * image.size[0] + (x + margin) * 2];
+        let paper = EscPosViewer::THERMAL_PAPER_COLOR;
+        assert_eq!(at(0, 0), paper);
+
+        // El texto ocupa la primera celda de 24 puntos y 12 puntos por columna (48 columnas).
+        let inked = (0..24)
+            .flat_map(|y| (0..48).map(move |x| (x, y)))
+            .any(|(x, y)| at(x, y) != paper);
+        assert!(inked);
+        // El raster empieza después del interlineado por defecto (30 puntos).
+        assert_ne!(at(0, 30), paper);
+        assert_ne!(at(7, 31), paper);
+        assert_eq!(at(8, 30), paper);
+    }
+
+    #[test]
+    fn ticket_walk_resolves_the_text_both_renderers_draw() {
+        let mut viewer = test_viewer();
+        viewer.kitchen_orders = true;
+        viewer.tab_auto_table = true;
+        viewer.push_new_job(
+            "ordenes".to_string(),
+            b"A\tB\nCCC\tD\n\n\n\x1dV\x01Mesa 2\n".to_vec(),
+        );
+        let steps = |viewer: &EscPosViewer, lines_bottom_up| -> Vec<String> {
+            viewer
+                .ticket_walk(&viewer.jobs[0], " ", lines_bottom_up)
+                .filter_map(|step| match step {
+                    TicketStep::Order(number) => Some(format!("orden {number}")),
+                    TicketStep::Command { text, .. } | TicketStep::LineEnd(text) => {
+                        text.map(|(_, text)| text)
+                    }
+                    TicketStep::LineStart(_) => None,
+                })
+                .collect()
+        };
+        // Auto-tabla: "CCC" más 2 de separación; la segunda orden empieza tras el corte.
+        assert_eq!(
+            steps(&viewer, true),
+            ["orden 1", "A    B", "CCC  D", "orden 2", "Mesa 2"]
+        );
+
+        viewer.tab_auto_table = false;
+        viewer.flip_ticket = true;
+        // Al revés no se numeran órdenes; la vista previa recorre del último renglón al primero.
+        assert_eq!(steps(&viewer, true), ["Mesa 2", "CCC     D", "A       B"]);
+        assert_eq!(steps(&viewer, false), ["A       B", "CCC     D", "Mesa 2"]);
+    }
+
+    #[test]
+    fn exported_png_draws_header_bands_rules_and_order_tints() {
+        let mut viewer = test_viewer();
+        viewer.use_thermal_font = false;
+        viewer.realistic_effects = false;
+        viewer.shade_header_lines = true;
+        viewer.draw_separator_rules = true;
+        viewer.kitchen_orders = true;
+        viewer.order_separator = OrderSeparatorStyle::Tint;
+        let mut data = b"\x1bE\x01\x1b-\x01Total\n\x1bE\x00\x1b-\x00".to_vec();
+        data.extend_from_slice(&[b'-'; 48]);
+        data.extend_from_slice(b"\n\n\n\x1dV\x01Mesa 2\n");
+        viewer.push_new_job("orden".to_string(), data);
+        viewer.jobs[0].paper_width = Some(PaperWidth::W80mm);
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+
+        let image = viewer.render_ticket_image(&ctx, &viewer.jobs[0], 1);
+        let margin = EscPosViewer::PNG_MARGIN_DOTS;
+        let rows = image.size[1] - margin * 2;
+        let at = |x: usize, y: usize| image.pixels[(y + margin) * image.size[0] + x + margin];
+        let ink = EscPosViewer::density_ink(viewer.print_density);
+
+        // Encabezado (negrita + subrayado): banda gris hasta el borde derecho.
+        assert!((0..rows).any(|y| at(575, y) == egui::Color32::from_gray(228)));
+        // Separador: una raya continua a todo el ancho.
+        assert!((0..rows).any(|y| (0..576).all(|x| at(x, y) == ink)));
+        // La segunda orden lleva el fondo tenue (azulado).
+        assert!((0..rows).any(|y| at(575, y).b() > at(575, y).r()));
+    }
+
+    #[test]
+    fn printer_probe_results_reach_the_onboarding() {
+        let mut viewer = test_viewer();
+        let ctx = egui::Context::default();
+        let (tx, rx) = std::sync::mpsc::channel();
+        viewer.printer_probe_rx = Some(rx);
+        viewer.poll_printer_probe(&ctx);
+        assert_eq!(viewer.printer_installed, None);
+        assert!(viewer.printer_probe_rx.is_some());
+
+        tx.send(Ok(true)).unwrap();
+        viewer.poll_printer_probe(&ctx);
+        assert_eq!(viewer.printer_installed, Some(true));
+        assert!(viewer.printer_probe_rx.is_none());
+        assert!(viewer.printer_probed_at.is_some());
+
+        // Una instalación fallida deja el estado anterior y muestra el motivo.
+        let (tx, rx) = std::sync::mpsc::channel();
+        viewer.printer_probe_rx = Some(rx);
+        tx.send(Err("Acceso denegado".to_string())).unwrap();
+        viewer.poll_printer_probe(&ctx);
+        assert_eq!(viewer.printer_installed, Some(true));
+        assert_eq!(
+            viewer.printer_setup_message.as_deref(),
+            Some("Acceso denegado")
+        );
+    }
+}
@@ -0,0 +1,1258 @@
+//! Codificadores de códigos de barras 1D (GS k) a "runs" de módulos barra/espacio.
+
+/// Nombre de la simbología de GS k (forma A: m=0..6, forma B: m=65..73).
+pub fn symbology_name(m: u8) -> &'static str {
+    match m {
+        0 | 65 => "UPC-A",
+        1 | 66 => "UPC-E",
+        2 | 67 => "EAN-13",
+        3 | 68 => "EAN-8",
+        4 | 69 => "CODE39",
+        5 | 70 => "ITF",
+        6 | 71 => "CODABAR",
+        72 => "CODE93",
+        73 => "CODE128",
+        74 => "GS1-128",
+        75 => "GS1 DataBar Omnidirectional",
+        76 => "GS1 DataBar Truncated",
+        77 => "GS1 DataBar Limited",
+        78 => "GS1 DataBar Expanded",
+        79 => "CODE128 auto",
+        _ => "desconocido",
+    }
+}
+
+/// GS k forma A (m=0..6, terminada en NUL) usa los mismos códigos que la forma B (65..71).
+fn function_b_code(m: u8) -> u8 {
+    if m <= 6 {
+        m + 65
+    } else {
+        m
+    }
+}
+
+/// Simbologías que el preview dibuja como barras reales (el resto queda como texto).
+pub fn is_rendered(m: u8) -> bool {
+    matches!(function_b_code(m), 65..=74 | 79)
+}
+
+/// Codifica los datos de `GS k` según `m` (forma A o B). None si la simbología no está
+/// soportada (GS1 DataBar) o los datos no son válidos para ella.
+pub fn encode_runs(m: u8, data: &[u8]) -> Option<(Vec<u8>, String)> {
+    let text = String::from_utf8_lossy(data);
+    match function_b_code(m) {
+        65 => encode_upca_runs(&text),
+        66 => encode_upce_runs(&text),
+        67 | 68 => encode_ean_runs(&text),
+        69 => encode_code39_runs(&text),
+        70 => encode_itf_runs(&text),
+        71 => encode_codabar_runs(&text),
+        72 => encode_code93_runs(&text),
+        73 | 79 => encode_code128_runs(data),
+        74 => {
+            // GS1-128: Code128 con FNC1 al inicio.
+            let mut gs1 = b"{1".to_vec();
+            gs1.extend_from_slice(data);
+            encode_code128_runs(&gs1)
+        }
+        // 75..78: GS1 DataBar (no soportado todavía).
+        _ => None,
+    }
+}
+
+fn bits01_to_runs(bits: &[u8]) -> Option<(Vec<u8>, bool)> {
+    if bits.is_empty() {
+        return None;
+    }
+    let mut runs: Vec<u8> = Vec::new();
+    let mut current = bits[0];
+    let mut len: usize = 0;
+    for &b in bits {
+        if b == current {
+            len += 1;
+        } else {
+            runs.push(len.min(255) as u8);
+            current = b;
+            len = 1;
+        }
+    }
+    runs.push(len.min(255) as u8);
+    let start_with_black = bits[0] == 1;
+    Some((runs, start_with_black))
+}
+
+fn clean_code128_hri(data: &[u8]) -> String {
+    // ESC/POS suele enviar prefijos como "{B" y escapes "{{".
+    let s = String::from_utf8_lossy(data);
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+
+    // Consumir prefijo inicial {A/{B/{C}
+    if let Some('{') = chars.peek().copied() {
+        let mut clone = chars.clone();
+        let _ = clone.next();
+        if let Some(next) = clone.next() {
+            if matches!(next, 'A' | 'B' | 'C') {
+                let _ = chars.next();
+                let _ = chars.next();
+            }
+        }
+    }
+
+    while let Some(ch) = chars.next() {
+        if ch == '{' {
+            match chars.peek().copied() {
+                Some('{') => {
+                    let _ = chars.next();
+                    out.push('{');
+                }
+                Some('A' | 'B' | 'C') => {
+                    let _ = chars.next();
+                    // cambio de code set: no se imprime
+                }
+                Some('1' | '2' | '3' | '4') => {
+                    let _ = chars.next();
+                    // FNC*: omitimos en HRI
+                }
+                _ => {
+                    // Si no reconocemos, imprimimos el '{'
+                    out.push('{');
+                }
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CodeSet {
+    A,
+    B,
+    C,
+}
+
+impl CodeSet {
+    fn start_code(self) -> u8 {
+        match self {
+            CodeSet::A => 103,
+            CodeSet::B => 104,
+            CodeSet::C => 105,
+        }
+    }
+
+    /// Código para cambiar a este set desde otro.
+    fn switch_code(self) -> u8 {
+        match self {
+            CodeSet::A => 101,
+            CodeSet::B => 100,
+            CodeSet::C => 99,
+        }
+    }
+}
+
+// Tabla Code128 (widths alternando bar/space). Stop (106) tiene 7 dígitos.
+const CODE128_PATTERNS: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+    "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+    "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+    "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+    "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+    "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+    "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+    "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+    "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+    "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+
+/// Codifica Code128. Si los datos traen cambios de set explícitos (`{A`, `{B`, `{C`)
+/// se respetan tal cual; si no, se elige la secuencia de sets más corta (como la impresora).
+pub fn encode_code128_runs(data: &[u8]) -> Option<(Vec<u8>, String)> {
+    let s = String::from_utf8_lossy(data);
+    let bytes = s.as_bytes();
+    let hri = clean_code128_hri(bytes);
+
+    let (start, codes) = if has_code128_set_switch(bytes) {
+        code128_explicit_codes(bytes)
+    } else {
+        code128_auto_codes(bytes)
+    };
+
+    let runs = code128_symbol_runs(start.start_code(), &codes)?;
+    Some((runs, hri))
+}
+
+fn has_code128_set_switch(bytes: &[u8]) -> bool {
+    let mut i = 0usize;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'{' {
+            match bytes[i + 1] {
+                b'A' | b'B' | b'C' => return true,
+                // "{{" es un '{' literal: saltarlo entero.
+                _ => i += 2,
+            }
+        } else {
+            i += 1;
+        }
+    }
+    false
+}
+
+/// Modo explícito: se arranca en el set del prefijo (B por defecto) y se obedecen los `{x`.
+fn code128_explicit_codes(mut bytes: &[u8]) -> (CodeSet, Vec<u8>) {
+    let mut set = CodeSet::B;
+    if bytes.len() >= 2 && bytes[0] == b'{' {
+        match bytes[1] {
+            b'A' => {
+                set = CodeSet::A;
+                bytes = &bytes[2..];
+            }
+            b'B' => {
+                set = CodeSet::B;
+                bytes = &bytes[2..];
+            }
+            b'C' => {
+                set = CodeSet::C;
+                bytes = &bytes[2..];
+            }
+            _ => {}
+        }
+    }
+    let start = set;
+
+    let mut codes: Vec<u8> = Vec::new();
+    let mut i = 0usize;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'{' && i + 1 < bytes.len() {
+            let n = bytes[i + 1];
+            match n {
+                b'{' => {
+                    // literal '{'
+                    match set {
+                        CodeSet::B => {
+                            codes.push(b'{' - 32);
+                        }
+                        CodeSet::A => {
+                            codes.push(b'{' - 32);
+                        }
+                        CodeSet::C => {
+                            // en C no cabe, cambiamos a B
+                            codes.push(100);
+                            set = CodeSet::B;
+                            codes.push(b'{' - 32);
+                        }
+                    }
+                    i += 2;
+                    continue;
+                }
+                b'A' => {
+                    codes.push(101);
+                    set = CodeSet::A;
+                    i += 2;
+                    continue;
+                }
+                b'B' => {
+                    codes.push(100);
+                    set = CodeSet::B;
+                    i += 2;
+                    continue;
+                }
+                b'C' => {
+                    codes.push(99);
+                    set = CodeSet::C;
+                    i += 2;
+                    continue;
+                }
+                b'1' => {
+                    // FNC1
+                    codes.push(102);
+                    i += 2;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        match set {
+            CodeSet::C => {
+                if i + 1 < bytes.len() && bytes[i].is_ascii_digit() && bytes[i + 1].is_ascii_digit()
+                {
+                    let v = (bytes[i] - b'0') * 10 + (bytes[i + 1] - b'0');
+                    codes.push(v);
+                    i += 2;
+                } else {
+                    // Cambiar a B para seguir.
+                    codes.push(100);
+                    set = CodeSet::B;
+                }
+            }
+            CodeSet::B => {
+                // Code B: ASCII 32..127
+                if (32..=127).contains(&b) {
+                    codes.push(b - 32);
+                } else {
+                    codes.push(b'?' - 32);
+                }
+                i += 1;
+            }
+            CodeSet::A => {
+                // Code A: 0..95
+                let v: u8 = if b < 32 {
+                    b + 64
+                } else if b <= 95 {
+                    b - 32
+                } else {
+                    b'?' - 32
+                };
+                codes.push(v);
+                i += 1;
+            }
+        }
+    }
+
+    (start, codes)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Code128Token {
+    Byte(u8),
+    Fnc1,
+}
+
+fn code128_value(set: CodeSet, tokens: &[Code128Token], i: usize) -> Option<(u8, usize)> {
+    match tokens[i] {
+        Code128Token::Fnc1 => Some((102, 1)),
+        Code128Token::Byte(b) => match set {
+            CodeSet::C => match tokens.get(i + 1) {
+                Some(Code128Token::Byte(b2)) if b.is_ascii_digit() && b2.is_ascii_digit() => {
+                    Some(((b - b'0') * 10 + (b2 - b'0'), 2))
+                }
+                _ => None,
+            },
+            // Fuera de rango se imprime '?', igual que en modo explícito.
+            CodeSet::B => Some((
+                if (32..=127).contains(&b) {
+                    b - 32
+                } else {
+                    b'?' - 32
+                },
+                1,
+            )),
+            CodeSet::A => match b {
+                0..=31 => Some((b + 64, 1)),
+                32..=95 => Some((b - 32, 1)),
+                _ => None,
+            },
+        },
+    }
+}
+
+/// Modo automático: programación dinámica sobre (posición, set) minimizando la cantidad
+/// de símbolos, incluidos los cambios de set. Con empates se prefiere B, luego C, luego A.
+fn code128_auto_codes(bytes: &[u8]) -> (CodeSet, Vec<u8>) {
+    const SETS: [CodeSet; 3] = [CodeSet::B, CodeSet::C, CodeSet::A];
+
+    let mut tokens: Vec<Code128Token> = Vec::with_capacity(bytes.len());
+    let mut i = 0usize;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'{', Some(b'{')) => {
+                tokens.push(Code128Token::Byte(b'{'));
+                i += 2;
+            }
+            (b'{', Some(b'1')) => {
+                tokens.push(Code128Token::Fnc1);
+                i += 2;
+            }
+            (b, _) => {
+                tokens.push(Code128Token::Byte(b));
+                i += 1;
+            }
+        }
+    }
+
+    let n = tokens.len();
+    // direct[i][s]: símbolos desde i codificando el token i en el set s (sin cambiar antes).
+    // best[i][s]: mínimo desde i estando en s (puede cambiar de set antes del token i).
+    let mut direct = vec![[usize::MAX; 3]; n + 1];
+    let mut best = vec![[(0usize, 0usize); 3]; n + 1]; // (costo, set a usar)
+    best[n] = [(0, 0), (0, 1), (0, 2)];
+    for i in (0..n).rev() {
+        for (k, &set) in SETS.iter().enumerate() {
+            if let Some((_, adv)) = code128_value(set, &tokens, i) {
+                direct[i][k] = 1 + best[i + adv][k].0;
+            }
+        }
+        let row = direct[i];
+        best[i] = std::array::from_fn(|k| {
+            // Cambiar de set cuesta un símbolo; con el mismo costo se prefiere no cambiar.
+            (0..SETS.len())
+                .filter(|&t| row[t] != usize::MAX)
+                .map(|t| (row[t] + usize::from(t != k), t))
+                .min_by_key(|&(cost, t)| (cost, t != k))
+                .unwrap_or((usize::MAX, k))
+        });
+    }
+
+    let start_k = (0..SETS.len()).min_by_key(|&k| direct[0][k]).unwrap_or(0);
+
+    let mut codes: Vec<u8> = Vec::new();
+    let mut k = start_k;
+    let mut i = 0usize;
+    while i < n {
+        let next = best[i][k].1;
+        if next != k {
+            codes.push(SETS[next].switch_code());
+            k = next;
+        }
+        let Some((value, adv)) = code128_value(SETS[k], &tokens, i) else {
+            break;
+        };
+        codes.push(value);
+        i += adv;
+    }
+
+    (SETS[start_k], codes)
+}
+
+/// Agrega start, checksum y stop, y traduce cada símbolo a sus anchos de barra/espacio.
+fn code128_symbol_runs(start_code: u8, codes: &[u8]) -> Option<Vec<u8>> {
+    // Checksum
+    let mut sum: u32 = start_code as u32;
+    for (pos, &c) in codes.iter().enumerate() {
+        sum = sum.wrapping_add((c as u32) * ((pos + 1) as u32));
+    }
+    let checksum: u8 = (sum % 103) as u8;
+
+    let mut all_codes: Vec<u8> = Vec::with_capacity(3 + codes.len());
+    all_codes.push(start_code);
+    all_codes.extend_from_slice(codes);
+    all_codes.push(checksum);
+    all_codes.push(106);
+
+    let mut runs: Vec<u8> = Vec::new();
+    for &code in &all_codes {
+        let pat = CODE128_PATTERNS.get(code as usize)?;
+        for ch in pat.chars() {
+            let d = ch.to_digit(10)? as u8;
+            runs.push(d);
+        }
+    }
+    Some(runs)
+}
+
+// Tablas EAN/UPC: juegos L (impar), G (par) y R por dígito, y paridad del lado izquierdo
+// de EAN-13 según el primer dígito.
+const EAN_L: [&str; 10] = [
+    "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+    "0110111", "0001011",
+];
+const EAN_G: [&str; 10] = [
+    "0100111", "0110011", "0011011", "0100001", "0011101", "0111001", "0000101", "0010001",
+    "0001001", "0010111",
+];
+const EAN_R: [&str; 10] = [
+    "1110010", "1100110", "1101100", "1000010", "1011100", "1001110", "1010000", "1000100",
+    "1001000", "1110100",
+];
+const EAN_PARITY: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL",
+    "LGGLGL",
+];
+
+pub fn encode_ean_runs(digits: &str) -> Option<(Vec<u8>, String)> {
+    // Devuelve runs (módulos) para EAN-13 o EAN-8, según longitud.
+    let mut s: String = digits.chars().filter(|c| c.is_ascii_digit()).collect();
+    if s.len() == 7 || s.len() == 12 {
+        // calcular checksum y anexar
+        let sum: u32 = s
+            .chars()
+            .rev()
+            .enumerate()
+            .map(|(i, c)| {
+                let d = c.to_digit(10).unwrap_or(0);
+                let w = if i % 2 == 0 { 3 } else { 1 };
+                d * w
+            })
+            .sum();
+        let chk = (10 - (sum % 10)) % 10;
+        s.push(char::from(b'0' + (chk as u8)));
+    }
+
+    if s.len() == 13 {
+        let first = s.chars().next()?.to_digit(10)? as usize;
+        let parity = EAN_PARITY[first];
+        let left = &s[1..7];
+        let right = &s[7..13];
+
+        let mut bits: Vec<u8> = Vec::with_capacity(95);
+        // start guard
+        bits.extend_from_slice(&[1, 0, 1]);
+        // left
+        for (i, ch) in left.chars().enumerate() {
+            let d = ch.to_digit(10)? as usize;
+            let pat = match parity.chars().nth(i)? {
+                'L' => EAN_L[d],
+                'G' => EAN_G[d],
+                _ => EAN_L[d],
+            };
+            for b in pat.bytes() {
+                bits.push((b == b'1') as u8);
+            }
+        }
+        // middle guard
+        bits.extend_from_slice(&[0, 1, 0, 1, 0]);
+        // right
+        for ch in right.chars() {
+            let d = ch.to_digit(10)? as usize;
+            let pat = EAN_R[d];
+            for b in pat.bytes() {
+                bits.push((b == b'1') as u8);
+            }
+        }
+        // end guard
+        bits.extend_from_slice(&[1, 0, 1]);
+
+        let (runs, start_black) = bits01_to_runs(&bits)?;
+        if !start_black {
+            return None;
+        }
+        return Some((runs, s));
+    }
+
+    if s.len() == 8 {
+        let left = &s[0..4];
+        let right = &s[4..8];
+
+        let mut bits: Vec<u8> = Vec::with_capacity(67);
+        bits.extend_from_slice(&[1, 0, 1]);
+        for ch in left.chars() {
+            let d = ch.to_digit(10)? as usize;
+            for b in EAN_L[d].bytes() {
+                bits.push((b == b'1') as u8);
+            }
+        }
+        bits.extend_from_slice(&[0, 1, 0, 1, 0]);
+        for ch in right.chars() {
+            let d = ch.to_digit(10)? as usize;
+            for b in EAN_R[d].bytes() {
+                bits.push((b == b'1') as u8);
+            }
+        }
+        bits.extend_from_slice(&[1, 0, 1]);
+
+        let (runs, start_black) = bits01_to_runs(&bits)?;
+        if !start_black {
+            return None;
+        }
+        return Some((runs, s));
+    }
+
+    None
+}
+
+/// UPC-A: 11 dígitos (+ verificador). Es un EAN-13 con primer dígito 0.
+pub fn encode_upca_runs(digits: &str) -> Option<(Vec<u8>, String)> {
+    let s: String = digits.chars().filter(|c| c.is_ascii_digit()).collect();
+    if s.len() != 11 && s.len() != 12 {
+        return None;
+    }
+    let (runs, ean) = encode_ean_runs(&format!("0{s}"))?;
+    Some((runs, ean[1..].to_string()))
+}
+
+fn upc_check_digit(upca11: &[u8]) -> u8 {
+    let sum: u32 = upca11
+        .iter()
+        .enumerate()
+        .map(|(i, &d)| d as u32 * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    ((10 - (sum % 10)) % 10) as u8
+}
+
+/// Expande los 6 dígitos de UPC-E a los 11 de UPC-A (sin verificador).
+fn upce_to_upca(ns: u8, d: &[u8; 6]) -> [u8; 11] {
+    match d[5] {
+        0..=2 => [ns, d[0], d[1], d[5], 0, 0, 0, 0, d[2], d[3], d[4]],
+        3 => [ns, d[0], d[1], d[2], 0, 0, 0, 0, 0, d[3], d[4]],
+        4 => [ns, d[0], d[1], d[2], d[3], 0, 0, 0, 0, 0, d[4]],
+        _ => [ns, d[0], d[1], d[2], d[3], d[4], 0, 0, 0, 0, d[5]],
+    }
+}
+
+/// Comprime un UPC-A (11 dígitos) a UPC-E si sigue alguna de las reglas de ceros.
+fn upca_to_upce(a: &[u8]) -> Option<(u8, [u8; 6])> {
+    let zeros = |r: std::ops::Range<usize>| a[r].iter().all(|&x| x == 0);
+    let d = if a[3] <= 2 && zeros(4..8) {
+        [a[1], a[2], a[8], a[9], a[10], a[3]]
+    } else if zeros(4..9) {
+        [a[1], a[2], a[3], a[9], a[10], 3]
+    } else if zeros(5..10) {
+        [a[1], a[2], a[3], a[4], a[10], 4]
+    } else if a[10] >= 5 && zeros(6..10) {
+        [a[1], a[2], a[3], a[4], a[5], a[10]]
+    } else {
+        return None;
+    };
+    Some((a[0], d))
+}
+
+/// UPC-E: 6, 7 (sistema + 6) u 8 dígitos (sistema + 6 + verificador), o un UPC-A
+/// de 11/12 dígitos comprimible. El sistema numérico solo puede ser 0 o 1.
+pub fn encode_upce_runs(digits: &str) -> Option<(Vec<u8>, String)> {
+    const L: [&str; 10] = [
+        "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+        "0110111", "0001011",
+    ];
+    const G: [&str; 10] = [
+        "0100111", "0110011", "0011011", "0100001", "0011101", "0111001", "0000101", "0010001",
+        "0001001", "0010111",
+    ];
+    // Paridad con sistema 0 ('E' = G, 'O' = L) según el verificador; con sistema 1 se invierte.
+    const PAR: [&str; 10] = [
+        "EEEOOO", "EEOEOO", "EEOOEO", "EEOOOE", "EOEEOO", "EOOEEO", "EOOOEE", "EOEOEO", "EOEOOE",
+        "EOOEOE",
+    ];
+
+    let v: Vec<u8> = digits
+        .bytes()
+        .filter(|b| b.is_ascii_digit())
+        .map(|b| b - b'0')
+        .collect();
+    let (ns, body) = match v.len() {
+        6 => (0, [v[0], v[1], v[2], v[3], v[4], v[5]]),
+        7 | 8 => (v[0], [v[1], v[2], v[3], v[4], v[5], v[6]]),
+        11 | 12 => upca_to_upce(&v[..11])?,
+        _ => return None,
+    };
+    if ns > 1 {
+        return None;
+    }
+    let check = upc_check_digit(&upce_to_upca(ns, &body));
+
+    let mut bits: Vec<u8> = Vec::with_capacity(51);
+    bits.extend_from_slice(&[1, 0, 1]);
+    for (i, &d) in body.iter().enumerate() {
+        let even = (PAR[check as usize].as_bytes()[i] == b'E') != (ns == 1);
+        let pat = if even { G[d as usize] } else { L[d as usize] };
+        for b in pat.bytes() {
+            bits.push((b == b'1') as u8);
+        }
+    }
+    bits.extend_from_slice(&[0, 1, 0, 1, 0, 1]);
+
+    let (runs, start_black) = bits01_to_runs(&bits)?;
+    if !start_black {
+        return None;
+    }
+    let hri: String = std::iter::once(ns)
+        .chain(body)
+        .chain(std::iter::once(check))
+        .map(|d| char::from(b'0' + d))
+        .collect();
+    Some((runs, hri))
+}
+
+/// CODABAR (NW-7): 0-9 - $ : / . + con caracteres de inicio/fin A-D (se agrega A si faltan).
+pub fn encode_codabar_runs(data: &str) -> Option<(Vec<u8>, String)> {
+    // 7 elementos por carácter (barra, espacio, ...); 1 = ancho.
+    const PATTERNS: &[(char, &str)] = &[
+        ('0', "0000011"),
+        ('1', "0000110"),
+        ('2', "0001001"),
+        ('3', "1100000"),
+        ('4', "0010010"),
+        ('5', "1000010"),
+        ('6', "0100001"),
+        ('7', "0100100"),
+        ('8', "0110000"),
+        ('9', "1001000"),
+        ('-', "0001100"),
+        ('$', "0011000"),
+        (':', "1000101"),
+        ('/', "1010001"),
+        ('.', "1010100"),
+        ('+', "0010101"),
+        ('A', "0011010"),
+        ('B', "0101001"),
+        ('C', "0001011"),
+        ('D', "0001110"),
+    ];
+    const WIDE: u8 = 3;
+
+    let is_start_stop = |c: char| matches!(c, 'A'..='D');
+    let mut text: String = data.trim().to_ascii_uppercase();
+    if text.is_empty() {
+        return None;
+    }
+    if !text.starts_with(is_start_stop) || !text.ends_with(is_start_stop) || text.len() < 2 {
+        text = format!("A{}A", text.trim_matches(is_start_stop));
+    }
+
+    let mut runs: Vec<u8> = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let pattern = PATTERNS.iter().find(|(ch, _)| *ch == c)?.1;
+        if i > 0 {
+            runs.push(1); // separación entre caracteres
+        }
+        runs.extend(pattern.bytes().map(|b| if b == b'1' { WIDE } else { 1 }));
+    }
+    Some((runs, text))
+}
+
+const CODE93_CHARS: &[u8; 43] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%";
+
+/// Anchos (barra, espacio, ...) de los 47 valores de Code93; 43..46 son los shift.
+const CODE93_PATTERNS: [&str; 47] = [
+    "131112", "111213", "111312", "111411", "121113", "121212", "121311", "111114", "131211",
+    "141111", "211113", "211212", "211311", "221112", "221211", "231111", "112113", "112212",
+    "112311", "122112", "132111", "111123", "111222", "111321", "121122", "131121", "212112",
+    "212211", "211122", "211221", "221121", "222111", "112122", "112221", "122121", "123111",
+    "121131", "311112", "311211", "321111", "112131", "113121", "211131", "121221", "312111",
+    "311121", "122211",
+];
+
+/// Dígitos verificadores C y K de Code93 (pesos cíclicos 20 y 15 desde la derecha).
+fn code93_checks(values: &[u8]) -> (u8, u8) {
+    let weighted = |vals: &[u8], max_weight: usize| -> u8 {
+        let sum: usize = vals
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, &v)| v as usize * (i % max_weight + 1))
+            .sum();
+        (sum % 47) as u8
+    };
+    let c = weighted(values, 20);
+    let mut with_c = values.to_vec();
+    with_c.push(c);
+    let k = weighted(&with_c, 15);
+    (c, k)
+}
+
+/// Code93: caracteres directos y minúsculas vía shift (+). Agrega los verificadores C y K.
+pub fn encode_code93_runs(data: &str) -> Option<(Vec<u8>, String)> {
+    const SHIFT_PLUS: u8 = 46;
+
+    let mut values: Vec<u8> = Vec::new();
+    for c in data.chars() {
+        if c.is_ascii_lowercase() {
+            values.push(SHIFT_PLUS);
+            values.push(10 + (c as u8 - b'a'));
+        } else {
+            let pos = CODE93_CHARS.iter().position(|&b| b as char == c)?;
+            values.push(pos as u8);
+        }
+    }
+    if values.is_empty() {
+        return None;
+    }
+    let (c, k) = code93_checks(&values);
+
+    const START_STOP: &str = "111141";
+    let mut runs: Vec<u8> = Vec::new();
+    let mut push = |pat: &str| runs.extend(pat.bytes().map(|b| b - b'0'));
+    push(START_STOP);
+    for v in values.iter().copied().chain([c, k]) {
+        push(CODE93_PATTERNS[v as usize]);
+    }
+    push(START_STOP);
+    runs.push(1); // barra de terminación
+
+    Some((runs, data.to_string()))
+}
+
+/// Encode Code39 barcode. Supports digits, uppercase letters, and special chars: - . $ / + % SPACE
+pub fn encode_code39_runs(data: &str) -> Option<(Vec<u8>, String)> {
+    // Code39 patterns: 9 elements per character (5 bars, 4 spaces)
+    // 1 = wide, 0 = narrow. Pattern is: BSBSBSBSB (bar-space alternating)
+    const PATTERNS: &[(char, &str)] = &[
+        ('0', "101001101101"),
+        ('1', "110100101011"),
+        ('2', "101100101011"),
+        ('3', "110110010101"),
+        ('4', "101001101011"),
+        ('5', "110100110101"),
+        ('6', "101100110101"),
+        ('7', "101001011011"),
+        ('8', "110100101101"),
+        ('9', "101100101101"),
+        ('A', "110101001011"),
+        ('B', "101101001011"),
+        ('C', "110110100101"),
+        ('D', "101011001011"),
+        ('E', "110101100101"),
+        ('F', "101101100101"),
+        ('G', "101010011011"),
+        ('H', "110101001101"),
+        ('I', "101101001101"),
+        ('J', "101011001101"),
+        ('K', "110101010011"),
+        ('L', "101101010011"),
+        ('M', "110110101001"),
+        ('N', "101011010011"),
+        ('O', "110101101001"),
+        ('P', "101101101001"),
+        ('Q', "101010110011"),
+        ('R', "110101011001"),
+        ('S', "101101011001"),
+        ('T', "101011011001"),
+        ('U', "110010101011"),
+        ('V', "100110101011"),
+        ('W', "110011010101"),
+        ('X', "100101101011"),
+        ('Y', "110010110101"),
+        ('Z', "100110110101"),
+        ('-', "100101011011"),
+        ('.', "110010101101"),
+        (' ', "100110101101"),
+        ('$', "100100100101"),
+        ('/', "100100101001"),
+        ('+', "100101001001"),
+        ('%', "101001001001"),
+        ('*', "100101101101"), // Start/stop character
+    ];
+
+    fn get_pattern(c: char) -> Option<&'static str> {
+        PATTERNS
+            .iter()
+            .find(|(ch, _)| *ch == c.to_ascii_uppercase())
+            .map(|(_, p)| *p)
+    }
+
+    let hri: String = data
+        .chars()
+        .filter(|c| get_pattern(*c).is_some() && *c != '*')
+        .collect();
+
+    if hri.is_empty() {
+        return None;
+    }
+
+    let mut bits: Vec<u8> = Vec::new();
+
+    // Start character (*)
+    let start = get_pattern('*')?;
+    for b in start.bytes() {
+        bits.push((b == b'1') as u8);
+    }
+    bits.push(0); // Inter-character gap
+
+    // Data characters
+    for c in hri.chars() {
+        let pattern = get_pattern(c)?;
+        for b in pattern.bytes() {
+            bits.push((b == b'1') as u8);
+        }
+        bits.push(0); // Inter-character gap
+    }
+
+    // Stop character (*)
+    let stop = get_pattern('*')?;
+    for b in stop.bytes() {
+        bits.push((b == b'1') as u8);
+    }
+
+    let (runs, start_black) = bits01_to_runs(&bits)?;
+    if !start_black {
+        return None;
+    }
+
+    Some((runs, hri))
+}
+
+pub fn encode_itf_runs(digits: &str) -> Option<(Vec<u8>, String)> {
+    let mut s: String = digits.chars().filter(|c| c.is_ascii_digit()).collect();
+    if s.is_empty() {
+        return None;
+    }
+    if s.len() % 2 == 1 {
+        s.insert(0, '0');
+    }
+
+    fn pat(d: u8) -> [u8; 5] {
+        match d {
+            0 => [1, 1, 3, 3, 1],
+            1 => [3, 1, 1, 1, 3],
+            2 => [1, 3, 1, 1, 3],
+            3 => [3, 3, 1, 1, 1],
+            4 => [1, 1, 3, 1, 3],
+            5 => [3, 1, 3, 1, 1],
+            6 => [1, 3, 3, 1, 1],
+            7 => [1, 1, 1, 3, 3],
+            8 => [3, 1, 1, 3, 1],
+            _ => [1, 3, 1, 3, 1],
+        }
+    }
+
+    let bytes = s.as_bytes();
+    let mut runs: Vec<u8> = Vec::new();
+    // Start: 1010 => [1,1,1,1]
+    runs.extend_from_slice(&[1, 1, 1, 1]);
+
+    let mut i = 0usize;
+    while i + 1 < bytes.len() {
+        let a = bytes[i] - b'0';
+        let b = bytes[i + 1] - b'0';
+        let pa = pat(a);
+        let pb = pat(b);
+        for k in 0..5 {
+            runs.push(pa[k]); // bar
+            runs.push(pb[k]); // space
+        }
+        i += 2;
+    }
+
+    // Stop: wide bar, narrow space, narrow bar => [3,1,1]
+    runs.extend_from_slice(&[3, 1, 1]);
+    Some((runs, s))
+}
+
+// --- Verificación: lectura de los runs ya codificados, como lo haría un lector ---
+
+/// Resultado de leer un barcode ya dibujado (autoverificación del render).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScanCheck {
+    Ok,
+    /// Se leyó, pero el contenido no coincide con el HRI.
+    Mismatch(String),
+    /// Margen blanco menor al mínimo de la simbología (en módulos).
+    QuietZone {
+        needed: usize,
+    },
+    Unreadable,
+}
+
+impl ScanCheck {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ScanCheck::Ok)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ScanCheck::Ok => "✅ se lee correctamente".to_string(),
+            ScanCheck::Mismatch(read) => format!("⚠ se lee \"{read}\" (no coincide con el HRI)"),
+            ScanCheck::QuietZone { needed } => {
+                format!("⚠ zona de silencio insuficiente (mínimo {needed} módulos)")
+            }
+            ScanCheck::Unreadable => "⚠ no se pudo leer".to_string(),
+        }
+    }
+}
+
+/// Simbologías con lector propio: CODE128 (y GS1-128), EAN-13/EAN-8 y UPC-A.
+pub fn has_decoder(m: u8) -> bool {
+    matches!(function_b_code(m), 65 | 67 | 68 | 73 | 74 | 79)
+}
+
+/// Decodifica los runs (anchos en módulos, empezando por barra) de un símbolo sin zonas de
+/// silencio. None si no es válido (patrón desconocido, guardas o dígito verificador malos).
+pub fn decode_runs(m: u8, runs: &[u8]) -> Option<String> {
+    match function_b_code(m) {
+        65 => decode_ean_runs(runs)?.strip_prefix('0').map(str::to_string),
+        67 | 68 => decode_ean_runs(runs),
+        73 | 74 | 79 => decode_code128_runs(runs),
+        _ => None,
+    }
+}
+
+/// Lee el símbolo y lo compara con el HRI esperado; `quiet_modules` es el margen blanco
+/// más chico a los lados.
+pub fn scan_check(m: u8, runs: &[u8], quiet_modules: usize, expected: &str) -> ScanCheck {
+    let Some(read) = decode_runs(m, runs) else {
+        return ScanCheck::Unreadable;
+    };
+    let needed = match (function_b_code(m), read.len()) {
+        (65, _) => 9,
+        (67 | 68, 8) => 7,
+        (67 | 68, _) => 11,
+        _ => 10,
+    };
+    if read != expected {
+        ScanCheck::Mismatch(read)
+    } else if quiet_modules < needed {
+        ScanCheck::QuietZone { needed }
+    } else {
+        ScanCheck::Ok
+    }
+}
+
+fn decode_code128_runs(runs: &[u8]) -> Option<String> {
+    // Símbolos de 6 runs (11 módulos) y stop de 7 (13 módulos).
+    if runs.len() < 6 * 3 + 7 || !(runs.len() - 7).is_multiple_of(6) {
+        return None;
+    }
+    let (body, stop) = runs.split_at(runs.len() - 7);
+    let pattern = |chunk: &[u8]| -> String { chunk.iter().map(|d| d.to_string()).collect() };
+    if pattern(stop) != CODE128_PATTERNS[106] {
+        return None;
+    }
+    let codes: Vec<u8> = body
+        .chunks(6)
+        .map(|chunk| {
+            let pat = pattern(chunk);
+            CODE128_PATTERNS[..106]
+                .iter()
+                .position(|&p| p == pat)
+                .map(|v| v as u8)
+        })
+        .collect::<Option<_>>()?;
+
+    let (&start, rest) = codes.split_first()?;
+    let (&checksum, data) = rest.split_last()?;
+    let sum = data
+        .iter()
+        .enumerate()
+        .fold(start as u32, |acc, (pos, &c)| {
+            acc + c as u32 * (pos as u32 + 1)
+        });
+    if sum % 103 != checksum as u32 {
+        return None;
+    }
+
+    let mut set = match start {
+        103 => CodeSet::A,
+        104 => CodeSet::B,
+        105 => CodeSet::C,
+        _ => return None,
+    };
+    let mut out = String::new();
+    for &c in data {
+        match (set, c) {
+            // FNC1 no aparece en el HRI.
+            (_, 102) => {}
+            (_, 99) => set = CodeSet::C,
+            (CodeSet::A | CodeSet::C, 100) => set = CodeSet::B,
+            (CodeSet::B | CodeSet::C, 101) => set = CodeSet::A,
+            (CodeSet::C, 0..=98) => out.push_str(&format!("{c:02}")),
+            (CodeSet::B, 0..=95) => out.push((c + 32) as char),
+            (CodeSet::A, 0..=63) => out.push((c + 32) as char),
+            (CodeSet::A, 64..=95) => out.push((c - 64) as char),
+            // SHIFT / FNC2..4: el visor no los genera.
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+fn decode_ean_runs(runs: &[u8]) -> Option<String> {
+    let mut bits = String::new();
+    for (i, &run) in runs.iter().enumerate() {
+        let bit = if i % 2 == 0 { '1' } else { '0' };
+        bits.extend(std::iter::repeat_n(bit, run as usize));
+    }
+    let half = match bits.len() {
+        95 => 6,
+        67 => 4,
+        _ => return None,
+    };
+    let middle = 3 + half * 7;
+    if &bits[..3] != "101"
+        || &bits[middle..middle + 5] != "01010"
+        || &bits[bits.len() - 3..] != "101"
+    {
+        return None;
+    }
+
+    let digit = |table: &[&str; 10], pat: &str| table.iter().position(|&p| p == pat);
+    let mut left = String::new();
+    let mut parity = String::new();
+    for k in 0..half {
+        let pat = &bits[3 + k * 7..3 + (k + 1) * 7];
+        if let Some(d) = digit(&EAN_L, pat) {
+            left.push(char::from(b'0' + d as u8));
+            parity.push('L');
+        } else {
+            let d = digit(&EAN_G, pat)?;
+            left.push(char::from(b'0' + d as u8));
+            parity.push('G');
+        }
+    }
+    let mut right = String::new();
+    for k in 0..half {
+        let start = middle + 5 + k * 7;
+        let d = digit(&EAN_R, &bits[start..start + 7])?;
+        right.push(char::from(b'0' + d as u8));
+    }
+
+    let text = if half == 6 {
+        let first = EAN_PARITY.iter().position(|&p| p == parity)?;
+        format!("{first}{left}{right}")
+    } else if parity == "LLLL" {
+        format!("{left}{right}")
+    } else {
+        return None;
+    };
+
+    // Dígito verificador (pesos 3/1 desde la derecha, sin contar el propio verificador).
+    let digits: Vec<u32> = text.chars().filter_map(|c| c.to_digit(10)).collect();
+    let (&check, payload) = digits.split_last()?;
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+    ((10 - sum % 10) % 10 == check).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_b_symbologies_render_with_valid_data() {
+        let samples: [(u8, &[u8]); 11] = [
+            (65, b"03600029145"),
+            (66, b"0425261"),
+            (67, b"400638133393"),
+            (68, b"9638507"),
+            (69, b"CODE39"),
+            (70, b"123456"),
+            (71, b"A40156B"),
+            (72, b"TEST93"),
+            (73, b"{B12345"),
+            (74, b"0112345678901231"),
+            (79, b"123456789012"),
+        ];
+        for (m, data) in samples {
+            assert!(is_rendered(m), "m={m}");
+            assert!(encode_runs(m, data).is_some(), "m={m}");
+        }
+        // Forma A (m=0..6) usa las mismas simbologías.
+        assert!(encode_runs(0, b"03600029145").is_some());
+        // GS1 DataBar todavía no.
+        for m in 75..=78 {
+            assert!(!is_rendered(m));
+            assert!(encode_runs(m, b"0112345678901231").is_none());
+        }
+    }
+
+    #[test]
+    fn upc_a_and_upc_e_compute_check_digits() {
+        let (_, hri) = encode_upca_runs("03600029145").unwrap();
+        assert_eq!(hri, "036000291452");
+
+        // 0 425261 -> UPC-A 04210000526 -> verificador 4.
+        let (runs, hri) = encode_upce_runs("0425261").unwrap();
+        assert_eq!(hri, "04252614");
+        assert_eq!(runs.iter().map(|&r| r as usize).sum::<usize>(), 51);
+        // El mismo código dado como UPC-A se comprime a UPC-E.
+        assert_eq!(encode_upce_runs("042100005264").unwrap().1, "04252614");
+    }
+
+    #[test]
+    fn code93_check_characters_match_reference() {
+        let values: Vec<u8> = "TEST93"
+            .bytes()
+            .map(|b| CODE93_CHARS.iter().position(|&c| c == b).unwrap() as u8)
+            .collect();
+        // Ejemplo de referencia: TEST93 -> C='+', K='6'.
+        assert_eq!(code93_checks(&values), (41, 6));
+
+        let (runs, _) = encode_code93_runs("TEST93").unwrap();
+        // (start + 6 + C + K + stop) * 9 módulos + barra final.
+        assert_eq!(runs.iter().map(|&r| r as usize).sum::<usize>(), 10 * 9 + 1);
+    }
+
+    fn modules(runs: &[u8]) -> usize {
+        runs.iter().map(|&r| r as usize).sum()
+    }
+
+    #[test]
+    fn code128_auto_packs_digits_into_code_c() {
+        let (explicit, hri_explicit) = encode_code128_runs(b"{B123456789012").unwrap();
+        let (auto, hri_auto) = encode_code128_runs(b"123456789012").unwrap();
+
+        assert_eq!(hri_explicit, "123456789012");
+        assert_eq!(hri_auto, "123456789012");
+        // Start + 12 símbolos B + checksum = 14 de 11 módulos, más stop (13).
+        assert_eq!(modules(&explicit), 14 * 11 + 13);
+        // Start C + 6 pares + checksum = 8 de 11 módulos, más stop.
+        assert_eq!(modules(&auto), 8 * 11 + 13);
+    }
+
+    #[test]
+    fn code128_auto_switches_to_c_only_for_long_digit_runs() {
+        let (start, codes) = code128_auto_codes(b"AB12");
+        assert_eq!(start, CodeSet::B);
+        // Dos dígitos no justifican el cambio a C.
+        assert_eq!(codes, vec![33, 34, 17, 18]);
+
+        let (start, codes) = code128_auto_codes(b"AB123456");
+        assert_eq!(start, CodeSet::B);
+        assert_eq!(codes, vec![33, 34, 99, 12, 34, 56]);
+    }
+
+    #[test]
+    fn code128_explicit_switches_stay_authoritative() {
+        let (start, codes) = code128_explicit_codes(b"{B1234");
+        assert_eq!(start, CodeSet::B);
+        assert_eq!(codes, vec![17, 18, 19, 20]);
+
+        let (start, codes) = code128_explicit_codes(b"{C12{B34");
+        assert_eq!(start, CodeSet::C);
+        assert_eq!(codes, vec![12, 100, 19, 20]);
+    }
+
+    #[test]
+    fn code128_literal_brace_does_not_count_as_switch() {
+        assert!(!has_code128_set_switch(b"{{A12"));
+        assert!(has_code128_set_switch(b"12{C34"));
+    }
+    #[test]
+    fn code128_encode_decode_round_trip() {
+        let samples: [(u8, &[u8]); 6] = [
+            (73, b"{B12345"),
+            (73, b"123456789012"),
+            (73, b"AB123456cd"),
+            (73, b"{C12{B34"),
+            (74, b"0112345678901231"),
+            (79, b"Hola {{mundo}"),
+        ];
+        for (m, data) in samples {
+            let (runs, hri) = encode_runs(m, data).unwrap();
+            assert_eq!(
+                decode_runs(m, &runs).as_deref(),
+                Some(hri.as_str()),
+                "{data:?}"
+            );
+            assert_eq!(scan_check(m, &runs, 10, &hri), ScanCheck::Ok);
+        }
+    }
+
+    #[test]
+    fn ean_and_upca_encode_decode_round_trip() {
+        for (m, data) in [(67, "400638133393"), (68, "9638507"), (65, "03600029145")] {
+            let (runs, hri) = encode_runs(m, data.as_bytes()).unwrap();
+            assert_eq!(
+                decode_runs(m, &runs).as_deref(),
+                Some(hri.as_str()),
+                "{data}"
+            );
+        }
+    }
+
+    #[test]
+    fn scan_check_flags_bad_symbols_and_small_quiet_zones() {
+        let (mut runs, hri) = encode_runs(67, b"400638133393").unwrap();
+        assert_eq!(scan_check(67, &runs, 11, &hri), ScanCheck::Ok);
+        assert_eq!(
+            scan_check(67, &runs, 4, &hri),
+            ScanCheck::QuietZone { needed: 11 }
+        );
+        assert!(matches!(
+            scan_check(67, &runs, 11, "4006381333930"),
+            ScanCheck::Mismatch(_)
+        ));
+
+        // Una barra más ancha rompe el patrón (o el verificador).
+        runs[5] += 1;
+        assert_eq!(scan_check(67, &runs, 11, &hri), ScanCheck::Unreadable);
+        assert!(!has_decoder(69));
+    }
+}
@@ -0,0 +1,278 @@
+//! Imágenes monocromas (raster, ESC *, QR y barcodes) como matrices de puntos, sin depender de
+//! la UI: el visor las pinta con egui, pero sirven para cualquier otro destino.
+
+use qrcode::types::Color;
+use qrcode::{EcLevel, QrCode, Version};
+
+use crate::model::QrModel;
+
+/// Matriz de puntos fila por fila; `true` = punto negro.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub dots: Vec<bool>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            dots: vec![false; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.dots[y * self.width + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.dots[y * self.width + x] = true;
+    }
+
+    /// Puntos negros entre los 8 vecinos de (x, y).
+    pub fn black_neighbors(&self, x: usize, y: usize) -> usize {
+        (y.saturating_sub(1)..(y + 2).min(self.height))
+            .flat_map(|ny| (x.saturating_sub(1)..(x + 2).min(self.width)).map(move |nx| (nx, ny)))
+            .filter(|&(nx, ny)| (nx, ny) != (x, y) && self.get(nx, ny))
+            .count()
+    }
+}
+
+/// Raster `GS v 0`: `width_bytes` bytes por fila, bit 7 = punto de la izquierda. None si faltan
+/// datos o la imagen está vacía.
+pub fn raster_bitmap(width_bytes: u16, height: u16, data: &[u8]) -> Option<Bitmap> {
+    let width_bits = (width_bytes as usize).checked_mul(8)?;
+    let height = height as usize;
+    if width_bits == 0 || height == 0 {
+        return None;
+    }
+    let expected = (width_bytes as usize).saturating_mul(height);
+    if data.len() < expected {
+        return None;
+    }
+
+    let mut bitmap = Bitmap::new(width_bits, height);
+    for y in 0..height {
+        for x in 0..width_bits {
+            let byte = data[y * width_bytes as usize + x / 8];
+            if (byte & (1 << (7 - x % 8))) != 0 {
+                bitmap.set(x, y);
+            }
+        }
+    }
+    Some(bitmap)
+}
+
+/// Imagen de bits `ESC *` (formato de 8/24 agujas, columnas verticales).
+/// mode 0,1 = 8 puntos (1 byte por columna); mode 32,33 = 24 puntos (3 bytes por columna).
+pub fn bit_image_bitmap(mode: u8, width: u16, data: &[u8]) -> Option<Bitmap> {
+    let width = width as usize;
+    if width == 0 {
+        return None;
+    }
+
+    let (bytes_per_col, height) = match mode {
+        0 | 1 => (1usize, 8usize),
+        32 | 33 => (3usize, 24usize),
+        _ => (1usize, 8usize),
+    };
+
+    let expected = width.saturating_mul(bytes_per_col);
+    if data.len() < expected {
+        return None;
+    }
+
+    let mut bitmap = Bitmap::new(width, height);
+    for col in 0..width {
+        let col_data = &data[col * bytes_per_col..(col + 1) * bytes_per_col];
+        for (byte_idx, &byte) in col_data.iter().enumerate() {
+            for bit in 0..8 {
+                let y = byte_idx * 8 + bit;
+                if y >= height {
+                    break;
+                }
+                if (byte & (1 << (7 - bit))) != 0 {
+                    bitmap.set(col, y);
+                }
+            }
+        }
+    }
+    Some(bitmap)
+}
+
+fn ecc_to_level(ecc: u8) -> EcLevel {
+    match ecc {
+        48 => EcLevel::L,
+        49 => EcLevel::M,
+        50 => EcLevel::Q,
+        51 => EcLevel::H,
+        _ => EcLevel::M,
+    }
+}
+
+/// Símbolo para el modelo pedido. El crate no genera modelo 1 (se dibuja como modelo 2) y un
+/// Micro QR que no entra en M1..M4 pasa a QR normal; en esos casos se devuelve una nota.
+fn qr_code(data: &[u8], ecc: u8, model: QrModel) -> Option<(QrCode, Option<&'static str>)> {
+    let ec_level = ecc_to_level(ecc);
+    if model == QrModel::Micro {
+        if let Some(code) =
+            (1..=4).find_map(|v| QrCode::with_version(data, Version::Micro(v), ec_level).ok())
+        {
+            return Some((code, None));
+        }
+    }
+    let code = QrCode::with_error_correction_level(data, ec_level).ok()?;
+    let note = match model {
+        QrModel::Model1 => Some("Modelo 1: se dibuja como modelo 2"),
+        QrModel::Micro => Some("Micro QR: los datos no entran, se dibuja como QR"),
+        QrModel::Model2 => None,
+    };
+    Some((code, note))
+}
+
+/// QR de `GS ( k` con su zona de silencio, `module_size` puntos por módulo (1..=16). La nota
+/// avisa si el símbolo dibujado no es el modelo pedido.
+pub fn qr_bitmap(
+    data: &[u8],
+    ecc: u8,
+    module_size: u8,
+    model: QrModel,
+) -> Option<(Bitmap, Option<&'static str>)> {
+    let (code, note) = qr_code(data, ecc, model)?;
+    let width = code.width();
+    if width == 0 {
+        return None;
+    }
+
+    let module = (module_size as usize).clamp(1, 16);
+    // El Micro QR lleva zona de silencio de 2 módulos en vez de 4.
+    let quiet = if matches!(code.version(), Version::Micro(_)) {
+        2usize
+    } else {
+        4usize
+    };
+    let out_w = (width + 2 * quiet) * module;
+    let mut bitmap = Bitmap::new(out_w, out_w);
+
+    let colors = code.to_colors();
+    for y in 0..width {
+        for x in 0..width {
+            if colors[y * width + x] != Color::Dark {
+                continue;
+            }
+            let base_x = (x + quiet) * module;
+            let base_y = (y + quiet) * module;
+            for dy in 0..module {
+                for dx in 0..module {
+                    bitmap.set(base_x + dx, base_y + dy);
+                }
+            }
+        }
+    }
+    Some((bitmap, note))
+}
+
+/// Barcode 1D a partir de los runs de `barcode::encode_runs` (módulos alternando barra y
+/// espacio), con `quiet_zone_modules` de margen blanco a cada lado.
+pub fn runs_bitmap(
+    runs: &[u8],
+    start_with_black: bool,
+    module_px: usize,
+    height_px: usize,
+    quiet_zone_modules: usize,
+) -> Option<Bitmap> {
+    if runs.is_empty() || module_px == 0 || height_px == 0 {
+        return None;
+    }
+
+    let total_modules: usize =
+        runs.iter().map(|&r| r as usize).sum::<usize>() + quiet_zone_modules.saturating_mul(2);
+    if total_modules == 0 {
+        return None;
+    }
+
+    let width_px = total_modules.saturating_mul(module_px).max(1);
+    let mut bitmap = Bitmap::new(width_px, height_px);
+
+    // Quiet zone a la izquierda.
+    let mut x_px = quiet_zone_modules.saturating_mul(module_px);
+    let mut black = start_with_black;
+
+    for &run in runs {
+        let run_px = (run as usize).saturating_mul(module_px);
+        if black && run_px > 0 {
+            let x0 = x_px.min(width_px);
+            let x1 = (x_px + run_px).min(width_px);
+            for y in 0..height_px {
+                for x in x0..x1 {
+                    bitmap.set(x, y);
+                }
+            }
+        }
+
+        x_px = x_px.saturating_add(run_px);
+        black = !black;
+        if x_px >= width_px {
+            break;
+        }
+    }
+
+    Some(bitmap)
+}
+
+/// Lee la fila central como un lector láser: anchos de barras y espacios en módulos, y el
+/// margen blanco más chico a los lados. None si algún ancho no es múltiplo del módulo.
+pub fn scan_middle_row(bitmap: &Bitmap, module_px: usize) -> Option<(Vec<u8>, usize)> {
+    let width = bitmap.width;
+    let y = bitmap.height / 2;
+    let row = &bitmap.dots[y * width..(y + 1) * width];
+
+    let first = row.iter().position(|&b| b)?;
+    let last = row.iter().rposition(|&b| b)?;
+    let mut runs: Vec<u8> = Vec::new();
+    let mut x = first;
+    while x <= last {
+        let black = row[x];
+        let len = row[x..=last].iter().take_while(|&&b| b == black).count();
+        if !len.is_multiple_of(module_px) {
+            return None;
+        }
+        runs.push(u8::try_from(len / module_px).ok()?);
+        x += len;
+    }
+    let quiet_px = first.min(width - 1 - last);
+    Some((runs, quiet_px / module_px))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raster_and_runs_become_dot_matrices() {
+        // 1 byte por fila: 0b1000_0001 y 0b0100_0000.
+        let bitmap = raster_bitmap(1, 2, &[0x81, 0x40]).unwrap();
+        assert_eq!((bitmap.width, bitmap.height), (8, 2));
+        assert!(bitmap.get(0, 0) && bitmap.get(7, 0) && bitmap.get(1, 1));
+        assert!(!bitmap.get(1, 0));
+        assert_eq!(bitmap.black_neighbors(0, 0), 1);
+        assert!(raster_bitmap(2, 2, &[0x81, 0x40]).is_none());
+
+        let bars = runs_bitmap(&[1, 2, 3], true, 2, 4, 1).unwrap();
+        assert_eq!((bars.width, bars.height), (16, 4));
+        assert_eq!(scan_middle_row(&bars, 2), Some((vec![1, 2, 3], 1)));
+    }
+
+    #[test]
+    fn qr_has_a_quiet_zone_of_four_modules() {
+        let (bitmap, note) = qr_bitmap(b"hola", 48, 3, QrModel::Model2).unwrap();
+        assert_eq!(note, None);
+        assert_eq!(bitmap.width, bitmap.height);
+        // Versión 1: 21 módulos + 2 * 4 de silencio, a 3 puntos por módulo.
+        assert_eq!(bitmap.width, (21 + 8) * 3);
+        assert!(!bitmap.get(11, 11));
+        assert!(bitmap.get(12, 12));
+    }
+}
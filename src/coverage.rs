@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use crate::barcode;
+use crate::escpos::ParsedCommand;
+use crate::model::{CommandType, Control, QrModel};
+
+/// Nivel de soporte del visor para un comando ESC/POS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Support {
+    Unsupported,
+    Partial,
+    Full,
+}
+
+impl Support {
+    pub fn label(self) -> &'static str {
+        match self {
+            Support::Unsupported => "no soportado",
+            Support::Partial => "parcial",
+            Support::Full => "soportado",
+        }
+    }
+
+    /// Clave estable para las salidas JSON.
+    pub fn key(self) -> &'static str {
+        match self {
+            Support::Unsupported => "unsupported",
+            Support::Partial => "partial",
+            Support::Full => "full",
+        }
+    }
+}
+
+/// Una fila del reporte: comando distinto encontrado en el job.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoverageEntry {
+    pub command: String,
+    pub support: Support,
+    pub count: usize,
+}
+
+/// Tabla de capacidades. Actualizar cuando se implemente (o mejore) un comando.
+pub fn classify(command: &CommandType) -> Option<(String, Support)> {
+    let control = match command {
+        CommandType::Text(_) => return None,
+        CommandType::Unknown(b) => return Some((format!("0x{:02X}", b), Support::Unsupported)),
+        CommandType::Control(c) => c,
+    };
+
+    let (name, support) = match control {
+        Control::Newline => ("LF".to_string(), Support::Full),
+        // Tabuladores fijos cada 8 columnas (ESC D no se interpreta).
+        Control::Tab => ("HT".to_string(), Support::Partial),
+        Control::Init => ("ESC @".to_string(), Support::Full),
+        Control::Bold(_) => ("ESC E".to_string(), Support::Full),
+        Control::Align(_) => ("ESC a".to_string(), Support::Full),
+        Control::CodePage(_) => ("ESC t".to_string(), Support::Full),
+        // Tabla sin equivalente en el visor: se decodifica con la más parecida.
+        Control::CodePageFallback { n, .. } => (format!("ESC t {n}"), Support::Partial),
+        Control::Size { .. } => ("GS !".to_string(), Support::Full),
+        Control::Cut { .. } => ("GS V".to_string(), Support::Full),
+        Control::RasterImage { .. } => ("GS v 0".to_string(), Support::Full),
+        // El modelo 1 se dibuja como modelo 2.
+        Control::Qr {
+            model: QrModel::Model1,
+            ..
+        } => ("GS ( k QR modelo 1".to_string(), Support::Partial),
+        Control::Qr { .. } => ("GS ( k QR".to_string(), Support::Full),
+        // Se muestra el contenido en un recuadro, sin dibujar el símbolo.
+        Control::TwoDimBarcode { kind, .. } => {
+            (format!("GS ( k {}", kind.label()), Support::Partial)
+        }
+        Control::Barcode { m, .. } => (
+            format!("GS k {}", barcode::symbology_name(*m)),
+            if barcode::is_rendered(*m) {
+                Support::Full
+            } else {
+                Support::Unsupported
+            },
+        ),
+        Control::BarcodeHriPosition(_) => ("GS H".to_string(), Support::Full),
+        Control::BarcodeHeight(_) => ("GS h".to_string(), Support::Full),
+        Control::BarcodeModuleWidth(_) => ("GS w".to_string(), Support::Full),
+        // La fuente del HRI se registra pero no cambia el render.
+        Control::BarcodeHriFont(_) => ("GS f".to_string(), Support::Partial),
+        // La posición se aproxima con espacios en columnas de texto.
+        Control::AbsolutePosition { .. } => ("ESC $".to_string(), Support::Partial),
+        Control::RelativePosition { .. } => ("ESC \\".to_string(), Support::Partial),
+        // Se aplican al texto; imágenes y barcodes siguen alineados al papel.
+        Control::LeftMargin { .. } => ("GS L".to_string(), Support::Partial),
+        Control::PrintAreaWidth { .. } => ("GS W".to_string(), Support::Partial),
+        Control::Underline(_) => ("ESC -".to_string(), Support::Full),
+        Control::Reverse(_) => ("GS B".to_string(), Support::Full),
+        // Solo gira el ticket entero si llega antes del contenido (o con la opción de vista).
+        Control::UpsideDown(_) => ("ESC {".to_string(), Support::Partial),
+        Control::MasterSelect(_) => ("ESC !".to_string(), Support::Full),
+        Control::LineSpacingDefault => ("ESC 2".to_string(), Support::Full),
+        Control::LineSpacing(_) => ("ESC 3".to_string(), Support::Full),
+        Control::FeedLines(_) => ("ESC d".to_string(), Support::Full),
+        Control::FeedDots(_) => ("ESC J".to_string(), Support::Full),
+        Control::BitImage { .. } => ("ESC *".to_string(), Support::Full),
+        Control::FontSelect(_) => ("ESC M".to_string(), Support::Full),
+        Control::OpenDrawer => ("ESC p".to_string(), Support::Full),
+        Control::Bell => ("BEL".to_string(), Support::Full),
+        Control::Buzzer { .. } => ("ESC ( A".to_string(), Support::Full),
+        // Sin modo página: avanza como un LF.
+        Control::FormFeed => ("FF".to_string(), Support::Partial),
+        Control::DoubleByteMode(true) => ("FS &".to_string(), Support::Full),
+        Control::DoubleByteMode(false) => ("FS .".to_string(), Support::Full),
+        // La imagen vive en la impresora: se dibuja la asignada en Configuración o un recuadro.
+        Control::NvLogo { .. } => ("FS p".to_string(), Support::Partial),
+        Control::GsBlock { function, .. } => {
+            let name = match function {
+                // Con cn sin soporte: PDF417, MaxiCode, etc.
+                0x6B => "GS ( k (2D no QR)".to_string(),
+                0x4C => "GS ( L (gráficos)".to_string(),
+                f if f.is_ascii_graphic() => format!("GS ( {}", *f as char),
+                f => format!("GS ( {:02X}", f),
+            };
+            (name, Support::Unsupported)
+        }
+        Control::EscBlock { function, .. } => {
+            let name = if function.is_ascii_graphic() {
+                format!("ESC ( {}", *function as char)
+            } else {
+                format!("ESC ( {:02X}", function)
+            };
+            (name, Support::Unsupported)
+        }
+        Control::EscUnknown(b) => (format!("ESC {:02X}", b), Support::Unsupported),
+        Control::GsUnknown(b) => (format!("GS {:02X}", b), Support::Unsupported),
+    };
+    Some((name, support))
+}
+
+/// Reporte de cobertura de un job: cada comando distinto con su cantidad y soporte.
+/// Primero los no soportados, luego los parciales; dentro de cada grupo, por cantidad.
+pub fn coverage_report(commands: &[ParsedCommand]) -> Vec<CoverageEntry> {
+    let mut counts: BTreeMap<String, (Support, usize)> = BTreeMap::new();
+    for (_, command) in commands {
+        if let Some((name, support)) = classify(command) {
+            counts.entry(name).or_insert((support, 0)).1 += 1;
+        }
+    }
+
+    let mut report: Vec<CoverageEntry> = counts
+        .into_iter()
+        .map(|(command, (support, count))| CoverageEntry {
+            command,
+            support,
+            count,
+        })
+        .collect();
+    report.sort_by(|a, b| a.support.cmp(&b.support).then(b.count.cmp(&a.count)));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escpos::parse_escpos_simple;
+    use crate::model::CodePage;
+
+    #[test]
+    fn coverage_counts_commands_and_lists_unsupported_first() {
+        let mut data = vec![0x1B, 0x40];
+        data.extend_from_slice(b"A\n");
+        data.extend_from_slice(b"B\n");
+        // GS ( k con cn=0x30 (PDF417), dos veces.
+        for _ in 0..2 {
+            data.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x30, 0x41, 0x00]);
+        }
+        // GS k GS1 DataBar (m=75) y CODE128 (m=73).
+        data.extend_from_slice(&[0x1D, 0x6B, 75, 2, b'0', b'1']);
+        data.extend_from_slice(&[0x1D, 0x6B, 73, 3, b'{', b'B', b'1']);
+
+        let report = coverage_report(&parse_escpos_simple(&data, CodePage::Utf8Lossy));
+        let find = |name: &str| report.iter().find(|e| e.command == name).cloned();
+
+        assert_eq!(
+            find("GS ( k (2D no QR)"),
+            Some(CoverageEntry {
+                command: "GS ( k (2D no QR)".to_string(),
+                support: Support::Unsupported,
+                count: 2,
+            })
+        );
+        assert_eq!(
+            find("GS k GS1 DataBar Omnidirectional").map(|e| e.support),
+            Some(Support::Unsupported)
+        );
+        assert_eq!(find("GS k CODE128").map(|e| e.support), Some(Support::Full));
+        assert_eq!(find("LF").map(|e| e.count), Some(2));
+        assert_eq!(find("ESC @").map(|e| e.count), Some(1));
+
+        assert_eq!(report[0].command, "GS ( k (2D no QR)");
+        assert!(report.windows(2).all(|w| w[0].support <= w[1].support));
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOG_FILE: &str = "escpos_viewer.log";
+/// Tamaño máximo del archivo activo antes de rotar.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// Archivos rotados que se conservan (`escpos_viewer.1.log` ... `.3.log`): ~4 MiB en total.
+const MAX_ROTATED: usize = 3;
+
+/// Log de diagnóstico global (desactivado por defecto). Se usa desde la UI y desde el hilo TCP.
+static LOG: Mutex<Option<RotatingLog>> = Mutex::new(None);
+
+/// Carpeta de logs (`<config>/logs`).
+pub fn log_dir() -> Option<PathBuf> {
+    crate::settings::config_dir().map(|d| d.join("logs"))
+}
+
+pub fn set_enabled(enabled: bool) {
+    if let Ok(mut log) = LOG.lock() {
+        *log = if enabled {
+            log_dir().map(|dir| RotatingLog::new(dir, MAX_FILE_BYTES, MAX_ROTATED))
+        } else {
+            None
+        };
+    }
+}
+
+pub fn is_enabled() -> bool {
+    LOG.lock().map(|log| log.is_some()).unwrap_or(false)
+}
+
+/// Registra un evento general (listener, conexiones).
+pub fn log(message: &str) {
+    write_line(None, message);
+}
+
+/// Registra un evento asociado a un job del historial.
+pub fn log_job(job_id: u64, message: &str) {
+    write_line(Some(job_id), message);
+}
+
+/// Abre la carpeta de logs en el explorador de archivos (la crea si no existe).
+pub fn open_log_dir() -> Result<(), String> {
+    let dir = log_dir().ok_or_else(|| "No se encontró la carpeta de configuración".to_string())?;
+    fs::create_dir_all(&dir).map_err(|e| format!("No se pudo crear {}: {e}", dir.display()))?;
+
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    std::process::Command::new(program)
+        .arg(&dir)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("No se pudo abrir {}: {e}", dir.display()))
+}
+
+fn write_line(job_id: Option<u64>, message: &str) {
+    let Ok(mut log) = LOG.lock() else {
+        return;
+    };
+    if let Some(log) = log.as_mut() {
+        // Un fallo al escribir el log nunca debe afectar la captura.
+        let _ = log.append(&format_line(SystemTime::now(), job_id, message));
+    }
+}
+
+fn format_line(now: SystemTime, job_id: Option<u64>, message: &str) -> String {
+    let secs = now
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let job = job_id.map(|id| format!(" [job #{id}]")).unwrap_or_default();
+    format!(
+        "{}{} {}\n",
+        format_utc(secs),
+        job,
+        message.replace(['\r', '\n'], " ")
+    )
+}
+
+/// `YYYY-MM-DD HH:MM:SSZ` en UTC (sin dependencias de fecha).
+fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Conversión días -> fecha civil (algoritmo de Howard Hinnant).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}Z",
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60
+    )
+}
+
+/// Archivo de log con rotación por tamaño: `.log` -> `.1.log` -> ... -> `.{max_rotated}.log`.
+struct RotatingLog {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_rotated: usize,
+}
+
+impl RotatingLog {
+    fn new(dir: PathBuf, max_file_bytes: u64, max_rotated: usize) -> Self {
+        Self {
+            dir,
+            max_file_bytes,
+            max_rotated,
+        }
+    }
+
+    fn path(&self, n: usize) -> PathBuf {
+        if n == 0 {
+            self.dir.join(LOG_FILE)
+        } else {
+            self.dir.join(format!("escpos_viewer.{n}.log"))
+        }
+    }
+
+    fn append(&mut self, line: &str) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let current = self.path(0);
+        let size = fs::metadata(&current).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_file_bytes {
+            self.rotate();
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&current)?;
+        file.write_all(line.as_bytes())
+    }
+
+    fn rotate(&self) {
+        let _ = fs::remove_file(self.path(self.max_rotated));
+        for n in (0..self.max_rotated).rev() {
+            let _ = fs::rename(self.path(n), self.path(n + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_have_utc_timestamp_and_job_id() {
+        let t = UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            format_line(t, Some(12), "recibido\n64 bytes"),
+            "2023-11-14 22:13:20Z [job #12] recibido 64 bytes\n"
+        );
+        assert_eq!(
+            format_line(UNIX_EPOCH, None, "x"),
+            "1970-01-01 00:00:00Z x\n"
+        );
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00:00Z");
+    }
+
+    #[test]
+    fn rotation_caps_the_number_of_files() {
+        let dir = std::env::temp_dir().join(format!("escpos_log_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut log = RotatingLog::new(dir.clone(), 100, 2);
+
+        for i in 0..40 {
+            log.append(&format!("linea de prueba {i:03}\n")).unwrap();
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "escpos_viewer.1.log",
+                "escpos_viewer.2.log",
+                "escpos_viewer.log"
+            ]
+        );
+        for name in &names {
+            assert!(fs::metadata(dir.join(name)).unwrap().len() <= 100);
+        }
+        let current = fs::read_to_string(dir.join(LOG_FILE)).unwrap();
+        assert!(current.ends_with("linea de prueba 039\n"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,5 +1,12 @@
-use crate::model::{Align, BarcodeHriPosition, CodePage, CommandType, Control, PrinterState};
-use oem_cp::{Cp437, Cp850, StringExt};
+use std::ops::Range;
+
+use crate::model::{
+    Align, BarcodeHriPosition, CodePage, CommandType, Control, PrinterState, QrModel, TwoDimKind,
+};
+use oem_cp::{
+    Cp437, Cp720, Cp737, Cp775, Cp850, Cp852, Cp855, Cp857, Cp858, Cp860, Cp861, Cp862, Cp863,
+    Cp864, Cp865, Cp869, IncompleteCp, StrExt, StringExt,
+};
 
 pub type ParsedCommand = (PrinterState, CommandType);
 
@@ -7,60 +14,336 @@ fn decode_text(bytes: &[u8], codepage: CodePage) -> String {
     match codepage {
         // Muchísimos POS envían bytes tipo Windows-1252/Latin1 (p.ej. 0xA1 = '¡')
         // y NO UTF-8. Si decodificamos como UTF-8 (lossy) sale '�'.
-        // Solución: intentar UTF-8 estricto; si falla, hacer fallback a Windows-1252.
-        CodePage::Utf8Lossy => match std::str::from_utf8(bytes) {
-            Ok(s) => s.to_string(),
-            Err(_) => {
-                let (text, _, _) = encoding_rs::WINDOWS_1252.decode(bytes);
-                text.into_owned()
+        // Solución: los tramos UTF-8 válidos quedan como UTF-8 y solo los bytes inválidos
+        // se interpretan como Windows-1252 (un byte suelto no arruina el resto de la línea).
+        CodePage::Utf8Lossy => {
+            let mut out = String::with_capacity(bytes.len());
+            for chunk in bytes.utf8_chunks() {
+                out.push_str(chunk.valid());
+                if !chunk.invalid().is_empty() {
+                    let (text, _, _) = encoding_rs::WINDOWS_1252.decode(chunk.invalid());
+                    out.push_str(&text);
+                }
             }
-        },
+            out
+        }
         CodePage::Cp437 => String::from_cp::<Cp437>(bytes),
         CodePage::Cp850 => String::from_cp::<Cp850>(bytes),
         CodePage::Windows1252 => {
             let (text, _, _) = encoding_rs::WINDOWS_1252.decode(bytes);
             text.into_owned()
         }
-        CodePage::Pc858 => {
-            // PC858 is CP850 with Euro symbol at 0xD5
-            // For simplicity, use CP850 as base (Euro symbol mapping is close)
-            String::from_cp::<Cp850>(bytes)
+        CodePage::Pc858 => String::from_cp::<Cp858>(bytes),
+        // encoding_rs no tiene Latin-1 puro (lo trata como Windows-1252): cada byte es el
+        // punto de código del mismo valor.
+        CodePage::Iso88591 => bytes.iter().map(|&b| char::from(b)).collect(),
+        CodePage::Cp866 => {
+            let (text, _, _) = encoding_rs::IBM866.decode(bytes);
+            text.into_owned()
+        }
+        CodePage::Cp860 => String::from_cp::<Cp860>(bytes),
+        CodePage::Cp865 => String::from_cp::<Cp865>(bytes),
+        CodePage::ShiftJis => {
+            let (text, _, _) = encoding_rs::SHIFT_JIS.decode(bytes);
+            text.into_owned()
         }
-        CodePage::Iso88591 => {
-            let (text, _, _) = encoding_rs::ISO_8859_2.decode(bytes);
+        CodePage::Gbk => {
+            let (text, _, _) = encoding_rs::GBK.decode(bytes);
             text.into_owned()
         }
-        CodePage::Cp866 => {
-            let (text, _, _) = encoding_rs::IBM866.decode(bytes);
+        CodePage::Cp863 => String::from_cp::<Cp863>(bytes),
+        CodePage::Cp857 => String::from_cp_lossy::<Cp857>(bytes),
+        CodePage::Cp737 => String::from_cp::<Cp737>(bytes),
+        CodePage::Cp852 => String::from_cp::<Cp852>(bytes),
+        CodePage::Cp720 => String::from_cp::<Cp720>(bytes),
+        CodePage::Cp775 => String::from_cp::<Cp775>(bytes),
+        CodePage::Cp855 => String::from_cp::<Cp855>(bytes),
+        CodePage::Cp861 => String::from_cp::<Cp861>(bytes),
+        CodePage::Cp862 => String::from_cp::<Cp862>(bytes),
+        CodePage::Cp864 => String::from_cp_lossy::<Cp864>(bytes),
+        CodePage::Cp869 => String::from_cp::<Cp869>(bytes),
+        // El resto son tablas de un byte de encoding_rs.
+        _ => {
+            let encoding = single_byte_encoding(codepage).unwrap_or(encoding_rs::WINDOWS_1252);
+            let (text, _, _) = encoding.decode(bytes);
             text.into_owned()
         }
-        CodePage::Cp860 => {
-            // Portuguese - fallback to CP850 (similar)
-            String::from_cp::<Cp850>(bytes)
+    }
+}
+
+/// Tablas de un byte que resuelve encoding_rs (ISO-8859-x y Windows-125x).
+fn single_byte_encoding(codepage: CodePage) -> Option<&'static encoding_rs::Encoding> {
+    Some(match codepage {
+        CodePage::Iso88592 => encoding_rs::ISO_8859_2,
+        CodePage::Iso88597 => encoding_rs::ISO_8859_7,
+        CodePage::Iso885915 => encoding_rs::ISO_8859_15,
+        CodePage::Windows1250 => encoding_rs::WINDOWS_1250,
+        CodePage::Windows1251 => encoding_rs::WINDOWS_1251,
+        CodePage::Windows1253 => encoding_rs::WINDOWS_1253,
+        CodePage::Windows1254 => encoding_rs::WINDOWS_1254,
+        CodePage::Windows1255 => encoding_rs::WINDOWS_1255,
+        CodePage::Windows1256 => encoding_rs::WINDOWS_1256,
+        CodePage::Windows1257 => encoding_rs::WINDOWS_1257,
+        CodePage::Windows1258 => encoding_rs::WINDOWS_1258,
+        CodePage::Windows874 => encoding_rs::WINDOWS_874,
+        _ => return None,
+    })
+}
+
+/// Inversa de `decode_text` (mismas tablas por codepage), para armar tickets de ejemplo o de
+/// prueba en otras codificaciones. Lo que el codepage no puede representar sale como `?`.
+pub fn encode_text(text: &str, codepage: CodePage) -> Vec<u8> {
+    let with_encoding = |encoding: &'static encoding_rs::Encoding| {
+        move |c: &str| {
+            let (bytes, _, had_errors) = encoding.encode(c);
+            (!had_errors).then(|| bytes.into_owned())
+        }
+    };
+    match codepage {
+        CodePage::Utf8Lossy => text.as_bytes().to_vec(),
+        CodePage::Cp437 => encode_chars(text, encode_oem::<Cp437>),
+        CodePage::Cp850 => encode_chars(text, encode_oem::<Cp850>),
+        CodePage::Pc858 => encode_chars(text, encode_oem::<Cp858>),
+        CodePage::Cp860 => encode_chars(text, encode_oem::<Cp860>),
+        CodePage::Cp865 => encode_chars(text, encode_oem::<Cp865>),
+        CodePage::Windows1252 => encode_chars(text, with_encoding(encoding_rs::WINDOWS_1252)),
+        CodePage::Iso88591 => encode_chars(text, |c| {
+            let code = u32::from(c.chars().next()?);
+            u8::try_from(code).ok().map(|b| vec![b])
+        }),
+        CodePage::Cp866 => encode_chars(text, with_encoding(encoding_rs::IBM866)),
+        CodePage::ShiftJis => encode_chars(text, with_encoding(encoding_rs::SHIFT_JIS)),
+        CodePage::Gbk => encode_chars(text, with_encoding(encoding_rs::GBK)),
+        CodePage::Cp863 => encode_chars(text, encode_oem::<Cp863>),
+        CodePage::Cp857 => encode_chars(text, encode_oem::<Cp857>),
+        CodePage::Cp737 => encode_chars(text, encode_oem::<Cp737>),
+        CodePage::Cp852 => encode_chars(text, encode_oem::<Cp852>),
+        CodePage::Cp720 => encode_chars(text, encode_oem::<Cp720>),
+        CodePage::Cp775 => encode_chars(text, encode_oem::<Cp775>),
+        CodePage::Cp855 => encode_chars(text, encode_oem::<Cp855>),
+        CodePage::Cp861 => encode_chars(text, encode_oem::<Cp861>),
+        CodePage::Cp862 => encode_chars(text, encode_oem::<Cp862>),
+        CodePage::Cp864 => encode_chars(text, encode_oem::<Cp864>),
+        CodePage::Cp869 => encode_chars(text, encode_oem::<Cp869>),
+        _ => encode_chars(
+            text,
+            with_encoding(single_byte_encoding(codepage).unwrap_or(encoding_rs::WINDOWS_1252)),
+        ),
+    }
+}
+
+/// Tabla de `ESC t n` (numeración Epson). Devuelve el codepage y si es exacto: las tablas que
+/// el visor no tiene (Katakana, Thai, TCVN, PC851…) se aproximan con la más parecida.
+pub fn esc_t_codepage(n: u8) -> (CodePage, bool) {
+    let exact = match n {
+        0 => CodePage::Cp437,
+        2 => CodePage::Cp850,
+        3 => CodePage::Cp860,
+        4 => CodePage::Cp863,
+        5 => CodePage::Cp865,
+        // Epson lo usa para Hiragana; los clones chinos, para ISO-8859-1.
+        6 => CodePage::Iso88591,
+        13 => CodePage::Cp857,
+        14 => CodePage::Cp737,
+        15 => CodePage::Iso88597,
+        16 => CodePage::Windows1252,
+        17 => CodePage::Cp866,
+        18 => CodePage::Cp852,
+        19 => CodePage::Pc858,
+        32 => CodePage::Cp720,
+        34 => CodePage::Cp855,
+        35 => CodePage::Cp861,
+        36 => CodePage::Cp862,
+        37 => CodePage::Cp864,
+        38 => CodePage::Cp869,
+        39 => CodePage::Iso88592,
+        40 => CodePage::Iso885915,
+        45 => CodePage::Windows1250,
+        46 => CodePage::Windows1251,
+        47 => CodePage::Windows1253,
+        48 => CodePage::Windows1254,
+        49 => CodePage::Windows1255,
+        50 => CodePage::Windows1256,
+        51 => CodePage::Windows1257,
+        52 => CodePage::Windows1258,
+        _ => {
+            let nearest = match n {
+                // Katakana / Hiragana / Kanji de un paso: la mitad alta de Shift-JIS.
+                1 | 7 | 8 => CodePage::ShiftJis,
+                11 => CodePage::Cp869,            // PC851 Greek
+                12 => CodePage::Cp857,            // PC853 Turkish
+                20..=26 => CodePage::Windows874,  // Thai Character Code 42/11/13/14/16/17/18
+                30 | 31 => CodePage::Windows1258, // TCVN-3 (Vietnamese)
+                33 | 42 => CodePage::Cp775,       // WPC775 / PC1118 (Baltic)
+                41 => CodePage::Cp864,            // PC1098 (Farsi)
+                43 | 44 => CodePage::Cp866,       // PC1119 / PC1125 (Cyrillic)
+                53 => CodePage::Windows1251,      // KZ-1048 (Kazakh)
+                // Indic (66..82), páginas de usuario (254/255) y números no asignados.
+                _ => CodePage::Cp437,
+            };
+            return (nearest, false);
         }
-        CodePage::Cp865 => {
-            // Nordic - fallback to CP850 (similar)
-            String::from_cp::<Cp850>(bytes)
+    };
+    (exact, true)
+}
+
+/// Un carácter con las tablas de oem_cp; `None` si el codepage no lo tiene.
+fn encode_oem<T: IncompleteCp>(c: &str) -> Option<Vec<u8>>
+where
+    u8: From<T>,
+    char: From<T>,
+{
+    c.to_cp::<T>()
+        .ok()
+        .map(|cps| cps.into_iter().map(u8::from).collect())
+}
+
+/// Codifica carácter por carácter; `None` = sin representación (se reemplaza por `?`).
+fn encode_chars(text: &str, encode: impl Fn(&str) -> Option<Vec<u8>>) -> Vec<u8> {
+    let mut buf = [0u8; 4];
+    let mut out = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match encode(c.encode_utf8(&mut buf)) {
+            Some(bytes) => out.extend_from_slice(&bytes),
+            None => out.push(b'?'),
         }
     }
+    out
 }
 
 // --- Lógica de Parsing (Simplificada) ---
-pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
+
+/// Opciones del parser: lo que la impresora asume al arrancar (y de nuevo tras `ESC @`) y
+/// cómo tratar algunos bytes dudosos. Un `CodePage` solo se convierte en las opciones por
+/// defecto con esa tabla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Tabla de caracteres hasta el primer `ESC t`.
+    pub codepage: CodePage,
+    /// Alineación del texto hasta el primer `ESC a` (para POS que nunca lo mandan).
+    pub alignment: Align,
+    /// CR suelto (no seguido de LF) como salto de línea, para POS que terminan las líneas
+    /// solo con CR. Por defecto se ignora, como en la mayoría de las impresoras.
+    pub cr_as_newline: bool,
+    /// Tope de bytes de un raster `GS v 0`: uno más grande se saltea entero sin emitir imagen
+    /// (p.ej. una cabecera corrupta que declara megas de datos). `None` = sin tope.
+    pub max_raster_bytes: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            codepage: CodePage::Utf8Lossy,
+            alignment: Align::Left,
+            cr_as_newline: false,
+            max_raster_bytes: None,
+        }
+    }
+}
+
+impl From<CodePage> for ParseOptions {
+    fn from(codepage: CodePage) -> Self {
+        Self {
+            codepage,
+            ..Self::default()
+        }
+    }
+}
+
+/// Parsea un stream ESC/POS completo: cada comando junto con el estado de la impresora
+/// (negrita, alineación, tamaño…) vigente en ese punto. `options` da el estado inicial;
+/// `ESC t` / `ESC a` lo cambian sobre la marcha.
+///
+/// ```
+/// use escpos_viewer::escpos::{parse_escpos, ParseOptions};
+/// use escpos_viewer::model::{CodePage, CommandType, Control};
+///
+/// // ESC E 1 (negrita), texto y salto de línea.
+/// let options = ParseOptions {
+///     codepage: CodePage::Cp437,
+///     ..ParseOptions::default()
+/// };
+/// let commands = parse_escpos(b"\x1bE\x01Total\n", &options);
+/// let (state, text) = commands
+///     .iter()
+///     .find(|(_, cmd)| matches!(cmd, CommandType::Text(_)))
+///     .unwrap();
+/// assert!(matches!(text, CommandType::Text(t) if t == "Total"));
+/// assert!(state.is_bold);
+/// assert!(commands
+///     .iter()
+///     .any(|(_, cmd)| matches!(cmd, CommandType::Control(Control::Newline))));
+/// ```
+pub fn parse_escpos(data: &[u8], options: &ParseOptions) -> Vec<ParsedCommand> {
+    parse_escpos_with_spans(data, options).0
+}
+
+/// `parse_escpos` con las opciones por defecto y la tabla `codepage`.
+pub fn parse_escpos_simple(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
+    parse_escpos(data, &codepage.into())
+}
+
+/// Como `parse_escpos`, y además el rango de bytes de `data` que consumió cada comando
+/// (mismo largo que la lista de comandos). Para la vista hex anotada.
+pub fn parse_escpos_with_spans(
+    data: &[u8],
+    options: &ParseOptions,
+) -> (Vec<ParsedCommand>, Vec<Range<usize>>) {
+    let (commands, spans, _) = parse_with_spans(data, options, false);
+    (commands, spans)
+}
+
+/// Como `parse_escpos_with_spans`, y además los ESC/FS/GS descartados sin emitir nada:
+/// truncados (faltan bytes) o con parámetros inválidos. Para `--validate`.
+pub fn parse_escpos_checked(
+    data: &[u8],
+    options: &ParseOptions,
+) -> (Vec<ParsedCommand>, Vec<Range<usize>>, Vec<Range<usize>>) {
+    parse_with_spans(data, options, false)
+}
+
+/// Para un buffer que todavía está creciendo (simulación de impresión): un ESC/GS al final
+/// al que le faltan bytes queda pendiente en vez de descartar su cabecera y leer el resto
+/// como texto. Se parsea entero cuando llegan los bytes que faltan.
+pub fn parse_escpos_incremental(
+    data: &[u8],
+    options: &ParseOptions,
+) -> (Vec<ParsedCommand>, Vec<Range<usize>>) {
+    let (commands, spans, _) = parse_with_spans(data, options, true);
+    (commands, spans)
+}
+
+fn parse_with_spans(
+    data: &[u8],
+    options: &ParseOptions,
+    defer_incomplete_tail: bool,
+) -> (Vec<ParsedCommand>, Vec<Range<usize>>, Vec<Range<usize>>) {
     let mut commands = Vec::new();
+    let mut spans: Vec<Range<usize>> = Vec::new();
+    let mut discarded: Vec<Range<usize>> = Vec::new();
     let mut i = 0;
 
-    let mut state = PrinterState::default();
-    let mut active_codepage = codepage;
+    let initial_state = PrinterState {
+        alignment: options.alignment,
+        ..PrinterState::default()
+    };
+    let mut state = initial_state.clone();
+    let mut active_codepage = options.codepage;
 
     // Estado QR (GS ( k): se arma con Store, y se emite en Print.
-    let mut qr_model: u8 = 2; // 1 o 2 (default: 2)
+    let mut qr_model = QrModel::Model2;
     let mut qr_module_size: u8 = 4; // 1..16 (default: 4)
     let mut qr_ecc: u8 = 48; // 48..51 (L/M/Q/H) (default: 48)
     let mut qr_data: Vec<u8> = Vec::new();
+    // Aztec / DataMatrix (GS ( k cn=53/54): mismo esquema Store + Print que el QR.
+    let mut aztec_data: Vec<u8> = Vec::new();
+    let mut datamatrix_data: Vec<u8> = Vec::new();
 
     while i < data.len() {
         let byte = data[i];
+        let start = i;
+        // Al ESC/FS/GS le faltan bytes (cabecera o datos) que pueden llegar después.
+        let mut truncated = false;
 
         match byte {
             // LF
@@ -75,8 +358,28 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                 commands.push((state.clone(), CommandType::Control(Control::Tab)));
                 i += 1;
             }
-            // CR
+            // CR: se ignora salvo que las opciones lo pidan como salto (sin duplicar el de CR LF).
             0x0D => {
+                if options.cr_as_newline && data.get(i + 1) != Some(&0x0A) {
+                    commands.push((state.clone(), CommandType::Control(Control::Newline)));
+                    state.cursor_x = None;
+                }
+                i += 1;
+            }
+            // NUL (relleno), DC1..DC4 (control de flujo) y CAN (sin modo página no hace nada):
+            // se consumen como el CR, sin comando, para no llenar el log de Unknown.
+            0x00 | 0x11..=0x14 | 0x18 => {
+                i += 1;
+            }
+            // BEL
+            0x07 => {
+                commands.push((state.clone(), CommandType::Control(Control::Bell)));
+                i += 1;
+            }
+            // FF
+            0x0C => {
+                commands.push((state.clone(), CommandType::Control(Control::FormFeed)));
+                state.cursor_x = None;
                 i += 1;
             }
 
@@ -86,14 +389,21 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                     let next_byte = data[i + 1];
                     match next_byte {
                         0x40 => {
-                            // ESC @
+                            // ESC @: según Epson vuelve a los modos de encendido. Se reinician
+                            // TODOS los campos de PrinterState (texto, alineación, interlineado,
+                            // posición y configuración de barcode GS h/w/H/f) y la tabla de
+                            // caracteres y la alineación vuelven a las configuradas (ESC t / ESC a
+                            // se pierden). No se tocan datos ya recibidos.
                             commands.push((state.clone(), CommandType::Control(Control::Init)));
-                            state = PrinterState::default();
+                            state = initial_state.clone();
+                            active_codepage = options.codepage;
                             // Resetear estado de QR
-                            qr_model = 2;
+                            qr_model = QrModel::Model2;
                             qr_module_size = 4;
                             qr_ecc = 48;
                             qr_data.clear();
+                            aztec_data.clear();
+                            datamatrix_data.clear();
                             i += 2;
                         }
                         0x45 => {
@@ -107,6 +417,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -125,39 +436,28 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
                         0x74 => {
-                            // ESC t n (selección de code table / codepage)
-                            // Mapeo común (Epson): 0=CP437, 2=CP850, 16=Windows-1252, etc.
+                            // ESC t n (selección de code table / codepage), ver `esc_t_codepage`.
                             if i + 2 < data.len() {
                                 let n = data[i + 2];
-                                if let Some(mapped) = match n {
-                                    0 => Some(CodePage::Cp437),
-                                    2 => Some(CodePage::Cp850),
-                                    3 => Some(CodePage::Cp860), // Portuguese
-                                    4 => Some(CodePage::Cp865), // Nordic
-                                    6 => Some(CodePage::Iso88591), // ISO-8859-1
-                                    16 => Some(CodePage::Windows1252),
-                                    17 => Some(CodePage::Cp866), // Cyrillic
-                                    19 => Some(CodePage::Pc858), // CP850 + Euro
-                                    _ => None,
-                                } {
-                                    active_codepage = mapped;
-                                    commands.push((
-                                        state.clone(),
-                                        CommandType::Control(Control::CodePage(mapped)),
-                                    ));
+                                let (mapped, exact) = esc_t_codepage(n);
+                                active_codepage = mapped;
+                                let control = if exact {
+                                    Control::CodePage(mapped)
                                 } else {
-                                    // Consumimos igualmente para no filtrar el parámetro como texto.
-                                    commands.push((
-                                        state.clone(),
-                                        CommandType::Control(Control::EscUnknown(next_byte)),
-                                    ));
-                                }
+                                    Control::CodePageFallback {
+                                        n,
+                                        codepage: mapped,
+                                    }
+                                };
+                                commands.push((state.clone(), CommandType::Control(control)));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -174,6 +474,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 4;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -183,47 +484,103 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 let n_l = data[i + 2] as u16;
                                 let n_h = data[i + 3] as u16;
                                 let offset = (n_l | (n_h << 8)) as i16;
+                                // Se suma a la última posición fijada (o al inicio de la línea)
+                                // sin salir del área de impresión.
+                                let base = i32::from(state.cursor_x.unwrap_or(0));
+                                let max = i32::from(state.print_width_dots.unwrap_or(u16::MAX));
+                                state.cursor_x =
+                                    Some((base + i32::from(offset)).clamp(0, max) as u16);
                                 commands.push((
                                     state.clone(),
                                     CommandType::Control(Control::RelativePosition { offset }),
                                 ));
                                 i += 4;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
                         0x2D => {
                             // ESC - n (Underline)
                             if i + 2 < data.len() {
+                                // n = 0/1/2 o '0'/'1'/'2': sin subrayado, 1 punto, 2 puntos.
                                 let n = data[i + 2];
-                                state.is_underline = n == 1 || n == 2;
+                                state.underline_weight = match n {
+                                    1 | b'1' => 1,
+                                    2 | b'2' => 2,
+                                    _ => 0,
+                                };
+                                state.is_underline = state.underline_weight > 0;
                                 commands.push((
                                     state.clone(),
                                     CommandType::Control(Control::Underline(state.is_underline)),
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
                         0x21 => {
                             // ESC ! n (Master select)
-                            // Bit 3: Bold, Bit 4: Double height, Bit 5: Double width, Bit 7: Underline
+                            // Bit 0: Font B, Bit 3: Bold, Bit 4: Double height, Bit 5: Double width,
+                            // Bit 7: Underline
                             if i + 2 < data.len() {
                                 let n = data[i + 2];
+                                state.is_font_b = (n & 0x01) != 0;
                                 state.is_bold = (n & 0x08) != 0;
                                 state.is_underline = (n & 0x80) != 0;
-                                let dh = if (n & 0x10) != 0 { 1 } else { 0 };
-                                let dw = if (n & 0x20) != 0 { 1 } else { 0 };
-                                state.char_height_mul = dh + 1;
-                                state.char_width_mul = dw + 1;
-                                state.font_scale = state.char_height_mul as f32;
+                                // ESC ! solo activa el subrayado de 1 punto.
+                                state.underline_weight = state.is_underline as u8;
+                                let dh = if (n & 0x10) != 0 { 2 } else { 1 };
+                                let dw = if (n & 0x20) != 0 { 2 } else { 1 };
+                                state.set_char_size(dw, dh);
                                 commands.push((
                                     state.clone(),
                                     CommandType::Control(Control::MasterSelect(n)),
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
+                                i += 2;
+                            }
+                        }
+                        0x28 => {
+                            // ESC ( fn pL pH ... : bloques agrupados con longitud explícita.
+                            // Se consumen enteros para que los parámetros no salgan como texto.
+                            if i + 4 < data.len() {
+                                let function = data[i + 2];
+                                let total = data[i + 3] as usize | (data[i + 4] as usize) << 8;
+                                let start = i + 5;
+                                let end = start.saturating_add(total);
+                                if end > data.len() {
+                                    // Truncado; consumir cabecera y seguir.
+                                    truncated = true;
+                                    i += 2;
+                                } else {
+                                    let payload = &data[start..end];
+                                    let control = if function == b'A' {
+                                        // ESC ( A: fn=48 [a n c] (n = repeticiones),
+                                        // fn=97 [n t1 t2] (n = pitidos).
+                                        let count = match payload.first() {
+                                            Some(48) => payload.get(2),
+                                            Some(97) => payload.get(1),
+                                            _ => None,
+                                        };
+                                        Control::Buzzer {
+                                            count: count.copied().unwrap_or(1).max(1),
+                                        }
+                                    } else {
+                                        Control::EscBlock {
+                                            function,
+                                            len: total as u16,
+                                        }
+                                    };
+                                    commands.push((state.clone(), CommandType::Control(control)));
+                                    i = end;
+                                }
+                            } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -247,6 +604,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -278,9 +636,11 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                     ));
                                     i = end;
                                 } else {
+                                    truncated = true;
                                     i += 2;
                                 }
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -295,9 +655,40 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
+                                i += 2;
+                            }
+                        }
+                        0x64 | 0x4A => {
+                            // ESC d n (avanzar n líneas) / ESC J n (avanzar n puntos): imprimen
+                            // el renglón en curso como un LF.
+                            if i + 2 < data.len() {
+                                let n = data[i + 2];
+                                let control = if next_byte == 0x64 {
+                                    Control::FeedLines(n)
+                                } else {
+                                    Control::FeedDots(n)
+                                };
+                                commands.push((state.clone(), CommandType::Control(control)));
+                                state.cursor_x = None;
+                                i += 3;
+                            } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
+                        0x69 | 0x6D => {
+                            // ESC i (corte total) / ESC m (corte parcial): cortes legacy sin
+                            // parámetros. El preview los muestra igual que GS V.
+                            commands.push((
+                                state.clone(),
+                                CommandType::Control(Control::Cut {
+                                    partial: next_byte == 0x6D,
+                                    feed: 0,
+                                }),
+                            ));
+                            i += 2;
+                        }
                         0x70 => {
                             // ESC p m t1 t2 (Generate pulse / Open drawer)
                             if i + 4 < data.len() {
@@ -307,6 +698,21 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 5;
                             } else {
+                                truncated = true;
+                                i += 2;
+                            }
+                        }
+                        0x7B => {
+                            // ESC { n (Upside-down printing: bit 0)
+                            if i + 2 < data.len() {
+                                state.is_upside_down = (data[i + 2] & 0x01) != 0;
+                                commands.push((
+                                    state.clone(),
+                                    CommandType::Control(Control::UpsideDown(state.is_upside_down)),
+                                ));
+                                i += 3;
+                            } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -319,6 +725,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                         }
                     }
                 } else {
+                    truncated = true;
                     i += 1;
                 }
             }
@@ -346,6 +753,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -362,6 +770,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -378,6 +787,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -394,6 +804,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -412,7 +823,12 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                     (width_bytes as usize).saturating_mul(height as usize);
                                 let start = i + 8;
                                 let end = start.saturating_add(data_len);
-                                if end <= data.len() {
+                                if options.max_raster_bytes.is_some_and(|max| data_len > max)
+                                    && end <= data.len()
+                                {
+                                    // Más grande que el tope: se saltea sin emitir la imagen.
+                                    i = end;
+                                } else if end <= data.len() {
                                     let img = data[start..end].to_vec();
                                     commands.push((
                                         state.clone(),
@@ -426,85 +842,120 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                     i = end;
                                 } else {
                                     // Truncado; consumir cabecera y seguir.
+                                    truncated = true;
                                     i += 2;
                                 }
                             } else {
+                                // Sin la cabecera entera; con otro m no es un raster.
+                                truncated = data.get(i + 2).is_none_or(|&m| m == 0x30);
                                 i += 2;
                             }
                         }
                         0x28 => {
-                            // GS ( k  pL pH cn fn ...
-                            if i + 5 < data.len() && data[i + 2] == 0x6B {
+                            // GS ( fn pL pH ... : bloques agrupados con longitud explícita.
+                            // GS ( k con cn = 49 es QR; el resto se consume entero por longitud
+                            // para que sus parámetros no se filtren como texto.
+                            if i + 4 < data.len() {
+                                let function = data[i + 2];
                                 let p_l = data[i + 3] as usize;
                                 let p_h = data[i + 4] as usize;
                                 let total = p_l | (p_h << 8);
                                 let start = i + 5;
                                 let end = start.saturating_add(total);
-                                if end <= data.len() && total >= 2 {
-                                    let cn = data[start];
+                                if end > data.len() {
+                                    // Truncado; consumir cabecera y seguir.
+                                    truncated = true;
+                                    i += 2;
+                                } else if function == 0x6B && total >= 2 && data[start] == 0x31 {
                                     let fn_ = data[start + 1];
                                     let payload = &data[start + 2..end];
 
                                     // QR: cn = 49 (0x31)
-                                    if cn == 0x31 {
-                                        match fn_ {
-                                            0x41 => {
-                                                // Set model: [m, 0]
-                                                if payload.len() >= 1 {
-                                                    qr_model = payload[0];
-                                                }
-                                            }
-                                            0x43 => {
-                                                // Set module size: [n]
-                                                if payload.len() >= 1 {
-                                                    qr_module_size = payload[0];
-                                                }
+                                    match fn_ {
+                                        0x41 => {
+                                            // Set model: [n1, n2]; n1 fuera de rango se ignora.
+                                            if let Some(model) = payload
+                                                .first()
+                                                .copied()
+                                                .and_then(QrModel::from_byte)
+                                            {
+                                                qr_model = model;
                                             }
-                                            0x45 => {
-                                                // Set ECC: [n]
-                                                if payload.len() >= 1 {
-                                                    qr_ecc = payload[0];
-                                                }
-                                            }
-                                            0x50 => {
-                                                // Store data: [m=48, data...]
-                                                if payload.len() >= 1 {
-                                                    let m = payload[0];
-                                                    if m == 0x30 {
-                                                        qr_data.extend_from_slice(&payload[1..]);
-                                                    }
-                                                }
-                                            }
-                                            0x51 => {
-                                                // Print: [m=48]
-                                                if !qr_data.is_empty() {
-                                                    commands.push((
-                                                        state.clone(),
-                                                        CommandType::Control(Control::Qr {
-                                                            model: qr_model,
-                                                            module_size: qr_module_size,
-                                                            ecc: qr_ecc,
-                                                            data: qr_data.clone(),
-                                                        }),
-                                                    ));
-                                                    qr_data.clear();
-                                                }
-                                            }
-                                            _ => {}
                                         }
-                                        i = end;
+                                        // Set module size: [n]
+                                        0x43 if !payload.is_empty() => {
+                                            qr_module_size = payload[0];
+                                        }
+                                        // Set ECC: [n]
+                                        0x45 if !payload.is_empty() => {
+                                            qr_ecc = payload[0];
+                                        }
+                                        // Store data: [m=48, data...]
+                                        0x50 if payload.first() == Some(&0x30) => {
+                                            qr_data.extend_from_slice(&payload[1..]);
+                                        }
+                                        // Print: [m=48]
+                                        0x51 if !qr_data.is_empty() => {
+                                            commands.push((
+                                                state.clone(),
+                                                CommandType::Control(Control::Qr {
+                                                    model: qr_model,
+                                                    module_size: qr_module_size,
+                                                    ecc: qr_ecc,
+                                                    data: qr_data.clone(),
+                                                }),
+                                            ));
+                                            qr_data.clear();
+                                        }
+                                        _ => {}
+                                    }
+                                    i = end;
+                                } else if function == 0x6B
+                                    && total >= 2
+                                    && matches!(data[start], 0x35 | 0x36)
+                                {
+                                    // cn = 53 (Aztec) / 54 (DataMatrix). Los ajustes de tamaño y
+                                    // modo se consumen sin efecto; Store reemplaza el símbolo
+                                    // guardado y Print lo emite sin borrarlo (se puede repetir).
+                                    let (kind, stored) = if data[start] == 0x35 {
+                                        (TwoDimKind::Aztec, &mut aztec_data)
                                     } else {
-                                        // Otro GS ( k
-                                        commands.push((
-                                            state.clone(),
-                                            CommandType::Control(Control::GsUnknown(0x28)),
-                                        ));
-                                        i += 2;
+                                        (TwoDimKind::DataMatrix, &mut datamatrix_data)
+                                    };
+                                    let fn_ = data[start + 1];
+                                    let payload = &data[start + 2..end];
+                                    match fn_ {
+                                        // Store data: [m=48, data...]
+                                        0x50 if payload.first() == Some(&0x30) => {
+                                            stored.clear();
+                                            stored.extend_from_slice(&payload[1..]);
+                                        }
+                                        // Print: [m=48]
+                                        0x51 if !stored.is_empty() => {
+                                            commands.push((
+                                                state.clone(),
+                                                CommandType::Control(Control::TwoDimBarcode {
+                                                    kind,
+                                                    data: stored.clone(),
+                                                }),
+                                            ));
+                                        }
+                                        _ => {}
                                     }
+                                    i = end;
                                 } else {
-                                    i += 2;
+                                    // Otro GS ( X (GS ( H, GS ( N, GS ( E, PDF417...)
+                                    commands.push((
+                                        state.clone(),
+                                        CommandType::Control(Control::GsBlock {
+                                            function,
+                                            len: total as u16,
+                                        }),
+                                    ));
+                                    i = end;
                                 }
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -518,6 +969,8 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                     while j < data.len() && data[j] != 0x00 {
                                         j += 1;
                                     }
+                                    // Sin NUL todavía puede faltar parte de los datos.
+                                    truncated = j == data.len();
                                     let end = j.min(data.len());
                                     let payload = data[i + 3..end].to_vec();
                                     commands.push((
@@ -543,13 +996,16 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                             ));
                                             i = end;
                                         } else {
+                                            truncated = true;
                                             i += 2;
                                         }
                                     } else {
+                                        truncated = true;
                                         i += 2;
                                     }
                                 }
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -558,11 +1014,11 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                             if i + 2 < data.len() {
                                 let n = data[i + 2];
                                 // ESC/POS: low nibble = width, high nibble = height.
-                                let width = n & 0x0F;
-                                let height = (n >> 4) & 0x0F;
-                                state.char_width_mul = width.saturating_add(1);
-                                state.char_height_mul = height.saturating_add(1);
-                                state.font_scale = state.char_height_mul as f32;
+                                // Solo 0..=7 (x1..x8) son válidos: drivers rotos mandan 0xFF y
+                                // eso no debe convertirse en una letra x16.
+                                let width = (n & 0x0F).min(7);
+                                let height = (n >> 4).min(7);
+                                state.set_char_size(width + 1, height + 1);
                                 commands.push((
                                     state.clone(),
                                     CommandType::Control(Control::Size {
@@ -573,14 +1029,64 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
                         0x56 => {
-                            // GS V (Cut)
-                            commands.push((state.clone(), CommandType::Control(Control::Cut)));
-                            // hack: saltar args comunes
-                            i += 3;
+                            // GS V m: 0/48 corte total, 1/49 parcial.
+                            // GS V m n: 65/97 total y 66/98 parcial después de avanzar n puntos.
+                            match data.get(i + 2) {
+                                Some(&m @ (65 | 66 | 97 | 98)) => {
+                                    if let Some(&feed) = data.get(i + 3) {
+                                        commands.push((
+                                            state.clone(),
+                                            CommandType::Control(Control::Cut {
+                                                partial: matches!(m, 66 | 98),
+                                                feed,
+                                            }),
+                                        ));
+                                        i += 4;
+                                    } else {
+                                        // Falta n: m tampoco es texto.
+                                        truncated = true;
+                                        i += 3;
+                                    }
+                                }
+                                Some(&m) => {
+                                    commands.push((
+                                        state.clone(),
+                                        CommandType::Control(Control::Cut {
+                                            partial: matches!(m, 1 | 49),
+                                            feed: 0,
+                                        }),
+                                    ));
+                                    i += 3;
+                                }
+                                None => {
+                                    truncated = true;
+                                    i += 2;
+                                }
+                            }
+                        }
+                        0x4C | 0x57 => {
+                            // GS L nL nH (margen izquierdo) / GS W nL nH (ancho del área de
+                            // impresión), en puntos.
+                            if i + 3 < data.len() {
+                                let dots = u16::from_le_bytes([data[i + 2], data[i + 3]]);
+                                let control = if next_byte == 0x4C {
+                                    state.left_margin_dots = dots;
+                                    Control::LeftMargin { dots }
+                                } else {
+                                    state.print_width_dots = Some(dots);
+                                    Control::PrintAreaWidth { dots }
+                                };
+                                commands.push((state.clone(), CommandType::Control(control)));
+                                i += 4;
+                            } else {
+                                truncated = true;
+                                i += 2;
+                            }
                         }
                         0x42 => {
                             // GS B n (Reverse printing - white on black)
@@ -593,6 +1099,7 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                                 ));
                                 i += 3;
                             } else {
+                                truncated = true;
                                 i += 2;
                             }
                         }
@@ -605,10 +1112,51 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                         }
                     }
                 } else {
+                    truncated = true;
                     i += 1;
                 }
             }
 
+            // FS: se interpretan FS & / FS . (modo de doble byte) y FS p (logo NV).
+            0x1C => match data.get(i + 1) {
+                Some(&0x70) => {
+                    // FS p n m: el logo está guardado en la impresora; el stream solo lo nombra.
+                    if i + 3 < data.len() {
+                        commands.push((
+                            state.clone(),
+                            CommandType::Control(Control::NvLogo {
+                                n: data[i + 2],
+                                m: data[i + 3],
+                            }),
+                        ));
+                        i += 4;
+                    } else {
+                        truncated = true;
+                        i += 2;
+                    }
+                }
+                Some(&0x26) => {
+                    state.double_byte = true;
+                    commands.push((
+                        state.clone(),
+                        CommandType::Control(Control::DoubleByteMode(true)),
+                    ));
+                    i += 2;
+                }
+                Some(&0x2E) => {
+                    state.double_byte = false;
+                    commands.push((
+                        state.clone(),
+                        CommandType::Control(Control::DoubleByteMode(false)),
+                    ));
+                    i += 2;
+                }
+                _ => {
+                    commands.push((state.clone(), CommandType::Unknown(byte)));
+                    i += 1;
+                }
+            },
+
             // Texto
             _ => {
                 let mut text_bytes = Vec::new();
@@ -627,7 +1175,12 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                 }
 
                 if !text_bytes.is_empty() {
-                    let text = decode_text(&text_bytes, active_codepage);
+                    let text_codepage = if state.double_byte {
+                        active_codepage.double_byte()
+                    } else {
+                        active_codepage
+                    };
+                    let text = decode_text(&text_bytes, text_codepage);
                     commands.push((state.clone(), CommandType::Text(text)));
                     i = j;
                 } else {
@@ -636,9 +1189,27 @@ pub fn parse_escpos(data: &[u8], codepage: CodePage) -> Vec<ParsedCommand> {
                 }
             }
         }
+
+        // Un comando al que le faltan bytes puede completarse con lo que llegue después: en un
+        // buffer que crece se deja pendiente, aunque ya haya emitido algo (GS k sin su NUL).
+        if truncated && defer_incomplete_tail {
+            commands.truncate(spans.len());
+            break;
+        }
+        // Un ESC/FS/GS descartado no emite nada: truncado o con parámetros inválidos, que
+        // consumen 1-2 bytes (los bloques guardados no emiten pero consumen más).
+        if matches!(byte, 0x1B..=0x1D)
+            && commands.len() == spans.len()
+            && (truncated || i - start <= 2)
+        {
+            discarded.push(start..i);
+        }
+
+        // Todos los comandos emitidos en esta vuelta provienen de los mismos bytes.
+        spans.resize(commands.len(), start..i);
     }
 
-    commands
+    (commands, spans, discarded)
 }
 
 #[cfg(test)]
@@ -658,7 +1229,7 @@ mod tests {
     #[test]
     fn parses_basic_text_and_newline() {
         let data = b"Hola\n";
-        let parsed = parse_escpos(data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(data, CodePage::Utf8Lossy);
         assert!(parsed
             .iter()
             .any(|(_, c)| matches!(c, CommandType::Text(t) if t.contains("Hola"))));
@@ -670,7 +1241,7 @@ mod tests {
     #[test]
     fn esc_init_resets_state() {
         let data = [0x1B, 0x45, 0x01, b'A', 0x1B, 0x40, b'B'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
 
         // A debe estar en bold
         let a_state = parsed
@@ -695,11 +1266,11 @@ mod tests {
         // En UTF-8 estos bytes no son v e1lidos y salen como U+FFFD.
         let data = [0xFB, 0xAC, 0x3D, 0xAB];
 
-        let parsed_cp437 = parse_escpos(&data, CodePage::Cp437);
+        let parsed_cp437 = parse_escpos_simple(&data, CodePage::Cp437);
         let texts_cp437 = collect_text(&parsed_cp437);
         assert_eq!(texts_cp437, vec!["√¼=½".to_string()]);
 
-        let parsed_utf8 = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed_utf8 = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         let texts_utf8 = collect_text(&parsed_utf8);
         assert_eq!(texts_utf8.len(), 1);
         assert_ne!(texts_utf8[0], "√¼=½");
@@ -707,6 +1278,53 @@ mod tests {
         assert!(!texts_utf8[0].contains('\u{FFFD}'));
     }
 
+    #[test]
+    fn codepages_decode_their_distinctive_bytes() {
+        let cases = [
+            (CodePage::Pc858, 0xD5, "€"),
+            (CodePage::Iso88591, 0xA4, "¤"),
+            (CodePage::Iso88591, 0xF1, "ñ"),
+            (CodePage::Cp866, 0x80, "А"),
+            (CodePage::Cp860, 0x84, "ã"),
+            (CodePage::Cp865, 0x9B, "ø"),
+        ];
+        for (codepage, byte, expected) in cases {
+            let parsed = parse_escpos_simple(&[byte], codepage);
+            assert_eq!(
+                collect_text(&parsed),
+                vec![expected],
+                "{codepage:?} {byte:#04x}"
+            );
+        }
+    }
+
+    #[test]
+    fn less_common_codepages_decode_their_tables() {
+        let cases = [
+            (CodePage::Cp857, 0x98, "İ"),
+            (CodePage::Cp857, 0x9E, "Ş"),
+            // Hueco de la tabla (donde PC858 pone el €): sale U+FFFD en vez de fallar.
+            (CodePage::Cp857, 0xD5, "\u{FFFD}"),
+            (CodePage::Cp737, 0x80, "Α"),
+            (CodePage::Cp852, 0xA5, "ą"),
+            (CodePage::Cp720, 0xA0, "ب"),
+            (CodePage::Cp775, 0xD0, "ą"),
+            (CodePage::Cp855, 0x80, "ђ"),
+            (CodePage::Cp861, 0x8C, "ð"),
+            (CodePage::Cp862, 0x80, "א"),
+            (CodePage::Cp864, 0xB0, "٠"),
+            (CodePage::Cp864, 0xA6, "\u{FFFD}"),
+            (CodePage::Cp869, 0xA4, "Α"),
+        ];
+        for (codepage, byte, expected) in cases {
+            assert_eq!(
+                decode_text(&[byte], codepage),
+                expected,
+                "{codepage:?} {byte:#04x}"
+            );
+        }
+    }
+
     #[test]
     fn codepage_cp850_decodes_extended_bytes() {
         // Para CP850, verificamos que se decodifica distinto a UTF-8.
@@ -714,11 +1332,11 @@ mod tests {
         let data = [0x82];
         let expected = String::from_cp::<Cp850>(&data);
 
-        let parsed_cp850 = parse_escpos(&data, CodePage::Cp850);
+        let parsed_cp850 = parse_escpos_simple(&data, CodePage::Cp850);
         let texts_cp850 = collect_text(&parsed_cp850);
         assert_eq!(texts_cp850, vec![expected.clone()]);
 
-        let parsed_utf8 = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed_utf8 = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         let texts_utf8 = collect_text(&parsed_utf8);
         assert_eq!(texts_utf8.len(), 1);
         // En modo UTF-8 (auto), si no es UTF-8 válido cae a Windows-1252.
@@ -731,17 +1349,27 @@ mod tests {
         // En Windows-1252: 0xA1 = '¡'.
         // En UTF-8 estricto esto NO es válido como byte suelto.
         let data = [0xA1, b'G', b'r', b'a', b'c', b'i', b'a', b's'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         let text = collect_text(&parsed).concat();
         assert!(text.contains("¡Gracias"));
     }
 
+    #[test]
+    fn utf8_text_with_one_stray_cp1252_byte_keeps_the_utf8_part() {
+        // "Café ñandú " en UTF-8 + 0xA1 ('¡' en 1252) + "Gracias!".
+        let mut data = "Café ñandú ".as_bytes().to_vec();
+        data.push(0xA1);
+        data.extend_from_slice(b"Gracias!");
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        assert_eq!(collect_text(&parsed).concat(), "Café ñandú ¡Gracias!");
+    }
+
     #[test]
     fn esc_t_selects_windows1252_for_subsequent_text() {
         // ESC t 16 (Windows-1252) seguido de 0xA1 ('¡') debe decodificar correctamente.
         let data = [0x1B, 0x74, 0x10, 0xA1, b'H', b'o', b'l', b'a'];
         // Arrancamos con un codepage distinto para asegurar que el cambio ocurre.
-        let parsed = parse_escpos(&data, CodePage::Cp437);
+        let parsed = parse_escpos_simple(&data, CodePage::Cp437);
         let text = collect_text(&parsed).concat();
         assert!(text.contains("¡Hola"));
         assert!(parsed.iter().any(|(_, c)| matches!(
@@ -750,13 +1378,127 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn esc_t_switches_from_cp437_to_cp866_mid_line() {
+        // 0x82 es 'é' en CP437 y 'В' (cirílica) en CP866; el codepage inicial vale hasta ESC t.
+        let data = [0x82, b'-', 0x1B, 0x74, 0x11, 0x82];
+        let parsed = parse_escpos_simple(&data, CodePage::Cp437);
+        assert_eq!(collect_text(&parsed), vec!["é-", "В"]);
+        assert!(matches!(
+            parsed[1].1,
+            CommandType::Control(Control::CodePage(CodePage::Cp866))
+        ));
+    }
+
+    #[test]
+    fn default_alignment_applies_until_esc_a_and_after_esc_at() {
+        let options = ParseOptions {
+            codepage: CodePage::Cp437,
+            alignment: Align::Center,
+            ..ParseOptions::default()
+        };
+        let data = b"Antes\n\x1ba\x02Derecha\n\x1b@Reset\n";
+        let aligned: Vec<(String, Align)> = parse_escpos(data, &options)
+            .into_iter()
+            .filter_map(|(state, cmd)| match cmd {
+                CommandType::Text(t) => Some((t, state.alignment)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            aligned,
+            vec![
+                ("Antes".to_string(), Align::Center),
+                ("Derecha".to_string(), Align::Right),
+                ("Reset".to_string(), Align::Center),
+            ]
+        );
+        // Con solo un codepage, la alineación inicial es la izquierda.
+        let parsed = parse_escpos_simple(b"Hola", CodePage::Cp437);
+        assert_eq!(parsed[0].0.alignment, Align::Left);
+    }
+
+    #[test]
+    fn cr_as_newline_breaks_lone_cr_but_not_cr_lf() {
+        let data = b"A\rB\r\nC";
+        let newlines = |parsed: &[ParsedCommand]| {
+            parsed
+                .iter()
+                .filter(|(_, c)| matches!(c, CommandType::Control(Control::Newline)))
+                .count()
+        };
+        assert_eq!(newlines(&parse_escpos_simple(data, CodePage::Cp437)), 1);
+        let options = ParseOptions {
+            cr_as_newline: true,
+            ..CodePage::Cp437.into()
+        };
+        let parsed = parse_escpos(data, &options);
+        assert_eq!(newlines(&parsed), 2);
+        assert_eq!(collect_text(&parsed), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn rasters_over_the_cap_are_skipped_whole() {
+        // GS v 0 de 2x2 bytes (4 de datos) seguido de texto.
+        let data = b"\x1dv0\x00\x02\x00\x02\x00\xff\xff\xff\xffOK";
+        let options = ParseOptions {
+            max_raster_bytes: Some(3),
+            ..CodePage::Cp437.into()
+        };
+        let parsed = parse_escpos(data, &options);
+        assert!(!parsed
+            .iter()
+            .any(|(_, c)| matches!(c, CommandType::Control(Control::RasterImage { .. }))));
+        assert_eq!(collect_text(&parsed), vec!["OK"]);
+
+        let parsed = parse_escpos_simple(data, CodePage::Cp437);
+        assert!(parsed.iter().any(|(_, c)| matches!(
+            c,
+            CommandType::Control(Control::RasterImage { height: 2, .. })
+        )));
+    }
+
+    #[test]
+    fn initial_codepage_decodes_text_until_the_first_esc_t() {
+        // Sin ESC t el stream se lee con la tabla inicial (CP850: 0x82 = 'é').
+        let data = [0x82, b'\n', 0x1B, 0x74, 0x10, 0x82];
+        let parsed = parse_escpos_simple(&data, CodePage::Cp850);
+        assert_eq!(collect_text(&parsed), vec!["é", "\u{201A}"]);
+        let parsed = parse_escpos_simple(&data[..2], CodePage::Windows1252);
+        assert_eq!(collect_text(&parsed), vec!["\u{201A}"]);
+    }
+
+    #[test]
+    fn esc_t_table_covers_epson_numbers_and_flags_fallbacks() {
+        assert_eq!(esc_t_codepage(0), (CodePage::Cp437, true));
+        assert_eq!(esc_t_codepage(4), (CodePage::Cp863, true));
+        assert_eq!(esc_t_codepage(5), (CodePage::Cp865, true));
+        assert_eq!(esc_t_codepage(18), (CodePage::Cp852, true));
+        assert_eq!(esc_t_codepage(46), (CodePage::Windows1251, true));
+        assert_eq!(esc_t_codepage(52), (CodePage::Windows1258, true));
+        assert_eq!(esc_t_codepage(12), (CodePage::Cp857, false));
+        assert_eq!(esc_t_codepage(255), (CodePage::Cp437, false));
+
+        // PC858 ya no se aproxima con CP850: 0xD5 es el euro.
+        let data = [0x1B, 0x74, 19, 0xD5, 0x1B, 0x74, 21, b'A'];
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        assert_eq!(collect_text(&parsed).concat(), "€A");
+        assert!(parsed.iter().any(|(_, c)| matches!(
+            c,
+            CommandType::Control(Control::CodePageFallback {
+                n: 21,
+                codepage: CodePage::Windows874
+            })
+        )));
+    }
+
     #[test]
     fn parses_raster_image_gs_v_0() {
         // GS v 0 m xL xH yL yH d...
         // 1 byte por fila => 8 pixeles de ancho, 1 fila.
         // 0x80: primer pixel negro.
         let data = [0x1D, 0x76, 0x30, 0x00, 0x01, 0x00, 0x01, 0x00, 0x80];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
 
         assert!(parsed.iter().any(|(_, c)| match c {
             CommandType::Control(Control::RasterImage {
@@ -790,7 +1532,7 @@ mod tests {
         // Print
         bytes.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x51, 0x30]);
 
-        let parsed = parse_escpos(&bytes, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&bytes, CodePage::Utf8Lossy);
         assert!(parsed.iter().any(|(_, c)| match c {
             CommandType::Control(Control::Qr { data, .. }) => data == b"HI",
             _ => false,
@@ -801,7 +1543,7 @@ mod tests {
     fn gs_bang_size_0x10_is_double_height_not_double_width() {
         // GS ! 0x10 => height x2, width x1.
         let data = [0x1D, 0x21, 0x10, b'A'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
 
         let a_state = parsed
             .iter()
@@ -813,11 +1555,51 @@ mod tests {
         assert_eq!(a_state.char_height_mul, 2);
     }
 
+    #[test]
+    fn gs_bang_out_of_range_nibbles_clamp_to_8x8() {
+        let data = [0x1D, 0x21, 0xFF, b'A'];
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        assert!(parsed.iter().any(|(_, c)| matches!(
+            c,
+            CommandType::Control(Control::Size {
+                raw: 0xFF,
+                width: 7,
+                height: 7
+            })
+        )));
+        let (a_state, _) = parsed
+            .iter()
+            .find(|(_, c)| matches!(c, CommandType::Text(t) if t == "A"))
+            .unwrap();
+        assert_eq!((a_state.char_width_mul, a_state.char_height_mul), (8, 8));
+        assert_eq!(a_state.font_scale, 8.0);
+    }
+
+    #[test]
+    fn gs_bang_split_at_the_end_is_deferred_not_read_as_text() {
+        // El parámetro llega en el siguiente chunk: no se emite nada del GS !.
+        for partial in [&b"A\x1d"[..], &b"A\x1d!"[..]] {
+            let (commands, spans) = parse_escpos_incremental(partial, &CodePage::Utf8Lossy.into());
+            assert_eq!(spans.last().map(|s| s.end), Some(1));
+            assert!(commands
+                .iter()
+                .all(|(_, c)| !matches!(c, CommandType::Text(t) if t.contains('!'))));
+        }
+        let (commands, spans) =
+            parse_escpos_incremental(b"A\x1d!\x11B", &CodePage::Utf8Lossy.into());
+        assert_eq!(spans.last().map(|s| s.end), Some(5));
+        let (b_state, _) = commands
+            .iter()
+            .find(|(_, c)| matches!(c, CommandType::Text(t) if t == "B"))
+            .unwrap();
+        assert_eq!((b_state.char_width_mul, b_state.char_height_mul), (2, 2));
+    }
+
     #[test]
     fn gs_h_parameter_is_consumed_not_emitted_as_text() {
         // Algunos sistemas mandan GS H '2' (ASCII) y no queremos ver un "2" impreso.
         let data = [0x1D, 0x48, b'2', b'A'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         let texts = collect_text(&parsed).concat();
         assert!(!texts.contains('2'));
         assert!(texts.contains('A'));
@@ -827,7 +1609,7 @@ mod tests {
     fn esc_dollar_sets_absolute_position() {
         // ESC $ 100 0 (posición 100 dots)
         let data = [0x1B, 0x24, 0x64, 0x00, b'A'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         assert!(parsed.iter().any(|(s, _)| s.cursor_x == Some(100)));
         assert!(parsed.iter().any(|(_, c)| matches!(
             c,
@@ -835,35 +1617,290 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn esc_backslash_moves_the_cursor_relative_to_the_last_position() {
+        let data = [
+            0x1B, 0x24, 0x30, 0x00, // ESC $ 48
+            0x1B, 0x5C, 0x18, 0x00, b'A', // ESC \ +24
+            0x1B, 0x5C, 0xE8, 0xFF, b'B', // ESC \ -24
+            0x1B, 0x5C, 0x00, 0x80, b'C', // ESC \ -32768: no baja de 0
+            0x1D, 0x57, 0x64, 0x00, // GS W 100
+            0x1B, 0x5C, 0xFF, 0x7F, b'D', // ESC \ +32767: tope en el área de impresión
+        ];
+        let positions: Vec<_> = parse_escpos_simple(&data, CodePage::Utf8Lossy)
+            .into_iter()
+            .filter(|(_, c)| matches!(c, CommandType::Text(_)))
+            .map(|(s, _)| s.cursor_x)
+            .collect();
+        assert_eq!(positions, vec![Some(72), Some(48), Some(0), Some(100)]);
+    }
+
+    #[test]
+    fn esc_dollar_combines_the_low_and_high_bytes() {
+        let data = [0x1B, 0x24, 0x30, 0x00, b'A', 0x1B, 0x24, 0x2C, 0x01, b'B'];
+        let positions: Vec<_> = parse_escpos_simple(&data, CodePage::Utf8Lossy)
+            .into_iter()
+            .filter(|(_, c)| matches!(c, CommandType::Text(_)))
+            .map(|(s, _)| s.cursor_x)
+            .collect();
+        assert_eq!(positions, vec![Some(48), Some(300)]);
+    }
+
     #[test]
     fn esc_minus_enables_underline() {
-        let data = [0x1B, 0x2D, 0x01, b'A'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
-        let a_state = parsed
+        let data = [0x1B, 0x2D, 0x01, b'A', 0x1B, 0x2D, 0x00, b'B'];
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        let underlined = |ch: char| {
+            parsed
+                .iter()
+                .find(|(_, c)| matches!(c, CommandType::Text(t) if t.contains(ch)))
+                .map(|(s, _)| s.is_underline)
+                .unwrap()
+        };
+        assert!(underlined('A'));
+        assert!(!underlined('B'));
+
+        let toggles: Vec<bool> = parsed
             .iter()
-            .find(|(_, c)| matches!(c, CommandType::Text(t) if t.contains('A')))
-            .map(|(s, _)| s)
+            .filter_map(|(_, c)| match c {
+                CommandType::Control(Control::Underline(on)) => Some(*on),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(toggles, [true, false]);
+    }
+
+    #[test]
+    fn esc_minus_sets_underline_weight() {
+        let data = [
+            0x1B, 0x2D, 0x02, b'A', 0x1B, 0x2D, b'1', b'B', 0x1B, 0x2D, b'0', b'C', 0x1B, 0x21,
+            0x80, b'D',
+        ];
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        let weight_of = |ch: char| {
+            parsed
+                .iter()
+                .find(|(_, c)| matches!(c, CommandType::Text(t) if t.contains(ch)))
+                .map(|(s, _)| (s.is_underline, s.underline_weight))
+                .unwrap()
+        };
+        assert_eq!(weight_of('A'), (true, 2));
+        assert_eq!(weight_of('B'), (true, 1));
+        assert_eq!(weight_of('C'), (false, 0));
+        assert_eq!(weight_of('D'), (true, 1));
+    }
+
+    #[test]
+    fn spans_cover_the_bytes_of_each_command() {
+        let data = [
+            0x1B, 0x45, 0x01, b'H', b'o', b'l', b'a', b'\n', 0x1D, 0x56, 0x01,
+        ];
+        let (commands, spans) = parse_escpos_with_spans(&data, &CodePage::Cp437.into());
+        assert_eq!(commands.len(), spans.len());
+        assert_eq!(spans, vec![0..3, 3..7, 7..8, 8..11]);
+        assert!(matches!(&commands[1].1, CommandType::Text(t) if t == "Hola"));
+    }
+
+    fn gs_k_2d(cn: u8, fn_: u8, params: &[u8]) -> Vec<u8> {
+        let len = (params.len() + 2) as u16;
+        let mut out = vec![0x1D, 0x28, 0x6B];
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&[cn, fn_]);
+        out.extend_from_slice(params);
+        out
+    }
+
+    #[test]
+    fn aztec_and_datamatrix_blocks_are_consumed_and_printed() {
+        for (cn, kind) in [(53, TwoDimKind::Aztec), (54, TwoDimKind::DataMatrix)] {
+            let mut data = Vec::new();
+            data.extend(gs_k_2d(cn, 0x43, &[6])); // tamaño de módulo
+            let mut store = vec![0x30];
+            store.extend_from_slice(b"M1PEREZ/JUAN EABC123");
+            data.extend(gs_k_2d(cn, 0x50, &store));
+            data.extend(gs_k_2d(cn, 0x51, &[0x30]));
+            data.extend_from_slice(b"OK\n");
+
+            let parsed = parse_escpos_simple(&data, CodePage::Cp437);
+            let cmds: Vec<&CommandType> = parsed.iter().map(|(_, c)| c).collect();
+            assert_eq!(cmds.len(), 3, "cn={cn}: {cmds:?}");
+            match cmds[0] {
+                CommandType::Control(Control::TwoDimBarcode { kind: k, data }) => {
+                    assert_eq!(*k, kind);
+                    assert_eq!(data.as_slice(), b"M1PEREZ/JUAN EABC123");
+                }
+                other => panic!("cn={cn}: {other:?}"),
+            }
+            assert!(matches!(cmds[1], CommandType::Text(t) if t == "OK"));
+        }
+    }
+
+    #[test]
+    fn a_stored_two_dimensional_symbol_can_be_printed_again() {
+        let store = |payload: &[u8]| {
+            let mut params = vec![0x30];
+            params.extend_from_slice(payload);
+            gs_k_2d(54, 0x50, &params)
+        };
+        let print = gs_k_2d(54, 0x51, &[0x30]);
+        let mut data = store(b"ORDEN-1");
+        data.extend(&print);
+        data.extend(&print);
+        // Un Store nuevo reemplaza el símbolo guardado.
+        data.extend(store(b"ORDEN-2"));
+        data.extend(&print);
+
+        let printed: Vec<Vec<u8>> = parse_escpos_simple(&data, CodePage::Cp437)
+            .into_iter()
+            .filter_map(|(_, c)| match c {
+                CommandType::Control(Control::TwoDimBarcode { data, .. }) => Some(data),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(printed, [&b"ORDEN-1"[..], b"ORDEN-1", b"ORDEN-2"]);
+    }
+
+    #[test]
+    fn gs_v_keeps_the_cut_mode_and_feed_amount() {
+        let cuts = |data: &[u8]| -> Vec<Control> {
+            parse_escpos_simple(data, CodePage::Cp437)
+                .into_iter()
+                .filter_map(|(_, c)| match c {
+                    CommandType::Control(cut @ Control::Cut { .. }) => Some(cut),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(
+            cuts(&[0x1D, 0x56, 66, 0x50, b'A']),
+            vec![Control::Cut {
+                partial: true,
+                feed: 0x50
+            }]
+        );
+        assert_eq!(
+            cuts(&[0x1D, 0x56, 0x00, 0x1D, 0x56, 49, 0x1D, 0x56, 65, 3]),
+            vec![
+                Control::Cut {
+                    partial: false,
+                    feed: 0
+                },
+                Control::Cut {
+                    partial: true,
+                    feed: 0
+                },
+                Control::Cut {
+                    partial: false,
+                    feed: 3
+                },
+            ]
+        );
+        // El parámetro n no se filtra como texto.
+        let parsed = parse_escpos_simple(&[0x1D, 0x56, 66, 0x50, b'A'], CodePage::Cp437);
+        assert_eq!(collect_text(&parsed).concat(), "A");
+
+        // Truncado antes de n: se descarta entero (m, una letra, no sale como texto) y en vivo
+        // espera a que llegue n.
+        for m in [65, 66, 97, 98] {
+            let data = [b'A', 0x1D, 0x56, m];
+            let (parsed, _, discarded) = parse_escpos_checked(&data, &CodePage::Cp437.into());
+            assert_eq!(collect_text(&parsed), vec!["A"], "m = {m}");
+            assert_eq!(discarded, vec![1..4], "m = {m}");
+            let (parsed, spans) = parse_escpos_incremental(&data, &CodePage::Cp437.into());
+            assert_eq!(parsed.len(), 1, "m = {m}");
+            assert_eq!(spans, vec![0..1], "m = {m}");
+        }
+    }
+
+    #[test]
+    fn legacy_esc_i_and_esc_m_cut_the_paper() {
+        for cut in [0x69, 0x6D] {
+            let data = [b'A', b'\n', 0x1B, cut, b'B', b'\n'];
+            let parsed = parse_escpos_simple(&data, CodePage::Cp437);
+            let cmds: Vec<&CommandType> = parsed.iter().map(|(_, c)| c).collect();
+            assert!(
+                matches!(cmds[2], CommandType::Control(Control::Cut { feed: 0, .. })),
+                "ESC {cut:02X}"
+            );
+            assert!(
+                matches!(cmds[3], CommandType::Text(t) if t == "B"),
+                "ESC {cut:02X}"
+            );
+            assert!(!cmds
+                .iter()
+                .any(|c| matches!(c, CommandType::Control(Control::EscUnknown(_)))));
+        }
+    }
+
+    #[test]
+    fn esc_at_restores_power_on_modes() {
+        let mut data = vec![
+            0x1B, 0x45, 0x01, // ESC E 1
+            0x1B, 0x2D, 0x02, // ESC - 2
+            0x1D, 0x42, 0x01, // GS B 1
+            0x1B, 0x7B, 0x01, // ESC { 1
+            0x1B, 0x4D, 0x01, // ESC M 1
+            0x1B, 0x61, 0x01, // ESC a 1
+            0x1D, 0x21, 0x11, // GS ! 0x11
+            0x1B, 0x33, 0x40, // ESC 3 64
+            0x1D, 0x68, 0x30, // GS h 48
+            0x1D, 0x77, 0x02, // GS w 2
+            0x1D, 0x48, 0x02, // GS H 2
+            0x1B, 0x74, 0x10, // ESC t 16 (Windows-1252)
+        ];
+        data.extend_from_slice(b"X\n");
+        data.extend_from_slice(&[0x1B, 0x40, 0x82, b'\n']);
+
+        let parsed = parse_escpos_simple(&data, CodePage::Cp437);
+        let (before, _) = parsed
+            .iter()
+            .find(|(_, c)| matches!(c, CommandType::Text(t) if t == "X"))
             .unwrap();
-        assert!(a_state.is_underline);
+        assert!(before.is_bold && before.is_underline && before.is_reverse && before.is_font_b);
+        assert!(before.is_upside_down);
+        assert_eq!(before.barcode_height, 48);
+
+        let (after, text) = parsed
+            .iter()
+            .rev()
+            .find_map(|(s, c)| match c {
+                CommandType::Text(t) => Some((s, t.clone())),
+                _ => None,
+            })
+            .unwrap();
+        // Todos los campos vuelven al default, incluida la configuración de barcode.
+        assert_eq!(*after, PrinterState::default());
+        // Y la tabla de caracteres vuelve a la del visor (0x82 = 'é' en CP437, no '‚').
+        assert_eq!(text, "é");
     }
 
     #[test]
     fn gs_b_enables_reverse() {
-        let data = [0x1D, 0x42, 0x01, b'A'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
-        let a_state = parsed
+        // Solo cuenta el bit 0: 2 apaga, 3 enciende.
+        let data = [
+            0x1D, 0x42, 0x01, b'A', 0x1D, 0x42, 0x02, b'B', 0x1D, 0x42, 0x03, b'C',
+        ];
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        let reversed = |ch: char| {
+            parsed
+                .iter()
+                .find(|(_, c)| matches!(c, CommandType::Text(t) if t.contains(ch)))
+                .map(|(s, _)| s.is_reverse)
+                .unwrap()
+        };
+        assert!(reversed('A'));
+        assert!(!reversed('B'));
+        assert!(reversed('C'));
+        assert!(parsed
             .iter()
-            .find(|(_, c)| matches!(c, CommandType::Text(t) if t.contains('A')))
-            .map(|(s, _)| s)
-            .unwrap();
-        assert!(a_state.is_reverse);
+            .all(|(_, c)| !matches!(c, CommandType::Control(Control::GsUnknown(_)))));
     }
 
     #[test]
     fn esc_bang_master_select_bold_and_double_height() {
         // ESC ! 0x18 = bold (bit 3) + double height (bit 4)
         let data = [0x1B, 0x21, 0x18, b'A'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         let a_state = parsed
             .iter()
             .find(|(_, c)| matches!(c, CommandType::Text(t) if t.contains('A')))
@@ -873,18 +1910,141 @@ mod tests {
         assert_eq!(a_state.char_height_mul, 2);
     }
 
+    #[test]
+    fn esc_bang_decodes_every_bit_and_clears_the_rest() {
+        let styles: Vec<_> =
+            parse_escpos_simple(b"\x1b!\x38A\x1b!\x81B\x1b!\x00C", CodePage::Utf8Lossy)
+                .into_iter()
+                .filter(|(_, c)| matches!(c, CommandType::Text(_)))
+                .map(|(s, _)| {
+                    (
+                        s.is_bold,
+                        s.char_width_mul,
+                        s.char_height_mul,
+                        s.is_font_b,
+                        s.is_underline,
+                    )
+                })
+                .collect();
+        assert_eq!(
+            styles,
+            vec![
+                (true, 2, 2, false, false),
+                (false, 1, 1, true, true),
+                (false, 1, 1, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn esc_bang_and_gs_bang_share_the_size_and_the_last_one_wins() {
+        // ESC ! 0x30 (doble ancho + alto), luego GS ! 0x00 (normal).
+        let data = [0x1B, 0x21, 0x30, b'A', 0x1D, 0x21, 0x00, b'B'];
+        let sizes: Vec<_> = parse_escpos_simple(&data, CodePage::Utf8Lossy)
+            .into_iter()
+            .filter(|(_, c)| matches!(c, CommandType::Text(_)))
+            .map(|(s, _)| (s.char_width_mul, s.char_height_mul, s.font_scale))
+            .collect();
+        assert_eq!(sizes, vec![(2, 2, 2.0), (1, 1, 1.0)]);
+
+        // Y al revés: GS ! 0x77 (8x8) y después ESC ! 0x00 lo devuelve a normal.
+        let data = [0x1D, 0x21, 0x77, b'A', 0x1B, 0x21, 0x00, b'B'];
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        let (last, _) = parsed
+            .iter()
+            .rfind(|(_, c)| matches!(c, CommandType::Text(_)))
+            .unwrap();
+        assert_eq!((last.char_width_mul, last.char_height_mul), (1, 1));
+    }
+
+    #[test]
+    fn incremental_parse_waits_for_the_rest_of_a_split_command() {
+        let (commands, _) = parse_escpos_incremental(b"Hola\x1b", &CodePage::Utf8Lossy.into());
+        assert_eq!(commands.len(), 1);
+        assert!(matches!(&commands[0].1, CommandType::Text(t) if t == "Hola"));
+
+        let (commands, spans) = parse_escpos_incremental(b"Hola\x1b@", &CodePage::Utf8Lossy.into());
+        assert_eq!(commands.len(), 2);
+        assert!(matches!(commands[1].1, CommandType::Control(Control::Init)));
+        assert_eq!(spans[1], 4..6);
+
+        // Raster a medio llegar: nada de su contenido se cuela como texto.
+        let mut raster = b"A\n\x1dv0\x00\x01\x00\x04\x00".to_vec();
+        raster.extend([0x30, 0x31]);
+        let (commands, _) = parse_escpos_incremental(&raster, &CodePage::Utf8Lossy.into());
+        assert_eq!(commands.len(), 2);
+        raster.extend([0x32, 0x33]);
+        let (commands, _) = parse_escpos_incremental(&raster, &CodePage::Utf8Lossy.into());
+        assert!(matches!(
+            commands[2].1,
+            CommandType::Control(Control::RasterImage { height: 4, .. })
+        ));
+
+        // Con el buffer completo un comando truncado se sigue descartando como antes.
+        let (commands, _) = parse_escpos_with_spans(b"Hola\x1b", &CodePage::Utf8Lossy.into());
+        assert_eq!(commands.len(), 1);
+
+        // Barcode terminado en NUL sin su NUL: pueden faltar dígitos, no se emite a medias.
+        let (commands, _) = parse_escpos_incremental(b"A\n\x1dk\x04123", &CodePage::Cp437.into());
+        assert_eq!(commands.len(), 2);
+        let (commands, _) =
+            parse_escpos_incremental(b"A\n\x1dk\x04123\x00", &CodePage::Cp437.into());
+        assert!(matches!(
+            &commands[2].1,
+            CommandType::Control(Control::Barcode { m: 4, data }) if data == b"123"
+        ));
+        // Al final del job completo se muestra con lo que haya.
+        let commands = parse_escpos_simple(b"A\n\x1dk\x04123", CodePage::Cp437);
+        assert_eq!(commands.len(), 3);
+    }
+
+    #[test]
+    fn incremental_and_full_parses_agree_once_the_buffer_is_complete() {
+        // GS v con m inválido a mitad del stream: se descarta, pero lo que sigue no espera.
+        let inputs: [&[u8]; 3] = [
+            b"\x1dv\x00TAIL TEXT\n",
+            b"A\x1b\x01B\n",
+            b"\x1dk\x41\x02AB\x1dv0\x00\x01\x00\x01\x00\xffC\n",
+        ];
+        let options = CodePage::Utf8Lossy.into();
+        for data in inputs {
+            let (full, full_spans) = parse_escpos_with_spans(data, &options);
+            let (incremental, incremental_spans) = parse_escpos_incremental(data, &options);
+            assert_eq!(format!("{full:?}"), format!("{incremental:?}"), "{data:?}");
+            assert_eq!(full_spans, incremental_spans, "{data:?}");
+        }
+        let (commands, _) = parse_escpos_incremental(b"\x1dv\x00TAIL TEXT\n", &options);
+        assert_eq!(commands.len(), 2);
+    }
+
     #[test]
     fn esc_3_sets_line_spacing() {
         let data = [0x1B, 0x33, 0x30]; // ESC 3 48
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         assert!(parsed.iter().any(|(s, _)| s.line_spacing == Some(48)));
     }
 
+    #[test]
+    fn esc_2_restores_the_default_line_spacing() {
+        let data = [0x1B, 0x33, 0x10, b'A', 0x1B, 0x32, b'B'];
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+        let spacing: Vec<_> = parsed
+            .iter()
+            .filter(|(_, c)| matches!(c, CommandType::Text(_)))
+            .map(|(s, _)| s.line_spacing)
+            .collect();
+        assert_eq!(spacing, vec![Some(16), None]);
+        assert!(matches!(
+            parsed[2].1,
+            CommandType::Control(Control::LineSpacingDefault)
+        ));
+    }
+
     #[test]
     fn esc_star_parses_bit_image() {
         // ESC * mode=0, width=2, data=[0x80, 0x40]
         let data = [0x1B, 0x2A, 0x00, 0x02, 0x00, 0x80, 0x40];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         assert!(parsed.iter().any(|(_, c)| matches!(
             c,
             CommandType::Control(Control::BitImage {
@@ -895,10 +2055,29 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn esc_star_consumes_exactly_one_column_per_dot_row_group() {
+        // Una columna de 8 puntos y otra de 24 (3 bytes); el texto sigue justo después.
+        let data = b"\x1b*\x00\x01\x00\x81A\x1b*\x21\x01\x00\x01\x02\x03B";
+        let parsed = parse_escpos_simple(data, CodePage::Utf8Lossy);
+        let commands: Vec<_> = parsed.into_iter().map(|(_, c)| c).collect();
+        assert!(matches!(
+            &commands[0],
+            CommandType::Control(Control::BitImage { mode: 0, width: 1, data }) if data == &[0x81]
+        ));
+        assert!(matches!(&commands[1], CommandType::Text(t) if t == "A"));
+        assert!(matches!(
+            &commands[2],
+            CommandType::Control(Control::BitImage { mode: 33, width: 1, data })
+                if data == &[1, 2, 3]
+        ));
+        assert!(matches!(&commands[3], CommandType::Text(t) if t == "B"));
+    }
+
     #[test]
     fn esc_m_selects_font_b() {
         let data = [0x1B, 0x4D, 0x01, b'A', 0x1B, 0x4D, 0x00, b'B'];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         
         let a_state = parsed
             .iter()
@@ -918,10 +2097,249 @@ mod tests {
     #[test]
     fn esc_p_opens_drawer() {
         let data = [0x1B, 0x70, 0x00, 0x19, 0xFA];
-        let parsed = parse_escpos(&data, CodePage::Utf8Lossy);
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
         assert!(parsed.iter().any(|(_, c)| matches!(
             c,
             CommandType::Control(Control::OpenDrawer)
         )));
     }
+
+    #[test]
+    fn gs_paren_blocks_are_consumed_by_length() {
+        // GS ( H pL=6 pH=0 fn=0x30 m=0x30 d1..d4, luego texto.
+        let mut data = vec![
+            0x1D, 0x28, 0x48, 0x06, 0x00, 0x30, 0x30, b'1', b'2', b'3', b'4',
+        ];
+        data.extend_from_slice(b"OK");
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+
+        assert_eq!(collect_text(&parsed), vec!["OK".to_string()]);
+        assert!(parsed.iter().any(|(_, c)| matches!(
+            c,
+            CommandType::Control(Control::GsBlock {
+                function: 0x48,
+                len: 6
+            })
+        )));
+    }
+
+    #[test]
+    fn esc_paren_a_beeps_and_other_esc_paren_blocks_are_consumed() {
+        // ESC ( A pL=3 pH=0 fn=97 n=3 t=2, luego ESC E 1 "Hola".
+        let mut data = vec![0x1B, 0x28, 0x41, 0x03, 0x00, 0x61, 0x03, 0x02];
+        data.extend_from_slice(&[0x1B, 0x45, 0x01]);
+        data.extend_from_slice(b"Hola");
+        // ESC ( Y pL=2 pH=0 d1 d2 (función desconocida) y "Fin".
+        data.extend_from_slice(&[0x1B, 0x28, 0x59, 0x02, 0x00, b'X', b'Y']);
+        data.extend_from_slice(b"Fin");
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+
+        assert_eq!(
+            collect_text(&parsed),
+            vec!["Hola".to_string(), "Fin".to_string()]
+        );
+        assert!(matches!(
+            parsed[0].1,
+            CommandType::Control(Control::Buzzer { count: 3 })
+        ));
+        assert!(matches!(
+            parsed[1].1,
+            CommandType::Control(Control::Bold(true))
+        ));
+        assert!(parsed.iter().any(|(_, c)| matches!(
+            c,
+            CommandType::Control(Control::EscBlock {
+                function: 0x59,
+                len: 2
+            })
+        )));
+    }
+
+    #[test]
+    fn gs_paren_e_and_n_blocks_do_not_leak_payload() {
+        // GS ( E pL=3 pH=0 fn=1 d1 d2 (user setting) + GS ( N pL=2 pH=0 fn=0x30 m=0x31
+        let mut data = vec![0x1D, 0x28, 0x45, 0x03, 0x00, 0x01, b'X', b'Y'];
+        data.extend_from_slice(&[0x1D, 0x28, 0x4E, 0x02, 0x00, 0x30, 0x31]);
+        data.extend_from_slice(b"Hola");
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+
+        assert_eq!(collect_text(&parsed), vec!["Hola".to_string()]);
+        let blocks = parsed
+            .iter()
+            .filter(|(_, c)| matches!(c, CommandType::Control(Control::GsBlock { .. })))
+            .count();
+        assert_eq!(blocks, 2);
+    }
+
+    #[test]
+    fn alternating_reverse_rows_keep_their_own_state() {
+        // Filas cebra: GS B 1 fila GS B 0 fila ...
+        let mut data = Vec::new();
+        for (idx, row) in ["Fila 1", "Fila 2", "Fila 3", "Fila 4"].iter().enumerate() {
+            data.extend_from_slice(&[0x1D, 0x42, (idx % 2 == 0) as u8]);
+            data.extend_from_slice(row.as_bytes());
+            data.push(b'\n');
+        }
+        let parsed = parse_escpos_simple(&data, CodePage::Utf8Lossy);
+
+        let rows: Vec<(String, bool)> = parsed
+            .iter()
+            .filter_map(|(s, c)| match c {
+                CommandType::Text(t) => Some((t.clone(), s.is_reverse)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("Fila 1".to_string(), true),
+                ("Fila 2".to_string(), false),
+                ("Fila 3".to_string(), true),
+                ("Fila 4".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_ampersand_decodes_shift_jis_until_fs_dot() {
+        // FS & "日本" (Shift-JIS) FS . "A"
+        let data = [0x1C, 0x26, 0x93, 0xFA, 0x96, 0x7B, 0x1C, 0x2E, b'A'];
+        let parsed = parse_escpos_simple(&data, CodePage::Cp437);
+
+        assert_eq!(
+            collect_text(&parsed),
+            vec!["日本".to_string(), "A".to_string()]
+        );
+        assert!(parsed
+            .iter()
+            .any(|(_, c)| matches!(c, CommandType::Control(Control::DoubleByteMode(true)))));
+        let last = parsed.last().unwrap();
+        assert!(!last.0.double_byte);
+    }
+
+    #[test]
+    fn fs_p_consumes_the_nv_logo_parameters() {
+        let data = b"A\x1c\x70\x01\x00B";
+        let parsed = parse_escpos_simple(data, CodePage::Cp437);
+
+        assert_eq!(
+            collect_text(&parsed),
+            vec!["A".to_string(), "B".to_string()]
+        );
+        assert!(matches!(
+            parsed[1].1,
+            CommandType::Control(Control::NvLogo { n: 1, m: 0 })
+        ));
+        assert_eq!(parsed.len(), 3);
+    }
+
+    #[test]
+    fn nul_padding_and_flow_control_bytes_produce_no_commands() {
+        let data = b"\x00\x00\x00Hola\x00\x00\n\x11\x13\x00\x00";
+        let parsed = parse_escpos_simple(data, CodePage::Cp437);
+
+        assert_eq!(parsed.len(), 2);
+        assert!(matches!(&parsed[0].1, CommandType::Text(t) if t == "Hola"));
+        assert!(matches!(
+            parsed[1].1,
+            CommandType::Control(Control::Newline)
+        ));
+    }
+
+    #[test]
+    fn bel_and_ff_are_controls_and_other_bytes_stay_unknown() {
+        let data = [0x07, b'A', 0x0C, 0x10];
+        let parsed = parse_escpos_simple(&data, CodePage::Cp437);
+        let cmds: Vec<&CommandType> = parsed.iter().map(|(_, c)| c).collect();
+
+        assert!(matches!(cmds[0], CommandType::Control(Control::Bell)));
+        assert!(matches!(cmds[2], CommandType::Control(Control::FormFeed)));
+        assert!(matches!(cmds[3], CommandType::Unknown(0x10)));
+    }
+
+    #[test]
+    fn esc_d_and_esc_j_consume_their_argument() {
+        // ESC d 3, "A", ESC J 40, "B"
+        let data = b"\x1bd\x03A\x1bJ\x28B";
+        let parsed = parse_escpos_simple(data, CodePage::Cp437);
+        let cmds: Vec<&CommandType> = parsed.iter().map(|(_, c)| c).collect();
+
+        assert_eq!(cmds.len(), 4);
+        assert!(matches!(
+            cmds[0],
+            CommandType::Control(Control::FeedLines(3))
+        ));
+        assert!(matches!(cmds[1], CommandType::Text(t) if t == "A"));
+        assert!(matches!(
+            cmds[2],
+            CommandType::Control(Control::FeedDots(40))
+        ));
+        assert!(matches!(cmds[3], CommandType::Text(t) if t == "B"));
+    }
+
+    #[test]
+    fn gs_k_qr_keeps_the_selected_model() {
+        fn qr_with_model(model_bytes: &[u8]) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            for &n in model_bytes {
+                // Set model: n1 = n, n2 = 0
+                bytes.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x04, 0x00, 0x31, 0x41, n, 0x00]);
+            }
+            bytes.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x05, 0x00, 0x31, 0x50, 0x30, b'H', b'I']);
+            bytes.extend_from_slice(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x51, 0x30]);
+            bytes
+        }
+        let model_of = |bytes: &[u8]| {
+            parse_escpos_simple(bytes, CodePage::Cp437)
+                .into_iter()
+                .find_map(|(_, c)| match c {
+                    CommandType::Control(Control::Qr { model, .. }) => Some(model),
+                    _ => None,
+                })
+        };
+
+        assert_eq!(model_of(&qr_with_model(&[])), Some(QrModel::Model2));
+        assert_eq!(model_of(&qr_with_model(&[49])), Some(QrModel::Model1));
+        assert_eq!(model_of(&qr_with_model(&[50])), Some(QrModel::Model2));
+        assert_eq!(model_of(&qr_with_model(&[51])), Some(QrModel::Micro));
+        // Un n1 fuera de rango no cambia el modelo anterior.
+        assert_eq!(model_of(&qr_with_model(&[49, 60])), Some(QrModel::Model1));
+    }
+
+    #[test]
+    fn encode_text_round_trips_every_codepage() {
+        let latin = "Señor: café, pingüino ¿sí? ¡Ñandú!";
+        for codepage in CodePage::ALL {
+            let sample = match codepage {
+                CodePage::Utf8Lossy => "Ñandú €5 日本",
+                CodePage::Windows1252 => "Señor: café, pingüino ¿sí? ¡Ñandú! 5 €",
+                CodePage::Cp866 => "Привет, мир",
+                CodePage::ShiftJis => "日本語のレシート",
+                CodePage::Gbk => "中文收据",
+                CodePage::Pc858 | CodePage::Iso885915 => "Señor: 5 € ¡Ñandú!",
+                CodePage::Cp863 => "Café, élève: à ç ê ô û",
+                CodePage::Cp861 => "Þórður, Íslandi: æ ö",
+                CodePage::Cp852 | CodePage::Iso88592 | CodePage::Windows1250 => "Zażółć gęślą jaźń",
+                CodePage::Cp775 | CodePage::Windows1257 => "Ąžuolas, šviesa",
+                CodePage::Cp737 | CodePage::Cp869 | CodePage::Iso88597 | CodePage::Windows1253 => {
+                    "Καλημέρα"
+                }
+                CodePage::Cp855 | CodePage::Windows1251 => "Привет, мир",
+                CodePage::Cp720 | CodePage::Windows1256 => "مرحبا",
+                CodePage::Cp864 => "٠١٢٣ ﺍ",
+                CodePage::Cp862 | CodePage::Windows1255 => "שלום",
+                CodePage::Windows874 => "สวัสดี",
+                _ => latin,
+            };
+            let bytes = encode_text(sample, codepage);
+            assert_eq!(decode_text(&bytes, codepage), sample, "{codepage:?}");
+        }
+    }
+
+    #[test]
+    fn encode_text_replaces_unmappable_chars() {
+        assert_eq!(encode_text("a€b", CodePage::Cp437), b"a?b");
+        assert_eq!(encode_text("日", CodePage::Windows1252), b"?");
+        assert_eq!(encode_text("ñ", CodePage::Cp437), [0xA4]);
+    }
 }
@@ -0,0 +1,396 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::coverage::coverage_report;
+use crate::escpos::ParsedCommand;
+use crate::model::{CommandType, Control, PaperWidth};
+use crate::receipt_meta::{extract_metadata_with, MetaPatterns, ReceiptMeta};
+
+/// Formatos a escribir por job en la exportación en lote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportFormats {
+    /// Bytes crudos tal como llegaron (reimprimibles).
+    pub prn: bool,
+    /// Texto plano del ticket.
+    pub txt: bool,
+    /// Datos del job y los detectados en el texto (fecha, total, artículos).
+    pub json: bool,
+    /// Imagen del ticket como se ve en el preview (la arma el visor: `ExportJob::png`).
+    pub png: bool,
+}
+
+impl Default for ExportFormats {
+    fn default() -> Self {
+        Self {
+            prn: true,
+            txt: false,
+            json: false,
+            png: false,
+        }
+    }
+}
+
+/// Datos de un job a exportar (se arma desde el historial del visor).
+pub struct ExportJob<'a> {
+    pub id: u64,
+    pub label: &'a str,
+    pub received_at: SystemTime,
+    /// Llegada relativa al primer job de la sesión (para reproducirla con la misma cadencia).
+    pub offset: Duration,
+    pub bytes: &'a [u8],
+    pub commands: &'a [ParsedCommand],
+    /// Nota del usuario sobre el job (vacía = sin nota).
+    pub note: &'a str,
+    /// Ancho de papel propio del job (`None` = el global del visor).
+    pub paper_width: Option<PaperWidth>,
+    /// PNG del ticket ya codificado, o por qué no se pudo generar. Solo se usa con
+    /// `ExportFormats::png`.
+    pub png: Option<Result<Vec<u8>, String>>,
+}
+
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub files_written: usize,
+    pub failures: Vec<String>,
+}
+
+/// Convierte una etiqueta en un nombre de archivo válido (también en Windows).
+pub fn sanitize_filename(label: &str) -> String {
+    const MAX: usize = 60;
+    let cleaned: String = label
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX)
+        .collect();
+    // Windows no admite nombres que terminen en punto o espacio.
+    let trimmed = cleaned.trim().trim_end_matches('.');
+    if trimmed.is_empty() {
+        "job".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Texto plano del ticket: el texto tal cual y marcadores para los elementos gráficos.
+pub fn job_plain_text(commands: &[ParsedCommand]) -> String {
+    let mut out = String::new();
+    for (_, command) in commands {
+        match command {
+            CommandType::Text(text) => out.push_str(text),
+            CommandType::Control(Control::Newline | Control::FormFeed | Control::FeedDots(_)) => {
+                out.push('\n')
+            }
+            CommandType::Control(Control::FeedLines(n)) => {
+                out.push_str(&"\n".repeat((*n).max(1) as usize))
+            }
+            CommandType::Control(Control::Tab) => out.push('\t'),
+            CommandType::Control(Control::Cut { .. }) => out.push_str("\n-- corte --\n"),
+            CommandType::Control(Control::Qr { data, .. }) => {
+                out.push_str(&format!("[QR {}]\n", String::from_utf8_lossy(data)));
+            }
+            CommandType::Control(Control::TwoDimBarcode { kind, data }) => {
+                out.push_str(&format!(
+                    "[{} {}]\n",
+                    kind.label().to_uppercase(),
+                    String::from_utf8_lossy(data)
+                ));
+            }
+            CommandType::Control(Control::Barcode { data, .. }) => {
+                out.push_str(&format!("[BARCODE {}]\n", String::from_utf8_lossy(data)));
+            }
+            CommandType::Control(Control::RasterImage { .. } | Control::BitImage { .. }) => {
+                out.push_str("[IMAGEN]\n");
+            }
+            CommandType::Control(Control::NvLogo { n, .. }) => {
+                out.push_str(&format!("[LOGO NV #{n}]\n"));
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Escribe cada job como `#{id}_{label}.prn` / `.txt` / `.json` / `.png` en `dir`, más un
+/// `index.csv` (con la nota y el ancho de papel de cada job, para recuperarlos al cargar la
+/// sesión). Los errores por archivo no cortan la exportación: se acumulan en el resumen.
+pub fn export_jobs(
+    dir: &Path,
+    jobs: &[ExportJob],
+    formats: ExportFormats,
+    meta_patterns: &MetaPatterns,
+) -> ExportSummary {
+    let mut summary = ExportSummary::default();
+    let mut index = String::from("id,received_unix,bytes,label,files,offset_ms,note,paper\n");
+
+    for job in jobs {
+        let base = format!("#{}_{}", job.id, sanitize_filename(job.label));
+        let mut files: Vec<String> = Vec::new();
+
+        let mut outputs: Vec<(String, Vec<u8>)> = Vec::new();
+        if formats.prn {
+            outputs.push((format!("{base}.prn"), job.bytes.to_vec()));
+        }
+        if formats.txt {
+            let mut text = job_plain_text(job.commands);
+            if !job.note.is_empty() {
+                text.push_str(&format!("\n-- nota --\n{}\n", job.note));
+            }
+            outputs.push((format!("{base}.txt"), text.into_bytes()));
+        }
+        if formats.json {
+            let meta = extract_metadata_with(&job_plain_text(job.commands), meta_patterns);
+            outputs.push((format!("{base}.json"), job_json(job, &meta).into_bytes()));
+        }
+        if formats.png {
+            match &job.png {
+                Some(Ok(png)) => outputs.push((format!("{base}.png"), png.clone())),
+                Some(Err(e)) => summary.failures.push(format!("{base}.png: {e}")),
+                None => {}
+            }
+        }
+
+        for (name, contents) in outputs {
+            match fs::write(dir.join(&name), contents) {
+                Ok(()) => {
+                    summary.files_written += 1;
+                    files.push(name);
+                }
+                Err(e) => summary.failures.push(format!("{name}: {e}")),
+            }
+        }
+
+        index.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            job.id,
+            received_unix(job),
+            job.bytes.len(),
+            csv_field(job.label),
+            csv_field(&files.join(";")),
+            job.offset.as_millis(),
+            csv_field(job.note),
+            job.paper_width.map_or("", PaperWidth::label)
+        ));
+    }
+
+    if let Err(e) = fs::write(dir.join("index.csv"), index) {
+        summary.failures.push(format!("index.csv: {e}"));
+    }
+    summary
+}
+
+fn received_unix(job: &ExportJob) -> u64 {
+    job.received_at
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Job como JSON: id, etiqueta, nota, papel, hora, tamaño y los datos detectados (experimental).
+fn job_json(job: &ExportJob, meta: &ReceiptMeta) -> String {
+    let opt = |value: &Option<String>| value.as_deref().map_or("null".to_string(), json_string);
+    let items: Vec<String> = meta
+        .items
+        .iter()
+        .map(|item| {
+            format!(
+                "      {{\"quantity\": {}, \"description\": {}, \"amount\": {}}}",
+                opt(&item.quantity),
+                json_string(&item.description),
+                json_string(&item.amount)
+            )
+        })
+        .collect();
+    let items = if items.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n{}\n    ]", items.join(",\n"))
+    };
+    let coverage: Vec<String> = coverage_report(job.commands)
+        .iter()
+        .map(|entry| {
+            format!(
+                "    {{\"command\": {}, \"support\": \"{}\", \"count\": {}}}",
+                json_string(&entry.command),
+                entry.support.key(),
+                entry.count
+            )
+        })
+        .collect();
+    let coverage = if coverage.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n{}\n  ]", coverage.join(",\n"))
+    };
+    format!(
+        "{{\n  \"id\": {},\n  \"label\": {},\n  \"note\": {},\n  \"paper\": {},\n  \"received_unix\": {},\n  \"bytes\": {},\n  \"meta\": {{\n    \"date\": {},\n    \"time\": {},\n    \"total\": {},\n    \"items\": {}\n  }},\n  \"coverage\": {}\n}}\n",
+        job.id,
+        json_string(job.label),
+        if job.note.is_empty() {
+            "null".to_string()
+        } else {
+            json_string(job.note)
+        },
+        job.paper_width
+            .map_or("null".to_string(), |w| json_string(w.label())),
+        received_unix(job),
+        job.bytes.len(),
+        opt(&meta.date),
+        opt(&meta.time),
+        opt(&meta.total),
+        items,
+        coverage
+    )
+}
+
+/// String JSON con las comillas y los caracteres de control escapados.
+pub fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escpos::parse_escpos_simple;
+    use crate::model::CodePage;
+
+    #[test]
+    fn sanitize_filename_replaces_reserved_characters() {
+        assert_eq!(
+            sanitize_filename("TCP 9100 (127.0.0.1:5000 -> 127.0.0.1:9100)"),
+            "TCP 9100 (127.0.0.1_5000 -_ 127.0.0.1_9100)"
+        );
+        assert_eq!(sanitize_filename(r"C:\tickets\a?.prn"), "C__tickets_a_.prn");
+        assert_eq!(sanitize_filename(" ... "), "job");
+    }
+
+    #[test]
+    fn export_jobs_writes_files_and_manifest() {
+        let dir = std::env::temp_dir().join(format!("escpos_export_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let bytes = b"\x1B@Hola\nMundo\n".to_vec();
+        let commands = parse_escpos_simple(&bytes, CodePage::Utf8Lossy);
+        let jobs = [ExportJob {
+            id: 7,
+            label: "caja, 1",
+            received_at: UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+            offset: Duration::from_millis(1_250),
+            bytes: &bytes,
+            commands: &commands,
+            note: "el total, \"mal\"",
+            paper_width: Some(PaperWidth::W58mm),
+            png: Some(Ok(b"\x89PNG".to_vec())),
+        }];
+
+        let summary = export_jobs(
+            &dir,
+            &jobs,
+            ExportFormats {
+                prn: true,
+                txt: true,
+                json: false,
+                png: true,
+            },
+            &MetaPatterns::default(),
+        );
+        assert_eq!(summary.files_written, 3);
+        assert!(summary.failures.is_empty());
+        assert_eq!(fs::read(dir.join("#7_caja, 1.png")).unwrap(), b"\x89PNG");
+
+        assert_eq!(fs::read(dir.join("#7_caja, 1.prn")).unwrap(), bytes);
+        assert_eq!(
+            fs::read_to_string(dir.join("#7_caja, 1.txt")).unwrap(),
+            "Hola\nMundo\n\n-- nota --\nel total, \"mal\"\n"
+        );
+        let index = fs::read_to_string(dir.join("index.csv")).unwrap();
+        assert_eq!(
+            index.lines().nth(1),
+            Some(
+                "7,1700000000,13,\"caja, 1\",\"#7_caja, 1.prn;#7_caja, 1.txt;#7_caja, 1.png\",1250,\
+                 \"el total, \"\"mal\"\"\",58mm"
+            )
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+    #[test]
+    fn json_export_includes_the_detected_receipt_data() {
+        let dir = std::env::temp_dir().join(format!("escpos_export_json_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let bytes = b"\x1B@14/03/2025 18:42\n2 Cafe \"doble\" 50.00\nTOTAL 50.00\n".to_vec();
+        let commands = parse_escpos_simple(&bytes, CodePage::Utf8Lossy);
+        let jobs = [ExportJob {
+            id: 3,
+            label: "caja",
+            received_at: UNIX_EPOCH,
+            offset: Duration::ZERO,
+            bytes: &bytes,
+            commands: &commands,
+            note: "",
+            paper_width: None,
+            png: None,
+        }];
+        let formats = ExportFormats {
+            prn: false,
+            txt: false,
+            json: true,
+            png: false,
+        };
+        let summary = export_jobs(&dir, &jobs, formats, &MetaPatterns::default());
+        assert_eq!(summary.files_written, 1);
+
+        let json = fs::read_to_string(dir.join("#3_caja.json")).unwrap();
+        assert!(json.contains("\"note\": null"), "{json}");
+        assert!(json.contains("\"paper\": null"), "{json}");
+        assert!(json.contains("\"date\": \"14/03/2025\""), "{json}");
+        assert!(json.contains("\"time\": \"18:42\""), "{json}");
+        assert!(json.contains("\"total\": \"50.00\""), "{json}");
+        assert!(
+            json.contains(
+                "{\"quantity\": \"2\", \"description\": \"Cafe \\\"doble\\\"\", \"amount\": \"50.00\"}"
+            ),
+            "{json}"
+        );
+        assert!(
+            json.contains("{\"command\": \"ESC @\", \"support\": \"full\", \"count\": 1}"),
+            "{json}"
+        );
+        assert!(
+            json.contains("{\"command\": \"LF\", \"support\": \"full\", \"count\": 3}"),
+            "{json}"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,11 +1,201 @@
-pub fn pretty_hex(data: &[u8]) -> String {
-    let mut output = String::new();
-    for (i, chunk) in data.chunks(16).enumerate() {
-        output.push_str(&format!("{:04x}: ", i * 16));
-        for b in chunk {
-            output.push_str(&format!("{:02x} ", b));
-        }
-        output.push('\n');
+/// Bytes por fila del volcado hexadecimal (por defecto y en el editor).
+pub const HEX_ROW_BYTES: usize = 16;
+
+/// Anchos de fila que ofrece el panel: compacto, normal y ancho.
+pub const HEX_ROW_WIDTHS: [usize; 3] = [8, 16, 32];
+
+/// Cantidad de filas del volcado de `len` bytes.
+pub fn hex_row_count(len: usize, bytes_per_row: usize) -> usize {
+    len.div_ceil(bytes_per_row.max(1))
+}
+
+/// Formatea una sola fila (offset, hex y ASCII) para poder virtualizar el panel. El offset
+/// tiene el mismo ancho en todas las filas de `data` (4 dígitos o más si hace falta).
+pub fn hex_row(data: &[u8], row: usize, bytes_per_row: usize) -> String {
+    let bytes_per_row = bytes_per_row.max(1);
+    let start = row * bytes_per_row;
+    let chunk = data
+        .get(start..(start + bytes_per_row).min(data.len()))
+        .unwrap_or(&[]);
+
+    let digits = format!("{:x}", data.len().saturating_sub(1)).len().max(4);
+    let mut output = format!("{:0digits$x}: ", start);
+    for b in chunk {
+        output.push_str(&format!("{:02x} ", b));
+    }
+    // Rellenar la última fila para que la columna ASCII quede alineada.
+    for _ in chunk.len()..bytes_per_row {
+        output.push_str("   ");
     }
+    output.push('|');
+    output.extend(chunk.iter().map(|&b| {
+        if b.is_ascii_graphic() || b == b' ' {
+            b as char
+        } else {
+            '.'
+        }
+    }));
+    output.push('|');
     output
 }
+
+/// Volcado editable: solo los bytes, `HEX_ROW_BYTES` por línea y sin offsets (quedarían
+/// desfasados al insertar o borrar). Se vuelve a leer con `parse_hex_dump`.
+pub fn hex_lines(data: &[u8]) -> String {
+    data.chunks(HEX_ROW_BYTES)
+        .map(|row| {
+            row.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Bytes de un comando en hex compacto (`1b 45 01`), cortado a `max` bytes con el resto indicado.
+pub fn hex_bytes(bytes: &[u8], max: usize) -> String {
+    let mut out: Vec<String> = bytes
+        .iter()
+        .take(max)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if bytes.len() > max {
+        out.push(format!("… (+{})", bytes.len() - max));
+    }
+    out.join(" ")
+}
+
+/// Interpreta texto como volcado hex: bytes sueltos (`1b 40`, `0x1B,0x40`, `\x1b\x40`), hex
+/// corrido (`1b40`) o filas con offset y columna ASCII como las de `hex_row` / `hexdump -C`.
+/// `None` si aparece algo que no es hex (entonces el texto no es un volcado).
+pub fn parse_hex_dump(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        // Sin la columna ASCII (`|...|`).
+        let line = line.split('|').next().unwrap_or("");
+        let mut tokens: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        // Offset al inicio de la fila: `0010:` o `00000010` seguido de bytes sueltos.
+        if let Some(first) = tokens.first() {
+            let is_offset = first.ends_with(':')
+                || (tokens.len() > 1
+                    && first.len() >= 4
+                    && tokens[1..].iter().all(|t| t.len() == 2));
+            if is_offset {
+                tokens.remove(0);
+            }
+        }
+
+        for token in tokens {
+            let digits: String = token
+                .split("\\x")
+                .map(|t| {
+                    t.strip_prefix("0x")
+                        .or_else(|| t.strip_prefix("0X"))
+                        .unwrap_or(t)
+                })
+                .collect();
+            if digits.is_empty() || !digits.len().is_multiple_of(2) {
+                return None;
+            }
+            for pair in digits.as_bytes().chunks(2) {
+                let pair = std::str::from_utf8(pair).ok()?;
+                out.push(u8::from_str_radix(pair, 16).ok()?);
+            }
+        }
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_bytes_truncates_long_commands() {
+        assert_eq!(hex_bytes(&[0x1B, 0x45, 0x01], 8), "1b 45 01");
+        assert_eq!(hex_bytes(b"ABCDEF", 4), "41 42 43 44 … (+2)");
+        assert_eq!(hex_bytes(&[], 4), "");
+    }
+
+    #[test]
+    fn hex_lines_round_trip_through_the_importer() {
+        let data: Vec<u8> = (0..=40).collect();
+        let text = hex_lines(&data);
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.starts_with("00 01 02"));
+        assert_eq!(parse_hex_dump(&text), Some(data));
+    }
+
+    #[test]
+    fn parse_hex_dump_accepts_common_formats() {
+        let expected = vec![0x1B, 0x40, b'H', b'i'];
+        assert_eq!(parse_hex_dump("1b 40 48 69"), Some(expected.clone()));
+        assert_eq!(
+            parse_hex_dump("0x1B, 0x40,\n0x48,0x69"),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            parse_hex_dump("\\x1b\\x40\\x48\\x69"),
+            Some(expected.clone())
+        );
+        assert_eq!(parse_hex_dump("1B404869\n"), Some(expected.clone()));
+        assert_eq!(
+            parse_hex_dump("00000000  1b 40 48 69  |.@Hi|"),
+            Some(expected.clone())
+        );
+        // Ida y vuelta con el volcado del propio visor.
+        let data: Vec<u8> = (0..40u8).collect();
+        let dump: Vec<String> = (0..hex_row_count(data.len(), HEX_ROW_BYTES))
+            .map(|row| hex_row(&data, row, HEX_ROW_BYTES))
+            .collect();
+        assert_eq!(parse_hex_dump(&dump.join("\n")), Some(data));
+    }
+
+    #[test]
+    fn parse_hex_dump_rejects_plain_text() {
+        assert_eq!(parse_hex_dump("Hola mundo"), None);
+        assert_eq!(parse_hex_dump("1b 4"), None);
+        assert_eq!(parse_hex_dump("   \n"), None);
+    }
+
+    #[test]
+    fn hex_row_pads_last_row_and_shows_ascii() {
+        let data: Vec<u8> = (0..18u8).map(|i| b'A' + i).chain([0x1B, 0x40]).collect();
+        assert_eq!(hex_row_count(data.len(), HEX_ROW_BYTES), 2);
+        assert_eq!(
+            hex_row(&data, 1, HEX_ROW_BYTES),
+            format!("0010: 51 52 1b 40 {}|QR.@|", "   ".repeat(12))
+        );
+        assert_eq!(
+            hex_row(&data, 5, HEX_ROW_BYTES),
+            format!("0050: {}||", "   ".repeat(16))
+        );
+    }
+
+    #[test]
+    fn hex_rows_keep_offsets_and_ascii_aligned_at_any_width() {
+        let data: Vec<u8> = (0..20u8).map(|i| b'a' + i).collect();
+        assert_eq!(hex_row_count(data.len(), 8), 3);
+        assert_eq!(hex_row_count(data.len(), 32), 1);
+        assert_eq!(
+            hex_row(&data, 1, 8),
+            "0008: 69 6a 6b 6c 6d 6e 6f 70 |ijklmnop|"
+        );
+        assert_eq!(
+            hex_row(&data, 2, 8),
+            format!("0010: 71 72 73 74 {}|qrst|", "   ".repeat(4))
+        );
+        let wide = hex_row(&data, 0, 32);
+        assert_eq!(wide.find('|'), Some(6 + 32 * 3));
+
+        // Más de 64 KB: todas las filas llevan el offset de 5 dígitos.
+        let big = vec![0u8; 0x10010];
+        assert!(hex_row(&big, 0, 32).starts_with("00000: "));
+        assert!(hex_row(&big, 0x800, 32).starts_with("10000: "));
+    }
+}
@@ -0,0 +1,223 @@
+use std::sync::mpsc::{self, Receiver};
+
+use eframe::egui;
+
+use crate::window_control::WindowControl;
+
+pub const DEFAULT_HOTKEY: &str = "Ctrl+Alt+R";
+
+// Modificadores de `RegisterHotKey` (valores de Win32, válidos en cualquier plataforma para
+// poder validar el texto de la configuración).
+const MOD_ALT: u32 = 0x0001;
+const MOD_CONTROL: u32 = 0x0002;
+const MOD_SHIFT: u32 = 0x0004;
+const MOD_WIN: u32 = 0x0008;
+
+/// Combinación validada: modificadores y virtual-key de Win32.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: u32,
+    pub vk: u32,
+}
+
+/// `Ctrl+Alt+R`, `Ctrl+Shift+F9`, `Win+0`… Hace falta al menos un modificador para no
+/// robarle una tecla suelta a las demás aplicaciones.
+pub fn parse_hotkey(text: &str) -> Result<Hotkey, String> {
+    let mut modifiers = 0;
+    let mut vk = None;
+    for part in text.split('+').map(str::trim) {
+        let upper = part.to_ascii_uppercase();
+        let modifier = match upper.as_str() {
+            "CTRL" | "CONTROL" => MOD_CONTROL,
+            "ALT" => MOD_ALT,
+            "SHIFT" => MOD_SHIFT,
+            "WIN" => MOD_WIN,
+            _ => 0,
+        };
+        if modifier != 0 {
+            modifiers |= modifier;
+            continue;
+        }
+        if vk.is_some() {
+            return Err(format!("Atajo inválido: más de una tecla en {text:?}"));
+        }
+        vk = Some(match upper.as_bytes() {
+            [c @ (b'A'..=b'Z' | b'0'..=b'9')] => *c as u32,
+            [b'F', digits @ ..] => match std::str::from_utf8(digits)
+                .ok()
+                .and_then(|d| d.parse::<u32>().ok())
+            {
+                // VK_F1 = 0x70 … VK_F24 = 0x87
+                Some(n @ 1..=24) => 0x6F + n,
+                _ => return Err(format!("Tecla desconocida: {part:?}")),
+            },
+            _ => return Err(format!("Tecla desconocida: {part:?}")),
+        });
+    }
+    let Some(vk) = vk else {
+        return Err(format!(
+            "Atajo sin tecla: {text:?} (p.ej. {DEFAULT_HOTKEY})"
+        ));
+    };
+    if modifiers == 0 {
+        return Err(format!(
+            "Falta un modificador (Ctrl, Alt, Shift o Win) en {text:?}"
+        ));
+    }
+    Ok(Hotkey { modifiers, vk })
+}
+
+/// Qué hizo la última pulsación, para que el visor sepa si quedó en la bandeja.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyEvent {
+    /// Solo lo emite el hilo del atajo, que existe en Windows.
+    #[cfg(target_os = "windows")]
+    Shown,
+    Hidden,
+}
+
+/// Atajo registrado en el sistema: un hilo con su cola de mensajes que muestra u oculta la
+/// ventana directamente (oculta, `update` no corre) y avisa al visor.
+pub struct GlobalHotkey {
+    events: Receiver<HotkeyEvent>,
+    #[cfg(target_os = "windows")]
+    thread_id: u32,
+}
+
+impl GlobalHotkey {
+    pub fn start(
+        hotkey: Hotkey,
+        window: WindowControl,
+        repaint_ctx: egui::Context,
+    ) -> Result<Self, String> {
+        let (tx, events) = mpsc::channel::<HotkeyEvent>();
+        #[cfg(target_os = "windows")]
+        {
+            let thread_id = imp::spawn_listener(hotkey, window, repaint_ctx, tx)?;
+            Ok(Self { events, thread_id })
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            let _ = (hotkey, window, repaint_ctx, tx, events);
+            Err("El atajo global solo está disponible en Windows".to_string())
+        }
+    }
+
+    pub fn try_recv_all(&self) -> Vec<HotkeyEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for GlobalHotkey {
+    fn drop(&mut self) {
+        imp::stop_listener(self.thread_id);
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use std::sync::mpsc::{self, Sender};
+    use std::{mem, ptr, thread};
+
+    use eframe::egui;
+    use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        RegisterHotKey, UnregisterHotKey, MOD_NOREPEAT,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetMessageW, PostThreadMessageW, MSG, WM_HOTKEY, WM_QUIT,
+    };
+
+    use super::{Hotkey, HotkeyEvent};
+    use crate::diag_log;
+    use crate::window_control::WindowControl;
+
+    const HOTKEY_ID: i32 = 1;
+
+    /// Registra el atajo en un hilo propio (WM_HOTKEY llega a la cola del hilo que lo
+    /// registró) y devuelve su id para poder cortarlo con WM_QUIT.
+    pub fn spawn_listener(
+        hotkey: Hotkey,
+        window: WindowControl,
+        repaint_ctx: egui::Context,
+        tx: Sender<HotkeyEvent>,
+    ) -> Result<u32, String> {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        thread::spawn(move || unsafe {
+            if RegisterHotKey(
+                ptr::null_mut(),
+                HOTKEY_ID,
+                hotkey.modifiers | MOD_NOREPEAT,
+                hotkey.vk,
+            ) == 0
+            {
+                let _ = ready_tx.send(None);
+                return;
+            }
+            let _ = ready_tx.send(Some(GetCurrentThreadId()));
+
+            let mut msg: MSG = mem::zeroed();
+            while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+                if msg.message != WM_HOTKEY {
+                    continue;
+                }
+                let event = if window.is_foreground() {
+                    window.hide_to_tray();
+                    HotkeyEvent::Hidden
+                } else {
+                    window.show_and_focus();
+                    HotkeyEvent::Shown
+                };
+                if tx.send(event).is_err() {
+                    break;
+                }
+                repaint_ctx.request_repaint();
+            }
+            let _ = UnregisterHotKey(ptr::null_mut(), HOTKEY_ID);
+        });
+
+        match ready_rx.recv() {
+            Ok(Some(thread_id)) => Ok(thread_id),
+            _ => {
+                diag_log::log("Atajo global: RegisterHotKey falló (combinación en uso)");
+                Err("ya la usa otra aplicación (elige otra combinación)".to_string())
+            }
+        }
+    }
+
+    pub fn stop_listener(thread_id: u32) {
+        unsafe {
+            let _ = PostThreadMessageW(thread_id, WM_QUIT, 0, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_letters_digits_and_function_keys() {
+        assert_eq!(
+            parse_hotkey(DEFAULT_HOTKEY),
+            Ok(Hotkey {
+                modifiers: MOD_CONTROL | MOD_ALT,
+                vk: b'R' as u32,
+            })
+        );
+        assert_eq!(
+            parse_hotkey(" shift + win + f12 "),
+            Ok(Hotkey {
+                modifiers: MOD_SHIFT | MOD_WIN,
+                vk: 0x7B,
+            })
+        );
+        assert_eq!(parse_hotkey("Alt+7").map(|h| h.vk), Ok(b'7' as u32));
+        assert!(parse_hotkey("R").is_err());
+        assert!(parse_hotkey("Ctrl+Alt").is_err());
+        assert!(parse_hotkey("Ctrl+R+T").is_err());
+        assert!(parse_hotkey("Ctrl+F25").is_err());
+        assert!(parse_hotkey("Ctrl+Enter").is_err());
+    }
+}
@@ -0,0 +1,15 @@
+//! Núcleo del visor ESC/POS sin interfaz: parser, modelo de comandos, codificadores de
+//! imágenes (raster, QR y barcodes) a matrices de puntos y extracción heurística de datos del
+//! texto de un ticket.
+//!
+//! Para usarlo desde otro proyecto sin arrastrar eframe/egui:
+//!
+//! ```toml
+//! escpos_viewer = { version = "1", default-features = false }
+//! ```
+
+pub mod barcode;
+pub mod bitmap;
+pub mod escpos;
+pub mod model;
+pub mod receipt_meta;
@@ -5,14 +5,28 @@
 
 mod app;
 mod app_icon;
-mod escpos;
+mod coverage;
+mod diag_log;
+mod export;
 mod hex_dump;
-mod model;
+mod hotkey;
+mod print_time;
 mod printer_setup;
+mod prune;
+mod replay;
+mod sample_ticket;
+mod settings;
+mod snippets;
 mod tcp_capture;
+mod ticket_image;
 mod tray;
+mod validate;
+mod webhook;
 mod window_control;
 
+// El parser y el modelo viven en la librería (`src/lib.rs`); el binario solo los consume.
+use escpos_viewer::{barcode, bitmap, escpos, model, receipt_meta};
+
 use eframe::egui;
 
 #[cfg(target_os = "windows")]
@@ -96,6 +110,19 @@ fn main() -> eframe::Result<()> {
         }
     }
 
+    // Modo CLI: valida una captura ESC/POS y sale (para CI). Sin ventana ni instancia única.
+    if args.iter().any(|a| a == "--validate") {
+        std::process::exit(validate::run_cli(&args[1..]));
+    }
+
+    // Archivo a abrir al iniciar (p.ej. "Abrir con" desde el explorador): el primer argumento
+    // que no es una opción.
+    let initial_file = args
+        .iter()
+        .skip(1)
+        .find(|a| !a.starts_with("--"))
+        .map(std::path::PathBuf::from);
+
     // Single instance: evita que una segunda instancia intente abrir el puerto 9100.
     let instance = single_instance::SingleInstance::new("visor-escpos-viewer")
         .expect("single-instance init failed");
@@ -133,9 +160,33 @@ fn main() -> eframe::Result<()> {
                 vec!["dotfont".to_owned()],
             );
 
+            // Fuente CJK del sistema como respaldo (texto en modo doble byte FS &).
+            if let Some(cjk) = load_cjk_font() {
+                fonts.font_data.insert("cjk".to_owned(), cjk);
+                for family in fonts.families.values_mut() {
+                    family.push("cjk".to_owned());
+                }
+            }
+
             cc.egui_ctx.set_fonts(fonts);
 
-            Ok(Box::new(app::EscPosViewer::default()))
+            Ok(Box::new(app::EscPosViewer::with_initial_file(initial_file)))
         }),
     )
 }
+
+/// Primera fuente con glifos japoneses/chinos que se encuentre instalada (egui no trae ninguna).
+fn load_cjk_font() -> Option<egui::FontData> {
+    const CANDIDATES: &[&str] = &[
+        "C:\\Windows\\Fonts\\msgothic.ttc",
+        "C:\\Windows\\Fonts\\msyh.ttc",
+        "C:\\Windows\\Fonts\\simsun.ttc",
+        "/System/Library/Fonts/Hiragino Sans GB.ttc",
+        "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+        "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    ];
+    CANDIDATES
+        .iter()
+        .find_map(|path| std::fs::read(path).ok())
+        .map(egui::FontData::from_owned)
+}
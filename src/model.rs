@@ -14,13 +14,22 @@ pub enum Control {
     Align(Align),
     /// Cambio de tabla de caracteres (ESC t n) interpretado a CodePage.
     CodePage(CodePage),
-    /// Raw size byte as received by GS ! n.
+    /// ESC t n sin tabla propia en el visor: se decodifica con la más parecida.
+    CodePageFallback {
+        n: u8,
+        codepage: CodePage,
+    },
+    /// Raw size byte as received by GS ! n. `width`/`height`: nibbles acotados a 0..=7 (x1..x8).
     Size {
         raw: u8,
         width: u8,
         height: u8,
     },
-    Cut,
+    /// GS V / ESC i / ESC m. `feed`: puntos que avanza el papel antes de cortar (GS V 65/66 n).
+    Cut {
+        partial: bool,
+        feed: u8,
+    },
 
     /// Raster bit image: GS v 0
     /// width_bytes = bytes por fila (ancho en bits = width_bytes * 8)
@@ -33,12 +42,19 @@ pub enum Control {
 
     /// QR generado con comandos GS ( k (Model/Size/ECC/Store/Print)
     Qr {
-        model: u8,
+        model: QrModel,
         module_size: u8,
         ecc: u8,
         data: Vec<u8>,
     },
 
+    /// Código 2D no QR (GS ( k cn=53 Aztec / cn=54 DataMatrix): se muestra como recuadro
+    /// con el contenido, sin codificar el símbolo.
+    TwoDimBarcode {
+        kind: TwoDimKind,
+        data: Vec<u8>,
+    },
+
     /// Barcode: GS k
     Barcode {
         m: u8,
@@ -59,11 +75,21 @@ pub enum Control {
     RelativePosition {
         offset: i16,
     },
+    /// GS L nL nH - Margen izquierdo (puntos)
+    LeftMargin {
+        dots: u16,
+    },
+    /// GS W nL nH - Ancho del área de impresión (puntos)
+    PrintAreaWidth {
+        dots: u16,
+    },
 
     /// ESC - n - Subrayado (0=off, 1=1dot, 2=2dot)
     Underline(bool),
     /// GS B n - Impresión invertida (blanco sobre negro)
     Reverse(bool),
+    /// ESC { n - Impresión al revés (girada 180°)
+    UpsideDown(bool),
     /// ESC ! n - Master select (combinación de bold, underline, size)
     MasterSelect(u8),
 
@@ -71,6 +97,10 @@ pub enum Control {
     LineSpacingDefault,
     /// ESC 3 n - Interlineado en puntos
     LineSpacing(u8),
+    /// ESC d n - Imprime el renglón y avanza `n` líneas
+    FeedLines(u8),
+    /// ESC J n - Imprime el renglón y avanza `n` puntos
+    FeedDots(u8),
 
     /// ESC * m nL nH d... - Bit image mode (8/24 pines legacy)
     BitImage {
@@ -81,13 +111,83 @@ pub enum Control {
 
     /// ESC M n - Seleccionar tipo de fuente (0=Font A, 1=Font B)
     FontSelect(bool),
+    /// FS & (true) / FS . (false) - Modo de caracteres de doble byte (Kanji/chino)
+    DoubleByteMode(bool),
+    /// FS p n m - Imprime el logo `n` de la memoria NV (m: 0 normal, 1 doble ancho,
+    /// 2 doble alto, 3 cuádruple)
+    NvLogo {
+        n: u8,
+        m: u8,
+    },
     /// ESC p m t1 t2 - Apertura del cajón portamonedas
     OpenDrawer,
+    /// BEL (0x07) - Zumbador
+    Bell,
+    /// ESC ( A pL pH fn ... - Zumbador integrado, `count` pitidos
+    Buzzer {
+        count: u8,
+    },
+    /// FF (0x0C) - Fin de página; en modo estándar imprime y avanza como un LF
+    FormFeed,
+
+    /// ESC ( fn pL pH ... - Bloque agrupado no soportado, consumido por longitud
+    EscBlock {
+        function: u8,
+        len: u16,
+    },
+    /// GS ( fn pL pH ... - Bloque agrupado no soportado, consumido por longitud
+    GsBlock {
+        function: u8,
+        len: u16,
+    },
 
     EscUnknown(u8),
     GsUnknown(u8),
 }
 
+/// Modelo de QR elegido con GS ( k fn 65 (n1 = 49/50/51).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum QrModel {
+    Model1,
+    Model2,
+    Micro,
+}
+
+impl QrModel {
+    /// `None` para valores fuera de rango (la impresora ignora el comando).
+    pub fn from_byte(n: u8) -> Option<Self> {
+        match n {
+            1 | 49 => Some(QrModel::Model1),
+            2 | 50 => Some(QrModel::Model2),
+            3 | 51 => Some(QrModel::Micro),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QrModel::Model1 => "Modelo 1",
+            QrModel::Model2 => "Modelo 2",
+            QrModel::Micro => "Micro QR",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TwoDimKind {
+    Aztec,
+    DataMatrix,
+}
+
+impl TwoDimKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            TwoDimKind::Aztec => "Aztec",
+            TwoDimKind::DataMatrix => "DataMatrix",
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BarcodeHriPosition {
     None,
@@ -96,11 +196,15 @@ pub enum BarcodeHriPosition {
     Both,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct PrinterState {
     pub is_bold: bool,
     pub is_underline: bool,
+    /// Grosor del subrayado en puntos (0 = sin subrayado, 1 o 2 según `ESC - n`).
+    pub underline_weight: u8,
     pub is_reverse: bool,
+    /// ESC { activo: la impresora gira la línea 180° (para cortar el papel desde arriba).
+    pub is_upside_down: bool,
     pub is_font_b: bool,
     pub alignment: Align,
     pub font_scale: f32,
@@ -111,11 +215,18 @@ pub struct PrinterState {
     pub cursor_x: Option<u16>,
     /// Interlineado en puntos. None = default (~30 dots).
     pub line_spacing: Option<u8>,
+    /// Margen izquierdo en puntos (GS L).
+    pub left_margin_dots: u16,
+    /// Ancho del área de impresión en puntos (GS W). None = hasta el borde del papel.
+    pub print_width_dots: Option<u16>,
 
     pub barcode_hri: BarcodeHriPosition,
     pub barcode_height: u8,
     pub barcode_module_width: u8,
     pub barcode_hri_font: u8,
+
+    /// Modo de doble byte activo (FS &): el texto se decodifica con Shift-JIS o GBK.
+    pub double_byte: bool,
 }
 
 impl Default for PrinterState {
@@ -123,7 +234,9 @@ impl Default for PrinterState {
         Self {
             is_bold: false,
             is_underline: false,
+            underline_weight: 0,
             is_reverse: false,
+            is_upside_down: false,
             is_font_b: false,
             alignment: Align::Left,
             font_scale: 1.0,
@@ -132,16 +245,30 @@ impl Default for PrinterState {
 
             cursor_x: None,
             line_spacing: None,
+            left_margin_dots: 0,
+            print_width_dots: None,
 
             barcode_hri: BarcodeHriPosition::None,
             // Valores típicos (pueden variar por impresora, pero sirven para preview).
             barcode_height: 80,
             barcode_module_width: 3,
             barcode_hri_font: 0,
+
+            double_byte: false,
         }
     }
 }
 
+impl PrinterState {
+    /// Tamaño de carácter (1..=8 por eje). `ESC !` y `GS !` comparten este estado: manda el
+    /// último comando recibido, como en la impresora.
+    pub fn set_char_size(&mut self, width_mul: u8, height_mul: u8) {
+        self.char_width_mul = width_mul.clamp(1, 8);
+        self.char_height_mul = height_mul.clamp(1, 8);
+        self.font_scale = self.char_height_mul as f32;
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Align {
     Left,
@@ -155,6 +282,30 @@ pub enum PaperWidth {
     W80mm,
 }
 
+impl PaperWidth {
+    pub fn label(self) -> &'static str {
+        match self {
+            PaperWidth::W58mm => "58mm",
+            PaperWidth::W80mm => "80mm",
+        }
+    }
+
+    /// Inversa de `label` (columna `paper` del `index.csv` exportado).
+    pub fn from_label(label: &str) -> Option<Self> {
+        [PaperWidth::W58mm, PaperWidth::W80mm]
+            .into_iter()
+            .find(|w| w.label() == label)
+    }
+
+    /// Ancho del papel en puntos de la impresora (203 dpi).
+    pub fn dots(self) -> f32 {
+        match self {
+            PaperWidth::W58mm => 384.0,
+            PaperWidth::W80mm => 576.0,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum CodePage {
     Utf8Lossy,
@@ -165,5 +316,167 @@ pub enum CodePage {
     Iso88591,    // n=6: ISO-8859-1 Latin-1
     Cp866,       // n=17: Cyrillic (Russian)
     Cp860,       // n=3: Portuguese
-    Cp865,       // n=4: Nordic
+    Cp865,       // n=5: Nordic
+    ShiftJis,    // Japonés (doble byte)
+    Gbk,         // Chino simplificado (doble byte)
+    Cp863,       // n=4: Canadian-French
+    Cp857,       // n=13: Turkish
+    Cp737,       // n=14: Greek
+    Cp852,       // n=18: Latin-2
+    Cp720,       // n=32: Arabic
+    Cp775,       // n=33: Baltic
+    Cp855,       // n=34: Cyrillic
+    Cp861,       // n=35: Icelandic
+    Cp862,       // n=36: Hebrew
+    Cp864,       // n=37: Arabic
+    Cp869,       // n=38: Greek
+    Iso88592,    // n=39: Latin-2
+    Iso88597,    // n=15: Greek
+    Iso885915,   // n=40: Latin-9
+    Windows1250, // n=45: Central Europe
+    Windows1251, // n=46: Cyrillic
+    Windows1253, // n=47: Greek
+    Windows1254, // n=48: Turkish
+    Windows1255, // n=49: Hebrew
+    Windows1256, // n=50: Arabic
+    Windows1257, // n=51: Baltic
+    Windows1258, // n=52: Vietnamese
+    Windows874,  // n=20..26 (aprox.): Thai
+}
+
+impl CodePage {
+    pub const ALL: [CodePage; 34] = [
+        CodePage::Utf8Lossy,
+        CodePage::Cp437,
+        CodePage::Cp850,
+        CodePage::Windows1252,
+        CodePage::Pc858,
+        CodePage::Iso88591,
+        CodePage::Cp866,
+        CodePage::Cp860,
+        CodePage::Cp865,
+        CodePage::ShiftJis,
+        CodePage::Gbk,
+        CodePage::Cp863,
+        CodePage::Cp857,
+        CodePage::Cp737,
+        CodePage::Cp852,
+        CodePage::Cp720,
+        CodePage::Cp775,
+        CodePage::Cp855,
+        CodePage::Cp861,
+        CodePage::Cp862,
+        CodePage::Cp864,
+        CodePage::Cp869,
+        CodePage::Iso88592,
+        CodePage::Iso88597,
+        CodePage::Iso885915,
+        CodePage::Windows1250,
+        CodePage::Windows1251,
+        CodePage::Windows1253,
+        CodePage::Windows1254,
+        CodePage::Windows1255,
+        CodePage::Windows1256,
+        CodePage::Windows1257,
+        CodePage::Windows1258,
+        CodePage::Windows874,
+    ];
+
+    /// Identificador estable (para guardar en preferencias).
+    pub fn key(self) -> &'static str {
+        match self {
+            CodePage::Utf8Lossy => "utf8",
+            CodePage::Cp437 => "cp437",
+            CodePage::Cp850 => "cp850",
+            CodePage::Windows1252 => "windows1252",
+            CodePage::Pc858 => "pc858",
+            CodePage::Iso88591 => "iso88591",
+            CodePage::Cp866 => "cp866",
+            CodePage::Cp860 => "cp860",
+            CodePage::Cp865 => "cp865",
+            CodePage::ShiftJis => "shift_jis",
+            CodePage::Gbk => "gbk",
+            CodePage::Cp863 => "cp863",
+            CodePage::Cp857 => "cp857",
+            CodePage::Cp737 => "cp737",
+            CodePage::Cp852 => "cp852",
+            CodePage::Cp720 => "cp720",
+            CodePage::Cp775 => "cp775",
+            CodePage::Cp855 => "cp855",
+            CodePage::Cp861 => "cp861",
+            CodePage::Cp862 => "cp862",
+            CodePage::Cp864 => "cp864",
+            CodePage::Cp869 => "cp869",
+            CodePage::Iso88592 => "iso88592",
+            CodePage::Iso88597 => "iso88597",
+            CodePage::Iso885915 => "iso885915",
+            CodePage::Windows1250 => "windows1250",
+            CodePage::Windows1251 => "windows1251",
+            CodePage::Windows1253 => "windows1253",
+            CodePage::Windows1254 => "windows1254",
+            CodePage::Windows1255 => "windows1255",
+            CodePage::Windows1256 => "windows1256",
+            CodePage::Windows1257 => "windows1257",
+            CodePage::Windows1258 => "windows1258",
+            CodePage::Windows874 => "windows874",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cp| cp.key() == key)
+    }
+
+    /// Nombre corto para la UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            CodePage::Utf8Lossy => "UTF-8 (auto)",
+            CodePage::Cp437 => "CP437",
+            CodePage::Cp850 => "CP850",
+            CodePage::Windows1252 => "Windows-1252",
+            CodePage::Pc858 => "PC858 (€)",
+            CodePage::Iso88591 => "ISO-8859-1",
+            CodePage::Cp866 => "CP866 (Cyrillic)",
+            CodePage::Cp860 => "CP860 (Portuguese)",
+            CodePage::Cp865 => "CP865 (Nordic)",
+            CodePage::ShiftJis => "Shift-JIS (Japanese)",
+            CodePage::Gbk => "GBK (Chinese)",
+            CodePage::Cp863 => "CP863 (Canadian-French)",
+            CodePage::Cp857 => "CP857 (Turkish)",
+            CodePage::Cp737 => "CP737 (Greek)",
+            CodePage::Cp852 => "CP852 (Latin-2)",
+            CodePage::Cp720 => "CP720 (Arabic)",
+            CodePage::Cp775 => "CP775 (Baltic)",
+            CodePage::Cp855 => "CP855 (Cyrillic)",
+            CodePage::Cp861 => "CP861 (Icelandic)",
+            CodePage::Cp862 => "CP862 (Hebrew)",
+            CodePage::Cp864 => "CP864 (Arabic)",
+            CodePage::Cp869 => "CP869 (Greek)",
+            CodePage::Iso88592 => "ISO-8859-2 (Latin-2)",
+            CodePage::Iso88597 => "ISO-8859-7 (Greek)",
+            CodePage::Iso885915 => "ISO-8859-15 (Latin-9)",
+            CodePage::Windows1250 => "Windows-1250",
+            CodePage::Windows1251 => "Windows-1251",
+            CodePage::Windows1253 => "Windows-1253",
+            CodePage::Windows1254 => "Windows-1254",
+            CodePage::Windows1255 => "Windows-1255",
+            CodePage::Windows1256 => "Windows-1256",
+            CodePage::Windows1257 => "Windows-1257",
+            CodePage::Windows1258 => "Windows-1258",
+            CodePage::Windows874 => "Windows-874 (Thai)",
+        }
+    }
+
+    /// Códec de doble byte para el modo Kanji (FS &): GBK si está configurado, si no Shift-JIS.
+    pub fn double_byte(self) -> Self {
+        match self {
+            CodePage::Gbk => CodePage::Gbk,
+            _ => CodePage::ShiftJis,
+        }
+    }
+
+    /// Siguiente codepage de `ALL` (vuelve al primero después del último).
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&cp| cp == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
 }
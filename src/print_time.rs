@@ -0,0 +1,164 @@
+use std::ops::Range;
+
+use crate::escpos::ParsedCommand;
+use crate::model::{BarcodeHriPosition, CommandType, Control, QrModel};
+
+/// Resolución de las térmicas de 203 dpi.
+const DOTS_PER_MM: f32 = 8.0;
+/// Alto de un renglón de Font A / Font B en puntos.
+const FONT_A_DOTS: u32 = 24;
+const FONT_B_DOTS: u32 = 17;
+/// Capacidad en bytes (ECC M) de las versiones 1..=10 de QR, para estimar su alto.
+const QR_BYTES_ECC_M: [usize; 10] = [14, 26, 42, 62, 84, 106, 122, 152, 180, 213];
+
+/// Modelo para estimar cuánto tarda un ticket en imprimirse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrintRate {
+    /// Bytes por segundo (la velocidad de la simulación): mide la transmisión.
+    BytesPerSec(usize),
+    /// Avance de papel de la impresora real en mm/s: mide el papel impreso.
+    MmPerSec(f32),
+}
+
+/// Segundos acumulados al terminar cada comando (mismo índice que `commands`).
+pub fn elapsed_marks(
+    commands: &[ParsedCommand],
+    spans: &[Range<usize>],
+    rate: PrintRate,
+) -> Vec<f32> {
+    match rate {
+        PrintRate::BytesPerSec(bps) => {
+            let bps = bps.max(1) as f32;
+            let mut last_end = 0;
+            (0..commands.len())
+                .map(|idx| {
+                    last_end = spans.get(idx).map_or(last_end, |span| span.end);
+                    last_end as f32 / bps
+                })
+                .collect()
+        }
+        PrintRate::MmPerSec(mm_per_sec) => {
+            let dots_per_sec = mm_per_sec.max(1.0) * DOTS_PER_MM;
+            let mut dots = 0u32;
+            commands
+                .iter()
+                .map(|cmd| {
+                    dots += feed_dots(cmd);
+                    dots as f32 / dots_per_sec
+                })
+                .collect()
+        }
+    }
+}
+
+/// Tiempo total estimado del job.
+pub fn total_secs(commands: &[ParsedCommand], spans: &[Range<usize>], rate: PrintRate) -> f32 {
+    elapsed_marks(commands, spans, rate)
+        .last()
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// `≈2.3s` / `≈1m 05s`.
+pub fn format_secs(secs: f32) -> String {
+    if secs < 60.0 {
+        format!("≈{secs:.1}s")
+    } else {
+        let secs = secs.round() as u64;
+        format!("≈{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Papel (en puntos) que avanza la impresora con un comando. El texto avanza al llegar el LF.
+fn feed_dots((state, cmd): &ParsedCommand) -> u32 {
+    let CommandType::Control(control) = cmd else {
+        return 0;
+    };
+    match control {
+        Control::Newline | Control::FormFeed | Control::FeedLines(_) => {
+            let glyph = if state.is_font_b {
+                FONT_B_DOTS
+            } else {
+                FONT_A_DOTS
+            };
+            let line = state.line_spacing.unwrap_or(30) as u32;
+            let first = line.max(glyph * state.char_height_mul.max(1) as u32);
+            // ESC d n: el renglón impreso más n-1 líneas con el interlineado vigente.
+            match control {
+                Control::FeedLines(n) => first + (*n).saturating_sub(1) as u32 * line,
+                _ => first,
+            }
+        }
+        Control::FeedDots(n) => *n as u32,
+        Control::RasterImage { height, .. } => *height as u32,
+        Control::Cut { feed, .. } => *feed as u32,
+        Control::BitImage { mode, .. } => {
+            if matches!(mode, 32 | 33) {
+                24
+            } else {
+                8
+            }
+        }
+        Control::Barcode { .. } => {
+            let hri_lines = match state.barcode_hri {
+                BarcodeHriPosition::None => 0,
+                BarcodeHriPosition::Above | BarcodeHriPosition::Below => 1,
+                BarcodeHriPosition::Both => 2,
+            };
+            state.barcode_height as u32 + hri_lines * FONT_A_DOTS
+        }
+        Control::Qr {
+            model: QrModel::Micro,
+            module_size,
+            ..
+        } => 17 * (*module_size).clamp(1, 16) as u32,
+        Control::Qr {
+            module_size, data, ..
+        } => {
+            // Más allá de la versión 10 se suma una versión cada ~60 bytes.
+            let version = QR_BYTES_ECC_M
+                .iter()
+                .position(|&cap| data.len() <= cap)
+                .map_or(10 + (data.len() - 213).div_ceil(60), |v| v + 1)
+                .min(40);
+            (17 + 4 * version as u32) * (*module_size).clamp(1, 16) as u32
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escpos::parse_escpos_with_spans;
+    use crate::model::CodePage;
+
+    #[test]
+    fn byte_rate_marks_follow_span_offsets() {
+        let data = b"Hola\nmundo\n\x1dV\x00";
+        let (commands, spans) = parse_escpos_with_spans(data, &CodePage::Cp437.into());
+        let marks = elapsed_marks(&commands, &spans, PrintRate::BytesPerSec(5));
+        assert_eq!(marks.len(), commands.len());
+        assert!(marks.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(
+            total_secs(&commands, &spans, PrintRate::BytesPerSec(5)),
+            data.len() as f32 / 5.0
+        );
+    }
+
+    #[test]
+    fn paper_speed_counts_fed_lines_and_images() {
+        // Dos renglones de 30 puntos + raster de 100 filas = 160 puntos = 20 mm.
+        let mut data = b"A\nB\n\x1dv0\x00\x01\x00\x64\x00".to_vec();
+        data.extend([0u8; 100]);
+        let (commands, spans) = parse_escpos_with_spans(&data, &CodePage::Cp437.into());
+        let secs = total_secs(&commands, &spans, PrintRate::MmPerSec(10.0));
+        assert!((secs - 2.0).abs() < 1e-4, "{secs}");
+    }
+
+    #[test]
+    fn format_secs_switches_to_minutes() {
+        assert_eq!(format_secs(2.34), "≈2.3s");
+        assert_eq!(format_secs(65.0), "≈1m 05s");
+    }
+}
@@ -1,5 +1,7 @@
 #[cfg(windows)]
 use std::process::Command;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
 
 #[cfg(windows)]
 pub fn install_printer() -> Result<(), String> {
@@ -154,6 +156,21 @@ pub fn set_printer_offline(offline: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// Indica si la impresora virtual ya existe en el sistema.
+#[cfg(windows)]
+pub fn printer_installed() -> bool {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let script = "if (Get-Printer -Name 'ESCPos Viewer (TCP 9100)' -ErrorAction SilentlyContinue) { 'yes' } else { 'no' }";
+    Command::new("powershell")
+        .args(["-NoProfile", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "yes")
+        .unwrap_or(false)
+}
+
 #[cfg(not(windows))]
 pub fn install_printer() -> Result<(), String> {
     Err("Instalacion de impresora solo soportada en Windows".to_string())
@@ -167,4 +184,30 @@ pub fn uninstall_printer() -> Result<(), String> {
 #[cfg(not(windows))]
 pub fn set_printer_offline(_offline: bool) -> Result<(), String> {
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(not(windows))]
+pub fn printer_installed() -> bool {
+    false
+}
+
+/// `printer_installed` en un hilo aparte (PowerShell tarda en responder); el resultado llega
+/// por el canal.
+pub fn probe_printer() -> Receiver<Result<bool, String>> {
+    in_background(|| Ok(printer_installed()))
+}
+
+/// `install_printer` en un hilo aparte; si sale bien, vuelve a consultar si quedó instalada.
+pub fn install_printer_in_background() -> Receiver<Result<bool, String>> {
+    in_background(|| install_printer().map(|()| printer_installed()))
+}
+
+fn in_background(
+    task: impl FnOnce() -> Result<bool, String> + Send + 'static,
+) -> Receiver<Result<bool, String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(task());
+    });
+    rx
+}
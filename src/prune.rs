@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+/// Política de limpieza del historial.
+///
+/// Los jobs se ordenan del más nuevo al más viejo (por id) y se conservan:
+/// - siempre los `keep_min` más nuevos, aunque superen la edad máxima;
+/// - además, los que no superan `max_age` (si la autolimpieza por edad está activa);
+/// - nunca más de `max_jobs` en total.
+///
+/// Los fijados (★) no se eliminan ni cuentan para ningún límite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrunePolicy {
+    pub max_jobs: usize,
+    pub keep_min: usize,
+    /// `None` = sin autolimpieza por edad.
+    pub max_age: Option<Duration>,
+}
+
+/// Lo mínimo de un job que necesita la política.
+#[derive(Clone, Copy, Debug)]
+pub struct PruneCandidate {
+    pub id: u64,
+    pub age: Duration,
+    pub pinned: bool,
+}
+
+impl PrunePolicy {
+    /// Ids de los jobs que hay que eliminar.
+    pub fn ids_to_remove(&self, jobs: &[PruneCandidate]) -> Vec<u64> {
+        let mut unpinned: Vec<&PruneCandidate> = jobs.iter().filter(|j| !j.pinned).collect();
+        // Más nuevos primero (no por posición: las pestañas pueden haberse reordenado).
+        unpinned.sort_unstable_by_key(|j| std::cmp::Reverse(j.id));
+
+        let floor = self.keep_min.min(self.max_jobs);
+        unpinned
+            .into_iter()
+            .enumerate()
+            .filter(|(rank, job)| {
+                let within_count = *rank < self.max_jobs;
+                let within_age = self.max_age.is_none_or(|max| job.age <= max);
+                !(within_count && (*rank < floor || within_age))
+            })
+            .map(|(_, job)| job.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mins(m: u64) -> Duration {
+        Duration::from_secs(m * 60)
+    }
+
+    /// Jobs con ids 1..=n; el id más alto es el más nuevo (`age_step` minutos entre cada uno).
+    fn jobs(n: u64, age_step: u64) -> Vec<PruneCandidate> {
+        (1..=n)
+            .map(|id| PruneCandidate {
+                id,
+                age: mins((n - id) * age_step),
+                pinned: false,
+            })
+            .collect()
+    }
+
+    fn sorted(mut ids: Vec<u64>) -> Vec<u64> {
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn count_limit_removes_the_oldest() {
+        let policy = PrunePolicy {
+            max_jobs: 3,
+            keep_min: 0,
+            max_age: None,
+        };
+        assert_eq!(sorted(policy.ids_to_remove(&jobs(5, 1))), vec![1, 2]);
+    }
+
+    #[test]
+    fn age_pruning_never_goes_below_keep_min() {
+        let policy = PrunePolicy {
+            max_jobs: 100,
+            keep_min: 10,
+            max_age: Some(mins(30)),
+        };
+        // Ráfaga vieja: los 12 jobs tienen más de 30 min; quedan los 10 más nuevos.
+        let mut old = jobs(12, 1);
+        for job in &mut old {
+            job.age += mins(60);
+        }
+        assert_eq!(sorted(policy.ids_to_remove(&old)), vec![1, 2]);
+
+        // Sin piso, la edad se los llevaba a todos.
+        let no_floor = PrunePolicy {
+            keep_min: 0,
+            ..policy
+        };
+        assert_eq!(no_floor.ids_to_remove(&old).len(), 12);
+    }
+
+    #[test]
+    fn recent_jobs_beyond_the_floor_are_kept_up_to_max_jobs() {
+        // 40 jobs, uno por minuto: 31 tienen <= 30 min.
+        let policy = PrunePolicy {
+            max_jobs: 100,
+            keep_min: 10,
+            max_age: Some(mins(30)),
+        };
+        assert_eq!(
+            sorted(policy.ids_to_remove(&jobs(40, 1))),
+            (1..=9).collect::<Vec<_>>()
+        );
+
+        // max_jobs manda sobre la edad.
+        let capped = PrunePolicy {
+            max_jobs: 20,
+            ..policy
+        };
+        assert_eq!(
+            sorted(capped.ids_to_remove(&jobs(40, 1))),
+            (1..=20).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn keep_min_is_capped_by_max_jobs_and_pinned_jobs_are_ignored() {
+        let policy = PrunePolicy {
+            max_jobs: 2,
+            keep_min: 10,
+            max_age: Some(mins(1)),
+        };
+        let mut list = jobs(5, 10);
+        list[0].pinned = true; // id 1: el más viejo, pero fijado.
+        assert_eq!(sorted(policy.ids_to_remove(&list)), vec![2, 3]);
+    }
+}
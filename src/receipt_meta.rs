@@ -0,0 +1,288 @@
+/// Datos que se intentan reconocer en el texto de un ticket (heurístico, sin garantías).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReceiptMeta {
+    /// Fecha tal como aparece (`dd/mm/aaaa`, `aaaa-mm-dd`, …).
+    pub date: Option<String>,
+    /// Hora `hh:mm` u `hh:mm:ss`.
+    pub time: Option<String>,
+    /// Importe de la línea de total, normalizado con punto decimal (`1234.50`).
+    pub total: Option<String>,
+    pub items: Vec<ReceiptItem>,
+}
+
+/// Línea de artículo: descripción con un importe al final y, si la hay, cantidad al principio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceiptItem {
+    pub quantity: Option<String>,
+    pub description: String,
+    pub amount: String,
+}
+
+/// Palabras y símbolo que se buscan (configurables en Debug).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaPatterns {
+    /// Palabras que marcan la línea del total (sin distinguir mayúsculas, palabra completa:
+    /// `TOTAL` no reconoce `SUBTOTAL`).
+    pub total_keywords: Vec<String>,
+    /// Símbolo de moneda que puede preceder a los importes (`$`, `€`, `S/`…).
+    pub currency: String,
+}
+
+impl Default for MetaPatterns {
+    fn default() -> Self {
+        Self {
+            total_keywords: vec![
+                "TOTAL".to_string(),
+                "A PAGAR".to_string(),
+                "IMPORTE".to_string(),
+            ],
+            currency: "$".to_string(),
+        }
+    }
+}
+
+impl MetaPatterns {
+    /// Palabras separadas por coma, como se guardan en `settings.ini`.
+    pub fn parse_keywords(text: &str) -> Vec<String> {
+        text.split(',')
+            .map(|k| k.trim().to_string())
+            .filter(|k| !k.is_empty())
+            .collect()
+    }
+}
+
+/// Extrae fecha, hora, total y artículos del texto plano de un ticket con los patrones por
+/// defecto (`TOTAL`, `A PAGAR`, `IMPORTE` y `$`).
+pub fn extract_metadata(text: &str) -> ReceiptMeta {
+    extract_metadata_with(text, &MetaPatterns::default())
+}
+
+/// Como `extract_metadata`, con palabras de total y moneda configurables.
+pub fn extract_metadata_with(text: &str, patterns: &MetaPatterns) -> ReceiptMeta {
+    let mut meta = ReceiptMeta::default();
+    let mut after_total = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            let token = token.trim_matches(|c: char| matches!(c, ',' | ';' | '(' | ')'));
+            if meta.date.is_none() && is_date(token) {
+                meta.date = Some(token.to_string());
+            } else if meta.time.is_none() && is_time(token) {
+                meta.time = Some(token.to_string());
+            }
+        }
+
+        let Some((head, amount)) = trailing_amount(line, &patterns.currency) else {
+            continue;
+        };
+        let upper = head.to_uppercase();
+        let keywords = patterns.total_keywords.iter().map(|k| k.to_uppercase());
+        if keywords.clone().any(|k| contains_word(&upper, &k)) {
+            if meta.total.is_none() {
+                meta.total = Some(amount);
+            }
+            after_total = true;
+            continue;
+        }
+        // Subtotales, impuestos y pagos (van después del total o lo contienen) no son artículos.
+        if after_total || keywords.clone().any(|k| upper.contains(&k)) {
+            continue;
+        }
+        if let Some(item) = item_line(head, amount) {
+            meta.items.push(item);
+        }
+    }
+    meta
+}
+
+/// Separa `line` en (texto, importe normalizado) si termina en un importe con 2 decimales.
+fn trailing_amount<'a>(line: &'a str, currency: &str) -> Option<(&'a str, String)> {
+    let (head, last) = line
+        .rsplit_once(char::is_whitespace)
+        .map_or(("", line), |(head, last)| (head.trim_end(), last));
+    let (negative, raw) = match last.strip_prefix('-') {
+        Some(raw) => (true, raw),
+        None => (false, last),
+    };
+    let (head, raw) = if currency.is_empty() {
+        (head, raw)
+    } else {
+        // El símbolo puede ir pegado al importe (`$45,00`) o separado (`TOTAL $ 45,00`).
+        (
+            head.strip_suffix(currency).map_or(head, str::trim_end),
+            raw.strip_prefix(currency).unwrap_or(raw),
+        )
+    };
+    let amount = normalize_amount(raw)?;
+    Some((
+        head,
+        if negative {
+            format!("-{amount}")
+        } else {
+            amount
+        },
+    ))
+}
+
+/// `1.234,50` / `1,234.50` / `113.50` / `113,50` → `1234.50`. Exige dos decimales para no
+/// confundir cantidades, códigos u horas con importes.
+fn normalize_amount(raw: &str) -> Option<String> {
+    if !raw
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+    {
+        return None;
+    }
+    let sep = raw.rfind(['.', ','])?;
+    let (int_part, decimals) = (&raw[..sep], &raw[sep + 1..]);
+    if decimals.len() != 2 || int_part.is_empty() {
+        return None;
+    }
+    let int_digits: String = int_part.chars().filter(char::is_ascii_digit).collect();
+    if int_digits.is_empty() {
+        return None;
+    }
+    Some(format!("{int_digits}.{decimals}"))
+}
+
+fn item_line(head: &str, amount: String) -> Option<ReceiptItem> {
+    let mut words = head.split_whitespace().peekable();
+    let quantity = words
+        .peek()
+        .map(|w| w.trim_end_matches(['x', 'X']))
+        .filter(|w| {
+            !w.is_empty()
+                && w.chars()
+                    .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+        })
+        .map(str::to_string);
+    if quantity.is_some() {
+        words.next();
+    }
+    let description = words.collect::<Vec<_>>().join(" ");
+    description
+        .chars()
+        .any(char::is_alphabetic)
+        .then_some(ReceiptItem {
+            quantity,
+            description,
+            amount,
+        })
+}
+
+fn contains_word(haystack: &str, word: &str) -> bool {
+    haystack.match_indices(word).any(|(idx, _)| {
+        let before = haystack[..idx].chars().next_back();
+        let after = haystack[idx + word.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// `dd/mm/aaaa`, `dd-mm-aa`, `dd.mm.aaaa` o `aaaa-mm-dd`.
+fn is_date(token: &str) -> bool {
+    let Some(sep) = token.chars().find(|c| matches!(c, '/' | '-' | '.')) else {
+        return false;
+    };
+    let parts: Vec<&str> = token.split(sep).collect();
+    if parts.len() != 3
+        || !parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return false;
+    }
+    let num = |p: &str| p.parse::<u32>().unwrap_or(0);
+    let lens: Vec<usize> = parts.iter().map(|p| p.len()).collect();
+    match lens.as_slice() {
+        [4, 1..=2, 1..=2] => (1..=12).contains(&num(parts[1])) && (1..=31).contains(&num(parts[2])),
+        [1..=2, 1..=2, 2 | 4] => {
+            (1..=31).contains(&num(parts[0]))
+                && (1..=31).contains(&num(parts[1]))
+                && (num(parts[0]) <= 12 || num(parts[1]) <= 12)
+        }
+        _ => false,
+    }
+}
+
+/// `hh:mm` u `hh:mm:ss`.
+fn is_time(token: &str) -> bool {
+    let parts: Vec<&str> = token.split(':').collect();
+    if !(2..=3).contains(&parts.len())
+        || !parts
+            .iter()
+            .all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return false;
+    }
+    let num = |p: &str| p.parse::<u32>().unwrap_or(99);
+    num(parts[0]) < 24 && parts[1..].iter().all(|p| num(p) < 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_date_time_total_and_items() {
+        let text = "MI TIENDA\n\
+                    Fecha: 14/03/2025  Hora: 18:42\n\
+                    Cant Descripción        Total\n\
+                    2  Café americano     50.00\n\
+                    1  Medialuna          18.50\n\
+                    3x Agua sin gas  $45,00\n\
+                    SUBTOTAL 113.50\n\
+                    TOTAL $ 1.113,50\n\
+                    EFECTIVO 1200.00\n";
+        let meta = extract_metadata(text);
+        assert_eq!(meta.date.as_deref(), Some("14/03/2025"));
+        assert_eq!(meta.time.as_deref(), Some("18:42"));
+        assert_eq!(meta.total.as_deref(), Some("1113.50"));
+        assert_eq!(
+            meta.items,
+            vec![
+                ReceiptItem {
+                    quantity: Some("2".to_string()),
+                    description: "Café americano".to_string(),
+                    amount: "50.00".to_string(),
+                },
+                ReceiptItem {
+                    quantity: Some("1".to_string()),
+                    description: "Medialuna".to_string(),
+                    amount: "18.50".to_string(),
+                },
+                ReceiptItem {
+                    quantity: Some("3".to_string()),
+                    description: "Agua sin gas".to_string(),
+                    amount: "45.00".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn total_keywords_and_currency_are_configurable() {
+        let patterns = MetaPatterns {
+            total_keywords: MetaPatterns::parse_keywords(" Summe , GESAMT"),
+            currency: "€".to_string(),
+        };
+        let meta = extract_metadata_with("Brot 2,50\nSUMME €12,80\n", &patterns);
+        assert_eq!(meta.total.as_deref(), Some("12.80"));
+        assert_eq!(meta.items.len(), 1);
+        // Con los patrones por defecto no hay total.
+        assert_eq!(extract_metadata("Brot 2,50\nSUMME €12,80\n").total, None);
+    }
+
+    #[test]
+    fn codes_and_quantities_are_not_amounts() {
+        assert_eq!(normalize_amount("0001"), None);
+        assert_eq!(normalize_amount("12.5"), None);
+        assert_eq!(normalize_amount("1,234.56").as_deref(), Some("1234.56"));
+        assert!(!is_date("1.234,50"));
+        assert!(is_date("2025-03-14"));
+        assert!(!is_time("25:00"));
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::model::PaperWidth;
+
+/// Un job de la sesión a reproducir: bytes, nota, ancho de papel propio y momento de llegada
+/// relativo al primero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayJob {
+    pub label: String,
+    pub bytes: Vec<u8>,
+    pub offset: Duration,
+    pub note: String,
+    pub paper_width: Option<PaperWidth>,
+}
+
+/// Reproducción en curso: entrega los jobs con la cadencia original dividida por `speed`.
+pub struct Replay {
+    pending: VecDeque<ReplayJob>,
+    total: usize,
+    speed: f32,
+    started_at: Instant,
+}
+
+impl Replay {
+    pub fn new(mut jobs: Vec<ReplayJob>, speed: f32, now: Instant) -> Self {
+        jobs.sort_by_key(|j| j.offset);
+        // El primer job sale enseguida aunque la sesión no empiece en 0.
+        let base = jobs.first().map_or(Duration::ZERO, |j| j.offset);
+        for job in &mut jobs {
+            job.offset -= base;
+        }
+        Self {
+            total: jobs.len(),
+            pending: jobs.into(),
+            speed: speed.max(0.01),
+            started_at: now,
+        }
+    }
+
+    /// Jobs cuyo momento ya llegó (en orden).
+    pub fn due(&mut self, now: Instant) -> Vec<ReplayJob> {
+        let elapsed = now.saturating_duration_since(self.started_at);
+        let mut out = Vec::new();
+        while self
+            .pending
+            .front()
+            .is_some_and(|j| j.offset.div_f32(self.speed) <= elapsed)
+        {
+            out.extend(self.pending.pop_front());
+        }
+        out
+    }
+
+    /// Tiempo hasta el próximo job (para pedir el repintado justo a tiempo).
+    pub fn next_in(&self, now: Instant) -> Option<Duration> {
+        let next = self.pending.front()?;
+        let at = self.started_at + next.offset.div_f32(self.speed);
+        Some(at.saturating_duration_since(now))
+    }
+
+    /// (enviados, total)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.total - self.pending.len(), self.total)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Lee una sesión exportada (`index.csv` + los `.prn` de la misma carpeta). Usa `offset_ms`
+/// y, en exportaciones anteriores sin esa columna, la hora de recepción en segundos.
+pub fn load_session(index_path: &Path) -> Result<Vec<ReplayJob>, String> {
+    let dir = index_path.parent().unwrap_or(Path::new("."));
+    let text = fs::read_to_string(index_path).map_err(|e| format!("index.csv: {e}"))?;
+    let mut lines = text.lines();
+    let header = split_csv_line(lines.next().unwrap_or(""));
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (Some(received_col), Some(label_col), Some(files_col)) =
+        (column("received_unix"), column("label"), column("files"))
+    else {
+        return Err("index.csv sin las columnas de una exportación".to_string());
+    };
+    let offset_col = column("offset_ms");
+    let note_col = column("note");
+    let paper_col = column("paper");
+
+    let mut jobs = Vec::new();
+    let mut first_received: Option<u64> = None;
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let fields = split_csv_line(line);
+        let field = |idx: usize| fields.get(idx).map(String::as_str).unwrap_or("");
+        let Some(prn) = field(files_col).split(';').find(|f| f.ends_with(".prn")) else {
+            continue;
+        };
+        let bytes = fs::read(dir.join(prn)).map_err(|e| format!("{prn}: {e}"))?;
+
+        let offset = match offset_col.and_then(|c| field(c).parse::<u64>().ok()) {
+            Some(ms) => Duration::from_millis(ms),
+            None => {
+                let received: u64 = field(received_col).parse().unwrap_or(0);
+                let first = *first_received.get_or_insert(received);
+                Duration::from_secs(received.saturating_sub(first))
+            }
+        };
+        jobs.push(ReplayJob {
+            label: field(label_col).to_string(),
+            bytes,
+            offset,
+            note: note_col.map(|c| field(c).to_string()).unwrap_or_default(),
+            paper_width: paper_col.and_then(|c| PaperWidth::from_label(field(c))),
+        });
+    }
+
+    if jobs.is_empty() {
+        return Err("La sesión no tiene archivos .prn".to_string());
+    }
+    Ok(jobs)
+}
+
+/// Campos de una línea CSV (comillas dobles con `""` escapado, como escribe `export_jobs`).
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escpos::parse_escpos_simple;
+    use crate::export::{export_jobs, ExportFormats, ExportJob};
+    use crate::model::CodePage;
+    use crate::receipt_meta::MetaPatterns;
+    use std::time::UNIX_EPOCH;
+
+    fn job(label: &str, offset_ms: u64) -> ReplayJob {
+        ReplayJob {
+            label: label.to_string(),
+            bytes: label.as_bytes().to_vec(),
+            offset: Duration::from_millis(offset_ms),
+            note: String::new(),
+            paper_width: None,
+        }
+    }
+
+    #[test]
+    fn replay_keeps_the_cadence_scaled_by_speed() {
+        let now = Instant::now();
+        let mut replay = Replay::new(
+            vec![job("b", 3_000), job("a", 1_000), job("c", 5_000)],
+            2.0,
+            now,
+        );
+
+        // El primero sale enseguida; a 2x, "b" llega 1 s después y "c" 2 s después.
+        assert_eq!(replay.due(now), vec![job("a", 0)]);
+        assert_eq!(replay.next_in(now), Some(Duration::from_secs(1)));
+        assert!(replay.due(now + Duration::from_millis(900)).is_empty());
+        assert_eq!(replay.due(now + Duration::from_secs(1)).len(), 1);
+        assert_eq!(replay.progress(), (2, 3));
+        assert_eq!(replay.due(now + Duration::from_secs(10)).len(), 1);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn load_session_reads_an_export_with_offsets() {
+        let dir = std::env::temp_dir().join(format!("escpos_replay_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let first = b"Uno\n".to_vec();
+        let second = b"Dos\n".to_vec();
+        let commands = parse_escpos_simple(&first, CodePage::Utf8Lossy);
+        let export = |id, label, bytes, offset_ms, note, paper_width| ExportJob {
+            id,
+            label,
+            received_at: UNIX_EPOCH,
+            offset: Duration::from_millis(offset_ms),
+            bytes,
+            commands: &commands,
+            note,
+            paper_width,
+            png: None,
+        };
+        let jobs = [
+            export(1, "caja, 1", &first, 0, "", None),
+            export(
+                2,
+                "caja 2",
+                &second,
+                2_500,
+                "salió cortado",
+                Some(PaperWidth::W80mm),
+            ),
+        ];
+        export_jobs(
+            &dir,
+            &jobs,
+            ExportFormats::default(),
+            &MetaPatterns::default(),
+        );
+
+        let session = load_session(&dir.join("index.csv")).unwrap();
+        assert_eq!(
+            session,
+            vec![
+                ReplayJob {
+                    label: "caja, 1".to_string(),
+                    bytes: first,
+                    offset: Duration::ZERO,
+                    note: String::new(),
+                    paper_width: None,
+                },
+                ReplayJob {
+                    label: "caja 2".to_string(),
+                    bytes: second,
+                    offset: Duration::from_millis(2_500),
+                    note: "salió cortado".to_string(),
+                    paper_width: Some(PaperWidth::W80mm),
+                },
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
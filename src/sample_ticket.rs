@@ -0,0 +1,102 @@
+use crate::escpos::encode_text;
+use crate::model::CodePage;
+
+/// Ticket de ejemplo para probar el visor sin una impresora/POS conectado.
+/// Usa los comandos más comunes: tamaños, negrita, subrayado, invertido, barcode, QR y corte.
+/// El texto va en Windows-1252 (`ESC t 16`) para mostrar acentos con cualquier codepage elegido.
+pub fn sample_ticket() -> Vec<u8> {
+    const ESC: u8 = 0x1B;
+    const GS: u8 = 0x1D;
+
+    let mut out: Vec<u8> = Vec::new();
+    let line = |out: &mut Vec<u8>, text: &str| {
+        out.extend_from_slice(&encode_text(text, CodePage::Windows1252));
+        out.push(b'\n');
+    };
+
+    out.extend_from_slice(&[ESC, b'@']);
+    out.extend_from_slice(&[ESC, b't', 16]);
+
+    out.extend_from_slice(&[ESC, b'a', 1]);
+    out.extend_from_slice(&[GS, b'!', 0x11]);
+    line(&mut out, "VISOR ESC/POS");
+    out.extend_from_slice(&[GS, b'!', 0x00]);
+    line(&mut out, "Ticket de ejemplo");
+    line(&mut out, "");
+
+    out.extend_from_slice(&[ESC, b'a', 0]);
+    out.extend_from_slice(&[ESC, b'E', 1]);
+    line(&mut out, "Cant Descripción        Total");
+    out.extend_from_slice(&[ESC, b'E', 0]);
+    line(&mut out, "  2  Café americano     50.00");
+    line(&mut out, "  1  Medialuna          18.50");
+    line(&mut out, "  3  Agua sin gas       45.00");
+    out.extend_from_slice(&[ESC, b'-', 1]);
+    line(&mut out, "                            ");
+    out.extend_from_slice(&[ESC, b'-', 0]);
+
+    out.extend_from_slice(&[ESC, b'a', 2]);
+    out.extend_from_slice(&[GS, b'!', 0x01]);
+    line(&mut out, "TOTAL 113.50");
+    out.extend_from_slice(&[GS, b'!', 0x00]);
+    line(&mut out, "");
+
+    out.extend_from_slice(&[ESC, b'a', 1]);
+    out.extend_from_slice(&[GS, b'B', 1]);
+    line(&mut out, " GRACIAS POR SU COMPRA ");
+    out.extend_from_slice(&[GS, b'B', 0]);
+    line(&mut out, "");
+
+    // Barcode CODE128 con HRI debajo.
+    let code = b"{B0001-2345";
+    out.extend_from_slice(&[GS, b'H', 2, GS, b'h', 60, GS, b'w', 2]);
+    out.extend_from_slice(&[GS, b'k', 73, code.len() as u8]);
+    out.extend_from_slice(code);
+    line(&mut out, "");
+
+    // QR: modelo 2, módulo 5, ECC M, guardar y imprimir.
+    let qr = b"https://github.com/moises717/escpos-viewer-pro";
+    let store_len = (qr.len() + 3) as u16;
+    out.extend_from_slice(&[GS, b'(', b'k', 4, 0, 49, 65, 50, 0]);
+    out.extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 67, 5]);
+    out.extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 69, 49]);
+    out.extend_from_slice(&[GS, b'(', b'k']);
+    out.extend_from_slice(&store_len.to_le_bytes());
+    out.extend_from_slice(&[49, 80, 48]);
+    out.extend_from_slice(qr);
+    out.extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 81, 48]);
+    line(&mut out, "");
+
+    // GS V 1: corte parcial.
+    out.extend_from_slice(&[GS, b'V', 1]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escpos::parse_escpos_simple;
+    use crate::model::{CommandType, Control};
+
+    #[test]
+    fn sample_ticket_parses_without_unknown_commands() {
+        let commands = parse_escpos_simple(&sample_ticket(), CodePage::Utf8Lossy);
+        let has = |f: &dyn Fn(&Control) -> bool| {
+            commands
+                .iter()
+                .any(|(_, c)| matches!(c, CommandType::Control(ctrl) if f(ctrl)))
+        };
+
+        assert!(has(&|c| matches!(c, Control::Barcode { m: 73, .. })));
+        assert!(has(&|c| matches!(c, Control::Qr { .. })));
+        assert!(has(&|c| matches!(c, Control::Cut { .. })));
+        assert!(commands
+            .iter()
+            .any(|(_, c)| matches!(c, CommandType::Text(t) if t.contains("Café americano"))));
+        assert!(!commands.iter().any(|(_, c)| matches!(
+            c,
+            CommandType::Unknown(_)
+                | CommandType::Control(Control::EscUnknown(_) | Control::GsUnknown(_))
+        )));
+    }
+}
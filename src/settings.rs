@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+const SETTINGS_FILE: &str = "settings.ini";
+
+/// Carpeta de configuración del visor (%APPDATA%\escpos_viewer en Windows).
+pub fn config_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+
+    #[cfg(not(target_os = "windows"))]
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+
+    base.map(|b| b.join("escpos_viewer"))
+}
+
+/// Preferencias persistentes en formato `clave=valor` (una por línea).
+/// Sin dependencias extra: los valores se guardan como texto y se interpretan al leer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SettingsStore {
+    values: BTreeMap<String, String>,
+}
+
+impl SettingsStore {
+    pub fn load() -> Self {
+        let Some(path) = config_dir().map(|d| d.join(SETTINGS_FILE)) else {
+            return Self::default();
+        };
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(dir) = config_dir() else {
+            return Ok(());
+        };
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(SETTINGS_FILE), self.serialize())
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut values = BTreeMap::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((k, v)) = line.split_once('=') {
+                values.insert(k.trim().to_string(), v.trim().to_string());
+            }
+        }
+        Self { values }
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = String::from("# Visor ESC-POS - preferencias\n");
+        for (k, v) in &self.values {
+            out.push_str(k);
+            out.push('=');
+            out.push_str(v);
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        // Los saltos de línea romperían el formato; se reemplazan por espacios.
+        let value = value.to_string().replace(['\r', '\n'], " ");
+        self.values.insert(key.to_string(), value);
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+
+    pub fn get_bool(&self, key: &str, default: bool) -> bool {
+        match self.get_str(key) {
+            Some("1" | "true") => true,
+            Some("0" | "false") => false,
+            _ => default,
+        }
+    }
+
+    pub fn get_parsed<T: std::str::FromStr>(&self, key: &str, default: T) -> T {
+        self.get_str(key)
+            .and_then(|v| v.parse::<T>().ok())
+            .unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialized_settings_parse_back_to_the_same_values() {
+        let mut store = SettingsStore::default();
+        store.set("tcp_port", 9101);
+        store.set("docked_mode", true);
+        store.set("webhook_url", "http://host/?a=1&b=2");
+        store.set("note", "dos\nlíneas");
+
+        let parsed = SettingsStore::parse(&store.serialize());
+        assert_eq!(parsed, store);
+        assert_eq!(parsed.get_parsed("tcp_port", 0u16), 9101);
+        assert!(parsed.get_bool("docked_mode", false));
+        assert_eq!(parsed.get_str("webhook_url"), Some("http://host/?a=1&b=2"));
+        assert_eq!(parsed.get_str("note"), Some("dos líneas"));
+        assert_eq!(parsed.get_parsed("falta", 7u8), 7);
+    }
+}
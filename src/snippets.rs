@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::escpos::encode_text;
+use crate::export::json_string;
+use crate::model::CodePage;
+use crate::settings::config_dir;
+
+const SNIPPETS_FILE: &str = "snippets.json";
+
+/// Fragmento ESC/POS con nombre. La plantilla es texto con escapes `\xNN` / `\n` / `\\` y
+/// parámetros entre llaves:
+/// - `{nombre}`: el valor del parámetro, codificado en el codepage del visor.
+/// - `{len:nombre}`: largo del valor en 1 byte; `{len2:nombre}` en 2 bytes (pL pH).
+///   Admiten un ajuste: `{len2+3:url}` (p.ej. el bloque de datos de `GS ( k`).
+///
+/// Una llave literal se escribe `\x7B`; una `{` sin su `}` es un error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub template: String,
+}
+
+impl Snippet {
+    fn new(name: &str, template: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            template: template.to_string(),
+        }
+    }
+}
+
+/// Biblioteca inicial (se usa mientras no exista `snippets.json`).
+pub fn default_snippets() -> Vec<Snippet> {
+    vec![
+        Snippet::new("Inicializar (ESC @)", r"\x1B@"),
+        Snippet::new(
+            "Encabezado negrita centrado",
+            r"\x1Ba\x01\x1BE\x01\x1D!\x11{titulo}\n\x1D!\x00\x1BE\x00\x1Ba\x00",
+        ),
+        Snippet::new("Texto", r"{texto}\n"),
+        Snippet::new(
+            "QR con URL",
+            r"\x1Ba\x01\x1D(k\x04\x00\x31\x41\x32\x00\x1D(k\x03\x00\x31\x43\x05\x1D(k\x03\x00\x31\x45\x31\x1D(k{len2+3:url}\x31\x50\x30{url}\x1D(k\x03\x00\x31\x51\x30\n\x1Ba\x00",
+        ),
+        Snippet::new(
+            "CODE128 de ejemplo",
+            r"\x1DH\x02\x1Dh\x3C\x1Dw\x02\x1Dk\x49{len+2:datos}\x7BB{datos}\n",
+        ),
+        Snippet::new("Corte", r"\n\n\n\x1DV\x00"),
+    ]
+}
+
+/// Parámetros de la plantilla en orden de aparición, sin repetir.
+pub fn params(template: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        let inner = &rest[open + 1..open + close];
+        let name = inner.split_once(':').map_or(inner, |(_, n)| n).trim();
+        if !name.is_empty() && !out.iter().any(|p| p == name) {
+            out.push(name.to_string());
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out
+}
+
+/// Arma los bytes de la plantilla con los valores dados (un parámetro sin valor queda vacío).
+pub fn assemble(
+    template: &str,
+    values: &HashMap<String, String>,
+    codepage: CodePage,
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut chars = template.chars().peekable();
+    let mut literal = String::new();
+    let flush = |literal: &mut String, out: &mut Vec<u8>| {
+        out.extend(encode_text(literal, codepage));
+        literal.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    let byte = u8::from_str_radix(&hex, 16)
+                        .map_err(|_| format!("Escape inválido: \\x{hex}"))?;
+                    flush(&mut literal, &mut out);
+                    out.push(byte);
+                }
+                Some('n') => literal.push('\n'),
+                Some('\\') => literal.push('\\'),
+                other => {
+                    return Err(format!(
+                        "Escape inválido: \\{}",
+                        other.map(String::from).unwrap_or_default()
+                    ))
+                }
+            },
+            '{' => {
+                let mut inner = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    inner.push(c);
+                }
+                if !closed {
+                    return Err(format!("Llave sin cerrar: {{{inner}"));
+                }
+                flush(&mut literal, &mut out);
+                out.extend(expand_param(&inner, values, codepage)?);
+            }
+            _ => literal.push(c),
+        }
+    }
+    flush(&mut literal, &mut out);
+    Ok(out)
+}
+
+fn expand_param(
+    inner: &str,
+    values: &HashMap<String, String>,
+    codepage: CodePage,
+) -> Result<Vec<u8>, String> {
+    let value =
+        |name: &str| encode_text(values.get(name.trim()).map_or("", String::as_str), codepage);
+    let Some((func, name)) = inner.split_once(':') else {
+        return Ok(value(inner));
+    };
+    let (func, extra) = match func.split_once('+') {
+        Some((f, n)) => (
+            f.trim(),
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Ajuste inválido en {{{inner}}}"))?,
+        ),
+        None => (func.trim(), 0),
+    };
+    let len = value(name).len() + extra;
+    match func {
+        "len" => u8::try_from(len)
+            .map(|n| vec![n])
+            .map_err(|_| format!("{} ocupa {len} bytes (máximo 255)", name.trim())),
+        "len2" => u16::try_from(len)
+            .map(|n| n.to_le_bytes().to_vec())
+            .map_err(|_| format!("{} ocupa {len} bytes (máximo 65535)", name.trim())),
+        _ => Err(format!("Función desconocida en {{{inner}}}")),
+    }
+}
+
+/// Lee `snippets.json` de la carpeta de configuración (o la biblioteca inicial si no existe).
+/// Un archivo que no se puede leer es un error: no se cambia por los ejemplos en silencio.
+pub fn load() -> Result<Vec<Snippet>, String> {
+    match config_dir() {
+        Some(dir) => load_from(&dir.join(SNIPPETS_FILE)),
+        None => Ok(default_snippets()),
+    }
+}
+
+pub fn save(snippets: &[Snippet]) -> Result<(), String> {
+    let dir = config_dir().ok_or("Sin carpeta de configuración")?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    save_to(&dir.join(SNIPPETS_FILE), snippets)
+}
+
+fn load_from(path: &Path) -> Result<Vec<Snippet>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => from_json(&text).map_err(|e| format!("{}: {e}", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(default_snippets()),
+        Err(e) => Err(format!("{}: {e}", path.display())),
+    }
+}
+
+/// No pisa un `snippets.json` editado a mano que no se puede leer: hay que arreglarlo (o
+/// borrarlo) antes de volver a guardar desde el visor.
+fn save_to(path: &Path, snippets: &[Snippet]) -> Result<(), String> {
+    if path.exists() {
+        load_from(path).map_err(|e| format!("{e} (no se guarda encima)"))?;
+    }
+    fs::write(path, to_json(snippets)).map_err(|e| e.to_string())
+}
+
+/// `[{"name": "...", "template": "..."}, ...]` (sin dependencias extra, como `settings.ini`).
+pub fn to_json(snippets: &[Snippet]) -> String {
+    let items: Vec<String> = snippets
+        .iter()
+        .map(|s| {
+            format!(
+                "  {{\"name\": {}, \"template\": {}}}",
+                json_string(&s.name),
+                json_string(&s.template)
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", items.join(",\n"))
+}
+
+/// Lee lo que escribe `to_json`: una lista de objetos con valores string (se ignoran otras
+/// claves).
+pub fn from_json(text: &str) -> Result<Vec<Snippet>, String> {
+    let mut p = JsonReader {
+        chars: text.chars().peekable(),
+    };
+    let mut snippets = Vec::new();
+    p.expect('[')?;
+    if p.peek() == Some(']') {
+        p.next();
+        return Ok(snippets);
+    }
+    loop {
+        p.expect('{')?;
+        let mut fields = HashMap::new();
+        if p.peek() != Some('}') {
+            loop {
+                let key = p.string()?;
+                p.expect(':')?;
+                fields.insert(key, p.string()?);
+                if p.peek() == Some(',') {
+                    p.next();
+                } else {
+                    break;
+                }
+            }
+        }
+        p.expect('}')?;
+        let (Some(name), Some(template)) = (fields.remove("name"), fields.remove("template"))
+        else {
+            return Err("Snippet sin \"name\" o \"template\"".to_string());
+        };
+        snippets.push(Snippet { name, template });
+        match p.next() {
+            Some(',') => continue,
+            Some(']') => break,
+            _ => return Err("Se esperaba ',' o ']'".to_string()),
+        }
+    }
+    Ok(snippets)
+}
+
+struct JsonReader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonReader<'_> {
+    /// Siguiente carácter que no sea espacio.
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.peek()?;
+        self.chars.next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.next() {
+            Some(got) if got == c => Ok(()),
+            got => Err(format!("Se esperaba '{c}' y vino {got:?}")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.chars.next().ok_or("String sin cerrar")? {
+                '"' => return Ok(out),
+                '\\' => match self.chars.next().ok_or("String sin cerrar")? {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    'b' => out.push('\u{8}'),
+                    'f' => out.push('\u{c}'),
+                    'u' => {
+                        let hex: String = self.chars.by_ref().take(4).collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .map_err(|_| format!("Escape inválido: \\u{hex}"))?;
+                        out.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                    }
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escpos::parse_escpos_simple;
+    use crate::model::{CommandType, Control};
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn default_snippets_assemble_into_the_expected_commands() {
+        let library = default_snippets();
+        let find = |name: &str| library.iter().find(|s| s.name == name).unwrap();
+
+        let qr = find("QR con URL");
+        assert_eq!(params(&qr.template), vec!["url"]);
+        let bytes = assemble(
+            &qr.template,
+            &values(&[("url", "https://example.com")]),
+            CodePage::Cp437,
+        )
+        .unwrap();
+        assert!(parse_escpos_simple(&bytes, CodePage::Cp437).iter().any(|(_, c)| matches!(
+            c,
+            CommandType::Control(Control::Qr { data, module_size: 5, .. }) if data == b"https://example.com"
+        )));
+
+        let code = find("CODE128 de ejemplo");
+        let bytes = assemble(
+            &code.template,
+            &values(&[("datos", "ABC-123")]),
+            CodePage::Cp437,
+        )
+        .unwrap();
+        let expected = b"\x1dk\x49\x09{BABC-123\n";
+        assert!(bytes.ends_with(expected));
+    }
+
+    #[test]
+    fn header_encodes_the_parameter_in_the_viewer_codepage() {
+        let bytes = assemble(
+            r"\x1BE\x01{titulo}\n",
+            &values(&[("titulo", "Menú")]),
+            CodePage::Windows1252,
+        )
+        .unwrap();
+        assert_eq!(bytes, b"\x1bE\x01Men\xfa\n");
+        assert!(assemble(r"\xZZ", &HashMap::new(), CodePage::Cp437).is_err());
+        assert_eq!(
+            assemble("A{titulo", &values(&[("titulo", "x")]), CodePage::Cp437),
+            Err("Llave sin cerrar: {titulo".to_string())
+        );
+        assert!(assemble(
+            "{len:x}",
+            &values(&[("x", &"a".repeat(300))]),
+            CodePage::Cp437
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn json_round_trip_keeps_escapes_and_quotes() {
+        let mut library = default_snippets();
+        library.push(Snippet::new("Con \"comillas\"\ny salto", r"\x1B@{a}\\"));
+        let json = to_json(&library);
+        assert_eq!(from_json(&json).unwrap(), library);
+        assert_eq!(from_json("[]").unwrap(), vec![]);
+        assert!(from_json("[{\"name\": \"x\"}]").is_err());
+    }
+
+    #[test]
+    fn unreadable_file_is_reported_and_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("escpos_snippets_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SNIPPETS_FILE);
+
+        // Sin archivo: la biblioteca inicial.
+        assert_eq!(load_from(&path).unwrap(), default_snippets());
+
+        let hand_edited = "[{\"name\": \"Mío\", \"template\": \"x\"},]";
+        fs::write(&path, hand_edited).unwrap();
+        assert!(load_from(&path).is_err());
+        assert!(save_to(&path, &default_snippets()).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), hand_edited);
+
+        // Un archivo válido sí se reemplaza.
+        let mine = vec![Snippet::new("Mío", "x")];
+        fs::write(&path, to_json(&mine)).unwrap();
+        save_to(&path, &default_snippets()).unwrap();
+        assert_eq!(load_from(&path).unwrap(), default_snippets());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
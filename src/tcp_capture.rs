@@ -1,14 +1,15 @@
 use std::io::Read;
-use std::net::{TcpListener, TcpStream};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc::{self, Receiver, Sender},
-    Arc,
+    Arc, Mutex,
 };
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use eframe::egui;
+use crate::diag_log;
 use crate::window_control::WindowControl;
 
 #[derive(Debug, Clone)]
@@ -17,27 +18,85 @@ pub struct CapturedJob {
     pub bytes: Vec<u8>,
 }
 
+/// Límites del listener: el puerto acepta datos arbitrarios, así que se acota lo que entra.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureLimits {
+    /// Conexiones aceptadas por segundo (0 = sin límite). El exceso se cierra sin leer.
+    pub max_connections_per_sec: u32,
+    /// Bytes máximos por job; lo que sobra se descarta y el job se marca como truncado.
+    pub max_job_bytes: usize,
+    /// Permite escuchar en una dirección que no sea loopback (127.0.0.1 / ::1).
+    pub allow_remote: bool,
+    /// Inactividad tras la cual se da el job por terminado si el POS no cierra la conexión.
+    pub read_timeout: Duration,
+}
+
+impl Default for CaptureLimits {
+    fn default() -> Self {
+        Self {
+            max_connections_per_sec: 20,
+            max_job_bytes: 8 * 1024 * 1024,
+            allow_remote: false,
+            read_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Puerto de la impresora virtual (al que apunta la impresora de Windows).
+pub const DEFAULT_PORT: u16 = 9100;
+
+/// Puertos que se prueban como alternativa cuando el configurado sigue ocupado.
+pub const FALLBACK_PORTS: std::ops::RangeInclusive<u16> = DEFAULT_PORT..=9110;
+
 pub struct TcpCapture {
     stop: Arc<AtomicBool>,
     join: Option<JoinHandle<()>>,
     rx: Receiver<CapturedJob>,
+    /// Si está en false, los jobs se capturan sin volver a mostrar la ventana.
+    show_on_job: Arc<AtomicBool>,
+    /// Con la ventana mostrada: traerla al frente con foco (o solo mostrarla sin activar).
+    focus_on_job: Arc<AtomicBool>,
+    limits: Arc<Mutex<CaptureLimits>>,
+    /// Dirección en la que quedó escuchando (la usa el envío de prueba).
+    local_addr: SocketAddr,
 }
 
 impl TcpCapture {
     pub fn start(
         bind_addr: &str,
+        limits: CaptureLimits,
         repaint_ctx: Option<egui::Context>,
         window: Option<WindowControl>,
     ) -> std::io::Result<Self> {
-        let listener = TcpListener::bind(bind_addr)?;
+        Self::start_with_clock(bind_addr, limits, repaint_ctx, window, Instant::now)
+    }
+
+    /// `start` con el reloj del límite de conexiones inyectado (los tests lo congelan).
+    fn start_with_clock(
+        bind_addr: &str,
+        limits: CaptureLimits,
+        repaint_ctx: Option<egui::Context>,
+        window: Option<WindowControl>,
+        clock: impl Fn() -> Instant + Send + 'static,
+    ) -> std::io::Result<Self> {
+        let addrs = check_bind_addr(bind_addr, limits.allow_remote)?;
+        let listener = TcpListener::bind(&addrs[..])?;
         listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
 
         let (tx, rx) = mpsc::channel::<CapturedJob>();
         let stop = Arc::new(AtomicBool::new(false));
         let stop_thread = stop.clone();
+        let show_on_job = Arc::new(AtomicBool::new(true));
+        let show_thread = show_on_job.clone();
+        let focus_on_job = Arc::new(AtomicBool::new(true));
+        let focus_thread = focus_on_job.clone();
+        let limits = Arc::new(Mutex::new(limits));
+        let limits_thread = limits.clone();
         let bind_addr_string = bind_addr.to_string();
 
         let join = thread::spawn(move || {
+            let mut rate = ConnectionRateLimiter::new(clock());
             loop {
                 if stop_thread.load(Ordering::Relaxed) {
                     break;
@@ -45,19 +104,45 @@ impl TcpCapture {
 
                 match listener.accept() {
                     Ok((stream, peer)) => {
+                        let limits = limits_thread.lock().map(|l| *l).unwrap_or_default();
+                        if !rate.allow(clock(), limits.max_connections_per_sec) {
+                            // Demasiadas conexiones: se cierra sin leer y se frena el loop.
+                            drop(stream);
+                            diag_log::log(&format!(
+                                "TCP: conexión de {peer} rechazada (límite {}/s)",
+                                limits.max_connections_per_sec
+                            ));
+                            thread::sleep(Duration::from_millis(25));
+                            continue;
+                        }
+
                         let tx = tx.clone();
                         let source = format!("{} -> {}", peer, bind_addr_string);
-                        if let Err(err) =
-                            read_one_job(stream, source, tx, repaint_ctx.clone(), window.clone())
-                        {
-                            let _ = err; // silencioso
+                        diag_log::log(&format!("TCP: conexión {source}"));
+                        let window = if show_thread.load(Ordering::Relaxed) {
+                            window.clone()
+                        } else {
+                            None
+                        };
+                        let focus = focus_thread.load(Ordering::Relaxed);
+                        if let Err(err) = read_one_job(
+                            stream,
+                            source.clone(),
+                            &limits,
+                            tx,
+                            repaint_ctx.clone(),
+                            window,
+                            focus,
+                        ) {
+                            diag_log::log(&format!("TCP: error leyendo {source}: {err}"));
                         }
                     }
                     Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
                         thread::sleep(Duration::from_millis(25));
                     }
-                    Err(_) => {
+                    Err(e) => {
                         // Si el accept falla por otra cosa, salimos para evitar loop caliente.
+                        diag_log::log(&format!("TCP: accept falló, listener detenido: {e}"));
                         break;
                     }
                 }
@@ -68,9 +153,32 @@ impl TcpCapture {
             stop,
             join: Some(join),
             rx,
+            show_on_job,
+            focus_on_job,
+            limits,
+            local_addr,
         })
     }
 
+    /// Aplica nuevos límites sin reiniciar el listener (rigen desde la próxima conexión).
+    pub fn set_limits(&self, limits: CaptureLimits) {
+        if let Ok(mut current) = self.limits.lock() {
+            *current = limits;
+        }
+    }
+
+    pub fn set_show_on_job(&self, enabled: bool) {
+        self.show_on_job.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_focus_on_job(&self, enabled: bool) {
+        self.focus_on_job.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
     pub fn try_recv_all(&self) -> Vec<CapturedJob> {
         self.rx.try_iter().collect()
     }
@@ -89,28 +197,141 @@ impl Drop for TcpCapture {
     }
 }
 
+/// Cliente de prueba: abre una conexión a `addr`, manda `bytes` y cierra (como un POS).
+/// Corre en un hilo aparte; el resultado llega por el canal devuelto.
+pub fn send_test_job(addr: SocketAddr, bytes: Vec<u8>) -> Receiver<Result<(), String>> {
+    let addr = connect_addr(addr);
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = TcpStream::connect_timeout(&addr, Duration::from_secs(2))
+            .map_err(|e| format!("No se pudo conectar a {addr} (¿captura desactivada?): {e}"))
+            .and_then(|mut stream| {
+                std::io::Write::write_all(&mut stream, &bytes)
+                    .map_err(|e| format!("Error enviando a {addr}: {e}"))
+            });
+        diag_log::log(&match &result {
+            Ok(()) => format!("TCP: prueba enviada a {addr} ({} bytes)", bytes.len()),
+            Err(err) => format!("TCP: prueba falló: {err}"),
+        });
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+/// Dirección a la que conectarse para llegar a un listener en `addr`: escuchando en 0.0.0.0 o
+/// `::` se va por loopback (Windows no acepta conectar a la dirección sin especificar).
+fn connect_addr(mut addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => addr.set_ip(Ipv4Addr::LOCALHOST.into()),
+        IpAddr::V6(ip) if ip.is_unspecified() => addr.set_ip(Ipv6Addr::LOCALHOST.into()),
+        _ => {}
+    }
+    addr
+}
+
+/// Primer puerto de `ports` en el que se puede escuchar ahora mismo en 127.0.0.1.
+pub fn first_free_port(ports: impl IntoIterator<Item = u16>) -> Option<u16> {
+    ports
+        .into_iter()
+        .find(|&port| TcpListener::bind(("127.0.0.1", port)).is_ok())
+}
+
+/// Espera antes de volver a intentar escuchar en un puerto ocupado: 2 s, 4 s, 8 s… hasta 1 min.
+pub fn bind_retry_delay(failures: u32) -> Duration {
+    Duration::from_secs(1u64 << failures.clamp(1, 6)).min(Duration::from_secs(60))
+}
+
+/// Resuelve la dirección y rechaza las que no son loopback salvo que se permita explícitamente.
+fn check_bind_addr(bind_addr: &str, allow_remote: bool) -> std::io::Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = bind_addr.to_socket_addrs()?.collect();
+    if addrs.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Dirección inválida: {bind_addr}"),
+        ));
+    }
+    if !allow_remote && addrs.iter().any(|a| !a.ip().is_loopback()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{bind_addr} no es loopback; solo se permite escuchar en 127.0.0.1"),
+        ));
+    }
+    Ok(addrs)
+}
+
+/// Ventana fija de 1 segundo: cuenta conexiones y rechaza las que exceden el máximo.
+struct ConnectionRateLimiter {
+    window_start: Instant,
+    count: u32,
+}
+
+impl ConnectionRateLimiter {
+    fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            count: 0,
+        }
+    }
+
+    fn allow(&mut self, now: Instant, max_per_sec: u32) -> bool {
+        if max_per_sec == 0 {
+            return true;
+        }
+        if now.duration_since(self.window_start) >= Duration::from_secs(1) {
+            self.window_start = now;
+            self.count = 0;
+        }
+        if self.count >= max_per_sec {
+            return false;
+        }
+        self.count += 1;
+        true
+    }
+}
+
 fn read_one_job(
     mut stream: TcpStream,
-    source: String,
+    mut source: String,
+    limits: &CaptureLimits,
     tx: Sender<CapturedJob>,
     repaint_ctx: Option<egui::Context>,
     window: Option<WindowControl>,
+    focus: bool,
 ) -> std::io::Result<()> {
     // Normalmente Windows abre conexin, manda bytes y cierra (EOF) por job.
     // Pongo timeout por si el peer se queda abierto.
-    // Un timeout muy corto puede partir un ticket en 2 jobs si el POS manda en ráfagas.
-    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    // Un timeout muy corto puede partir un ticket en 2 jobs si el POS manda en ráfagas
+    // (configurable en Captura).
+    let _ = stream.set_read_timeout(Some(limits.read_timeout.max(Duration::from_millis(100))));
+    let max_bytes = limits.max_job_bytes;
 
     let mut buf = Vec::new();
     let mut tmp = [0u8; 8192];
 
     loop {
-        match stream.read(&mut tmp) {
+        // Con tope se lee como mucho un byte más de lo permitido: un job que llega justo al
+        // tope y todavía manda datos se detecta como truncado.
+        let want = if max_bytes > 0 {
+            (max_bytes + 1 - buf.len()).min(tmp.len())
+        } else {
+            tmp.len()
+        };
+        match stream.read(&mut tmp[..want]) {
             Ok(0) => break,
-            Ok(n) => buf.extend_from_slice(&tmp[..n]),
+            Ok(n) => {
+                buf.extend_from_slice(&tmp[..n]);
+                if max_bytes > 0 && buf.len() > max_bytes {
+                    // Se corta el job; el resto de la conexión se descarta al cerrarla.
+                    buf.truncate(max_bytes);
+                    source.push_str(" [truncado]");
+                    diag_log::log(&format!("TCP: job truncado a {max_bytes} bytes: {source}"));
+                    break;
+                }
+            }
             Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
             Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
                 // Consideramos fin de job por inactividad.
+                diag_log::log(&format!("TCP: fin de job por inactividad ({source})"));
                 break;
             }
             Err(e) => return Err(e),
@@ -120,7 +341,11 @@ fn read_one_job(
     if !buf.is_empty() {
         let _ = tx.send(CapturedJob { source, bytes: buf });
         if let Some(w) = window {
-            w.show_and_focus();
+            if focus {
+                w.show_and_focus();
+            } else {
+                w.show_no_activate();
+            }
         }
         if let Some(ctx) = repaint_ctx {
             ctx.request_repaint();
@@ -129,3 +354,177 @@ fn read_one_job(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_rejects_burst_and_recovers_next_second() {
+        let t0 = Instant::now();
+        let mut rate = ConnectionRateLimiter::new(t0);
+
+        // Ráfaga de 50 conexiones en el mismo instante con límite de 20/s.
+        let accepted = (0..50)
+            .filter(|i| rate.allow(t0 + Duration::from_millis(*i), 20))
+            .count();
+        assert_eq!(accepted, 20);
+
+        // En el segundo siguiente vuelve a aceptar.
+        assert!(rate.allow(t0 + Duration::from_millis(1000), 20));
+    }
+
+    #[test]
+    fn rate_limiter_zero_means_unlimited() {
+        let t0 = Instant::now();
+        let mut rate = ConnectionRateLimiter::new(t0);
+        assert!((0..1000).all(|_| rate.allow(t0, 0)));
+    }
+
+    #[test]
+    fn rapid_connections_are_capped_by_the_listener() {
+        let limits = CaptureLimits {
+            max_connections_per_sec: 3,
+            ..CaptureLimits::default()
+        };
+        // Reloj congelado: las 10 conexiones caen en la misma ventana aunque la máquina vaya lenta.
+        let t0 = Instant::now();
+        let mut capture =
+            TcpCapture::start_with_clock("127.0.0.1:0", limits, None, None, move || t0).unwrap();
+
+        for _ in 0..10 {
+            let mut s = TcpStream::connect(capture.local_addr()).unwrap();
+            let _ = std::io::Write::write_all(&mut s, b"ABC");
+            let _ = s.shutdown(std::net::Shutdown::Write);
+            // El listener cierra la conexión al terminar con ella (job leído o rechazada).
+            let _ = s.set_read_timeout(Some(Duration::from_secs(5)));
+            let _ = s.read(&mut [0u8; 1]);
+        }
+        let jobs = capture.try_recv_all();
+        capture.stop();
+        assert_eq!(jobs.len(), 3);
+    }
+
+    #[test]
+    fn test_job_goes_through_the_listener() {
+        let mut capture =
+            TcpCapture::start("127.0.0.1:0", CaptureLimits::default(), None, None).unwrap();
+        let result = send_test_job(capture.local_addr(), b"\x1b@Prueba\n".to_vec())
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(result, Ok(()));
+
+        let mut jobs = Vec::new();
+        for _ in 0..40 {
+            jobs.extend(capture.try_recv_all());
+            if !jobs.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(25));
+        }
+        capture.stop();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].bytes, b"\x1b@Prueba\n");
+    }
+
+    #[test]
+    fn a_job_over_a_kb_multiple_limit_is_marked_truncated() {
+        let limits = CaptureLimits {
+            max_job_bytes: 16 * 1024,
+            ..CaptureLimits::default()
+        };
+        let mut capture = TcpCapture::start("127.0.0.1:0", limits, None, None).unwrap();
+        let receive = |capture: &TcpCapture| {
+            for _ in 0..80 {
+                let jobs = capture.try_recv_all();
+                if !jobs.is_empty() {
+                    return jobs;
+                }
+                thread::sleep(Duration::from_millis(25));
+            }
+            Vec::new()
+        };
+
+        // Justo el tope: entra completo.
+        let _ = send_test_job(capture.local_addr(), vec![b'A'; 16 * 1024])
+            .recv_timeout(Duration::from_secs(5));
+        let jobs = receive(&capture);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].bytes.len(), 16 * 1024);
+        assert!(!jobs[0].source.ends_with("[truncado]"));
+
+        // Más que el tope: se corta en el tope y se avisa.
+        let _ = send_test_job(capture.local_addr(), vec![b'B'; 20 * 1024])
+            .recv_timeout(Duration::from_secs(5));
+        let jobs = receive(&capture);
+        capture.stop();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].bytes.len(), 16 * 1024);
+        assert!(
+            jobs[0].source.ends_with(" [truncado]"),
+            "{}",
+            jobs[0].source
+        );
+    }
+
+    #[test]
+    fn test_job_reaches_a_listener_on_all_interfaces() {
+        let limits = CaptureLimits {
+            allow_remote: true,
+            ..CaptureLimits::default()
+        };
+        let mut capture = TcpCapture::start("0.0.0.0:0", limits, None, None).unwrap();
+        assert!(capture.local_addr().ip().is_unspecified());
+        let result = send_test_job(capture.local_addr(), b"red\n".to_vec())
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(result, Ok(()));
+
+        let mut jobs = Vec::new();
+        for _ in 0..40 {
+            jobs.extend(capture.try_recv_all());
+            if !jobs.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(25));
+        }
+        capture.stop();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].bytes, b"red\n");
+    }
+
+    #[test]
+    fn test_job_reports_a_closed_port() {
+        // Reservar un puerto y liberarlo: nadie escucha ahí.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let err = send_test_job(addr, b"x".to_vec())
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .unwrap_err();
+        assert!(err.contains("No se pudo conectar"), "{err}");
+    }
+
+    #[test]
+    fn a_busy_port_is_skipped_and_retries_back_off() {
+        let busy = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = busy.local_addr().unwrap().port();
+        assert_eq!(first_free_port([port]), None);
+        let free = first_free_port(port..=port.saturating_add(20)).unwrap();
+        assert_ne!(free, port);
+        assert!(TcpListener::bind(("127.0.0.1", free)).is_ok());
+
+        let delays: Vec<u64> = (1..=8).map(|n| bind_retry_delay(n).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 32, 60, 60, 60]);
+    }
+
+    #[test]
+    fn non_loopback_bind_is_rejected_unless_allowed() {
+        assert!(check_bind_addr("127.0.0.1:9100", false).is_ok());
+        assert!(check_bind_addr("[::1]:9100", false).is_ok());
+        let err = check_bind_addr("0.0.0.0:9100", false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(check_bind_addr("0.0.0.0:9100", true).is_ok());
+    }
+}
@@ -0,0 +1,315 @@
+use std::io::Cursor;
+use std::mem;
+use std::sync::Arc;
+
+use eframe::egui::{epaint::FontImage, Color32, ColorImage, Galley, Rect, Vec2};
+
+/// Lienzo en memoria para exportar el ticket como imagen. Las coordenadas van en puntos de la
+/// impresora (203 dpi) dentro del área del papel; cada punto ocupa `scale` × `scale` píxeles,
+/// así el resultado no depende del tamaño de la ventana. Crece hacia abajo a medida que se
+/// pinta.
+pub struct Canvas {
+    /// Ancho total en píxeles (papel + márgenes).
+    width: usize,
+    /// Margen alrededor del papel, en puntos.
+    margin: usize,
+    scale: usize,
+    paper: Color32,
+    pixels: Vec<Color32>,
+    /// Renglones maquetados que se pintan con `paint_text`.
+    text: Vec<TextRow>,
+}
+
+/// Renglón de texto ya maquetado con las fuentes del visor. Se pinta al final, cuando el atlas
+/// de fuentes tiene todos los glifos.
+struct TextRow {
+    galley: Arc<Galley>,
+    /// Esquina superior izquierda, en píxeles.
+    left: f32,
+    top: f32,
+    /// Estiramiento de los glifos para ocupar justo sus celdas.
+    stretch: Vec2,
+    color: Color32,
+    bold: bool,
+    /// Halo de sobrecalentamiento (negrita con efectos realistas).
+    bleed: bool,
+    pixels_per_point: f32,
+}
+
+impl Canvas {
+    pub fn new(width_dots: usize, margin_dots: usize, scale: usize, paper: Color32) -> Self {
+        let scale = scale.max(1);
+        Self {
+            width: (width_dots + margin_dots * 2).max(1) * scale,
+            margin: margin_dots,
+            scale,
+            paper,
+            pixels: Vec::new(),
+            text: Vec::new(),
+        }
+    }
+
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    fn height(&self) -> usize {
+        self.pixels.len() / self.width
+    }
+
+    fn ensure_height(&mut self, height_px: usize) {
+        if height_px > self.height() {
+            self.pixels.resize(height_px * self.width, self.paper);
+        }
+    }
+
+    /// Píxel de un punto del papel (con el margen ya sumado).
+    pub fn to_px(&self, dots: f32) -> f32 {
+        (dots + self.margin as f32) * self.scale as f32
+    }
+
+    /// Rectángulo lleno, en puntos.
+    pub fn fill_dots(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color32) {
+        let (left, top) = (
+            self.to_px(x).round() as usize,
+            self.to_px(y).round() as usize,
+        );
+        let right = (self.to_px(x + width).round() as usize).min(self.width);
+        let bottom = self.to_px(y + height).round() as usize;
+        self.ensure_height(bottom);
+        for row in top..bottom {
+            let line = row * self.width;
+            self.pixels[line + left.min(right)..line + right].fill(color);
+        }
+    }
+
+    /// Velo translúcido (con el alfa de `color`) sobre lo ya pintado, en puntos.
+    pub fn tint_dots(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color32) {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let tint = Color32::from_rgb(r, g, b);
+        let (left, top) = (
+            self.to_px(x).round() as usize,
+            self.to_px(y).round() as usize,
+        );
+        let right = (self.to_px(x + width).round() as usize).min(self.width);
+        let bottom = self.to_px(y + height).round() as usize;
+        for row in top..bottom {
+            for col in left..right {
+                self.blend_px(col, row, tint, a as f32 / 255.0);
+            }
+        }
+    }
+
+    /// Pinta `image` (vecino más cercano) en `width` × `height` puntos desde (x, y). Solo
+    /// oscurece: el blanco de la imagen deja ver el color del papel.
+    pub fn blit(&mut self, image: &ColorImage, x: f32, y: f32, width: f32, height: f32) {
+        let [src_w, src_h] = image.size;
+        let (left, top) = (
+            self.to_px(x).round() as usize,
+            self.to_px(y).round() as usize,
+        );
+        let dst_w = (width * self.scale as f32).round() as usize;
+        let dst_h = (height * self.scale as f32).round() as usize;
+        if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
+            return;
+        }
+        self.ensure_height(top + dst_h);
+        for dy in 0..dst_h {
+            let src_row = dy * src_h / dst_h * src_w;
+            for dx in 0..dst_w.min(self.width.saturating_sub(left)) {
+                let color = image.pixels[src_row + dx * src_w / dst_w];
+                let idx = (top + dy) * self.width + left + dx;
+                let base = self.pixels[idx];
+                self.pixels[idx] = Color32::from_rgb(
+                    base.r().min(color.r()),
+                    base.g().min(color.g()),
+                    base.b().min(color.b()),
+                );
+            }
+        }
+    }
+
+    /// Mezcla `color` con la cobertura de un glifo (0 a 1) en el píxel (x, y).
+    pub fn blend_px(&mut self, x: usize, y: usize, color: Color32, coverage: f32) {
+        if x >= self.width || coverage <= 0.0 {
+            return;
+        }
+        self.ensure_height(y + 1);
+        let idx = y * self.width + x;
+        let base = self.pixels[idx];
+        let alpha = coverage.min(1.0);
+        let mix =
+            |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * alpha).round() as u8;
+        self.pixels[idx] = Color32::from_rgb(
+            mix(base.r(), color.r()),
+            mix(base.g(), color.g()),
+            mix(base.b(), color.b()),
+        );
+    }
+
+    /// Encola `galley` para ocupar `cell` (en puntos). `pixels_per_point` es el de las fuentes
+    /// con las que se maquetó.
+    pub fn queue_text(
+        &mut self,
+        galley: Arc<Galley>,
+        cell: Rect,
+        color: Color32,
+        bold: bool,
+        bleed: bool,
+        pixels_per_point: f32,
+    ) {
+        let size = galley.size() * pixels_per_point;
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return;
+        }
+        self.text.push(TextRow {
+            left: self.to_px(cell.left()),
+            top: self.to_px(cell.top()),
+            stretch: cell.size() * self.scale as f32 / size,
+            galley,
+            color,
+            bold,
+            bleed,
+            pixels_per_point,
+        });
+    }
+
+    /// Pinta el texto encolado con la cobertura de sus glifos en el atlas de fuentes. La
+    /// negrita se reimprime un punto a la derecha, como el doble golpe de la impresora.
+    pub fn paint_text(&mut self, atlas: &FontImage) {
+        let dot = self.scale as f32;
+        for row in mem::take(&mut self.text) {
+            let mut strikes = vec![(0.0, 0.0, 1.0)];
+            if row.bold {
+                strikes.push(((dot * 0.5).max(1.0), 0.0, 1.0));
+            }
+            if row.bleed {
+                strikes.extend([(-1.0, 0.0, 0.25), (0.0, 1.0, 0.25)]);
+            }
+            let ppp = row.pixels_per_point;
+            for glyph in row.galley.rows.iter().flat_map(|r| &r.glyphs) {
+                let uv = glyph.uv_rect;
+                let (src_w, src_h) = (
+                    uv.max[0].saturating_sub(uv.min[0]) as usize,
+                    uv.max[1].saturating_sub(uv.min[1]) as usize,
+                );
+                if src_w == 0 || src_h == 0 {
+                    continue;
+                }
+                let left = row.left + (glyph.pos.x + uv.offset.x) * ppp * row.stretch.x;
+                let top = row.top + (glyph.pos.y + uv.offset.y) * ppp * row.stretch.y;
+                let width = uv.size.x * ppp * row.stretch.x;
+                let height = uv.size.y * ppp * row.stretch.y;
+                for dy in 0..height.ceil() as usize {
+                    let src_y = uv.min[1] as usize + (dy * src_h) / height.ceil().max(1.0) as usize;
+                    for dx in 0..width.ceil() as usize {
+                        let src_x =
+                            uv.min[0] as usize + (dx * src_w) / width.ceil().max(1.0) as usize;
+                        let coverage = atlas.pixels[src_y * atlas.size[0] + src_x];
+                        for &(ox, oy, strength) in &strikes {
+                            let (x, y) = (left + dx as f32 + ox, top + dy as f32 + oy);
+                            if x >= 0.0 && y >= 0.0 {
+                                self.blend_px(
+                                    x as usize,
+                                    y as usize,
+                                    row.color,
+                                    coverage * strength,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// La imagen terminada: el contenido hasta `bottom` puntos más el margen inferior; girada
+    /// 180° si el ticket se imprime al revés.
+    pub fn finish(mut self, bottom: f32, upside_down: bool) -> ColorImage {
+        let height = self.to_px(bottom + self.margin as f32).round() as usize;
+        self.ensure_height(height);
+        if upside_down {
+            self.pixels.reverse();
+        }
+        ColorImage {
+            size: [self.width, self.height()],
+            pixels: self.pixels,
+        }
+    }
+}
+
+/// PNG (RGBA de 8 bits) de la imagen.
+pub fn encode_png(img: &ColorImage) -> Result<Vec<u8>, String> {
+    let [width, height] = img.size;
+    let rgba: Vec<u8> = img.pixels.iter().flat_map(|c| c.to_array()).collect();
+    let buffer = image::RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or_else(|| "Imagen con tamaño inválido".to_string())?;
+    let mut out = Cursor::new(Vec::new());
+    buffer
+        .write_to(&mut out, image::ImageFormat::Png)
+        .map_err(|e| format!("No se pudo codificar el PNG: {e}"))?;
+    Ok(out.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAPER: Color32 = Color32::from_rgb(254, 250, 245);
+
+    #[test]
+    fn canvas_scales_dots_and_keeps_the_paper_under_white() {
+        let mut canvas = Canvas::new(4, 1, 2, PAPER);
+        // 2 × 1 puntos: negro a la izquierda, blanco a la derecha.
+        let image = ColorImage {
+            size: [2, 1],
+            pixels: vec![Color32::BLACK, Color32::WHITE],
+        };
+        canvas.blit(&image, 0.0, 0.0, 2.0, 1.0);
+        canvas.fill_dots(3.0, 1.0, 1.0, 1.0, Color32::BLACK);
+        let out = canvas.finish(2.0, false);
+
+        // (4 + 2 de margen) × 2 px de ancho; 2 puntos + 2 de margen de alto.
+        assert_eq!(out.size, [12, 8]);
+        let at = |x: usize, y: usize| out.pixels[y * 12 + x];
+        assert_eq!(at(0, 0), PAPER);
+        assert_eq!([at(2, 2), at(3, 3)], [Color32::BLACK; 2]);
+        assert_eq!(at(4, 2), PAPER);
+        assert_eq!([at(8, 4), at(9, 5)], [Color32::BLACK; 2]);
+        assert_eq!(at(8, 3), PAPER);
+    }
+
+    #[test]
+    fn tint_blends_over_what_is_already_painted() {
+        let mut canvas = Canvas::new(2, 0, 1, Color32::WHITE);
+        canvas.fill_dots(0.0, 0.0, 1.0, 1.0, Color32::BLACK);
+        let tint = Color32::from_rgba_unmultiplied(0, 0, 255, 51);
+        canvas.tint_dots(0.0, 0.0, 2.0, 1.0, tint);
+        let out = canvas.finish(1.0, false);
+        assert_eq!(out.pixels[0], Color32::from_rgb(0, 0, 51));
+        assert_eq!(out.pixels[1], Color32::from_rgb(204, 204, 255));
+    }
+
+    #[test]
+    fn upside_down_tickets_are_rotated_half_a_turn() {
+        let mut canvas = Canvas::new(2, 0, 1, Color32::WHITE);
+        canvas.fill_dots(0.0, 0.0, 1.0, 1.0, Color32::BLACK);
+        let out = canvas.finish(2.0, true);
+        assert_eq!(out.size, [2, 2]);
+        assert_eq!(out.pixels[3], Color32::BLACK);
+        assert_eq!(out.pixels[0], Color32::WHITE);
+    }
+
+    #[test]
+    fn png_round_trips_the_pixels() {
+        let img = ColorImage {
+            size: [2, 1],
+            pixels: vec![Color32::BLACK, PAPER],
+        };
+        let png = encode_png(&img).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), (2, 1));
+        assert_eq!(decoded.get_pixel(1, 0).0, [254, 250, 245, 255]);
+    }
+}
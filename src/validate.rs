@@ -0,0 +1,313 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::coverage::{classify, coverage_report, CoverageEntry, Support};
+use crate::escpos::parse_escpos_checked;
+use crate::export::json_string;
+use crate::model::{CodePage, CommandType, Control};
+
+/// Ancho imprimible más grande (80mm, 576 puntos): una imagen más ancha se recorta.
+const MAX_IMAGE_DOTS: usize = 576;
+/// Capacidad de un QR versión 40 en modo byte (ECC L).
+const MAX_QR_BYTES: usize = 2953;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// ESC/FS/GS al que le faltan bytes o con parámetros inválidos (lo que sigue sale como texto).
+    Truncated,
+    /// Imagen o datos que no entran en el papel / el símbolo.
+    Oversized,
+    /// Comando que el visor no interpreta.
+    Unknown,
+}
+
+impl WarningKind {
+    pub fn key(self) -> &'static str {
+        match self {
+            WarningKind::Truncated => "truncated",
+            WarningKind::Oversized => "oversized",
+            WarningKind::Unknown => "unknown",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            WarningKind::Truncated => "truncado",
+            WarningKind::Oversized => "excedido",
+            WarningKind::Unknown => "desconocido",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationWarning {
+    pub offset: usize,
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub bytes: usize,
+    pub commands: usize,
+    pub coverage: Vec<CoverageEntry>,
+    pub warnings: Vec<ValidationWarning>,
+}
+
+/// Parsea un stream y junta sus avisos, ordenados por offset.
+pub fn validate(data: &[u8]) -> ValidationReport {
+    let (commands, spans, discarded) = parse_escpos_checked(data, &CodePage::Cp437.into());
+    let mut warnings: Vec<ValidationWarning> = discarded
+        .into_iter()
+        .map(|range| ValidationWarning {
+            offset: range.start,
+            kind: WarningKind::Truncated,
+            message: discarded_message(data, range),
+        })
+        .collect();
+
+    for ((_, command), span) in commands.iter().zip(&spans) {
+        if let Some((name, Support::Unsupported)) = classify(command) {
+            warnings.push(ValidationWarning {
+                offset: span.start,
+                kind: WarningKind::Unknown,
+                message: format!("{name}: no soportado"),
+            });
+        }
+        if let Some(message) = oversized_message(command) {
+            warnings.push(ValidationWarning {
+                offset: span.start,
+                kind: WarningKind::Oversized,
+                message,
+            });
+        }
+    }
+    warnings.sort_by_key(|w| w.offset);
+
+    ValidationReport {
+        bytes: data.len(),
+        commands: commands.len(),
+        coverage: coverage_report(&commands),
+        warnings,
+    }
+}
+
+fn discarded_message(data: &[u8], range: Range<usize>) -> String {
+    let prefix = match data[range.start] {
+        0x1B => "ESC",
+        0x1C => "FS",
+        _ => "GS",
+    };
+    let name = match data.get(range.start + 1) {
+        Some(&b) if b.is_ascii_graphic() => format!("{prefix} {}", b as char),
+        Some(&b) => format!("{prefix} {b:02X}"),
+        None => return format!("{prefix} al final del stream, sin parámetros"),
+    };
+    let rest = &data[range.start..];
+    // GS v 0: se puede decir cuántos bytes de imagen faltan.
+    if rest.len() >= 8 && rest[1] == 0x76 && rest[2] == 0x30 {
+        let width_bytes = rest[4] as usize | (rest[5] as usize) << 8;
+        let height = rest[6] as usize | (rest[7] as usize) << 8;
+        return format!(
+            "GS v 0: declara {} bytes de imagen y quedan {}",
+            width_bytes * height,
+            rest.len() - 8
+        );
+    }
+    format!("{name}: faltan bytes o parámetros inválidos; el resto se lee como texto")
+}
+
+fn oversized_message(command: &CommandType) -> Option<String> {
+    let (name, dots) = match command {
+        CommandType::Control(Control::RasterImage { width_bytes, .. }) => {
+            ("GS v 0", *width_bytes as usize * 8)
+        }
+        CommandType::Control(Control::BitImage { width, .. }) => ("ESC *", *width as usize),
+        CommandType::Control(Control::Qr { data, .. }) if data.len() > MAX_QR_BYTES => {
+            return Some(format!(
+                "QR de {} bytes: no entra en un QR versión 40 ({MAX_QR_BYTES})",
+                data.len()
+            ));
+        }
+        _ => return None,
+    };
+    (dots > MAX_IMAGE_DOTS).then(|| {
+        format!(
+            "{name}: imagen de {dots} puntos, más ancha que el papel de 80mm ({MAX_IMAGE_DOTS})"
+        )
+    })
+}
+
+pub fn format_text(file: &str, report: &ValidationReport) -> String {
+    let mut out = format!(
+        "{file}: {} bytes, {} comandos\n",
+        report.bytes, report.commands
+    );
+    for entry in &report.coverage {
+        out.push_str(&format!(
+            "  {:<28} {:>6}  {}\n",
+            entry.command,
+            entry.count,
+            entry.support.label()
+        ));
+    }
+    if report.warnings.is_empty() {
+        out.push_str("Sin avisos\n");
+    } else {
+        out.push_str(&format!("Avisos: {}\n", report.warnings.len()));
+        for warning in &report.warnings {
+            out.push_str(&format!(
+                "  0x{:06X}  {:<11}  {}\n",
+                warning.offset,
+                warning.kind.label(),
+                warning.message
+            ));
+        }
+    }
+    out
+}
+
+pub fn format_json(file: &str, report: &ValidationReport) -> String {
+    let summary: Vec<String> = report
+        .coverage
+        .iter()
+        .map(|entry| {
+            format!(
+                "{{\"command\": {}, \"support\": \"{}\", \"count\": {}}}",
+                json_string(&entry.command),
+                entry.support.key(),
+                entry.count
+            )
+        })
+        .collect();
+    let warnings: Vec<String> = report
+        .warnings
+        .iter()
+        .map(|warning| {
+            format!(
+                "{{\"offset\": {}, \"kind\": \"{}\", \"message\": {}}}",
+                warning.offset,
+                warning.kind.key(),
+                json_string(&warning.message)
+            )
+        })
+        .collect();
+    format!(
+        "{{\"file\": {}, \"bytes\": {}, \"commands\": {}, \"summary\": [{}], \"warnings\": [{}]}}\n",
+        json_string(file),
+        report.bytes,
+        report.commands,
+        summary.join(", "),
+        warnings.join(", ")
+    )
+}
+
+/// `--validate <archivo> [--format json|text]`: imprime el reporte y devuelve el código de
+/// salida (0 = sin avisos, 1 = con avisos, 2 = uso incorrecto o archivo ilegible).
+pub fn run_cli(args: &[String]) -> i32 {
+    let value_of = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|pos| args.get(pos + 1))
+    };
+    let Some(file) = value_of("--validate") else {
+        eprintln!("Uso: --validate <archivo> [--format json|text]");
+        return 2;
+    };
+    let json = match value_of("--format").map(String::as_str) {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(other) => {
+            eprintln!("ERROR: formato desconocido: {other} (json o text)");
+            return 2;
+        }
+    };
+    let data = match fs::read(Path::new(file)) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("ERROR: {file}: {e}");
+            return 2;
+        }
+    };
+
+    let report = validate(&data);
+    if json {
+        print!("{}", format_json(file, &report));
+    } else {
+        print!("{}", format_text(file, &report));
+    }
+    if report.warnings.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_clean_ticket_has_no_warnings() {
+        let report = validate(b"\x1b@\x1b\x61\x01Hola\n\x1dV\x00");
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(report.bytes, 13);
+        assert!(format_text("t.prn", &report).ends_with("Sin avisos\n"));
+    }
+
+    #[test]
+    fn flags_truncated_images_oversized_images_and_unknown_commands() {
+        let mut data = b"\x1b\x7aA\n".to_vec();
+        // GS v 0 de 80 bytes por fila (640 puntos) x 1 fila.
+        data.extend_from_slice(&[0x1D, 0x76, 0x30, 0x00, 80, 0x00, 0x01, 0x00]);
+        data.extend_from_slice(&[0u8; 80]);
+        // GS v 0 que declara 4x4 = 16 bytes y trae 2.
+        let truncated_at = data.len();
+        data.extend_from_slice(&[0x1D, 0x76, 0x30, 0x00, 0x04, 0x00, 0x04, 0x00, 0xFF, 0xFF]);
+
+        let report = validate(&data);
+        let kinds: Vec<(usize, WarningKind)> =
+            report.warnings.iter().map(|w| (w.offset, w.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (0, WarningKind::Unknown),
+                (4, WarningKind::Oversized),
+                (truncated_at, WarningKind::Truncated),
+                // Los parámetros del GS v 0 descartado se filtran como bytes sueltos (0x04).
+                (truncated_at + 4, WarningKind::Unknown),
+                (truncated_at + 6, WarningKind::Unknown),
+            ]
+        );
+        assert_eq!(
+            report.warnings[2].message,
+            "GS v 0: declara 16 bytes de imagen y quedan 2"
+        );
+
+        let json = format_json("a\\b.prn", &report);
+        assert!(json.starts_with("{\"file\": \"a\\\\b.prn\", \"bytes\": 102,"));
+        assert!(json.contains(&format!(
+            "{{\"offset\": {truncated_at}, \"kind\": \"truncated\", "
+        )));
+    }
+
+    #[test]
+    fn cli_exit_codes() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(run_cli(&args(&["--validate"])), 2);
+        assert_eq!(run_cli(&args(&["--validate", "/no/existe.prn"])), 2);
+
+        let path = std::env::temp_dir().join("escpos_validate_cli_test.prn");
+        fs::write(&path, b"Hola\n").unwrap();
+        let file = path.display().to_string();
+        assert_eq!(
+            run_cli(&args(&["--validate", &file, "--format", "json"])),
+            0
+        );
+        assert_eq!(run_cli(&args(&["--validate", &file, "--format", "xml"])), 2);
+        fs::write(&path, b"Hola\n\x1b").unwrap();
+        assert_eq!(run_cli(&args(&["--validate", &file])), 1);
+        let _ = fs::remove_file(path);
+    }
+}
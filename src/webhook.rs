@@ -0,0 +1,419 @@
+use std::collections::VecDeque;
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc, Condvar, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::diag_log;
+use crate::export::json_string;
+
+/// Jobs que esperan envío como máximo; con el endpoint caído se descarta el más viejo.
+pub const QUEUE_LIMIT: usize = 50;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+/// Espera entre reintentos: se duplica en cada fallo hasta este tope.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Cuerpo del POST.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// Bytes crudos (`application/octet-stream`) con el id en `X-Job-Id`.
+    Raw,
+    /// JSON con metadatos y los bytes en base64.
+    Json,
+}
+
+impl WebhookFormat {
+    pub fn key(self) -> &'static str {
+        match self {
+            WebhookFormat::Raw => "raw",
+            WebhookFormat::Json => "json",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        [WebhookFormat::Raw, WebhookFormat::Json]
+            .into_iter()
+            .find(|f| f.key() == key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    /// `http://` o `https://host[:puerto]/ruta`.
+    pub url: String,
+    /// `Nombre: valor`, o solo el valor de `Authorization`. Vacío = sin cabecera.
+    pub auth_header: String,
+    pub format: WebhookFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookJob {
+    pub id: u64,
+    pub label: String,
+    pub received_unix: u64,
+    pub bytes: Vec<u8>,
+}
+
+/// Resultado de un envío, para el estado visible en Configuración.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookEvent {
+    Sent {
+        id: u64,
+    },
+    /// Falló y sigue en la cola: se reintenta en `retry_in`.
+    Failed {
+        id: u64,
+        error: String,
+        retry_in: Duration,
+    },
+    /// Cola llena: el job más viejo se descartó sin enviar.
+    Dropped {
+        id: u64,
+    },
+}
+
+struct Pending {
+    job: WebhookJob,
+    attempts: u32,
+    next_try: Instant,
+}
+
+struct Queue {
+    jobs: VecDeque<Pending>,
+    config: WebhookConfig,
+    stop: bool,
+}
+
+/// Hilo que envía cada job capturado al endpoint, en orden y con reintentos.
+pub struct WebhookSender {
+    shared: Arc<(Mutex<Queue>, Condvar)>,
+    events_tx: Sender<WebhookEvent>,
+    events: Receiver<WebhookEvent>,
+}
+
+impl WebhookSender {
+    pub fn start(config: WebhookConfig) -> Self {
+        let shared = Arc::new((
+            Mutex::new(Queue {
+                jobs: VecDeque::new(),
+                config,
+                stop: false,
+            }),
+            Condvar::new(),
+        ));
+        let (events_tx, events) = mpsc::channel();
+        let worker_shared = shared.clone();
+        let worker_tx = events_tx.clone();
+        thread::spawn(move || worker(worker_shared, worker_tx));
+        Self {
+            shared,
+            events_tx,
+            events,
+        }
+    }
+
+    /// Cambia URL / cabecera / formato; lo pendiente sale con la configuración nueva.
+    pub fn set_config(&self, config: WebhookConfig) {
+        let (queue, cvar) = &*self.shared;
+        if let Ok(mut queue) = queue.lock() {
+            if queue.config != config {
+                queue.config = config;
+                // Un endpoint nuevo merece un intento inmediato.
+                let now = Instant::now();
+                for pending in &mut queue.jobs {
+                    pending.next_try = now;
+                }
+                cvar.notify_one();
+            }
+        }
+    }
+
+    pub fn enqueue(&self, job: WebhookJob) {
+        let (queue, cvar) = &*self.shared;
+        let Ok(mut queue) = queue.lock() else {
+            return;
+        };
+        if queue.jobs.len() >= QUEUE_LIMIT {
+            if let Some(oldest) = queue.jobs.pop_front() {
+                diag_log::log(&format!(
+                    "Webhook: cola llena ({QUEUE_LIMIT}), job #{} descartado sin enviar",
+                    oldest.job.id
+                ));
+                let _ = self
+                    .events_tx
+                    .send(WebhookEvent::Dropped { id: oldest.job.id });
+            }
+        }
+        queue.jobs.push_back(Pending {
+            job,
+            attempts: 0,
+            next_try: Instant::now(),
+        });
+        cvar.notify_one();
+    }
+
+    /// Jobs esperando envío (incluye el que se está enviando si falla).
+    pub fn pending(&self) -> usize {
+        self.shared.0.lock().map(|q| q.jobs.len()).unwrap_or(0)
+    }
+
+    pub fn try_recv_all(&self) -> Vec<WebhookEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for WebhookSender {
+    fn drop(&mut self) {
+        let (queue, cvar) = &*self.shared;
+        if let Ok(mut queue) = queue.lock() {
+            queue.stop = true;
+        }
+        cvar.notify_one();
+    }
+}
+
+fn worker(shared: Arc<(Mutex<Queue>, Condvar)>, events: Sender<WebhookEvent>) {
+    let (queue, cvar) = &*shared;
+    // Sin redirecciones: un 3xx a un POST no se sigue, cuenta como fallo.
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(IO_TIMEOUT)
+        .timeout_write(IO_TIMEOUT)
+        .redirects(0)
+        .build();
+    loop {
+        // Se espera a que el primero de la cola esté listo; el envío va sin el lock tomado.
+        let (job, config) = {
+            let Ok(mut guard) = queue.lock() else {
+                return;
+            };
+            loop {
+                if guard.stop {
+                    return;
+                }
+                let now = Instant::now();
+                match guard.jobs.front() {
+                    Some(front) if front.next_try <= now => break,
+                    Some(front) => {
+                        let wait = front.next_try - now;
+                        guard = match cvar.wait_timeout(guard, wait) {
+                            Ok((guard, _)) => guard,
+                            Err(_) => return,
+                        };
+                    }
+                    None => {
+                        guard = match cvar.wait(guard) {
+                            Ok(guard) => guard,
+                            Err(_) => return,
+                        };
+                    }
+                }
+            }
+            let front = guard.jobs.front().expect("cola no vacía");
+            (front.job.clone(), guard.config.clone())
+        };
+
+        let result = post_job(&agent, &config, &job);
+
+        let Ok(mut guard) = queue.lock() else {
+            return;
+        };
+        // Mientras se enviaba, `enqueue` pudo descartar este mismo job por cola llena.
+        let still_first = guard.jobs.front().is_some_and(|p| p.job.id == job.id);
+        match result {
+            Ok(()) => {
+                if still_first {
+                    guard.jobs.pop_front();
+                }
+                let _ = events.send(WebhookEvent::Sent { id: job.id });
+            }
+            Err(error) => {
+                diag_log::log(&format!("Webhook: job #{} no enviado: {error}", job.id));
+                if let Some(front) = guard.jobs.front_mut().filter(|_| still_first) {
+                    front.attempts += 1;
+                    let retry_in = retry_delay(front.attempts);
+                    front.next_try = Instant::now() + retry_in;
+                    let _ = events.send(WebhookEvent::Failed {
+                        id: job.id,
+                        error,
+                        retry_in,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// 2 s, 4 s, 8 s… hasta `MAX_RETRY_DELAY`.
+fn retry_delay(attempts: u32) -> Duration {
+    Duration::from_secs(1u64 << attempts.clamp(1, 6)).min(MAX_RETRY_DELAY)
+}
+
+/// Cabecera de autenticación: `Nombre: valor`, o solo el valor de `Authorization`.
+fn auth_header(raw: &str) -> Option<(&str, &str)> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    Some(match raw.split_once(':') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => ("Authorization", raw),
+    })
+}
+
+/// Cuerpo y `Content-Type` del POST según el formato.
+fn request_body(format: WebhookFormat, job: &WebhookJob) -> (&'static str, Vec<u8>) {
+    match format {
+        WebhookFormat::Raw => ("application/octet-stream", job.bytes.clone()),
+        WebhookFormat::Json => {
+            let body = format!(
+                "{{\"id\": {}, \"label\": {}, \"received_unix\": {}, \"size\": {}, \"data_base64\": \"{}\"}}",
+                job.id,
+                json_string(&job.label),
+                job.received_unix,
+                job.bytes.len(),
+                base64(&job.bytes)
+            );
+            ("application/json", body.into_bytes())
+        }
+    }
+}
+
+/// POST del job (http o https): cualquier respuesta 2xx es éxito.
+fn post_job(agent: &ureq::Agent, config: &WebhookConfig, job: &WebhookJob) -> Result<(), String> {
+    let url = config.url.trim();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("URL inválida: {url}"));
+    }
+    let (content_type, body) = request_body(config.format, job);
+    let mut request = agent
+        .post(url)
+        .set("Content-Type", content_type)
+        .set("X-Job-Id", &job.id.to_string());
+    if let Some((name, value)) = auth_header(&config.auth_header) {
+        request = request.set(name, value);
+    }
+    match request.send_bytes(&body) {
+        Ok(response) if (200..300).contains(&response.status()) => Ok(()),
+        Ok(response) => Err(format!("HTTP {}", response.status())),
+        Err(ureq::Error::Status(code, _)) => Err(format!("HTTP {code}")),
+        Err(ureq::Error::Transport(e)) => Err(e.to_string()),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn job(id: u64) -> WebhookJob {
+        WebhookJob {
+            id,
+            label: "TCP 9100 (\"caja\")".to_string(),
+            received_unix: 1_700_000_000,
+            bytes: b"\x1b@Hola\n".to_vec(),
+        }
+    }
+
+    #[test]
+    fn posts_json_with_auth_header_and_reports_success() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            // Cabeceras + cuerpo: hasta cerrar el JSON.
+            while !request.ends_with(b"}") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let sender = WebhookSender::start(WebhookConfig {
+            url: format!("http://127.0.0.1:{port}/recibos"),
+            auth_header: "Bearer secreto".to_string(),
+            format: WebhookFormat::Json,
+        });
+        sender.enqueue(job(7));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /recibos HTTP/1.1\r\n"));
+        assert!(request.contains("\r\nAuthorization: Bearer secreto\r\n"));
+        assert!(request.contains("\"label\": \"TCP 9100 (\\\"caja\\\")\""));
+        assert!(request.contains("\"data_base64\": \"G0BIb2xhCg==\""));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut events = Vec::new();
+        while events.is_empty() && Instant::now() < deadline {
+            events = sender.try_recv_all();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(events, vec![WebhookEvent::Sent { id: 7 }]);
+        assert_eq!(sender.pending(), 0);
+    }
+
+    #[test]
+    fn a_down_endpoint_keeps_a_bounded_queue_and_drops_the_oldest() {
+        // Puerto libre que nadie escucha: la conexión se rechaza.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let sender = WebhookSender::start(WebhookConfig {
+            url: format!("http://127.0.0.1:{port}/"),
+            auth_header: String::new(),
+            format: WebhookFormat::Raw,
+        });
+        for id in 1..=QUEUE_LIMIT as u64 + 2 {
+            sender.enqueue(job(id));
+        }
+        assert_eq!(sender.pending(), QUEUE_LIMIT);
+        let events = sender.try_recv_all();
+        assert!(events.contains(&WebhookEvent::Dropped { id: 1 }));
+        assert!(events.contains(&WebhookEvent::Dropped { id: 2 }));
+    }
+
+    #[test]
+    fn auth_header_and_base64() {
+        assert_eq!(
+            auth_header(" Bearer secreto "),
+            Some(("Authorization", "Bearer secreto"))
+        );
+        assert_eq!(
+            auth_header("X-Api-Key: abc123"),
+            Some(("X-Api-Key", "abc123"))
+        );
+        assert_eq!(auth_header("  "), None);
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -1,21 +1,36 @@
+/// Esquina de la pantalla donde se acomoda la ventana de preview.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ScreenCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
 #[cfg(target_os = "windows")]
 mod imp {
     use std::sync::{atomic::{AtomicIsize, Ordering}, Arc};
 
     use raw_window_handle::{HasWindowHandle, RawWindowHandle};
     use windows_sys::Win32::UI::WindowsAndMessaging::{
-        BringWindowToTop, GetWindowLongPtrW, SetForegroundWindow,
-        SetWindowLongPtrW, SetWindowPos, ShowWindow,
-        GWL_EXSTYLE, SWP_FRAMECHANGED, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SW_HIDE, SW_RESTORE,
-        SW_SHOW, SWP_SHOWWINDOW, WS_EX_APPWINDOW, WS_EX_TOOLWINDOW, HWND_NOTOPMOST, HWND_TOPMOST,
+        BringWindowToTop, GetForegroundWindow, GetWindowLongPtrW, IsWindowVisible,
+        SetForegroundWindow, SetWindowLongPtrW, SetWindowPos, ShowWindow, GWL_EXSTYLE,
+        HWND_NOTOPMOST, HWND_TOPMOST, SWP_FRAMECHANGED, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER,
+        SWP_SHOWWINDOW, SW_HIDE, SW_RESTORE, SW_SHOW, SW_SHOWNOACTIVATE, WS_EX_APPWINDOW,
+        WS_EX_TOOLWINDOW,
     };
 
-    use windows_sys::Win32::Foundation::RECT;
+    use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM, RECT};
     use windows_sys::Win32::Graphics::Gdi::{
-        GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+        EnumDisplayMonitors, GetMonitorInfoW, MonitorFromWindow, HDC, HMONITOR, MONITORINFO,
+        MONITORINFOEXW, MONITOR_DEFAULTTONEAREST,
     };
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_CONTROL, VK_V};
     use windows_sys::Win32::UI::WindowsAndMessaging::GetWindowRect;
 
+    use super::ScreenCorner;
+
     #[derive(Clone, Default)]
     pub struct WindowControl {
         hwnd: Arc<AtomicIsize>,
@@ -79,6 +94,33 @@ mod imp {
             }
         }
 
+        /// Vuelve a mostrar la ventana (p.ej. desde la bandeja) sin quitarle el foco a la
+        /// aplicación que el usuario está usando.
+        pub fn show_no_activate(&self) {
+            let hwnd = self.hwnd_ptr();
+            if hwnd.is_null() {
+                return;
+            }
+            self.set_taskbar_visible(true);
+            unsafe {
+                let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            }
+        }
+
+        /// Visible y con foco: el atajo global la oculta en vez de traerla al frente.
+        pub fn is_foreground(&self) -> bool {
+            let hwnd = self.hwnd_ptr();
+            !hwnd.is_null()
+                && unsafe { IsWindowVisible(hwnd) != 0 && GetForegroundWindow() == hwnd }
+        }
+
+        /// Ctrl+V apretado con la ventana al frente, leído del teclado: egui-winit solo avisa
+        /// de un pegado cuando el portapapeles tiene texto (con solo archivos no llega nada).
+        pub fn paste_keys_down(&self) -> bool {
+            let down = |vk: u16| unsafe { GetAsyncKeyState(i32::from(vk)) } < 0;
+            self.is_foreground() && down(VK_CONTROL) && down(VK_V)
+        }
+
         pub fn show_and_focus(&self) {
             let hwnd = self.hwnd_ptr();
             if hwnd.is_null() {
@@ -115,42 +157,50 @@ mod imp {
             }
         }
 
-        pub fn snap_near_right(&self, margin_px: i32) {
-            let hwnd = self.hwnd_ptr();
-            if hwnd.is_null() {
-                return;
+        /// Lista los monitores conectados (nombre de dispositivo, p.ej. `\\.\DISPLAY2`).
+        pub fn monitor_names(&self) -> Vec<String> {
+            enumerate_monitors()
+                .into_iter()
+                .filter_map(|m| monitor_info(m).map(|(_, name)| name))
+                .collect()
+        }
+
+        /// Monitor destino: el índice pedido si existe; si no (desconectado), el más cercano.
+        fn target_work_area(&self, hwnd: HWND, monitor_index: Option<usize>) -> Option<RECT> {
+            if let Some(idx) = monitor_index {
+                if let Some(&monitor) = enumerate_monitors().get(idx) {
+                    if let Some((work, _)) = monitor_info(monitor) {
+                        return Some(work);
+                    }
+                }
+            }
+
+            let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+            if monitor.is_null() {
+                return None;
             }
+            monitor_info(monitor).map(|(work, _)| work)
+        }
 
+        fn window_rect(hwnd: HWND) -> Option<RECT> {
             unsafe {
                 let mut rect: RECT = core::mem::zeroed();
                 if GetWindowRect(hwnd, &mut rect) == 0 {
-                    return;
+                    return None;
                 }
+                Some(rect)
+            }
+        }
 
-                let w = (rect.right - rect.left).max(1);
-                let h = (rect.bottom - rect.top).max(1);
-
-                let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
-                if monitor.is_null() {
-                    return;
-                }
-
-                let mut mi: MONITORINFO = core::mem::zeroed();
-                mi.cbSize = core::mem::size_of::<MONITORINFO>() as u32;
-                if GetMonitorInfoW(monitor, &mut mi) == 0 {
-                    return;
-                }
-
-                // Usar work area (sin taskbar) y alinear abajo a la derecha.
-                let work = mi.rcWork;
-                let margin = margin_px.max(0);
-                let mut x = work.right - w - margin;
-                let mut y = work.bottom - h - margin;
-
-                // Clamp básico para no salir del work area.
-                x = x.max(work.left + margin).min(work.right - w - margin);
-                y = y.max(work.top + margin).min(work.bottom - h - margin);
+        fn window_size(rect: &RECT) -> (i32, i32) {
+            (
+                (rect.right - rect.left).max(1),
+                (rect.bottom - rect.top).max(1),
+            )
+        }
 
+        fn move_to(hwnd: HWND, x: i32, y: i32) {
+            unsafe {
                 let _ = SetWindowPos(
                     hwnd,
                     core::ptr::null_mut(),
@@ -163,49 +213,108 @@ mod imp {
             }
         }
 
-        pub fn center_on_screen(&self) {
+        /// Coloca la ventana en la esquina indicada del work area (sin taskbar) del monitor
+        /// `monitor_index` (orden de `monitor_names`). `None` = monitor actual.
+        pub fn snap_to_monitor(
+            &self,
+            monitor_index: Option<usize>,
+            corner: ScreenCorner,
+            margin_px: i32,
+        ) {
             let hwnd = self.hwnd_ptr();
             if hwnd.is_null() {
                 return;
             }
+            let Some(rect) = Self::window_rect(hwnd) else {
+                return;
+            };
+            let (w, h) = Self::window_size(&rect);
+            let Some(work) = self.target_work_area(hwnd, monitor_index) else {
+                return;
+            };
+
+            let margin = margin_px.max(0);
+            let mut x = match corner {
+                ScreenCorner::TopLeft | ScreenCorner::BottomLeft => work.left + margin,
+                ScreenCorner::TopRight | ScreenCorner::BottomRight => work.right - w - margin,
+            };
+            let mut y = match corner {
+                ScreenCorner::TopLeft | ScreenCorner::TopRight => work.top + margin,
+                ScreenCorner::BottomLeft | ScreenCorner::BottomRight => work.bottom - h - margin,
+            };
+
+            // Clamp básico para no salir del work area.
+            x = x.max(work.left + margin).min(work.right - w - margin);
+            y = y.max(work.top + margin).min(work.bottom - h - margin);
+
+            // Ya está en su lugar: sin `SetWindowPos` (el modo acoplado lo pide cada segundo).
+            if (rect.left, rect.top) != (x, y) {
+                Self::move_to(hwnd, x, y);
+            }
+        }
 
-            unsafe {
-                let mut rect: RECT = core::mem::zeroed();
-                if GetWindowRect(hwnd, &mut rect) == 0 {
-                    return;
-                }
+        pub fn center_on_screen(&self, monitor_index: Option<usize>) {
+            let hwnd = self.hwnd_ptr();
+            if hwnd.is_null() {
+                return;
+            }
+            let Some(rect) = Self::window_rect(hwnd) else {
+                return;
+            };
+            let (w, h) = Self::window_size(&rect);
+            let Some(work) = self.target_work_area(hwnd, monitor_index) else {
+                return;
+            };
 
-                let w = (rect.right - rect.left).max(1);
-                let h = (rect.bottom - rect.top).max(1);
+            let work_w = (work.right - work.left).max(1);
+            let work_h = (work.bottom - work.top).max(1);
 
-                let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
-                if monitor.is_null() {
-                    return;
-                }
+            let x = work.left + ((work_w - w) / 2);
+            let y = work.top + ((work_h - h) / 2);
 
-                let mut mi: MONITORINFO = core::mem::zeroed();
-                mi.cbSize = core::mem::size_of::<MONITORINFO>() as u32;
-                if GetMonitorInfoW(monitor, &mut mi) == 0 {
-                    return;
-                }
+            Self::move_to(hwnd, x, y);
+        }
+    }
 
-                let work = mi.rcWork;
-                let work_w = (work.right - work.left).max(1);
-                let work_h = (work.bottom - work.top).max(1);
+    fn enumerate_monitors() -> Vec<HMONITOR> {
+        unsafe extern "system" fn collect(
+            monitor: HMONITOR,
+            _hdc: HDC,
+            _rect: *mut RECT,
+            data: LPARAM,
+        ) -> BOOL {
+            let list = &mut *(data as *mut Vec<HMONITOR>);
+            list.push(monitor);
+            1
+        }
 
-                let x = work.left + ((work_w - w) / 2);
-                let y = work.top + ((work_h - h) / 2);
+        let mut list: Vec<HMONITOR> = Vec::new();
+        unsafe {
+            let _ = EnumDisplayMonitors(
+                core::ptr::null_mut(),
+                core::ptr::null(),
+                Some(collect),
+                &mut list as *mut Vec<HMONITOR> as LPARAM,
+            );
+        }
+        list
+    }
 
-                let _ = SetWindowPos(
-                    hwnd,
-                    core::ptr::null_mut(),
-                    x,
-                    y,
-                    0,
-                    0,
-                    SWP_NOSIZE | SWP_NOZORDER | SWP_SHOWWINDOW,
-                );
+    /// Work area y nombre de dispositivo de un monitor.
+    fn monitor_info(monitor: HMONITOR) -> Option<(RECT, String)> {
+        unsafe {
+            let mut mi: MONITORINFOEXW = core::mem::zeroed();
+            mi.monitorInfo.cbSize = core::mem::size_of::<MONITORINFOEXW>() as u32;
+            if GetMonitorInfoW(monitor, &mut mi as *mut MONITORINFOEXW as *mut MONITORINFO) == 0 {
+                return None;
             }
+            let len = mi
+                .szDevice
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(mi.szDevice.len());
+            let name = String::from_utf16_lossy(&mi.szDevice[..len]);
+            Some((mi.monitorInfo.rcWork, name))
         }
     }
 
@@ -220,9 +329,22 @@ mod imp {
     impl WindowControl {
         pub fn try_update_from_frame(&self, _frame: &mut eframe::Frame) {}
         pub fn hide_to_tray(&self) {}
+        pub fn show_no_activate(&self) {}
         pub fn show_and_focus(&self) {}
-        pub fn snap_near_right(&self, _margin_px: i32) {}
-        pub fn center_on_screen(&self) {}
+        pub fn paste_keys_down(&self) -> bool {
+            false
+        }
+        pub fn monitor_names(&self) -> Vec<String> {
+            Vec::new()
+        }
+        pub fn snap_to_monitor(
+            &self,
+            _monitor_index: Option<usize>,
+            _corner: super::ScreenCorner,
+            _margin_px: i32,
+        ) {
+        }
+        pub fn center_on_screen(&self, _monitor_index: Option<usize>) {}
     }
 
     pub use WindowControl as WindowControlExport;